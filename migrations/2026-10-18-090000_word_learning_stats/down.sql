@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX words_language_idx;
+DROP INDEX wordlearning_userid_idx;
+ALTER TABLE WordLearning
+  DROP CONSTRAINT wordlearning_word_userid_key,
+  DROP COLUMN successes,
+  DROP COLUMN attempts,
+  DROP COLUMN learned,
+  DROP COLUMN created;
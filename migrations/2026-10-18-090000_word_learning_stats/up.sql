@@ -0,0 +1,16 @@
+-- Your SQL goes here
+-- Nothing prevented learning a word twice until now, keep the most
+-- advanced row of each user for each word
+DELETE FROM WordLearning a USING WordLearning b
+  WHERE a.word = b.word AND a.userid = b.userid
+    AND (a.status < b.status OR (a.status = b.status AND a.id > b.id));
+
+ALTER TABLE WordLearning
+  ADD COLUMN created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  ADD COLUMN learned TIMESTAMP,
+  ADD COLUMN attempts INTEGER DEFAULT 0 NOT NULL,
+  ADD COLUMN successes INTEGER DEFAULT 0 NOT NULL,
+  ADD CONSTRAINT wordlearning_word_userid_key UNIQUE (word, userid);
+
+CREATE INDEX wordlearning_userid_idx ON WordLearning(userid);
+CREATE INDEX words_language_idx ON Words(language);
@@ -0,0 +1,38 @@
+-- Your SQL goes here
+CREATE TYPE RevisionAction as ENUM ('create', 'update', 'delete', 'revert');
+
+CREATE TABLE WordRevisions (
+  id SERIAL PRIMARY KEY,
+  word UUID NOT NULL, -- not a foreign key, the history must outlive the word
+  language UUID
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  author VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE SET NULL,
+  action RevisionAction NOT NULL,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  before JSONB, -- NULL when the word is created
+  after JSONB -- NULL when the word is deleted
+);
+
+CREATE INDEX wordrevisions_word_idx ON WordRevisions(word);
+CREATE INDEX wordrevisions_language_idx ON WordRevisions(language);
+
+CREATE TABLE LanguageRevisions (
+  id SERIAL PRIMARY KEY,
+  language UUID NOT NULL, -- not a foreign key, the history must outlive the language
+  author VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE SET NULL,
+  action RevisionAction NOT NULL,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  before JSONB, -- NULL when the language is created
+  after JSONB -- NULL when the language is deleted
+);
+
+CREATE INDEX languagerevisions_language_idx ON LanguageRevisions(language);
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX words_deleted_idx;
+DROP INDEX languages_deleted_idx;
+DELETE FROM Words WHERE deleted IS NOT NULL;
+DELETE FROM Languages WHERE deleted IS NOT NULL;
+ALTER TABLE Words DROP COLUMN deleted;
+ALTER TABLE Languages DROP COLUMN deleted;
+-- PostgreSQL cannot drop a value from an enum, 'restore' stays in RevisionAction
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TYPE RevisionAction ADD VALUE 'restore';
+
+ALTER TABLE Languages ADD COLUMN deleted TIMESTAMP; -- NULL unless in the trash
+ALTER TABLE Words ADD COLUMN deleted TIMESTAMP; -- NULL unless in the trash
+
+CREATE INDEX languages_deleted_idx ON Languages(deleted) WHERE deleted IS NOT NULL;
+CREATE INDEX words_deleted_idx ON Words(deleted) WHERE deleted IS NOT NULL;
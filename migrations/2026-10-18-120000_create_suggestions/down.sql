@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE Suggestions;
+DROP TYPE SuggestionStatus;
+DROP TYPE SuggestionKind;
+DELETE FROM LangAndAgents WHERE relationship = 'maintainer';
+-- PostgreSQL cannot drop a value from an enum, 'maintainer' stays in AgentLanguageRelation
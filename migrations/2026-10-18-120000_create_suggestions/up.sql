@@ -0,0 +1,36 @@
+-- Your SQL goes here
+ALTER TYPE AgentLanguageRelation ADD VALUE 'maintainer';
+
+CREATE TYPE SuggestionKind as ENUM ('new', 'edit', 'delete');
+CREATE TYPE SuggestionStatus as ENUM ('pending', 'accepted', 'rejected');
+
+CREATE TABLE Suggestions (
+  id SERIAL PRIMARY KEY,
+  language UUID
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  word UUID -- NULL when suggesting a new word
+    REFERENCES Words(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  author VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  kind SuggestionKind NOT NULL,
+  content JSONB, -- new word or changes to the word, NULL for deletions
+  comment TEXT, -- Markdown
+  status SuggestionStatus DEFAULT 'pending' NOT NULL,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  reviewer VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE SET NULL,
+  reviewed TIMESTAMP,
+  response TEXT -- Markdown
+);
+
+CREATE INDEX suggestions_language_status_idx ON Suggestions(language, status);
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+CREATE TABLE Comments (
+  id SERIAL PRIMARY KEY,
+  language UUID
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  word UUID -- NULL when commenting on the language itself
+    REFERENCES Words(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  parent INTEGER -- NULL when starting a new thread
+    REFERENCES Comments(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  author VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  body TEXT NOT NULL, -- Markdown
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  edited TIMESTAMP
+);
+
+CREATE INDEX comments_language_word_idx ON Comments(language, word);
+CREATE INDEX comments_parent_idx ON Comments(parent);
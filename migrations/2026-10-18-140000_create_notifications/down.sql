@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE UserFollows DROP CONSTRAINT userfollows_unique;
+DROP TABLE Notifications;
+DROP TYPE NotificationKind;
@@ -0,0 +1,34 @@
+-- Your SQL goes here
+CREATE TYPE NotificationKind as ENUM ('word_added', 'word_changed', 'word_deleted', 'language_created', 'new_follower');
+
+CREATE TABLE Notifications (
+  id SERIAL PRIMARY KEY,
+  recipient VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  kind NotificationKind NOT NULL,
+  actor VARCHAR(31) -- who triggered the notification
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE SET NULL,
+  language UUID
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  word UUID
+    REFERENCES Words(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  read BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+CREATE INDEX notifications_recipient_idx ON Notifications(recipient, id);
+CREATE INDEX notifications_unread_idx ON Notifications(recipient, id) WHERE NOT read;
+
+-- Nothing prevented following a user twice until now
+DELETE FROM UserFollows a USING UserFollows b
+  WHERE a.id > b.id AND a.follower = b.follower AND a.following = b.following;
+ALTER TABLE UserFollows ADD CONSTRAINT userfollows_unique UNIQUE (follower, following);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX languagerevisions_language_created_idx;
+DROP INDEX wordrevisions_language_created_idx;
+DROP INDEX userfollows_follower_idx;
+ALTER TABLE UserFollows DROP COLUMN created;
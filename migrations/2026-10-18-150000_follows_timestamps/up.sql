@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE UserFollows ADD COLUMN created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL;
+
+CREATE INDEX userfollows_follower_idx ON UserFollows(follower, created);
+CREATE INDEX wordrevisions_language_created_idx ON WordRevisions(language, created);
+CREATE INDEX languagerevisions_language_created_idx ON LanguageRevisions(language, created);
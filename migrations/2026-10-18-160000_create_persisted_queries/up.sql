@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE PersistedQueries (
+  hash CHAR(64) PRIMARY KEY, -- hex-encoded SHA-256 of the query
+  query TEXT NOT NULL,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
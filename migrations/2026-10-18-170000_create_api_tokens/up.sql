@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TYPE ApiTokenScope as ENUM ('read', 'write');
+
+CREATE TABLE ApiTokens (
+  id UUID DEFAULT uuid_generate_v4 () PRIMARY KEY,
+  owner VARCHAR(31)
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+    NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  hash CHAR(64) UNIQUE NOT NULL, -- hex-encoded SHA-256 of the token
+  scope ApiTokenScope NOT NULL,
+  language UUID -- NULL if the token is not restricted to a language
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  expires TIMESTAMP, -- NULL if the token never expires
+  last_used TIMESTAMP
+);
+
+CREATE INDEX apitokens_owner ON ApiTokens(owner);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE AuditLog;
+DROP TYPE AuditAction;
+ALTER TABLE Users DROP COLUMN admin;
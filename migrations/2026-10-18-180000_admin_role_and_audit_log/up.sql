@@ -0,0 +1,18 @@
+-- Your SQL goes here
+ALTER TABLE Users ADD COLUMN admin BOOLEAN DEFAULT FALSE NOT NULL;
+
+CREATE TYPE AuditAction as ENUM ('create_user', 'delete_user', 'list_users', 'set_admin');
+
+CREATE TABLE AuditLog (
+  id SERIAL PRIMARY KEY,
+  admin VARCHAR(31) -- NULL once the admin's account is deleted
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE SET NULL,
+  action AuditAction NOT NULL,
+  target VARCHAR(255), -- what the action was performed on, if anything
+  details JSONB,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX auditlog_created ON AuditLog(created DESC);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM AuditLog WHERE action = 'reconcile_users';
+-- PostgreSQL cannot drop a value from an enum, 'reconcile_users' stays in AuditAction
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TYPE AuditAction ADD VALUE 'reconcile_users';
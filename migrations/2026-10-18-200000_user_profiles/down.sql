@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE UserLanguages;
+DROP TYPE Proficiency;
+ALTER TABLE Users
+  DROP COLUMN display_name,
+  DROP COLUMN bio,
+  DROP COLUMN avatar,
+  DROP COLUMN interface_language,
+  DROP COLUMN links,
+  DROP COLUMN public_followers,
+  DROP COLUMN public_languages_followed,
+  DROP COLUMN public_words_learning;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+ALTER TABLE Users
+  ADD COLUMN display_name VARCHAR(64),
+  ADD COLUMN bio TEXT, -- Markdown
+  ADD COLUMN avatar VARCHAR(511),
+  ADD COLUMN interface_language VARCHAR(35), -- BCP 47 language tag
+  ADD COLUMN links TEXT[] DEFAULT '{}' NOT NULL,
+  ADD COLUMN public_followers BOOLEAN DEFAULT TRUE NOT NULL,
+  ADD COLUMN public_languages_followed BOOLEAN DEFAULT TRUE NOT NULL,
+  ADD COLUMN public_words_learning BOOLEAN DEFAULT FALSE NOT NULL;
+
+CREATE TYPE Proficiency as ENUM ('beginner', 'intermediate', 'advanced', 'fluent', 'native');
+
+CREATE TABLE UserLanguages (
+  id SERIAL PRIMARY KEY,
+  userid VARCHAR(31) NOT NULL
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  language VARCHAR(64) NOT NULL,
+  proficiency Proficiency NOT NULL,
+  UNIQUE(userid, language)
+);
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+CREATE TABLE AccountDeletions (
+  userid VARCHAR(31) PRIMARY KEY
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  requested TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  scheduled TIMESTAMP NOT NULL -- the account is deleted after this time
+);
+
+CREATE INDEX accountdeletions_scheduled ON AccountDeletions(scheduled);
+
+-- Languages handed over to a collaborator when the account is deleted,
+-- the other languages of the user are deleted with their account
+CREATE TABLE AccountDeletionTransfers (
+  id SERIAL PRIMARY KEY,
+  userid VARCHAR(31) NOT NULL
+    REFERENCES AccountDeletions(userid)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  language UUID NOT NULL UNIQUE
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  newowner VARCHAR(31) NOT NULL
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE
+);
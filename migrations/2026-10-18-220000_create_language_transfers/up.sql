@@ -0,0 +1,19 @@
+-- Your SQL goes here
+-- Pending offers to hand a language over to another user
+CREATE TABLE LanguageTransfers (
+  language UUID PRIMARY KEY
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  fromowner VARCHAR(31) NOT NULL
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  newowner VARCHAR(31) NOT NULL
+    REFERENCES Users(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX languagetransfers_newowner ON LanguageTransfers(newowner);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+-- Provenance of languages forked from another one
+CREATE TABLE LanguageForks (
+  language UUID PRIMARY KEY
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  source UUID -- NULL once the source language is deleted
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE SET NULL,
+  sourcename VARCHAR(255) NOT NULL,
+  languagerevision INTEGER, -- latest revision of the source's metadata when forked
+  wordrevision INTEGER, -- latest revision of the source's words when forked
+  created TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX languageforks_source ON LanguageForks(source);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+-- Parts of imported LIFT files Ordabok has no field for, written back
+-- when exporting to LIFT
+CREATE TABLE LiftHeaders (
+  language UUID PRIMARY KEY
+    REFERENCES Languages(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  header TEXT, -- <header> element of the imported file
+  normlang VARCHAR(64) NOT NULL, -- writing system of the norm of words
+  nativelang VARCHAR(64), -- writing system of their native form
+  analysislang VARCHAR(64) NOT NULL -- writing system of definitions and notes
+);
+
+CREATE TABLE LiftEntries (
+  word UUID PRIMARY KEY
+    REFERENCES Words(id)
+    ON UPDATE CASCADE
+    ON DELETE CASCADE,
+  extras JSONB NOT NULL
+);
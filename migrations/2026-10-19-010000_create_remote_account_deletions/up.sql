@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Users deleted from the database whose Appwrite account is still to
+-- be deleted, kept until Appwrite confirms it is gone
+CREATE TABLE RemoteAccountDeletions (
+  userid VARCHAR(31) PRIMARY KEY, -- no longer in Users
+  deleted TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
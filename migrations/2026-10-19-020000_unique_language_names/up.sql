@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- Users cannot own two languages with the same name outside of the
+-- trash, existing duplicates get their ID appended to their name
+UPDATE Languages l
+SET name = LEFT(l.name, 200) || ' (' || l.id || ')'
+FROM (
+  SELECT id, ROW_NUMBER() OVER (PARTITION BY owner, name ORDER BY created, id) AS n
+  FROM Languages
+  WHERE deleted IS NULL
+) duplicates
+WHERE l.id = duplicates.id AND duplicates.n > 1;
+
+CREATE UNIQUE INDEX languages_owner_name ON Languages(owner, name)
+  WHERE deleted IS NULL;
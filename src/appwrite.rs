@@ -1,5 +1,10 @@
+use std::{env, time::Duration};
+
 use color_eyre::eyre::Result;
 use rocket::serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::auth::{AuthError, AuthProvider};
 
 macro_rules! from_env {
     ($varname:expr) => {
@@ -8,44 +13,224 @@ macro_rules! from_env {
     };
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Ord, Eq)]
+/// Seconds to wait for Appwrite if `APPWRITE_TIMEOUT` is not set.
+const DEFAULT_TIMEOUT: u64 = 5;
+
+fn timeout() -> Duration {
+    Duration::from_secs(match env::var("APPWRITE_TIMEOUT") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid APPWRITE_TIMEOUT {val}, defaulting to {DEFAULT_TIMEOUT}: {e:?}"
+            );
+            DEFAULT_TIMEOUT
+        }),
+        Err(_) => DEFAULT_TIMEOUT,
+    })
+}
+
+#[derive(Clone, Debug)]
 pub struct APVariables {
     pub endpoint: String,
     pub project: String,
     pub api_key: String,
+    client: reqwest::Client,
+}
+
+/// Amount of users fetched at once from Appwrite.
+const USERS_PAGE_SIZE: usize = 100;
+
+/// User account as described by Appwrite.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AppwriteUser {
+    #[serde(rename = "$id")]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+}
+
+impl AppwriteUser {
+    /// Maximum length of usernames in the database.
+    const MAX_USERNAME_LENGTH: usize = 64;
+
+    /// Name of the user, falling back to the start of their email
+    /// address or to their ID.
+    pub fn username(&self) -> String {
+        let name = self.name.trim();
+        let name = if name.is_empty() {
+            self.email.split('@').next().unwrap_or_default().trim()
+        } else {
+            name
+        };
+        let name = if name.is_empty() { &self.id } else { name };
+        name.chars().take(Self::MAX_USERNAME_LENGTH).collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct UserList {
+    total: usize,
+    users: Vec<AppwriteUser>,
+}
+
+/// Appwrite instance the users of the database come from, if
+/// `APPWRITE_ENDPOINT` is set.
+#[derive(Debug, Clone)]
+pub struct Appwrite(Option<APVariables>);
+
+impl Appwrite {
+    pub fn get(&self) -> Option<&APVariables> {
+        self.0.as_ref()
+    }
+}
+
+impl Default for Appwrite {
+    fn default() -> Self {
+        Self(
+            env::var("APPWRITE_ENDPOINT")
+                .ok()
+                .map(|_| APVariables::default()),
+        )
+    }
 }
 
 impl APVariables {
+    /// Fetch all the users registered on Appwrite.
+    pub async fn list_users(&self) -> Result<Vec<AppwriteUser>> {
+        let url = format!("{}/users", self.endpoint);
+        let mut users = Vec::new();
+        loop {
+            let page = self
+                .client
+                .get(&url)
+                .query(&[
+                    ("queries[]", format!("limit({USERS_PAGE_SIZE})")),
+                    ("queries[]", format!("offset({})", users.len())),
+                ])
+                .header("X-Appwrite-Key", self.api_key.clone())
+                .header("X-Appwrite-Project", self.project.clone())
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<UserList>()
+                .await?;
+            let last = page.users.len() < USERS_PAGE_SIZE;
+            users.extend(page.users);
+            if last || users.len() >= page.total {
+                return Ok(users);
+            }
+        }
+    }
+
+    /// Fetch the account of the user `user_id`, `None` if Appwrite
+    /// does not know them.
+    pub async fn get_user(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<AppwriteUser>> {
+        let url = format!("{}/users/{user_id}", self.endpoint);
+        let response = self
+            .client
+            .get(url)
+            .header("X-Appwrite-Key", self.api_key.clone())
+            .header("X-Appwrite-Project", self.project.clone())
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+
+    /// Delete the account of the user `user_id` from Appwrite. An
+    /// account which no longer exists counts as deleted.
+    pub async fn delete_user(&self, user_id: &str) -> Result<()> {
+        let url = format!("{}/users/{user_id}", self.endpoint);
+        let response = self
+            .client
+            .delete(url)
+            .header("X-Appwrite-Key", self.api_key.clone())
+            .header("X-Appwrite-Project", self.project.clone())
+            .send()
+            .await?;
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+        }
+        Ok(())
+    }
+
     pub async fn check_session(
         &self,
         session_id: &str,
         user_id: &str,
     ) -> Result<bool> {
-        let client = reqwest::Client::new();
         let url = format!("{}/users/{user_id}/sessions", self.endpoint);
-        let response = client
+        let response = self
+            .client
             .get(url)
             .header("X-Appwrite-Key", self.api_key.clone())
             .header("X-Appwrite-Project", self.project.clone())
             .header("Content-Type", "application/json")
             .send()
-            .await?
-            .json::<UserSessions>()
             .await?;
+        // Unknown users have no session
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        let response =
+            response.error_for_status()?.json::<UserSessions>().await?;
         Ok(response.sessions.iter().any(|s| s.id == session_id))
     }
 }
 
-impl Default for APVariables {
-    fn default() -> Self {
+/// Sessions are given by Appwrite to users that are logged in, the
+/// `Authorization` header must be in the form `userId;userSessionId`.
+#[rocket::async_trait]
+impl AuthProvider for APVariables {
+    async fn authenticate(
+        &self,
+        credentials: &str,
+    ) -> Result<Option<String>, AuthError> {
+        let Some((user_id, session_id)) = credentials.split_once(';') else {
+            info!("Invalid session key: {}", credentials);
+            return Ok(None);
+        };
+        self.check_session(session_id, user_id)
+            .await
+            .map(|valid| valid.then(|| user_id.to_string()))
+            .map_err(|e| {
+                AuthError(format!("Error checking user session: {e:?}"))
+            })
+    }
+}
+
+impl APVariables {
+    pub fn new(endpoint: String, project: String, api_key: String) -> Self {
         Self {
-            endpoint: from_env!("APPWRITE_ENDPOINT"),
-            project: from_env!("APPWRITE_PROJECT"),
-            api_key: from_env!("APPWRITE_API_KEY"),
+            endpoint,
+            project,
+            api_key,
+            client: reqwest::Client::builder()
+                .timeout(timeout())
+                .build()
+                .expect("Could not build HTTP client"),
         }
     }
 }
 
+impl Default for APVariables {
+    fn default() -> Self {
+        Self::new(
+            from_env!("APPWRITE_ENDPOINT"),
+            from_env!("APPWRITE_PROJECT"),
+            from_env!("APPWRITE_API_KEY"),
+        )
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(crate = "rocket::serde")]
@@ -86,3 +271,97 @@ struct Sessions {
     country_name: String,
     current: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    /// Session `id` of `user` as Appwrite describes it.
+    fn session(id: &str, user: &str) -> Value {
+        json!({
+            "$id": id,
+            "$createdAt": "2023-01-04T10:00:00.000+00:00",
+            "userId": user,
+            "expire": "2024-01-04T10:00:00.000+00:00",
+            "provider": "email",
+            "providerUid": "alice@example.com",
+            "providerAccessToken": "",
+            "providerAccessTokenExpiry": "",
+            "providerRefreshToken": "",
+            "ip": "127.0.0.1",
+            "osCode": "LIN",
+            "osName": "GNU/Linux",
+            "osVersion": "",
+            "clientType": "browser",
+            "clientCode": "FF",
+            "clientName": "Firefox",
+            "clientVersion": "108.0",
+            "clientEngine": "Gecko",
+            "clientEngineVersion": "108.0",
+            "deviceName": "desktop",
+            "deviceBrand": "",
+            "deviceModel": "",
+            "countryCode": "--",
+            "countryName": "Unknown",
+            "current": false,
+        })
+    }
+
+    /// Mock Appwrite instance, where `alice` has the session
+    /// `session`, and which knows no other user.
+    async fn appwrite() -> (MockServer, APVariables) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/users/alice/sessions"))
+            .and(header("X-Appwrite-Key", "api-key"))
+            .and(header("X-Appwrite-Project", "ordabok"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "total": 1,
+                "sessions": [session("session", "alice")],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "message": "User with the requested ID could not be found.",
+                "code": 404,
+                "type": "user_not_found",
+            })))
+            .mount(&server)
+            .await;
+        let appwrite =
+            APVariables::new(server.uri(), "ordabok".into(), "api-key".into());
+        (server, appwrite)
+    }
+
+    #[rocket::async_test]
+    async fn accepts_valid_sessions() {
+        let (_server, appwrite) = appwrite().await;
+        assert_eq!(
+            Ok(Some("alice".to_string())),
+            appwrite.authenticate("alice;session").await
+        );
+    }
+
+    #[rocket::async_test]
+    async fn rejects_invalid_sessions() {
+        let (_server, appwrite) = appwrite().await;
+        assert_eq!(Ok(None), appwrite.authenticate("alice;other").await);
+        assert_eq!(Ok(None), appwrite.authenticate("bob;session").await);
+        assert_eq!(Ok(None), appwrite.authenticate("alice").await);
+    }
+
+    #[rocket::async_test]
+    async fn fails_when_appwrite_is_unreachable() {
+        let appwrite = APVariables::new(
+            "http://127.0.0.1:9".into(),
+            "ordabok".into(),
+            "api-key".into(),
+        );
+        assert!(appwrite.authenticate("alice;session").await.is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use super::{AuthError, AuthProvider};
+
+/// Seconds valid credentials are cached for if `AUTH_CACHE_TTL` is not
+/// set.
+const DEFAULT_TTL: u64 = 60;
+/// Seconds invalid credentials are cached for if
+/// `AUTH_CACHE_NEGATIVE_TTL` is not set.
+const DEFAULT_NEGATIVE_TTL: u64 = 10;
+/// Amount of cached credentials if `AUTH_CACHE_SIZE` is not set.
+const DEFAULT_SIZE: usize = 10_000;
+
+fn seconds_from_env(name: &str, default: u64) -> Duration {
+    Duration::from_secs(match env::var(name) {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!("Invalid {name} {val}, defaulting to {default}: {e:?}");
+            default
+        }),
+        Err(_) => default,
+    })
+}
+
+fn cache_size() -> usize {
+    match env::var("AUTH_CACHE_SIZE") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid AUTH_CACHE_SIZE {val}, defaulting to {DEFAULT_SIZE}: {e:?}"
+            );
+            DEFAULT_SIZE
+        }),
+        Err(_) => DEFAULT_SIZE,
+    }
+}
+
+/// When an entry expires, along with a counter telling apart entries
+/// expiring at the same instant.
+type Expiration = (Instant, u64);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    user: Option<String>,
+    expires: Expiration,
+}
+
+/// Cached answers, indexed by credentials and by expiration.
+#[derive(Debug, Default)]
+struct Entries {
+    users: HashMap<String, Entry>,
+    expirations: BTreeMap<Expiration, String>,
+    counter: u64,
+}
+
+impl Entries {
+    fn get(&self, credentials: &str) -> Option<Option<String>> {
+        self.users
+            .get(credentials)
+            .filter(|entry| entry.expires.0 > Instant::now())
+            .map(|entry| entry.user.clone())
+    }
+
+    fn remove_first(&mut self) -> bool {
+        let Some(first) = self.expirations.keys().next().copied() else {
+            return false;
+        };
+        if let Some(credentials) = self.expirations.remove(&first) {
+            self.users.remove(&credentials);
+        }
+        true
+    }
+
+    fn insert(
+        &mut self,
+        credentials: &str,
+        user: Option<String>,
+        expires: Instant,
+        capacity: usize,
+    ) {
+        if let Some(entry) = self.users.remove(credentials) {
+            self.expirations.remove(&entry.expires);
+        }
+        let now = Instant::now();
+        while self
+            .expirations
+            .keys()
+            .next()
+            .map_or(false, |(first, _)| *first <= now)
+        {
+            self.remove_first();
+        }
+        while self.users.len() >= capacity.max(1) && self.remove_first() {}
+        self.counter += 1;
+        let expires = (expires, self.counter);
+        self.expirations.insert(expires, credentials.to_string());
+        self.users
+            .insert(credentials.to_string(), Entry { user, expires });
+    }
+}
+
+/// Provider remembering for a while what another provider answered.
+///
+/// Invalid credentials are remembered too, for a shorter time, so
+/// retrying them does not hit the underlying provider either. Errors
+/// of the provider are not, the credentials are checked again on the
+/// next request. At most `AUTH_CACHE_SIZE` credentials are
+/// remembered, those closest to expiring are forgotten first.
+#[derive(Debug)]
+pub struct CachedProvider<P> {
+    provider: P,
+    entries: Mutex<Entries>,
+    ttl: Duration,
+    negative_ttl: Duration,
+    capacity: usize,
+}
+
+impl<P: AuthProvider> CachedProvider<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            entries: Mutex::default(),
+            ttl: seconds_from_env("AUTH_CACHE_TTL", DEFAULT_TTL),
+            negative_ttl: seconds_from_env(
+                "AUTH_CACHE_NEGATIVE_TTL",
+                DEFAULT_NEGATIVE_TTL,
+            ),
+            capacity: cache_size(),
+        }
+    }
+
+    fn get(&self, credentials: &str) -> Option<Option<String>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(credentials)
+    }
+
+    fn insert(&self, credentials: &str, user: Option<String>) {
+        let expires = Instant::now()
+            + if user.is_some() {
+                self.ttl
+            } else {
+                self.negative_ttl
+            };
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(credentials, user, expires, self.capacity);
+    }
+}
+
+#[rocket::async_trait]
+impl<P: AuthProvider> AuthProvider for CachedProvider<P> {
+    async fn authenticate(
+        &self,
+        credentials: &str,
+    ) -> Result<Option<String>, AuthError> {
+        if let Some(user) = self.get(credentials) {
+            debug!("Using cached authentication");
+            return Ok(user);
+        }
+        let user = self.provider.authenticate(credentials).await?;
+        self.insert(credentials, user.clone());
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    /// Provider accepting any credentials but `invalid`, failing to
+    /// check `unavailable` and counting how many times it is called.
+    #[derive(Debug, Default)]
+    struct Counting(AtomicUsize);
+
+    #[rocket::async_trait]
+    impl AuthProvider for Counting {
+        async fn authenticate(
+            &self,
+            credentials: &str,
+        ) -> Result<Option<String>, AuthError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            match credentials {
+                "unavailable" => Err(AuthError("Unavailable".into())),
+                "invalid" => Ok(None),
+                user => Ok(Some(user.to_string())),
+            }
+        }
+    }
+
+    fn cached(
+        ttl: Duration,
+        negative_ttl: Duration,
+        capacity: usize,
+    ) -> CachedProvider<Counting> {
+        CachedProvider {
+            provider: Counting::default(),
+            entries: Mutex::default(),
+            ttl,
+            negative_ttl,
+            capacity,
+        }
+    }
+
+    impl CachedProvider<Counting> {
+        fn calls(&self) -> usize {
+            self.provider.0.load(Ordering::SeqCst)
+        }
+    }
+
+    #[rocket::async_test]
+    async fn caches_valid_credentials() {
+        let cache = cached(HOUR, HOUR, 10);
+        assert_eq!(Ok(Some("alice".into())), cache.authenticate("alice").await);
+        assert_eq!(Ok(Some("alice".into())), cache.authenticate("alice").await);
+        assert_eq!(1, cache.calls());
+        assert_eq!(Ok(Some("bob".into())), cache.authenticate("bob").await);
+        assert_eq!(2, cache.calls());
+    }
+
+    #[rocket::async_test]
+    async fn caches_invalid_credentials() {
+        let cache = cached(HOUR, HOUR, 10);
+        assert_eq!(Ok(None), cache.authenticate("invalid").await);
+        assert_eq!(Ok(None), cache.authenticate("invalid").await);
+        assert_eq!(1, cache.calls());
+    }
+
+    #[rocket::async_test]
+    async fn does_not_cache_errors() {
+        let cache = cached(HOUR, HOUR, 10);
+        assert!(cache.authenticate("unavailable").await.is_err());
+        assert!(cache.authenticate("unavailable").await.is_err());
+        assert_eq!(2, cache.calls());
+    }
+
+    #[rocket::async_test]
+    async fn forgets_expired_credentials() {
+        let cache = cached(Duration::ZERO, Duration::ZERO, 10);
+        cache.authenticate("alice").await.unwrap();
+        cache.authenticate("alice").await.unwrap();
+        cache.authenticate("invalid").await.unwrap();
+        cache.authenticate("invalid").await.unwrap();
+        assert_eq!(4, cache.calls());
+    }
+
+    #[rocket::async_test]
+    async fn expires_invalid_credentials_separately() {
+        let cache = cached(HOUR, Duration::ZERO, 10);
+        cache.authenticate("alice").await.unwrap();
+        cache.authenticate("alice").await.unwrap();
+        assert_eq!(1, cache.calls());
+        cache.authenticate("invalid").await.unwrap();
+        cache.authenticate("invalid").await.unwrap();
+        assert_eq!(3, cache.calls());
+    }
+
+    #[rocket::async_test]
+    async fn evicts_the_oldest_credentials_when_full() {
+        let cache = cached(HOUR, HOUR, 2);
+        cache.authenticate("alice").await.unwrap();
+        cache.authenticate("bob").await.unwrap();
+        cache.authenticate("carol").await.unwrap();
+        assert_eq!(3, cache.calls());
+        cache.authenticate("bob").await.unwrap();
+        cache.authenticate("carol").await.unwrap();
+        assert_eq!(3, cache.calls());
+        cache.authenticate("alice").await.unwrap();
+        assert_eq!(4, cache.calls());
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(2, entries.users.len());
+        assert_eq!(2, entries.expirations.len());
+    }
+}
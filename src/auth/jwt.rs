@@ -0,0 +1,355 @@
+use std::{env, fs, time::Duration};
+
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use rocket::tokio::sync::OnceCell;
+use serde::Deserialize;
+use tracing::info;
+
+use super::{bearer, AuthError, AuthProvider};
+
+macro_rules! from_env {
+    ($varname:expr) => {
+        std::env::var($varname)
+            .expect(format!("{} must be set!", $varname).as_str())
+    };
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+/// Seconds to wait for the JWKS of the OpenID Connect provider.
+const JWKS_TIMEOUT: u64 = 10;
+
+/// Public keys JWTs are verified against.
+#[derive(Debug, Clone)]
+enum Keys {
+    /// Keys read from a local JWKS file.
+    File(JwkSet),
+    /// Keys served by the OpenID Connect provider, fetched the first
+    /// time a token is verified.
+    Url {
+        url: String,
+        client: reqwest::Client,
+        keys: OnceCell<JwkSet>,
+    },
+}
+
+/// Provider verifying `Bearer` JWTs issued by an OpenID Connect
+/// provider against its public keys.
+///
+/// Keys are read from the JWKS file `AUTH_JWKS_FILE`, or fetched from
+/// `AUTH_JWKS_URL` if it is set. The ID of the user is the `sub` claim
+/// of the token. If `AUTH_JWT_ISSUER` or `AUTH_JWT_AUDIENCE` are set,
+/// the `iss` and `aud` claims must match them.
+#[derive(Debug, Clone)]
+pub struct JwtProvider {
+    keys: Keys,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Default for JwtProvider {
+    fn default() -> Self {
+        let provider = match env::var("AUTH_JWKS_URL") {
+            Ok(url) if !url.is_empty() => Self::from_url(url),
+            _ => {
+                let path = from_env!("AUTH_JWKS_FILE");
+                let keys = fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|jwks| {
+                        serde_json::from_str(&jwks).map_err(|e| e.to_string())
+                    })
+                    .unwrap_or_else(|e| {
+                        panic!("Could not read JWKS file {path}: {e}")
+                    });
+                Self::from_keys(keys)
+            }
+        };
+        Self {
+            issuer: env::var("AUTH_JWT_ISSUER")
+                .ok()
+                .filter(|issuer| !issuer.is_empty()),
+            audience: env::var("AUTH_JWT_AUDIENCE")
+                .ok()
+                .filter(|audience| !audience.is_empty()),
+            ..provider
+        }
+    }
+}
+
+impl JwtProvider {
+    /// Verify tokens against the keys of `keys`.
+    pub fn from_keys(keys: JwkSet) -> Self {
+        Self {
+            keys: Keys::File(keys),
+            issuer: None,
+            audience: None,
+        }
+    }
+
+    /// Verify tokens against the keys of the JWKS served at `url`.
+    pub fn from_url(url: String) -> Self {
+        Self {
+            keys: Keys::Url {
+                url,
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(JWKS_TIMEOUT))
+                    .build()
+                    .expect("Could not build HTTP client"),
+                keys: OnceCell::new(),
+            },
+            issuer: None,
+            audience: None,
+        }
+    }
+
+    /// Public keys of the provider, fetching them if needed.
+    ///
+    /// Keys failing to download are fetched again on the next call.
+    async fn keys(&self) -> Result<&JwkSet, String> {
+        match &self.keys {
+            Keys::File(keys) => Ok(keys),
+            Keys::Url { url, client, keys } => {
+                keys.get_or_try_init(|| async {
+                    client
+                        .get(url)
+                        .send()
+                        .await
+                        .and_then(reqwest::Response::error_for_status)
+                        .map_err(|e| e.to_string())?
+                        .json::<JwkSet>()
+                        .await
+                        .map_err(|e| e.to_string())
+                })
+                .await
+            }
+        }
+    }
+
+    fn verify(&self, keys: &JwkSet, token: &str) -> Result<String, String> {
+        let header = decode_header(token).map_err(|e| e.to_string())?;
+        // Only accept asymmetric algorithms, the keys are public
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(format!("Refused algorithm {:?}", header.alg));
+        }
+        let jwk = match &header.kid {
+            Some(kid) => keys.find(kid),
+            None if keys.keys.len() == 1 => keys.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| format!("No key matches kid {:?}", header.kid))?;
+        if matches!(jwk.algorithm, AlgorithmParameters::OctetKey(_)) {
+            return Err("Symmetric keys are not supported".into());
+        }
+        let key = DecodingKey::from_jwk(jwk).map_err(|e| e.to_string())?;
+        let mut validation = Validation::new(header.alg);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        if let Some(audience) = &self.audience {
+            validation.set_audience(&[audience]);
+        }
+        decode::<Claims>(token, &key, &validation)
+            .map(|token| token.claims.sub)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[rocket::async_trait]
+impl AuthProvider for JwtProvider {
+    async fn authenticate(
+        &self,
+        credentials: &str,
+    ) -> Result<Option<String>, AuthError> {
+        let Some(token) = bearer(credentials) else {
+            info!("Invalid authorization, expected a bearer token");
+            return Ok(None);
+        };
+        let keys = self
+            .keys()
+            .await
+            .map_err(|e| AuthError(format!("Could not fetch JWKS: {e}")))?;
+        match self.verify(keys, token) {
+            Ok(user) => Ok(Some(user)),
+            Err(e) => {
+                info!("Invalid JWT: {e}");
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, get_current_timestamp, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const JWKS: &str = include_str!("../../tests/fixtures/jwks.json");
+    const PRIVATE_KEY: &str = include_str!("../../tests/fixtures/jwt_key.pem");
+    const KID: &str = "ordabok-test";
+    const ISSUER: &str = "https://auth.example.com";
+    const AUDIENCE: &str = "ordabok";
+
+    fn claims(sub: &str, exp: u64) -> Value {
+        json!({ "sub": sub, "iss": ISSUER, "aud": AUDIENCE, "exp": exp })
+    }
+
+    fn sign(kid: Option<&str>, claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = kid.map(String::from);
+        let key = EncodingKey::from_ed_pem(PRIVATE_KEY.as_bytes()).unwrap();
+        format!("Bearer {}", encode(&header, claims, &key).unwrap())
+    }
+
+    fn valid(sub: &str) -> String {
+        sign(Some(KID), &claims(sub, get_current_timestamp() + 3600))
+    }
+
+    fn local_provider() -> JwtProvider {
+        JwtProvider::from_keys(serde_json::from_str(JWKS).unwrap())
+    }
+
+    /// Mock OpenID Connect provider serving the JWKS fixture
+    /// `downloads` times.
+    async fn jwks_server(downloads: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(JWKS, "application/json"),
+            )
+            .expect(downloads)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[rocket::async_test]
+    async fn accepts_tokens_signed_with_served_keys() {
+        let server = jwks_server(1).await;
+        let provider = JwtProvider::from_url(format!(
+            "{}/.well-known/jwks.json",
+            server.uri()
+        ));
+        let token = valid("alice");
+        assert_eq!(
+            Ok(Some("alice".into())),
+            provider.authenticate(&token).await
+        );
+        // Keys are downloaded once
+        let token = valid("bob");
+        assert_eq!(Ok(Some("bob".into())), provider.authenticate(&token).await);
+    }
+
+    #[rocket::async_test]
+    async fn fetches_keys_again_after_failures() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(JWKS, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let provider = JwtProvider::from_url(server.uri());
+        let token = valid("alice");
+        assert!(provider.authenticate(&token).await.is_err());
+        assert_eq!(
+            Ok(Some("alice".into())),
+            provider.authenticate(&token).await
+        );
+    }
+
+    #[rocket::async_test]
+    async fn accepts_tokens_without_kid_if_there_is_a_single_key() {
+        let token = sign(None, &claims("alice", get_current_timestamp() + 60));
+        assert_eq!(
+            Ok(Some("alice".into())),
+            local_provider().authenticate(&token).await
+        );
+    }
+
+    #[rocket::async_test]
+    async fn rejects_invalid_tokens() {
+        let provider = local_provider();
+        let token = valid("alice");
+        // Not a bearer token
+        assert_eq!(Ok(None), provider.authenticate(&token[7..]).await);
+        // Unknown key
+        let token = sign(
+            Some("other"),
+            &claims("alice", get_current_timestamp() + 3600),
+        );
+        assert_eq!(Ok(None), provider.authenticate(&token).await);
+        // Expired
+        let token =
+            sign(Some(KID), &claims("alice", get_current_timestamp() - 3600));
+        assert_eq!(Ok(None), provider.authenticate(&token).await);
+        // Signature of another token
+        let (alice, bob) = (valid("alice"), valid("bob"));
+        let token = format!(
+            "{}{}",
+            &bob[..bob.rfind('.').unwrap()],
+            &alice[alice.rfind('.').unwrap()..]
+        );
+        assert_eq!(Ok(None), provider.authenticate(&token).await);
+        // Symmetric algorithm
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims("alice", get_current_timestamp() + 3600),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(None),
+            provider.authenticate(&format!("Bearer {token}")).await
+        );
+    }
+
+    #[rocket::async_test]
+    async fn checks_issuer_and_audience() {
+        let token = valid("alice");
+        let provider = JwtProvider {
+            issuer: Some(ISSUER.into()),
+            audience: Some(AUDIENCE.into()),
+            ..local_provider()
+        };
+        assert_eq!(
+            Ok(Some("alice".into())),
+            provider.authenticate(&token).await
+        );
+        let provider = JwtProvider {
+            issuer: Some("https://elsewhere.example.com".into()),
+            ..provider
+        };
+        assert_eq!(Ok(None), provider.authenticate(&token).await);
+        let provider = JwtProvider {
+            audience: Some("other".into()),
+            ..local_provider()
+        };
+        assert_eq!(Ok(None), provider.authenticate(&token).await);
+    }
+}
@@ -0,0 +1,100 @@
+//! Authentication of the users sending requests.
+//!
+//! Which provider checks the `Authorization` header of requests is
+//! selected through the `AUTH_PROVIDER` environment variable:
+//! - `appwrite` (default) :: `userId;userSessionId` keys checked
+//!   against Appwrite
+//! - `jwt` :: `Bearer` JWTs signed by an OpenID Connect provider,
+//!   verified against the keys of the JWKS file `AUTH_JWKS_FILE`
+//! - `dev` :: the header is trusted as is, for local testing only
+//!
+//! Appwrite sessions are cached, see [`CachedProvider`].
+
+mod cache;
+mod dev;
+mod jwt;
+
+use std::{env, fmt::Debug, sync::Arc};
+
+use tracing::warn;
+
+use crate::appwrite::APVariables;
+
+pub use cache::CachedProvider;
+pub use dev::DevProvider;
+pub use jwt::JwtProvider;
+
+/// Failure to check credentials, which may or may not be valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthError(pub String);
+
+/// Something able to tell who sends a request.
+#[rocket::async_trait]
+pub trait AuthProvider: Debug + Send + Sync {
+    /// Return the ID of the user authentified by `credentials`, the
+    /// value of the `Authorization` header, or `None` if they are
+    /// invalid.
+    ///
+    /// # Errors
+    ///
+    /// If the credentials could not be checked, for instance because
+    /// the provider is unreachable.
+    async fn authenticate(
+        &self,
+        credentials: &str,
+    ) -> Result<Option<String>, AuthError>;
+}
+
+/// Shared handle on the configured [`AuthProvider`].
+#[derive(Debug, Clone)]
+pub struct Auth(Arc<dyn AuthProvider>);
+
+impl Auth {
+    pub fn new<P: AuthProvider + 'static>(provider: P) -> Self {
+        Self(Arc::new(provider))
+    }
+
+    /// ID of the user authentified by `credentials`, if they can be
+    /// checked and are valid.
+    pub async fn authenticate(&self, credentials: &str) -> Option<String> {
+        self.0.authenticate(credentials).await.unwrap_or_else(|e| {
+            warn!("Could not check credentials: {}", e.0);
+            None
+        })
+    }
+}
+
+/// Whether users log in with Appwrite sessions, the provider selected
+/// when `AUTH_PROVIDER` is unset.
+///
+/// Only then are the users of the database those of Appwrite.
+pub fn uses_appwrite() -> bool {
+    matches!(
+        env::var("AUTH_PROVIDER").as_deref(),
+        Ok("appwrite") | Err(_)
+    )
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        match env::var("AUTH_PROVIDER").as_deref() {
+            Ok("jwt") => Self::new(JwtProvider::default()),
+            Ok("dev") => {
+                warn!("Using the dev authentication provider, any Authorization header is trusted!");
+                Self::new(DevProvider)
+            }
+            Ok("appwrite") | Err(_) => {
+                Self::new(CachedProvider::new(APVariables::default()))
+            }
+            Ok(other) => panic!("Unknown AUTH_PROVIDER {other}"),
+        }
+    }
+}
+
+/// Token of `Bearer` credentials.
+fn bearer(credentials: &str) -> Option<&str> {
+    credentials
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
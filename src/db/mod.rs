@@ -1,5 +1,7 @@
 pub mod models;
 pub mod schema;
+#[cfg(test)]
+pub mod testing;
 
 use self::models::languages::Language;
 use self::models::users::User;
@@ -13,41 +15,112 @@ use diesel::{insert_into, prelude::*};
 use dotenvy::dotenv;
 use juniper::{graphql_value, DefaultScalarValue, FieldError, IntoFieldError};
 use std::env;
-use tracing::info;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::graphql::request_id;
+
+/// What went wrong, exposed to clients as `extensions.code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    Unauthenticated,
+    Forbidden,
+    InvalidInput,
+    Conflict,
+    /// Failure of the server itself, whose details are only logged.
+    Internal,
+}
+
+impl ErrorKind {
+    /// Stable machine-readable code of the error.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::NotFound => "NOT_FOUND",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+            Self::Forbidden => "FORBIDDEN",
+            Self::InvalidInput => "INVALID_INPUT",
+            Self::Conflict => "CONFLICT",
+            Self::Internal => "INTERNAL_SERVER_ERROR",
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct DatabaseError {
-    long: String,
-    #[allow(dead_code)]
-    short: String,
+    message: String,
+    kind: ErrorKind,
+    /// Request during which the error happened, or a unique identifier
+    /// of the error itself outside of requests.
+    request_id: Uuid,
 }
 
 impl DatabaseError {
     #[allow(clippy::needless_pass_by_value)]
-    pub fn new<S, T>(long: S, short: T) -> Self
+    pub fn new<S>(message: S, kind: ErrorKind) -> Self
     where
-        T: ToString,
         S: ToString,
     {
         Self {
-            long: long.to_string(),
-            short: short.to_string(),
+            message: message.to_string(),
+            kind,
+            request_id: request_id::current().unwrap_or_else(Uuid::new_v4),
         }
     }
-}
 
-impl std::error::Error for DatabaseError {}
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
 
-impl std::fmt::Display for DatabaseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.long)
+    /// Message that can be shown to clients.
+    pub fn public_message(&self) -> &str {
+        match self.kind {
+            ErrorKind::Internal => "Internal server error",
+            _ => &self.message,
+        }
+    }
+}
+
+impl From<Error> for DatabaseError {
+    fn from(e: Error) -> Self {
+        use diesel::result::DatabaseErrorKind;
+        match e {
+            Error::NotFound => Self::new("Not found", ErrorKind::NotFound),
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                Self::new(
+                    "Conflicts with an existing entry",
+                    ErrorKind::Conflict,
+                )
+            }
+            e => {
+                Self::new(format!("Database error: {e:?}"), ErrorKind::Internal)
+            }
+        }
     }
 }
 
 impl IntoFieldError for DatabaseError {
     fn into_field_error(self) -> juniper::FieldError<DefaultScalarValue> {
-        let short = self.short;
-        FieldError::new(self.long, graphql_value!({ "error": short }))
+        let request_id = self.request_id.to_string();
+        if self.kind == ErrorKind::Internal {
+            warn!("Request {request_id} failed: {}", self.message);
+        } else {
+            info!("Request {request_id} failed: {}", self.message);
+        }
+        let code = self.kind.code();
+        FieldError::new(
+            self.public_message(),
+            graphql_value!({ "code": code, "requestId": request_id }),
+        )
+    }
+}
+
+// `DatabaseError` deliberately does not implement `Display`, which
+// would make juniper convert it to a `FieldError` without its code
+// and with its internal message.
+impl From<DatabaseError> for FieldError {
+    fn from(e: DatabaseError) -> Self {
+        e.into_field_error()
     }
 }
 
@@ -86,14 +159,13 @@ impl Database {
         self.conn.get().map_err(|e| {
             DatabaseError::new(
                 format!("Failed to connect to database: {e:?}"),
-                "Database connection error",
+                ErrorKind::Internal,
             )
         })
     }
 
     pub fn all_languages(&self) -> Result<Vec<Language>, DatabaseError> {
-        use self::schema::languages::dsl::languages;
-        languages
+        Language::visible()
             .load::<Language>(&mut self.conn()?)
             .map_err(|e| {
                 info!("Failed to retrieve languages from database: {e:?}");
@@ -101,7 +173,7 @@ impl Database {
             .map_err(|e| {
                 DatabaseError::new(
                     format!("Failed to retrieve languages: {e:?}"),
-                    "Failed to retrieve languages",
+                    ErrorKind::Internal,
                 )
             })
     }
@@ -110,8 +182,8 @@ impl Database {
         use self::schema::users::dsl::users;
         users.load::<User>(&mut self.conn()?).map_err(|e| {
             DatabaseError::new(
-                format!("Failed to retrieve languages: {e:?}"),
-                "Failed to retrieve languages",
+                format!("Failed to retrieve users: {e:?}"),
+                ErrorKind::Internal,
             )
         })
     }
@@ -121,7 +193,7 @@ impl Database {
         query: &str,
     ) -> Result<Vec<Language>, DatabaseError> {
         use self::schema::languages::dsl;
-        dsl::languages
+        Language::visible()
             .filter(dsl::name.ilike(format!("%{query}%")))
             .load::<Language>(&mut self.conn()?)
             .map_err(|e| {
@@ -129,7 +201,7 @@ impl Database {
                     format!(
                         "Failed to retrieve languages with query {query}: {e:?}"
                     ),
-                    "Failed to retrieve languages",
+                    ErrorKind::Internal,
                 )
             })
     }
@@ -144,7 +216,7 @@ impl Database {
                     format!(
                         "Failed to retrieve users with query {query}: {e:?}"
                     ),
-                    "Failed to retrieve languages",
+                    ErrorKind::Internal,
                 )
             })
     }
@@ -155,7 +227,7 @@ impl Database {
         owner: &str,
     ) -> Result<Option<Language>, DatabaseError> {
         use self::schema::languages::dsl;
-        match dsl::languages
+        match Language::visible()
             .filter(dsl::name.eq(name))
             .filter(dsl::owner.eq(owner))
             .first(&mut self.conn()?)
@@ -166,7 +238,7 @@ impl Database {
                 format!(
                     "Failed to find language {name} belonging to {owner}: {e:?}"
                 ),
-                "Database error",
+                ErrorKind::Internal,
             )),
         }
     }
@@ -178,55 +250,92 @@ impl Database {
             Err(Error::NotFound) => Ok(None),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to retrieve user {id} from database: {e:?}"),
-                "Database Error",
+                ErrorKind::Internal,
             )),
         }
     }
 
     pub fn insert_user(
-        &self,
+        conn: &mut PgConnection,
         username: String,
         id: String,
     ) -> Result<User, DatabaseError> {
-        use self::schema::users::dsl::users;
-        let user = User { id, username };
-        match insert_into(users).values(user.clone()).execute(
-            &mut self.conn().map_err(|e| {
-                DatabaseError::new(
-                    format!("Failed to connect to the database: {e:?}"),
-                    "Connection error",
-                )
-            })?,
-        ) {
-            Ok(_) => Ok(user),
-            Err(e) => Err(DatabaseError {
-                long: format!("Failed to insert user {user:?}: {e:?}"),
-                short: "Data insertion error".to_string(),
-            }),
+        use self::schema::users::dsl;
+        match insert_into(dsl::users)
+            .values((dsl::id.eq(&id), dsl::username.eq(&username)))
+            .get_result::<User>(conn)
+        {
+            Ok(user) => Ok(user),
+            Err(e) => Err(DatabaseError::new(
+                format!("Failed to insert user {id} ({username}): {e:?}"),
+                ErrorKind::Internal,
+            )),
         }
     }
 
-    pub fn delete_user(&self, id: &str) -> Result<(), DatabaseError> {
+    pub fn delete_user(
+        conn: &mut PgConnection,
+        id: &str,
+    ) -> Result<(), DatabaseError> {
         use self::schema::users::dsl::users;
-        match diesel::delete(users.find(id.to_string()))
-            .execute(&mut self.conn()?)
-        {
+        match diesel::delete(users.find(id.to_string())).execute(conn) {
             Ok(_) => Ok(()),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to delete user {id}: {e:?}"),
-                "User deletion error",
+                ErrorKind::Internal,
             )),
         }
     }
 
+    /// Grant or deny admin rights to the user `id`.
+    pub fn set_admin(
+        conn: &mut PgConnection,
+        id: &str,
+        admin: bool,
+    ) -> Result<User, DatabaseError> {
+        use self::schema::users::dsl;
+        diesel::update(dsl::users.find(id))
+            .set(dsl::admin.eq(admin))
+            .get_result::<User>(conn)
+            .map_err(|e| match e {
+                Error::NotFound => DatabaseError::new(
+                    format!("User {id} not found"),
+                    ErrorKind::NotFound,
+                ),
+                e => DatabaseError::new(
+                    format!("Failed to set admin rights of user {id}: {e:?}"),
+                    ErrorKind::Internal,
+                ),
+            })
+    }
+
+    /// Grant admin rights to the users `ids`, returning how many users
+    /// were not admins yet.
+    pub fn promote_admins(&self, ids: &[&str]) -> Result<usize, DatabaseError> {
+        use self::schema::users::dsl;
+        diesel::update(dsl::users.filter(dsl::id.eq_any(ids)))
+            .filter(dsl::admin.eq(false))
+            .set(dsl::admin.eq(true))
+            .execute(&mut self.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to promote {ids:?} to admins: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
     pub fn word_id(&self, id: uuid::Uuid) -> Result<Option<Word>, DatabaseError> {
         use self::schema::words::dsl;
-        match dsl::words.find(id).first::<Word>(&mut self.conn()?) {
+        match Word::visible()
+            .filter(dsl::id.eq(id))
+            .first::<Word>(&mut self.conn()?)
+        {
             Ok(val) => Ok(Some(val)),
             Err(Error::NotFound) => Ok(None),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to retrieve word {id} from database: {e:?}"),
-                "Database Error",
+                ErrorKind::Internal,
             )),
         }
     }
@@ -237,7 +346,7 @@ impl Database {
         word: &str,
     ) -> Result<Vec<Word>, DatabaseError> {
         use self::schema::words::dsl;
-        dsl::words
+        Word::visible()
             .filter(dsl::language.eq(language))
             .filter(dsl::norm.eq(word))
             .load::<Word>(&mut self.conn()?)
@@ -246,7 +355,7 @@ impl Database {
                     format!(
                         "Failed to retrieve word {word} from language {language}: {e:?}"
                     ),
-                    "Failed to retrieve languages",
+                    ErrorKind::Internal,
                 )
             })
     }
@@ -257,7 +366,7 @@ impl Database {
         query: &str,
     ) -> Result<Vec<Word>, DatabaseError> {
         use self::schema::words::dsl;
-        dsl::words
+        Word::visible()
             .filter(dsl::language.eq(language))
             .filter(dsl::norm.ilike(format!("%{query}%")))
             .load::<Word>(&mut self.conn()?)
@@ -266,8 +375,88 @@ impl Database {
                     format!(
                         "Failed to retrieve words from language {language} with query {query}: {e:?}"
                     ),
-                    "Failed to retrieve languages",
+                    ErrorKind::Internal,
                 )
             })
     }
+
+    /// Languages owned by `owner` currently in the trash.
+    pub fn trashed_languages(
+        &self,
+        owner: &str,
+    ) -> Result<Vec<Language>, DatabaseError> {
+        use self::schema::languages::dsl;
+        dsl::languages
+            .filter(dsl::owner.eq(owner))
+            .filter(dsl::deleted.is_not_null())
+            .order(dsl::deleted.desc())
+            .load::<Language>(&mut self.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve trashed languages of {owner}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Words currently in the trash belonging to languages owned by
+    /// `owner` which are themselves not in the trash.
+    pub fn trashed_words(
+        &self,
+        owner: &str,
+    ) -> Result<Vec<Word>, DatabaseError> {
+        use self::schema::{languages, words};
+        words::table
+            .filter(words::deleted.is_not_null())
+            .filter(
+                words::language.eq_any(
+                    languages::table
+                        .filter(languages::owner.eq(owner))
+                        .filter(languages::deleted.is_null())
+                        .select(languages::id),
+                ),
+            )
+            .order(words::deleted.desc())
+            .load::<Word>(&mut self.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve trashed words of {owner}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Permanently delete words and languages which have been in the
+    /// trash for more than `days` days.
+    ///
+    /// Returns the amount of words and languages deleted.
+    pub fn purge_trash(
+        &self,
+        days: i64,
+    ) -> Result<(usize, usize), DatabaseError> {
+        use self::schema::{languages, words};
+        let cutoff =
+            chrono::Utc::now().naive_utc() - chrono::Duration::days(days);
+        let conn = &mut self.conn()?;
+        conn.transaction::<_, Error, _>(|conn| {
+            let words =
+                diesel::delete(words::table.filter(words::deleted.lt(cutoff)))
+                    .execute(conn)?;
+            let languages = diesel::delete(
+                languages::table.filter(languages::deleted.lt(cutoff)),
+            )
+            .execute(conn)?;
+            Ok((words, languages))
+        })
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to purge the trash: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+    }
 }
@@ -0,0 +1,454 @@
+use super::super::schema;
+use super::{languages::Language, users::User};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Jsonb, Varchar};
+use juniper::{FieldResult, GraphQLInputObject};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use std::{env, str::FromStr};
+
+use schema::{
+    accountdeletions, accountdeletiontransfers, languagerevisions, languages,
+    remoteaccountdeletions,
+};
+
+/// Amount of days before an account is deleted if
+/// `ACCOUNT_DELETION_GRACE_DAYS` is not set.
+const DEFAULT_GRACE_DAYS: i64 = 30;
+
+fn grace_days() -> i64 {
+    match env::var("ACCOUNT_DELETION_GRACE_DAYS") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid ACCOUNT_DELETION_GRACE_DAYS {val}, defaulting to {DEFAULT_GRACE_DAYS}: {e:?}"
+            );
+            DEFAULT_GRACE_DAYS
+        }),
+        Err(_) => DEFAULT_GRACE_DAYS,
+    }
+}
+
+#[derive(QueryableByName, Debug)]
+struct DataExport {
+    #[diesel(sql_type = Jsonb)]
+    data: serde_json::Value,
+}
+
+/// Everything stored about `user`, as a pretty-printed JSON object.
+pub fn export_data(db: &Database, user: &str) -> Result<String, DatabaseError> {
+    let export = diesel::sql_query(
+        "SELECT json_build_object(
+           'exported', CURRENT_TIMESTAMP,
+           'profile', (SELECT row_to_json(u) FROM users u WHERE u.id = $1),
+           'spokenLanguages', (SELECT COALESCE(json_agg(ul), '[]')
+                               FROM userlanguages ul WHERE ul.userid = $1),
+           'languages', (SELECT COALESCE(json_agg(l), '[]')
+                         FROM languages l WHERE l.owner = $1),
+           'words', (SELECT COALESCE(json_agg(w), '[]')
+                     FROM words w
+                     INNER JOIN languages l ON l.id = w.language
+                     WHERE l.owner = $1),
+           'usersFollowed', (SELECT COALESCE(json_agg(uf), '[]')
+                             FROM userfollows uf WHERE uf.follower = $1),
+           'languagesFollowed', (SELECT COALESCE(json_agg(ufl), '[]')
+                                 FROM userfollowlanguage ufl
+                                 WHERE ufl.userid = $1),
+           'learning', (SELECT COALESCE(json_agg(wl), '[]')
+                        FROM wordlearning wl WHERE wl.userid = $1),
+           'comments', (SELECT COALESCE(json_agg(c), '[]')
+                        FROM comments c WHERE c.author = $1),
+           'suggestions', (SELECT COALESCE(json_agg(s), '[]')
+                           FROM suggestions s WHERE s.author = $1),
+           'languageRevisions', (SELECT COALESCE(json_agg(lr), '[]')
+                                 FROM languagerevisions lr
+                                 WHERE lr.author = $1),
+           'wordRevisions', (SELECT COALESCE(json_agg(wr), '[]')
+                             FROM wordrevisions wr WHERE wr.author = $1)
+         )::jsonb AS data",
+    )
+    .bind::<Varchar, _>(user)
+    .get_result::<DataExport>(&mut db.conn()?)
+    .map_err(|e| {
+        DatabaseError::new(
+            format!("Failed to export the data of user {user}: {e:?}"),
+            ErrorKind::Internal,
+        )
+    })?;
+    serde_json::to_string_pretty(&export.data).map_err(|e| {
+        DatabaseError::new(
+            format!("Failed to serialize the data of user {user}: {e:?}"),
+            ErrorKind::Internal,
+        )
+    })
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct LanguageTransfer {
+    #[graphql(description = "UUID of the language to hand over")]
+    pub language: String,
+    #[graphql(
+        description = "Collaborator of the language who will become its owner"
+    )]
+    pub new_owner: String,
+}
+
+#[derive(Queryable, Debug, Clone)]
+struct AccountDeletionTransfer {
+    #[allow(dead_code)]
+    id: i32,
+    #[allow(dead_code)]
+    userid: String,
+    language: Uuid,
+    newowner: String,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = accountdeletiontransfers)]
+struct NewAccountDeletionTransfer {
+    userid: String,
+    language: Uuid,
+    newowner: String,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct AccountDeletion {
+    userid: String,
+    requested: NaiveDateTime,
+    scheduled: NaiveDateTime,
+}
+
+impl AccountDeletion {
+    /// Check `transfers` can be made once the account of `user` is
+    /// deleted.
+    fn validate_transfers(
+        db: &Database,
+        user: &str,
+        transfers: &[LanguageTransfer],
+    ) -> Result<Vec<NewAccountDeletionTransfer>, DatabaseError> {
+        let conn = &mut db.conn()?;
+        let mut validated: Vec<NewAccountDeletionTransfer> = Vec::new();
+        for transfer in transfers {
+            let id = Uuid::from_str(&transfer.language).map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Could not parse {} as a valid UUID: {e:?}",
+                        transfer.language
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+            })?;
+            let language = Language::fetch(conn, id)?;
+            let new_owner = transfer.new_owner.as_str();
+            if !language.is_owned_by(user) {
+                return Err(DatabaseError::new(
+                    format!("User {user} does not own language {language}"),
+                    ErrorKind::Forbidden,
+                ));
+            }
+            if validated.iter().any(|t| t.language == id) {
+                return Err(DatabaseError::new(
+                    format!(
+                        "Language {language} is transferred more than once"
+                    ),
+                    ErrorKind::InvalidInput,
+                ));
+            }
+            if new_owner == user
+                || !language.is_collaborator(conn, new_owner)?
+            {
+                return Err(DatabaseError::new(
+                    format!(
+                        "User {new_owner} is not a collaborator of language {language}"
+                    ),
+                    ErrorKind::InvalidInput,
+                ));
+            }
+            if language.collides_for(conn, new_owner)? {
+                return Err(language.collision(new_owner));
+            }
+            validated.push(NewAccountDeletionTransfer {
+                userid: user.to_string(),
+                language: id,
+                newowner: new_owner.to_string(),
+            });
+        }
+        Ok(validated)
+    }
+
+    /// Schedule the deletion of the account of `user` after the grace
+    /// period set by `ACCOUNT_DELETION_GRACE_DAYS`.
+    ///
+    /// The languages listed in `transfers` are then handed over to
+    /// their new owner, the other languages of the user are deleted
+    /// along with their account.
+    pub fn request(
+        db: &Database,
+        user: &str,
+        transfers: &[LanguageTransfer],
+    ) -> Result<AccountDeletion, DatabaseError> {
+        use accountdeletions::dsl;
+        let transfers =
+            AccountDeletion::validate_transfers(db, user, transfers)?;
+        let scheduled = Utc::now().naive_utc() + Duration::days(grace_days());
+        db.conn()?.transaction::<_, DatabaseError, _>(|conn| {
+            let deletion = diesel::insert_into(dsl::accountdeletions)
+                .values((dsl::userid.eq(user), dsl::scheduled.eq(scheduled)))
+                .on_conflict_do_nothing()
+                .get_result::<AccountDeletion>(conn)
+                .optional()?
+                .ok_or_else(|| {
+                    DatabaseError::new(
+                        format!("Deletion of user {user} is already scheduled"),
+                        ErrorKind::Conflict,
+                    )
+                })?;
+            if !transfers.is_empty() {
+                diesel::insert_into(accountdeletiontransfers::table)
+                    .values(transfers)
+                    .execute(conn)?;
+            }
+            Ok(deletion)
+        })
+    }
+
+    /// Cancel the scheduled deletion of the account of `user`.
+    pub fn cancel(db: &Database, user: &str) -> Result<(), DatabaseError> {
+        use accountdeletions::dsl;
+        match diesel::delete(dsl::accountdeletions.find(user))
+            .execute(&mut db.conn()?)
+        {
+            Ok(0) => Err(DatabaseError::new(
+                format!("Deletion of user {user} is not scheduled"),
+                ErrorKind::NotFound,
+            )),
+            Ok(_) => Ok(()),
+            Err(e) => Err(DatabaseError::new(
+                format!("Failed to cancel deletion of user {user}: {e:?}"),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+
+    /// Scheduled deletion of the account of `user`, if any.
+    pub fn pending(
+        db: &Database,
+        user: &str,
+    ) -> Result<Option<AccountDeletion>, DatabaseError> {
+        use accountdeletions::dsl;
+        dsl::accountdeletions
+            .find(user)
+            .first::<AccountDeletion>(&mut db.conn()?)
+            .optional()
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve deletion of user {user}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Users deleted from the database whose account still has to be
+    /// deleted from the authentication provider, oldest first.
+    pub fn remote_pending(db: &Database) -> Result<Vec<String>, DatabaseError> {
+        use remoteaccountdeletions::dsl;
+        dsl::remoteaccountdeletions
+            .select(dsl::userid)
+            .order(dsl::deleted.asc())
+            .load::<String>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve pending remote deletions: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Forget the pending remote deletion of `user` once the
+    /// authentication provider deleted its account.
+    pub fn remote_done(db: &Database, user: &str) -> Result<(), DatabaseError> {
+        diesel::delete(remoteaccountdeletions::table.find(user))
+            .execute(&mut db.conn()?)?;
+        Ok(())
+    }
+
+    /// Deletions whose grace period is over.
+    pub fn due(db: &Database) -> Result<Vec<AccountDeletion>, DatabaseError> {
+        use accountdeletions::dsl;
+        dsl::accountdeletions
+            .filter(dsl::scheduled.le(Utc::now().naive_utc()))
+            .order(dsl::scheduled.asc())
+            .load::<AccountDeletion>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to retrieve due account deletions: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// ID of the user whose account is deleted.
+    pub fn user(&self) -> &str {
+        &self.userid
+    }
+
+    /// Hand the languages over and delete the account.
+    ///
+    /// A language which can no longer be transferred, for instance
+    /// because its new owner created a language with the same name in
+    /// the meantime, is deleted with the account, along with the
+    /// history of every deleted language.
+    ///
+    /// With `remote`, the deletion of the account from the
+    /// authentication provider is recorded as pending, see
+    /// [`AccountDeletion::remote_pending`].
+    pub fn execute(
+        &self,
+        db: &Database,
+        remote: bool,
+    ) -> Result<(), DatabaseError> {
+        let user = self.userid.as_str();
+        db.conn()?.transaction::<_, DatabaseError, _>(|conn| {
+            let transfers = accountdeletiontransfers::table
+                .filter(accountdeletiontransfers::userid.eq(user))
+                .load::<AccountDeletionTransfer>(conn)?;
+            for transfer in transfers {
+                let Some(language) = languages::table
+                    .find(transfer.language)
+                    .first::<Language>(conn)
+                    .optional()?
+                else {
+                    warn!(
+                        "Language {} was deleted before being transferred",
+                        transfer.language
+                    );
+                    continue;
+                };
+                if let Err(e) = conn.transaction::<_, DatabaseError, _>(|conn| {
+                    language.transfer_unchecked(conn, user, &transfer.newowner)
+                }) {
+                    warn!(
+                        "Could not transfer language {language} to {}, deleting it: {e:?}",
+                        transfer.newowner
+                    );
+                }
+            }
+            // The history of languages outlives them, but not the
+            // account of their owner
+            let owned = languages::table
+                .filter(languages::owner.eq(user))
+                .select(languages::id);
+            diesel::delete(
+                languagerevisions::table
+                    .filter(languagerevisions::language.eq_any(owned)),
+            )
+            .execute(conn)?;
+            if remote {
+                diesel::insert_into(remoteaccountdeletions::table)
+                    .values(remoteaccountdeletions::userid.eq(user))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+            Database::delete_user(conn, user)?;
+            info!("Deleted account of user {user}");
+            Ok(())
+        })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl AccountDeletion {
+    #[graphql(description = "Time at which the deletion was requested")]
+    fn requested(&self) -> String {
+        self.requested.to_string()
+    }
+
+    #[graphql(
+        description = "Time after which the account is deleted, unless the deletion is cancelled"
+    )]
+    fn scheduled(&self) -> String {
+        self.scheduled.to_string()
+    }
+
+    #[graphql(
+        description = "Languages handed over to a collaborator, the others are deleted"
+    )]
+    fn transfers(
+        &self,
+        context: &Context,
+    ) -> FieldResult<Vec<ScheduledTransfer>> {
+        accountdeletiontransfers::table
+            .filter(accountdeletiontransfers::userid.eq(&self.userid))
+            .load::<AccountDeletionTransfer>(&mut context.db.conn()?)
+            .map(|transfers| {
+                transfers
+                    .into_iter()
+                    .map(|transfer| ScheduledTransfer {
+                        language: transfer.language,
+                        new_owner: transfer.newowner,
+                    })
+                    .collect()
+            })
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve transfers of user {}: {e:?}",
+                        self.userid
+                    ),
+                    ErrorKind::Internal,
+                )
+                .into()
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledTransfer {
+    language: Uuid,
+    new_owner: String,
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl ScheduledTransfer {
+    #[graphql(description = "Language handed over")]
+    fn language(&self, context: &Context) -> FieldResult<Language> {
+        Language::find(&context.db, self.language).map_err(Into::into)
+    }
+
+    #[graphql(description = "Future owner of the language")]
+    fn new_owner(&self, context: &Context) -> FieldResult<Option<User>> {
+        context.db.user(&self.new_owner).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn remote_deletions_are_pending_until_done() {
+        let db = testing::database();
+        let user = testing::user(&mut db.conn().unwrap());
+        let deletion = AccountDeletion::request(&db, &user, &[]).unwrap();
+        deletion.execute(&db, true).unwrap();
+        assert!(AccountDeletion::remote_pending(&db)
+            .unwrap()
+            .contains(&user));
+        AccountDeletion::remote_done(&db, &user).unwrap();
+        assert!(!AccountDeletion::remote_pending(&db)
+            .unwrap()
+            .contains(&user));
+    }
+}
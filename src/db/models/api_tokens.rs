@@ -0,0 +1,321 @@
+use super::super::schema;
+use super::{languages::Language, persisted_queries::sha256};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use juniper::{FieldResult, GraphQLEnum, GraphQLInputObject, GraphQLObject};
+use tracing::info;
+use uuid::Uuid;
+
+use std::str::FromStr;
+
+use schema::apitokens;
+
+/// Prefix of API tokens, telling them apart from other bearer tokens.
+pub const API_TOKEN_PREFIX: &str = "ordabok_";
+
+/// Maximum length of the name of a token, in characters.
+const MAX_NAME_LENGTH: usize = 255;
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Apitokenscope"]
+pub enum ApiTokenScope {
+    #[graphql(description = "Only run queries")]
+    Read,
+    #[graphql(description = "Run queries and mutations")]
+    Write,
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct NewApiToken {
+    #[graphql(description = "Name of the token, to remember what it is for")]
+    pub name: String,
+    #[graphql(description = "What the token allows to do")]
+    pub scope: ApiTokenScope,
+    #[graphql(
+        description = "UUID of the only language the token can modify, if any"
+    )]
+    pub language: Option<String>,
+    #[graphql(
+        description = "Amount of days after which the token expires, never if null"
+    )]
+    pub expires_in_days: Option<i32>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = apitokens)]
+struct NewApiTokenInternal {
+    owner: String,
+    name: String,
+    hash: String,
+    scope: ApiTokenScope,
+    language: Option<Uuid>,
+    expires: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject, Debug, Clone)]
+#[graphql(context = Context, description = "Newly created API token")]
+pub struct CreatedApiToken {
+    #[graphql(
+        description = "The token itself, to send as `Authorization: Bearer <token>`. It cannot be retrieved again"
+    )]
+    token: String,
+    #[graphql(description = "Description of the token")]
+    api_token: ApiToken,
+}
+
+impl NewApiToken {
+    /// Create the token on behalf of `owner`.
+    pub fn create(
+        &self,
+        db: &Database,
+        owner: &str,
+    ) -> Result<CreatedApiToken, DatabaseError> {
+        let name = self.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(DatabaseError::new(
+                format!(
+                    "Token name must be between 1 and {MAX_NAME_LENGTH} characters long"
+                ),
+                ErrorKind::InvalidInput,
+            ));
+        }
+        let language = match &self.language {
+            Some(language) => {
+                let id = Uuid::from_str(language).map_err(|e| {
+                    DatabaseError::new(
+                        format!(
+                            "Could not parse {language} as a valid UUID: {e:?}"
+                        ),
+                        ErrorKind::InvalidInput,
+                    )
+                })?;
+                Some(Language::find(db, id)?.uuid())
+            }
+            None => None,
+        };
+        let expires = match self.expires_in_days {
+            Some(days) if days <= 0 => {
+                return Err(DatabaseError::new(
+                    "Tokens must expire in at least one day",
+                    ErrorKind::InvalidInput,
+                ))
+            }
+            Some(days) => Some(
+                Utc::now()
+                    .naive_utc()
+                    .checked_add_signed(Duration::days(i64::from(days)))
+                    .ok_or_else(|| {
+                        DatabaseError::new(
+                            format!("Tokens cannot expire in {days} days"),
+                            ErrorKind::InvalidInput,
+                        )
+                    })?,
+            ),
+            None => None,
+        };
+        let token = format!(
+            "{API_TOKEN_PREFIX}{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let api_token = diesel::insert_into(apitokens::table)
+            .values(NewApiTokenInternal {
+                owner: owner.to_string(),
+                name: name.to_string(),
+                hash: sha256(&token),
+                scope: self.scope,
+                language,
+                expires,
+            })
+            .get_result::<ApiToken>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to create API token for {owner}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })?;
+        Ok(CreatedApiToken { token, api_token })
+    }
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct ApiToken {
+    id: Uuid,
+    owner: String,
+    name: String,
+    #[allow(dead_code)]
+    hash: String,
+    scope: ApiTokenScope,
+    language: Option<Uuid>,
+    created: NaiveDateTime,
+    expires: Option<NaiveDateTime>,
+    last_used: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Whether the token allows running mutations.
+    pub fn can_write(&self) -> bool {
+        self.scope == ApiTokenScope::Write
+    }
+
+    /// Whether the token allows modifying any data, not only a single
+    /// language.
+    pub fn can_write_anything(&self) -> bool {
+        self.can_write() && self.language.is_none()
+    }
+
+    /// Whether the token allows modifying the language `language`.
+    pub fn may_write(&self, language: Uuid) -> bool {
+        self.can_write() && self.language.map_or(true, |l| l == language)
+    }
+
+    /// Find the unexpired token `token`, recording that it was used.
+    pub fn authenticate(
+        db: &Database,
+        token: &str,
+    ) -> Result<Option<ApiToken>, DatabaseError> {
+        use apitokens::dsl;
+        let now = Utc::now().naive_utc();
+        diesel::update(
+            dsl::apitokens
+                .filter(dsl::hash.eq(sha256(token)))
+                .filter(dsl::expires.is_null().or(dsl::expires.gt(now))),
+        )
+        .set(dsl::last_used.eq(now))
+        .get_result::<ApiToken>(&mut db.conn()?)
+        .optional()
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to check API token: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+    }
+
+    /// Tokens of `owner`, most recent first.
+    pub fn list(
+        db: &Database,
+        owner: &str,
+    ) -> Result<Vec<ApiToken>, DatabaseError> {
+        use apitokens::dsl;
+        dsl::apitokens
+            .filter(dsl::owner.eq(owner))
+            .order(dsl::created.desc())
+            .load::<ApiToken>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to retrieve API tokens of {owner}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Revoke the token `id` of `owner`, which can no longer be used.
+    pub fn revoke(
+        db: &Database,
+        owner: &str,
+        id: Uuid,
+    ) -> Result<ApiToken, DatabaseError> {
+        use apitokens::dsl;
+        diesel::delete(dsl::apitokens.find(id).filter(dsl::owner.eq(owner)))
+            .get_result::<ApiToken>(&mut db.conn()?)
+            .optional()
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to revoke API token {id}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })?
+            .ok_or_else(|| {
+                DatabaseError::new(
+                    format!("User {owner} has no API token {id}"),
+                    ErrorKind::NotFound,
+                )
+            })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl ApiToken {
+    #[graphql(description = "Unique identifier of the token")]
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+
+    #[graphql(description = "Name of the token")]
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[graphql(description = "What the token allows to do")]
+    fn scope(&self) -> ApiTokenScope {
+        self.scope
+    }
+
+    #[graphql(
+        description = "Only language the token can modify, null if it is not restricted"
+    )]
+    fn language(&self, context: &Context) -> FieldResult<Option<Language>> {
+        match self.language {
+            Some(language) => Language::find(&context.db, language)
+                .map(Some)
+                .map_err(|e| {
+                    info!("Failed to retrieve language {language}: {e:?}");
+                    e.into()
+                }),
+            None => Ok(None),
+        }
+    }
+
+    #[graphql(description = "Time at which the token was created")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(description = "Time at which the token expires, if ever")]
+    fn expires(&self) -> Option<String> {
+        self.expires.map(|expires| expires.to_string())
+    }
+
+    #[graphql(description = "Last time the token was used, if ever")]
+    fn last_used(&self) -> Option<String> {
+        self.last_used.map(|last_used| last_used.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn far_expiration_dates_are_refused() {
+        let db = testing::database();
+        let owner = testing::user(&mut db.conn().unwrap());
+        let token = NewApiToken {
+            name: "test".to_string(),
+            scope: ApiTokenScope::Read,
+            language: None,
+            expires_in_days: Some(i32::MAX),
+        };
+        let err = token.create(&db, &owner).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        let token = NewApiToken {
+            expires_in_days: Some(30),
+            ..token
+        };
+        assert!(token.create(&db, &owner).is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use super::super::schema;
+use super::{page_size, users::User};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use diesel::prelude::*;
+use juniper::{FieldResult, GraphQLEnum};
+
+use schema::auditlog;
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Auditaction"]
+pub enum AuditAction {
+    #[graphql(description = "A user was added to the database")]
+    CreateUser,
+    #[graphql(description = "A user was deleted from the database")]
+    DeleteUser,
+    #[graphql(description = "All users were listed")]
+    ListUsers,
+    #[graphql(description = "A user was granted or denied admin rights")]
+    SetAdmin,
+    #[graphql(description = "Users were synchronised with Appwrite")]
+    ReconcileUsers,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = auditlog)]
+struct NewAuditEntry<'a> {
+    admin: &'a str,
+    action: AuditAction,
+    target: Option<&'a str>,
+    details: Option<serde_json::Value>,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct AuditEntry {
+    id: i32,
+    admin: Option<String>,
+    action: AuditAction,
+    target: Option<String>,
+    details: Option<serde_json::Value>,
+    created: chrono::NaiveDateTime,
+}
+
+impl AuditEntry {
+    /// Perform `action` on behalf of `admin`, recording it in the audit
+    /// log in the same transaction.
+    pub fn perform<T, F>(
+        db: &Database,
+        admin: &str,
+        action: AuditAction,
+        target: Option<&str>,
+        details: Option<serde_json::Value>,
+        f: F,
+    ) -> Result<T, DatabaseError>
+    where
+        F: FnOnce(&mut PgConnection) -> Result<T, DatabaseError>,
+    {
+        db.conn()?.transaction::<_, DatabaseError, _>(|conn| {
+            let result = f(conn)?;
+            diesel::insert_into(auditlog::table)
+                .values(NewAuditEntry {
+                    admin,
+                    action,
+                    target,
+                    details,
+                })
+                .execute(conn)
+                .map_err(|e| {
+                    DatabaseError::new(
+                        format!("Failed to record {action:?} by {admin} in the audit log: {e:?}"),
+                        ErrorKind::Internal,
+                    )
+                })?;
+            Ok(result)
+        })
+    }
+
+    /// Entries of the audit log, most recent first.
+    ///
+    /// Only entries older than the entry `after` are returned, at most
+    /// `first` of them.
+    pub fn list(
+        db: &Database,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> Result<Vec<AuditEntry>, DatabaseError> {
+        use auditlog::dsl;
+        let mut query = dsl::auditlog.into_boxed();
+        if let Some(after) = after {
+            query = query.filter(dsl::id.lt(after));
+        }
+        query
+            .order(dsl::id.desc())
+            .limit(page_size(first))
+            .load::<AuditEntry>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to retrieve the audit log: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl AuditEntry {
+    #[graphql(description = "Number of the entry")]
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[graphql(
+        description = "Admin who performed the action, null if they were deleted since"
+    )]
+    fn admin(&self, context: &Context) -> FieldResult<Option<User>> {
+        match &self.admin {
+            Some(admin) => context.db.user(admin).map_err(Into::into),
+            None => Ok(None),
+        }
+    }
+
+    #[graphql(description = "What the admin did")]
+    fn action(&self) -> AuditAction {
+        self.action
+    }
+
+    #[graphql(description = "What the action was performed on, if anything")]
+    fn target(&self) -> Option<String> {
+        self.target.clone()
+    }
+
+    #[graphql(description = "Details of the action, as a JSON object")]
+    fn details(&self) -> Option<String> {
+        self.details.as_ref().map(ToString::to_string)
+    }
+
+    #[graphql(description = "Time at which the action was performed")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+}
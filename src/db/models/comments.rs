@@ -0,0 +1,418 @@
+use super::super::schema;
+use super::{languages::Language, page_size, users::User, words::Word};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use diesel::{pg::Pg, prelude::*};
+use juniper::{FieldResult, GraphQLInputObject};
+use tracing::info;
+use uuid::Uuid;
+
+use std::str::FromStr;
+
+use schema::comments;
+
+/// Maximum length of a comment, in characters.
+const MAX_BODY_LENGTH: usize = 10_000;
+
+fn validate_body(body: &str) -> Result<(), DatabaseError> {
+    if body.trim().is_empty() {
+        Err(DatabaseError::new(
+            "Comment cannot be empty",
+            ErrorKind::InvalidInput,
+        ))
+    } else if body.chars().count() > MAX_BODY_LENGTH {
+        Err(DatabaseError::new(
+            format!(
+                "Comment cannot be longer than {MAX_BODY_LENGTH} characters"
+            ),
+            ErrorKind::InvalidInput,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+#[graphql(
+    description = "Comment to post on a word or a language, or in reply to another comment"
+)]
+pub struct NewComment {
+    #[graphql(
+        description = "UUID of the language to comment on, ignored when replying"
+    )]
+    pub language: Option<String>,
+    #[graphql(
+        description = "UUID of the word to comment on, ignored when replying"
+    )]
+    pub word: Option<String>,
+    #[graphql(description = "Number of the comment to reply to")]
+    pub parent: Option<i32>,
+    #[graphql(description = "Content of the comment, in Markdown format")]
+    pub body: String,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = comments)]
+struct NewCommentInternal {
+    language: Uuid,
+    word: Option<Uuid>,
+    parent: Option<i32>,
+    author: String,
+    body: String,
+}
+
+/// Check that `user`, anonymous if `None`, can read the language
+/// `language`, whose comments would otherwise reveal it.
+fn check_readable(
+    db: &Database,
+    language: Uuid,
+    user: Option<&str>,
+) -> Result<(), DatabaseError> {
+    match Language::find_with_trashed(db, language)? {
+        Some(language) if language.is_readable_by(user) => Ok(()),
+        _ => Err(DatabaseError::new(
+            "Language not found or not public",
+            ErrorKind::NotFound,
+        )),
+    }
+}
+
+impl NewComment {
+    fn parse(id: &str) -> Result<Uuid, DatabaseError> {
+        Uuid::from_str(id).map_err(|e| {
+            DatabaseError::new(
+                format!("Could not parse {id} as a valid UUID: {e:?}"),
+                ErrorKind::InvalidInput,
+            )
+        })
+    }
+
+    /// Post the comment on behalf of `author`.
+    ///
+    /// Replies are attached to the same word or language as the
+    /// comment they answer.
+    pub fn post(
+        &self,
+        context: &Context,
+        author: &str,
+    ) -> Result<Comment, DatabaseError> {
+        validate_body(&self.body)?;
+        let (language, word) = match (self.parent, &self.word, &self.language) {
+            (Some(parent), _, _) => {
+                let parent = Comment::find(&context.db, parent)?;
+                (parent.language, parent.word)
+            }
+            (None, Some(word), _) => {
+                let word = Word::find(&context.db, NewComment::parse(word)?)?;
+                (word.language_id(), Some(word.uuid()))
+            }
+            (None, None, Some(language)) => {
+                let language =
+                    Language::find(&context.db, NewComment::parse(language)?)?;
+                (language.uuid(), None)
+            }
+            (None, None, None) => {
+                return Err(DatabaseError::new(
+                    "A comment needs a word, a language or a parent comment",
+                    ErrorKind::InvalidInput,
+                ))
+            }
+        };
+        check_readable(&context.db, language, Some(author))?;
+        let comment = NewCommentInternal {
+            language,
+            word,
+            parent: self.parent,
+            author: author.to_string(),
+            body: self.body.clone(),
+        };
+        diesel::insert_into(comments::dsl::comments)
+            .values(comment.clone())
+            .get_result::<Comment>(&mut context.db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to insert comment {comment:?}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct Comment {
+    id: i32,
+    language: Uuid,
+    word: Option<Uuid>,
+    parent: Option<i32>,
+    author: String,
+    body: String,
+    created: chrono::NaiveDateTime,
+    edited: Option<chrono::NaiveDateTime>,
+}
+
+impl Comment {
+    fn find(db: &Database, id: i32) -> Result<Comment, DatabaseError> {
+        comments::dsl::comments
+            .find(id)
+            .first::<Comment>(&mut db.conn()?)
+            .map_err(|e| match e {
+                diesel::NotFound => DatabaseError::new(
+                    format!("Comment {id} not found"),
+                    ErrorKind::NotFound,
+                ),
+                e => DatabaseError::new(
+                    format!("Error fetching comment {id} from database: {e:?}"),
+                    ErrorKind::Internal,
+                ),
+            })
+    }
+
+    /// Comments of a thread, oldest first.
+    ///
+    /// Only comments posted after the comment `after` are returned, at
+    /// most `first` of them.
+    fn page(
+        db: &Database,
+        query: comments::BoxedQuery<'static, Pg>,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> Result<Vec<Comment>, DatabaseError> {
+        use comments::dsl;
+        let query = match after {
+            Some(after) => query.filter(dsl::id.gt(after)),
+            None => query,
+        };
+        query
+            .order(dsl::id.asc())
+            .limit(page_size(first))
+            .load::<Comment>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to retrieve comments: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Comments starting a thread on a language itself, provided the
+    /// user of `context` can read it.
+    pub fn of_language(
+        context: &Context,
+        language: Uuid,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> Result<Vec<Comment>, DatabaseError> {
+        use comments::dsl;
+        check_readable(
+            &context.db,
+            language,
+            context.user_auth().map(String::as_str),
+        )?;
+        let query = dsl::comments
+            .filter(dsl::language.eq(language))
+            .filter(dsl::word.is_null())
+            .filter(dsl::parent.is_null())
+            .into_boxed();
+        Comment::page(&context.db, query, first, after)
+    }
+
+    /// Comments starting a thread on a word, provided the user of
+    /// `context` can read its language.
+    pub fn of_word(
+        context: &Context,
+        word: Uuid,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> Result<Vec<Comment>, DatabaseError> {
+        use comments::dsl;
+        check_readable(
+            &context.db,
+            Word::find(&context.db, word)?.language_id(),
+            context.user_auth().map(String::as_str),
+        )?;
+        let query = dsl::comments
+            .filter(dsl::word.eq(word))
+            .filter(dsl::parent.is_null())
+            .into_boxed();
+        Comment::page(&context.db, query, first, after)
+    }
+
+    /// Edit a comment written by `user`.
+    pub fn edit(
+        context: &Context,
+        id: i32,
+        user: &str,
+        body: String,
+    ) -> Result<Comment, DatabaseError> {
+        use comments::dsl;
+        validate_body(&body)?;
+        let comment = Comment::find(&context.db, id)?;
+        if comment.author != user {
+            return Err(DatabaseError::new(
+                format!("User {user} cannot edit comment {id} of another user"),
+                ErrorKind::Forbidden,
+            ));
+        }
+        diesel::update(dsl::comments.find(id))
+            .set((
+                dsl::body.eq(body),
+                dsl::edited.eq(diesel::dsl::now.nullable()),
+            ))
+            .get_result::<Comment>(&mut context.db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to edit comment {id}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Delete a comment and its replies.
+    ///
+    /// Comments can be deleted by their author or by the owner of the
+    /// language they were posted on.
+    pub fn delete(
+        context: &Context,
+        id: i32,
+        user: &str,
+    ) -> Result<(), DatabaseError> {
+        use comments::dsl;
+        let comment = Comment::find(&context.db, id)?;
+        if comment.author != user
+            && !Language::find(&context.db, comment.language)?.is_owned_by(user)
+        {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} cannot delete comment {id} of another user"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        match diesel::delete(dsl::comments.find(id))
+            .execute(&mut context.db.conn()?)
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(DatabaseError::new(
+                format!("Failed to delete comment {id}: {e:?}"),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl Comment {
+    #[graphql(description = "Number of the comment")]
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[graphql(description = "User who wrote the comment")]
+    fn author(&self, context: &Context) -> FieldResult<Option<User>> {
+        context.db.user(&self.author).map_err(Into::into)
+    }
+
+    #[graphql(description = "Content of the comment, in Markdown format")]
+    fn body(&self) -> String {
+        self.body.clone()
+    }
+
+    #[graphql(description = "Time at which the comment was posted")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(
+        description = "Time at which the comment was last edited, null if it never was"
+    )]
+    fn edited(&self) -> Option<String> {
+        self.edited.map(|edited| edited.to_string())
+    }
+
+    #[graphql(description = "Comment this comment replies to")]
+    fn parent(&self, context: &Context) -> Option<Comment> {
+        match self.parent {
+            Some(parent) => match Comment::find(&context.db, parent) {
+                Ok(comment) => Some(comment),
+                Err(e) => {
+                    info!("Failed to retrieve comment {parent}: {e:?}");
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    #[graphql(
+        description = "Replies to the comment, oldest first",
+        arguments(
+            first(description = "Maximum amount of replies to return"),
+            after(description = "Only return replies posted after this one")
+        )
+    )]
+    fn replies(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<Vec<Comment>> {
+        use comments::dsl;
+        let query = dsl::comments.filter(dsl::parent.eq(self.id)).into_boxed();
+        Comment::page(&context.db, query, first, after).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::languages::Release;
+    use super::*;
+    use crate::db::{schema::languages, testing};
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn comments_of_private_languages_stay_private() {
+        let db = testing::database();
+        let (owner, other, language) = {
+            let conn = &mut db.conn().unwrap();
+            let owner = testing::user(conn);
+            let language = testing::language(conn, &owner);
+            diesel::update(languages::table.find(language))
+                .set(languages::release.eq(Release::Private))
+                .execute(conn)
+                .unwrap();
+            (owner, testing::user(conn), language)
+        };
+        let comment = NewComment {
+            language: Some(language.to_string()),
+            word: None,
+            parent: None,
+            body: "Mjá?".to_string(),
+        };
+        let owner = Context::of_user(db.clone(), &owner);
+        let other = Context::of_user(db.clone(), &other);
+        let posted = comment.post(&owner, owner.user_auth().unwrap()).unwrap();
+        let err = comment
+            .post(&other, other.user_auth().unwrap())
+            .unwrap_err();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+        let reply = NewComment {
+            parent: Some(posted.id),
+            ..comment
+        };
+        let err = reply.post(&other, other.user_auth().unwrap()).unwrap_err();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+
+        assert_eq!(
+            1,
+            Comment::of_language(&owner, language, None, None)
+                .unwrap()
+                .len()
+        );
+        let err =
+            Comment::of_language(&other, language, None, None).unwrap_err();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+    }
+}
@@ -0,0 +1,741 @@
+use super::super::schema;
+use super::{
+    languages::Language,
+    lift::{self, LiftHeader, DEFAULT_NORM_LANG},
+    ontolex::{LexiconWriter, Syntax},
+    tei,
+    words::{PartOfSpeech, Word, WordRelationship},
+};
+use crate::db::{Database, DatabaseError, ErrorKind};
+
+use diesel::prelude::*;
+use serde::{Serialize, Serializer};
+use uuid::Uuid;
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use schema::{langtranslatesto, liftentries, liftheaders, wordrelation, words};
+
+/// Amount of words loaded from the database at once.
+const PAGE_SIZE: i64 = 500;
+
+/// Columns of CSV and TSV exports that are not fields of words: the
+/// exported ID of words, and the IDs of the words they relate to,
+/// which imports resolve among the imported words first.
+pub const EXPORT_ONLY_COLUMNS: [&str; 3] = ["id", "definitions", "related"];
+
+/// Note ending exports that failed partway, once the data already
+/// written cannot be taken back.
+pub const EXPORT_FAILED: &str = "Export failed: the data above is incomplete";
+
+/// Columns of CSV and TSV exports, readable by the bulk importer.
+pub const CSV_COLUMNS: [&str; 14] = [
+    "id",
+    "norm",
+    "native",
+    "lemma",
+    "partofspeech",
+    "audio",
+    "video",
+    "image",
+    "description",
+    "etymology",
+    "lusage",
+    "morphology",
+    "definitions",
+    "related",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values, one word per row
+    Csv,
+    /// Tab-separated values, one word per row
+    Tsv,
+    /// A single JSON document holding the language and its words
+    Json,
+    /// JSON Lines, the language then one word per line
+    Jsonl,
+    /// Lexicon Interchange FormaT, one entry per word
+    Lift,
+    /// TEI Lex-0 document, one entry per word
+    Tei,
+    /// OntoLex-Lemon lexicon as RDF Turtle
+    Turtle,
+    /// OntoLex-Lemon lexicon as JSON-LD
+    JsonLd,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            "json" => Ok(Self::Json),
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            "lift" => Ok(Self::Lift),
+            "tei" | "tei-lex-0" => Ok(Self::Tei),
+            "ttl" | "turtle" => Ok(Self::Turtle),
+            "jsonld" | "json-ld" => Ok(Self::JsonLd),
+            _ => Err(format!("Unknown export format {s}")),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Write [`EXPORT_FAILED`] after the incomplete data of a failed
+    /// export, as a line the importer rejects in CSV and TSV, or as a
+    /// comment making JSON, JSON Lines and unfinished XML documents
+    /// invalid.
+    pub fn write_failure<W: Write>(self, out: &mut W) -> io::Result<()> {
+        match self {
+            Self::Csv | Self::Tsv | Self::Jsonl | Self::Turtle => {
+                writeln!(out, "\n# {EXPORT_FAILED}")
+            }
+            Self::Json | Self::JsonLd => {
+                writeln!(out, "\n/* {EXPORT_FAILED} */")
+            }
+            Self::Lift | Self::Tei => {
+                writeln!(out, "\n<!-- {EXPORT_FAILED} -->")
+            }
+        }
+    }
+}
+
+/// Escape `text` for use in XML content and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[derive(Queryable, Debug, Clone)]
+struct ExportedWord {
+    id: Uuid,
+    norm: String,
+    native: Option<String>,
+    lemma: Option<Uuid>,
+    partofspeech: PartOfSpeech,
+    audio: Option<String>,
+    video: Option<String>,
+    image: Option<String>,
+    description: Option<String>,
+    etymology: Option<String>,
+    lusage: Option<String>,
+    morphology: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LemmaReference<'a> {
+    pub id: Uuid,
+    pub norm: &'a str,
+}
+
+fn serialize_tag<S: Serializer>(
+    partofspeech: &PartOfSpeech,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(partofspeech.tag())
+}
+
+/// Word a relation of an exported word points to.
+#[derive(Debug, Clone)]
+pub struct RelationTarget {
+    pub id: Uuid,
+    pub norm: String,
+    pub language: Uuid,
+    /// Writing system of the norm of the word's language
+    pub lang: String,
+}
+
+/// Exported form of a word along with its lemma and relations.
+#[derive(Serialize, Debug)]
+pub struct WordRecord<'a> {
+    pub id: Uuid,
+    pub norm: &'a str,
+    pub native: Option<&'a str>,
+    pub lemma: Option<LemmaReference<'a>>,
+    #[serde(serialize_with = "serialize_tag")]
+    pub partofspeech: &'a PartOfSpeech,
+    pub audio: Option<&'a str>,
+    pub video: Option<&'a str>,
+    pub image: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub etymology: Option<&'a str>,
+    pub lusage: Option<&'a str>,
+    pub morphology: Option<&'a str>,
+    pub definitions: &'a [Uuid],
+    pub related: &'a [Uuid],
+}
+
+impl WordRecord<'_> {
+    fn csv(&self) -> [String; 14] {
+        let text = |value: Option<&str>| value.unwrap_or_default().to_string();
+        let ids = |ids: &[Uuid]| {
+            ids.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        [
+            self.id.to_string(),
+            self.norm.to_string(),
+            text(self.native),
+            text(self.lemma.as_ref().map(|lemma| lemma.norm)),
+            self.partofspeech.tag().to_string(),
+            text(self.audio),
+            text(self.video),
+            text(self.image),
+            text(self.description),
+            text(self.etymology),
+            text(self.lusage),
+            text(self.morphology),
+            ids(self.definitions),
+            ids(self.related),
+        ]
+    }
+}
+
+/// Words of a language loaded at once, along with the norms of their
+/// lemmas, the targets of their relations and, depending on the
+/// format, what was kept from the LIFT entries they were imported
+/// from or the norms of the words they relate to.
+#[derive(Default)]
+struct Page {
+    words: Vec<ExportedWord>,
+    lemmas: HashMap<Uuid, String>,
+    definitions: HashMap<Uuid, Vec<Uuid>>,
+    related: HashMap<Uuid, Vec<Uuid>>,
+    lift: HashMap<Uuid, serde_json::Value>,
+    targets: HashMap<Uuid, RelationTarget>,
+}
+
+impl Page {
+    /// Load the words of `language` following the word `after`,
+    /// ordered by their UUID, or only the word `only` if set.
+    fn load(
+        conn: &mut PgConnection,
+        language: Uuid,
+        after: Option<Uuid>,
+        only: Option<Uuid>,
+        format: ExportFormat,
+    ) -> QueryResult<Page> {
+        let mut query = Word::visible()
+            .filter(words::language.eq(language))
+            .select((
+                words::id,
+                words::norm,
+                words::native,
+                words::lemma,
+                words::partofspeech,
+                words::audio,
+                words::video,
+                words::image,
+                words::description,
+                words::etymology,
+                words::lusage,
+                words::morphology,
+            ))
+            .order(words::id)
+            .limit(PAGE_SIZE);
+        if let Some(after) = after {
+            query = query.filter(words::id.gt(after));
+        }
+        if let Some(only) = only {
+            query = query.filter(words::id.eq(only));
+        }
+        let exported = query.load::<ExportedWord>(conn)?;
+        if exported.is_empty() {
+            return Ok(Page::default());
+        }
+        let ids = exported.iter().map(|word| word.id).collect::<Vec<_>>();
+        let lemmas = Word::visible()
+            .filter(
+                words::id.eq_any(
+                    exported
+                        .iter()
+                        .filter_map(|word| word.lemma)
+                        .collect::<Vec<_>>(),
+                ),
+            )
+            .select((words::id, words::norm))
+            .load::<(Uuid, String)>(conn)?
+            .into_iter()
+            .collect();
+        let mut definitions: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let mut target_ids = Vec::new();
+        let mut related: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (source, target, relationship) in wordrelation::table
+            .filter(wordrelation::wordsource.eq_any(&ids))
+            .filter(
+                wordrelation::wordtarget.eq_any(
+                    words::table
+                        .filter(words::deleted.is_null())
+                        .select(words::id),
+                ),
+            )
+            .select((
+                wordrelation::wordsource,
+                wordrelation::wordtarget,
+                wordrelation::relationship,
+            ))
+            .order(wordrelation::id)
+            .load::<(Uuid, Uuid, WordRelationship)>(conn)?
+        {
+            match relationship {
+                WordRelationship::Definition => &mut definitions,
+                WordRelationship::Related => &mut related,
+            }
+            .entry(source)
+            .or_default()
+            .push(target);
+            target_ids.push(target);
+        }
+        let lift = if format == ExportFormat::Lift {
+            liftentries::table
+                .filter(liftentries::word.eq_any(&ids))
+                .select((liftentries::word, liftentries::extras))
+                .load::<(Uuid, serde_json::Value)>(conn)?
+                .into_iter()
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let targets = if format == ExportFormat::Tei {
+            let targets = words::table
+                .filter(words::id.eq_any(target_ids))
+                .select((words::id, words::norm, words::language))
+                .load::<(Uuid, String, Uuid)>(conn)?;
+            let langs = liftheaders::table
+                .filter(
+                    liftheaders::language.eq_any(
+                        targets
+                            .iter()
+                            .map(|(_, _, language)| *language)
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+                .select((liftheaders::language, liftheaders::normlang))
+                .load::<(Uuid, String)>(conn)?
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+            targets
+                .into_iter()
+                .map(|(id, norm, language)| {
+                    let lang = langs.get(&language).map_or_else(
+                        || DEFAULT_NORM_LANG.to_string(),
+                        Clone::clone,
+                    );
+                    (
+                        id,
+                        RelationTarget {
+                            id,
+                            norm,
+                            language,
+                            lang,
+                        },
+                    )
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        Ok(Page {
+            words: exported,
+            lemmas,
+            definitions,
+            related,
+            lift,
+            targets,
+        })
+    }
+
+    fn record<'a>(&'a self, word: &'a ExportedWord) -> WordRecord<'a> {
+        WordRecord {
+            id: word.id,
+            norm: &word.norm,
+            native: word.native.as_deref(),
+            lemma: word.lemma.and_then(|id| {
+                self.lemmas.get(&id).map(|norm| LemmaReference { id, norm })
+            }),
+            partofspeech: &word.partofspeech,
+            audio: word.audio.as_deref(),
+            video: word.video.as_deref(),
+            image: word.image.as_deref(),
+            description: word.description.as_deref(),
+            etymology: word.etymology.as_deref(),
+            lusage: word.lusage.as_deref(),
+            morphology: word.morphology.as_deref(),
+            definitions: self
+                .definitions
+                .get(&word.id)
+                .map_or(&[][..], Vec::as_slice),
+            related: self.related.get(&word.id).map_or(&[][..], Vec::as_slice),
+        }
+    }
+}
+
+/// Export of all the words of a language.
+pub struct WordExport {
+    language: Language,
+    format: ExportFormat,
+}
+
+impl WordExport {
+    /// Export of the language `language` as `format`, whether or not
+    /// it is private.
+    pub fn new(
+        db: &Database,
+        language: Uuid,
+        format: ExportFormat,
+    ) -> Result<WordExport, DatabaseError> {
+        Ok(WordExport {
+            language: Language::find(db, language)?,
+            format,
+        })
+    }
+
+    pub fn language(&self) -> &Language {
+        &self.language
+    }
+
+    /// Call `write` on each page of words of the language, returning
+    /// the amount of words written.
+    ///
+    /// A connection is only checked out while a page is loaded, so that
+    /// clients slowly downloading exports do not hold on to them.
+    fn each_page<F>(
+        &self,
+        db: &Database,
+        mut write: F,
+    ) -> Result<usize, DatabaseError>
+    where
+        F: FnMut(&Page) -> std::io::Result<()>,
+    {
+        let mut count = 0;
+        let mut after = None;
+        loop {
+            let page = Page::load(
+                &mut db.conn()?,
+                self.language.uuid(),
+                after,
+                None,
+                self.format,
+            )
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve words of {}: {e:?}",
+                        self.language
+                    ),
+                    ErrorKind::Internal,
+                )
+            })?;
+            let Some(last) = page.words.last() else {
+                return Ok(count);
+            };
+            after = Some(last.id);
+            count += page.words.len();
+            write(&page).map_err(|e| self.write_error(&e))?;
+        }
+    }
+
+    fn write_error(&self, e: &std::io::Error) -> DatabaseError {
+        DatabaseError::new(
+            format!("Failed to write export of {}: {e:?}", self.language),
+            ErrorKind::Internal,
+        )
+    }
+
+    /// Header of JSON and JSON Lines exports.
+    fn json_header(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<serde_json::Value, DatabaseError> {
+        let translates_to = langtranslatesto::table
+            .filter(langtranslatesto::langfrom.eq(self.language.uuid()))
+            .select(langtranslatesto::langto)
+            .order(langtranslatesto::id)
+            .load::<Uuid>(conn)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve translation targets of {}: {e:?}",
+                        self.language
+                    ),
+                    ErrorKind::Internal,
+                )
+            })?;
+        Ok(serde_json::json!({
+            "language": self.language,
+            "translatesTo": translates_to,
+        }))
+    }
+
+    /// Write the words of the language to `out` page by page, so that
+    /// large dictionaries are never held in memory at once.
+    ///
+    /// Returns the amount of words exported.
+    pub fn write<W: Write>(
+        &self,
+        db: &Database,
+        out: &mut W,
+    ) -> Result<usize, DatabaseError> {
+        let count = match self.format {
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(if self.format == ExportFormat::Csv {
+                        b','
+                    } else {
+                        b'\t'
+                    })
+                    .from_writer(&mut *out);
+                writer
+                    .write_record(CSV_COLUMNS)
+                    .map_err(|e| self.write_error(&e.into()))?;
+                self.each_page(db, |page| {
+                    for word in &page.words {
+                        writer.write_record(page.record(word).csv())?;
+                    }
+                    writer.flush()
+                })?
+            }
+            ExportFormat::Json => {
+                // The header is written without its closing brace so
+                // that words can be appended to it.
+                let header = self.json_header(&mut db.conn()?)?.to_string();
+                write!(
+                    out,
+                    "{},\"words\":[",
+                    header.strip_suffix('}').unwrap_or(&header)
+                )
+                .map_err(|e| self.write_error(&e))?;
+                let mut first = true;
+                let count = self.each_page(db, |page| {
+                    for word in &page.words {
+                        if !first {
+                            out.write_all(b",")?;
+                        }
+                        first = false;
+                        serde_json::to_writer(&mut *out, &page.record(word))?;
+                    }
+                    Ok(())
+                })?;
+                out.write_all(b"]}\n").map_err(|e| self.write_error(&e))?;
+                count
+            }
+            ExportFormat::Jsonl => {
+                let header = self.json_header(&mut db.conn()?)?;
+                writeln!(out, "{header}").map_err(|e| self.write_error(&e))?;
+                self.each_page(db, |page| {
+                    for word in &page.words {
+                        serde_json::to_writer(&mut *out, &page.record(word))?;
+                        out.write_all(b"\n")?;
+                    }
+                    Ok(())
+                })?
+            }
+            ExportFormat::Lift => {
+                let header =
+                    LiftHeader::of(&mut db.conn()?, self.language.uuid())?;
+                lift::write_start(out, &header)
+                    .map_err(|e| self.write_error(&e))?;
+                let count = self.each_page(db, |page| {
+                    for word in &page.words {
+                        lift::write_entry(
+                            &mut *out,
+                            &header,
+                            &page.record(word),
+                            page.lift.get(&word.id),
+                        )?;
+                    }
+                    Ok(())
+                })?;
+                lift::write_end(out).map_err(|e| self.write_error(&e))?;
+                count
+            }
+            ExportFormat::Tei => {
+                let (metadata, header) = {
+                    let conn = &mut db.conn()?;
+                    (
+                        self.language.metadata(conn)?,
+                        LiftHeader::of(conn, self.language.uuid())?,
+                    )
+                };
+                tei::write_start(out, &metadata)
+                    .map_err(|e| self.write_error(&e))?;
+                let count = self.each_page(db, |page| {
+                    for word in &page.words {
+                        tei::write_entry(
+                            &mut *out,
+                            &header,
+                            metadata.id,
+                            &page.record(word),
+                            &page.targets,
+                        )?;
+                    }
+                    Ok(())
+                })?;
+                tei::write_end(out).map_err(|e| self.write_error(&e))?;
+                count
+            }
+            ExportFormat::Turtle | ExportFormat::JsonLd => {
+                let (metadata, header) = {
+                    let conn = &mut db.conn()?;
+                    (
+                        self.language.metadata(conn)?,
+                        LiftHeader::of(conn, self.language.uuid())?,
+                    )
+                };
+                let mut writer = LexiconWriter::new(self.syntax()?, header);
+                writer
+                    .write_start(out, Some(&metadata))
+                    .map_err(|e| self.write_error(&e))?;
+                let count = self.each_page(db, |page| {
+                    for word in &page.words {
+                        writer.write_entry(
+                            &mut *out,
+                            metadata.id,
+                            &page.record(word),
+                        )?;
+                    }
+                    Ok(())
+                })?;
+                writer.write_end(out).map_err(|e| self.write_error(&e))?;
+                count
+            }
+        };
+        out.flush().map_err(|e| self.write_error(&e))?;
+        Ok(count)
+    }
+
+    /// RDF syntax of the export, for OntoLex-Lemon formats only.
+    fn syntax(&self) -> Result<Syntax, DatabaseError> {
+        match self.format {
+            ExportFormat::Turtle => Ok(Syntax::Turtle),
+            ExportFormat::JsonLd => Ok(Syntax::JsonLd),
+            format => Err(DatabaseError::new(
+                format!("Words cannot be described alone as {format:?}"),
+                ErrorKind::InvalidInput,
+            )),
+        }
+    }
+
+    /// Write the lexical entry of the word `word` of the language to
+    /// `out`, as served at the IRI of the word.
+    ///
+    /// Only available for OntoLex-Lemon formats.
+    pub fn write_word<W: Write>(
+        &self,
+        db: &Database,
+        out: &mut W,
+        word: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let syntax = self.syntax()?;
+        let (header, page) = {
+            let conn = &mut db.conn()?;
+            (
+                LiftHeader::of(conn, self.language.uuid())?,
+                Page::load(
+                    conn,
+                    self.language.uuid(),
+                    None,
+                    Some(word),
+                    self.format,
+                )?,
+            )
+        };
+        let mut writer = LexiconWriter::new(syntax, header);
+        let Some(exported) = page.words.first() else {
+            return Err(DatabaseError::new(
+                format!("Word {word} not found in {}", self.language),
+                ErrorKind::NotFound,
+            ));
+        };
+        writer
+            .write_start(out, None)
+            .map_err(|e| self.write_error(&e))?;
+        writer
+            .write_entry(out, self.language.uuid(), &page.record(exported))
+            .map_err(|e| self.write_error(&e))?;
+        writer.write_end(out).map_err(|e| self.write_error(&e))?;
+        out.flush().map_err(|e| self.write_error(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+
+    /// Writer checking that a connection can be checked out of `db`
+    /// whenever something is written to it.
+    struct Probe<'a> {
+        db: &'a Database,
+        data: Vec<u8>,
+    }
+
+    impl Write for Probe<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            assert!(
+                self.db.conn().is_ok(),
+                "a connection is held while writing"
+            );
+            self.data.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn connections_are_given_back_before_writing() {
+        // The test database has a single connection, which is never
+        // available while an export holds it
+        let db = testing::database();
+        let (language, word) = {
+            let conn = &mut db.conn().unwrap();
+            let user = testing::user(conn);
+            let language = testing::language(conn, &user);
+            let word = testing::word(conn, language, "word", None);
+            let other = testing::word(conn, language, "other", Some(word));
+            testing::relate(conn, word, other, WordRelationship::Related);
+            (language, word)
+        };
+        for format in [
+            ExportFormat::Csv,
+            ExportFormat::Tsv,
+            ExportFormat::Json,
+            ExportFormat::Jsonl,
+            ExportFormat::Lift,
+            ExportFormat::Tei,
+            ExportFormat::Turtle,
+            ExportFormat::JsonLd,
+        ] {
+            let export = WordExport::new(&db, language, format).unwrap();
+            let mut out = Probe {
+                db: &db,
+                data: Vec::new(),
+            };
+            assert_eq!(2, export.write(&db, &mut out).unwrap(), "{format:?}");
+            assert!(!out.data.is_empty(), "{format:?}");
+            if matches!(format, ExportFormat::Turtle | ExportFormat::JsonLd) {
+                let mut out = Probe {
+                    db: &db,
+                    data: Vec::new(),
+                };
+                export.write_word(&db, &mut out, word).unwrap();
+                assert!(!out.data.is_empty(), "{format:?}");
+            }
+        }
+    }
+}
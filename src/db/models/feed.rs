@@ -0,0 +1,339 @@
+use super::{
+    languages::Language, page_size, revisions::RevisionAction, users::User,
+    words::Word,
+};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{
+    BigInt, Int4, Nullable, Text, Timestamp, Uuid as SqlUuid, Varchar,
+};
+use juniper::{FieldResult, GraphQLEnum};
+use tracing::info;
+use uuid::Uuid;
+
+use std::{fmt::Display, str::FromStr};
+
+const CURSOR_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Whether the language `l` can be read by the user bound as `$1`, as
+/// [`Language::is_readable_by`] tells.
+const READABLE: &str = "(l.owner = $1
+    OR (l.release <> 'PRIVATE' AND l.deleted IS NULL))";
+
+/// Events of the feed, most recent first, starting strictly after the
+/// cursor bound as `$2`, `$3` and `$4` and limited to `$5` events.
+///
+/// The events themselves must be selected by a subquery named
+/// `events`.
+const FEED_PAGE: &str = "
+    WHERE CAST($2 AS TIMESTAMP) IS NULL
+       OR (events.created, events.kind, events.id) < ($2, $3, $4)
+    ORDER BY events.created DESC, events.kind DESC, events.id DESC
+    LIMIT $5";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum FeedEventKind {
+    #[graphql(description = "A word was changed")]
+    Word,
+    #[graphql(description = "A language's metadata was changed")]
+    Language,
+    #[graphql(description = "A user followed another user")]
+    Follow,
+}
+
+impl FeedEventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Word => "word",
+            Self::Language => "language",
+            Self::Follow => "follow",
+        }
+    }
+}
+
+impl FromStr for FeedEventKind {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "word" => Ok(Self::Word),
+            "language" => Ok(Self::Language),
+            "follow" => Ok(Self::Follow),
+            _ => Err(DatabaseError::new(
+                format!("Unknown feed event kind {s}"),
+                ErrorKind::InvalidInput,
+            )),
+        }
+    }
+}
+
+/// Position of an event in a feed.
+///
+/// Events are ordered by time, then by kind and number to break ties,
+/// so the cursor is made of all three.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FeedCursor {
+    created: NaiveDateTime,
+    kind: FeedEventKind,
+    id: i32,
+}
+
+impl Display for FeedCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}",
+            self.created.format(CURSOR_TIME_FORMAT),
+            self.kind.as_str(),
+            self.id
+        )
+    }
+}
+
+impl FromStr for FeedCursor {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            DatabaseError::new(
+                format!("Invalid cursor {s}"),
+                ErrorKind::InvalidInput,
+            )
+        };
+        let mut parts = s.split('|');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(created), Some(kind), Some(id), None) => Ok(Self {
+                created: NaiveDateTime::parse_from_str(
+                    created,
+                    CURSOR_TIME_FORMAT,
+                )
+                .map_err(|_| invalid())?,
+                kind: kind.parse().map_err(|_| invalid())?,
+                id: id.parse().map_err(|_| invalid())?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+pub struct FeedEvent {
+    #[diesel(sql_type = Text)]
+    kind: String,
+    #[diesel(sql_type = Int4)]
+    id: i32,
+    #[diesel(sql_type = Timestamp)]
+    created: NaiveDateTime,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    actor: Option<String>,
+    #[diesel(sql_type = Nullable<crate::db::schema::sql_types::Revisionaction>)]
+    action: Option<RevisionAction>,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    language: Option<Uuid>,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    word: Option<Uuid>,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    followed: Option<String>,
+}
+
+impl FeedEvent {
+    fn event_kind(&self) -> Result<FeedEventKind, DatabaseError> {
+        self.kind.parse()
+    }
+
+    fn position(&self) -> Result<FeedCursor, DatabaseError> {
+        Ok(FeedCursor {
+            created: self.created,
+            kind: self.event_kind()?,
+            id: self.id,
+        })
+    }
+
+    fn parse_cursor(
+        after: Option<String>,
+    ) -> Result<Option<FeedCursor>, DatabaseError> {
+        after.map(|after| after.parse()).transpose()
+    }
+
+    /// Changes to the languages `user` follows, languages created by
+    /// the users they follow, and who these users started following.
+    ///
+    /// Changes to languages `user` cannot read, private or in the trash
+    /// and owned by someone else, are left out, and so are follows of
+    /// users hiding their followers.
+    pub fn feed(
+        db: &Database,
+        user: &str,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Vec<FeedEvent>, DatabaseError> {
+        let after = FeedEvent::parse_cursor(after)?;
+        diesel::sql_query(format!(
+            "SELECT events.* FROM (
+                SELECT 'word' AS kind, r.id, r.created, r.author AS actor,
+                       r.action, r.language, r.word,
+                       CAST(NULL AS VARCHAR) AS followed
+                FROM wordrevisions r
+                JOIN languages l ON l.id = r.language
+                WHERE r.language IN (
+                    SELECT lang FROM userfollowlanguage WHERE userid = $1)
+                  AND {READABLE}
+                UNION ALL
+                SELECT 'language', r.id, r.created, r.author, r.action,
+                       r.language, NULL, NULL
+                FROM languagerevisions r
+                JOIN languages l ON l.id = r.language
+                WHERE (r.language IN (
+                    SELECT lang FROM userfollowlanguage WHERE userid = $1)
+                   OR (r.action = 'create' AND r.author IN (
+                    SELECT following FROM userfollows WHERE follower = $1)))
+                  AND {READABLE}
+                UNION ALL
+                SELECT 'follow', f.id, f.created, f.follower, NULL, NULL,
+                       NULL, f.following
+                FROM userfollows f
+                JOIN users u ON u.id = f.following
+                WHERE f.follower IN (
+                    SELECT following FROM userfollows WHERE follower = $1)
+                  AND (u.public_followers OR f.following = $1)
+            ) events {FEED_PAGE}"
+        ))
+        .bind::<Varchar, _>(user)
+        .bind::<Nullable<Timestamp>, _>(after.as_ref().map(|c| c.created))
+        .bind::<Text, _>(after.as_ref().map_or("", |c| c.kind.as_str()))
+        .bind::<Int4, _>(after.as_ref().map_or(0, |c| c.id))
+        .bind::<BigInt, _>(page_size(first))
+        .load::<FeedEvent>(&mut db.conn()?)
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to retrieve feed of user {user}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+    }
+
+    /// Changes made to a language and its words.
+    pub fn recent_changes(
+        db: &Database,
+        language: Uuid,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Vec<FeedEvent>, DatabaseError> {
+        let after = FeedEvent::parse_cursor(after)?;
+        diesel::sql_query(format!(
+            "SELECT events.* FROM (
+                SELECT 'word' AS kind, r.id, r.created, r.author AS actor,
+                       r.action, r.language, r.word,
+                       CAST(NULL AS VARCHAR) AS followed
+                FROM wordrevisions r
+                WHERE r.language = $1
+                UNION ALL
+                SELECT 'language', r.id, r.created, r.author, r.action,
+                       r.language, NULL, NULL
+                FROM languagerevisions r
+                WHERE r.language = $1
+            ) events {FEED_PAGE}"
+        ))
+        .bind::<SqlUuid, _>(language)
+        .bind::<Nullable<Timestamp>, _>(after.as_ref().map(|c| c.created))
+        .bind::<Text, _>(after.as_ref().map_or("", |c| c.kind.as_str()))
+        .bind::<Int4, _>(after.as_ref().map_or(0, |c| c.id))
+        .bind::<BigInt, _>(page_size(first))
+        .load::<FeedEvent>(&mut db.conn()?)
+        .map_err(|e| {
+            DatabaseError::new(
+                format!(
+                    "Failed to retrieve recent changes of language {language}: {e:?}"
+                ),
+                ErrorKind::Internal,
+            )
+        })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl FeedEvent {
+    #[graphql(
+        description = "Opaque position of the event, to pass as `after` to get the events preceding it"
+    )]
+    fn cursor(&self) -> FieldResult<String> {
+        Ok(self.position()?.to_string())
+    }
+
+    #[graphql(description = "What the event is about")]
+    fn kind(&self) -> FieldResult<FeedEventKind> {
+        self.event_kind().map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "What was done to the word or language, null for follows"
+    )]
+    fn action(&self) -> Option<RevisionAction> {
+        self.action.clone()
+    }
+
+    #[graphql(description = "Time at which the event happened")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(
+        description = "User who triggered the event, if they still exist"
+    )]
+    fn actor(&self, context: &Context) -> FieldResult<Option<User>> {
+        match &self.actor {
+            Some(actor) => context.db.user(actor).map_err(Into::into),
+            None => Ok(None),
+        }
+    }
+
+    #[graphql(
+        description = "Language concerned by the event, null if it is in the trash"
+    )]
+    fn language(&self, context: &Context) -> Option<Language> {
+        match self.language {
+            Some(language) => match Language::find(&context.db, language) {
+                Ok(language) => Some(language),
+                Err(e) => {
+                    info!("Failed to retrieve language {language}: {e:?}");
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    #[graphql(
+        description = "Word concerned by the event, null if it is in the trash"
+    )]
+    fn word(&self, context: &Context) -> Option<Word> {
+        match self.word {
+            Some(word) => match Word::find(&context.db, word) {
+                Ok(word) => Some(word),
+                Err(e) => {
+                    info!("Failed to retrieve word {word}: {e:?}");
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    #[graphql(description = "Unique identifier of the word concerned")]
+    fn word_id(&self) -> Option<String> {
+        self.word.map(|word| word.to_string())
+    }
+
+    #[graphql(description = "User who was followed, for follows")]
+    fn followed(&self, context: &Context) -> FieldResult<Option<User>> {
+        match &self.followed {
+            Some(followed) => context.db.user(followed).map_err(Into::into),
+            None => Ok(None),
+        }
+    }
+}
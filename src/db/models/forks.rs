@@ -0,0 +1,404 @@
+use super::super::schema;
+use super::{
+    languages::{is_name_taken, name_taken, AgentLanguageRelation, Language},
+    notifications::Event,
+};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+use juniper::FieldResult;
+use tracing::info;
+use uuid::Uuid;
+
+use schema::{languageforks, languages};
+
+/// Maximum length of the name of a language, in characters.
+const MAX_NAME_LENGTH: usize = 255;
+
+#[derive(Queryable, Debug, Clone)]
+pub struct LanguageFork {
+    #[allow(dead_code)]
+    language: Uuid,
+    source: Option<Uuid>,
+    sourcename: String,
+    languagerevision: Option<i32>,
+    wordrevision: Option<i32>,
+    created: chrono::NaiveDateTime,
+}
+
+impl LanguageFork {
+    /// Where the language `language` was forked from, if it is a fork.
+    pub fn of(
+        db: &Database,
+        language: Uuid,
+    ) -> Result<Option<LanguageFork>, DatabaseError> {
+        languageforks::table
+            .find(language)
+            .first::<LanguageFork>(&mut db.conn()?)
+            .optional()
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve provenance of language {language}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Copy the words of `source` into the new language `target`,
+    /// then its translation targets.
+    ///
+    /// Everything is copied with `INSERT ... SELECT` statements through
+    /// a temporary table mapping the words of `source` to new UUIDs,
+    /// so that large dictionaries do not go through the server.
+    /// `lemma` links and relations between words of `source` point to
+    /// the copies, relations to words of other languages are kept as
+    /// is. Words in the trash are not copied, what was kept from the
+    /// LIFT entries of the others is.
+    fn copy_words(
+        conn: &mut PgConnection,
+        source: Uuid,
+        target: Uuid,
+    ) -> QueryResult<usize> {
+        diesel::sql_query(
+            "CREATE TEMPORARY TABLE forkedwords ON COMMIT DROP AS
+             SELECT id AS old, uuid_generate_v4() AS new
+             FROM words
+             WHERE language = $1 AND deleted IS NULL",
+        )
+        .bind::<SqlUuid, _>(source)
+        .execute(conn)?;
+        diesel::sql_query(
+            "CREATE UNIQUE INDEX forkedwords_old ON forkedwords(old)",
+        )
+        .execute(conn)?;
+        let words = diesel::sql_query(
+            "INSERT INTO words (id, norm, native, lemma, language,
+                                partofspeech, audio, video, image,
+                                description, etymology, lusage, morphology)
+             SELECT f.new, w.norm, w.native, l.new, $1,
+                    w.partofspeech, w.audio, w.video, w.image,
+                    w.description, w.etymology, w.lusage, w.morphology
+             FROM words w
+             INNER JOIN forkedwords f ON f.old = w.id
+             LEFT JOIN forkedwords l ON l.old = w.lemma",
+        )
+        .bind::<SqlUuid, _>(target)
+        .execute(conn)?;
+        diesel::sql_query(
+            "INSERT INTO wordrelation (wordsource, wordtarget, relationship)
+             SELECT s.new, COALESCE(t.new, r.wordtarget), r.relationship
+             FROM wordrelation r
+             INNER JOIN forkedwords s ON s.old = r.wordsource
+             INNER JOIN words tw ON tw.id = r.wordtarget
+             LEFT JOIN forkedwords t ON t.old = r.wordtarget
+             WHERE t.new IS NOT NULL OR tw.language <> $1",
+        )
+        .bind::<SqlUuid, _>(source)
+        .execute(conn)?;
+        diesel::sql_query(
+            "INSERT INTO liftentries (word, extras)
+             SELECT f.new, e.extras
+             FROM liftentries e
+             INNER JOIN forkedwords f ON f.old = e.word",
+        )
+        .execute(conn)?;
+        diesel::sql_query(
+            "INSERT INTO liftheaders (language, header, normlang,
+                                      nativelang, analysislang)
+             SELECT $2, header, normlang, nativelang, analysislang
+             FROM liftheaders WHERE language = $1",
+        )
+        .bind::<SqlUuid, _>(source)
+        .bind::<SqlUuid, _>(target)
+        .execute(conn)?;
+        diesel::sql_query(
+            "INSERT INTO langtranslatesto (langfrom, langto)
+             SELECT $2, langto FROM langtranslatesto WHERE langfrom = $1",
+        )
+        .bind::<SqlUuid, _>(source)
+        .bind::<SqlUuid, _>(target)
+        .execute(conn)?;
+        Ok(words)
+    }
+
+    /// Copy the language `source` and its words into a new language
+    /// named `name` owned by `user`.
+    ///
+    /// The fork keeps the release, rights and license of its source,
+    /// whose owner is credited as an author of the fork. Only the owner
+    /// can fork languages that are not public or whose license is
+    /// missing or forbids derivative works, see
+    /// [`Language::allows_derivatives`].
+    pub fn fork(
+        context: &Context,
+        user: &str,
+        source: Uuid,
+        name: &str,
+    ) -> Result<Language, DatabaseError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(DatabaseError::new(
+                format!(
+                    "Language name must be between 1 and {MAX_NAME_LENGTH} characters long"
+                ),
+                ErrorKind::InvalidInput,
+            ));
+        }
+        let source = Language::find(&context.db, source)?;
+        if !source.is_visible_to(Some(user)) {
+            return Err(DatabaseError::new(
+                format!("Language {} not found", source.uuid()),
+                ErrorKind::NotFound,
+            ));
+        }
+        let owns_source = source.is_owned_by(user);
+        if !owns_source && !source.allows_derivatives() {
+            return Err(DatabaseError::new(
+                format!(
+                    "Language {source} is not public or its license does not allow derivative works"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        let conn = &mut context.db.conn()?;
+        let (fork, words) =
+            conn.transaction::<_, DatabaseError, _>(|conn| {
+                let taken = diesel::select(diesel::dsl::exists(
+                    languages::table
+                        .filter(languages::owner.eq(user))
+                        .filter(languages::name.eq(name))
+                        .filter(languages::deleted.is_null()),
+                ))
+                .get_result::<bool>(conn)?;
+                if taken {
+                    return Err(name_taken(user, name));
+                }
+                let fork =
+                    source.insert_copy(conn, user, name).map_err(|e| {
+                        if is_name_taken(&e) {
+                            name_taken(user, name)
+                        } else {
+                            e.into()
+                        }
+                    })?;
+                if !owns_source {
+                    let owner = languages::table
+                        .find(source.uuid())
+                        .select(languages::owner)
+                        .first::<String>(conn)?;
+                    fork.add_agent(
+                        conn,
+                        &owner,
+                        AgentLanguageRelation::Author,
+                    )?;
+                }
+                let words =
+                    LanguageFork::copy_words(conn, source.uuid(), fork.uuid())?;
+                diesel::sql_query(
+                    "INSERT INTO languageforks (language, source, sourcename,
+                                            languagerevision, wordrevision)
+                 SELECT $1, l.id, l.name,
+                        (SELECT MAX(id) FROM languagerevisions
+                         WHERE language = l.id),
+                        (SELECT MAX(id) FROM wordrevisions
+                         WHERE language = l.id)
+                 FROM languages l
+                 WHERE l.id = $2",
+                )
+                .bind::<SqlUuid, _>(fork.uuid())
+                .bind::<SqlUuid, _>(source.uuid())
+                .execute(conn)?;
+                Ok((fork, words))
+            })?;
+        info!("User {user} forked {words} words of {source} into {fork}");
+        Event::LanguageCreated {
+            actor: user.to_string(),
+            language: fork.uuid(),
+        }
+        .dispatch(context);
+        Ok(fork)
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl LanguageFork {
+    #[graphql(
+        description = "Language the fork was made from, null if it was deleted since"
+    )]
+    fn source(&self, context: &Context) -> Option<Language> {
+        self.source.and_then(|source| {
+            match Language::find(&context.db, source) {
+                Ok(language)
+                    if language.is_visible_to(
+                        context.user_auth().map(String::as_str),
+                    ) =>
+                {
+                    Some(language)
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    info!("Failed to retrieve language {source}: {e:?}");
+                    None
+                }
+            }
+        })
+    }
+
+    #[graphql(description = "Name of the source language when it was forked")]
+    fn source_name(&self) -> String {
+        self.sourcename.clone()
+    }
+
+    #[graphql(
+        description = "Latest revision of the source's metadata when it was forked"
+    )]
+    fn language_revision(&self) -> Option<i32> {
+        self.languagerevision
+    }
+
+    #[graphql(
+        description = "Latest revision of the source's words when it was forked"
+    )]
+    fn word_revision(&self) -> Option<i32> {
+        self.wordrevision
+    }
+
+    #[graphql(description = "Time at which the language was forked")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::super::{languages::Release, words::WordRelationship};
+    use super::*;
+    use crate::db::{
+        schema::{langtranslatesto, liftentries, wordrelation, words},
+        testing,
+    };
+
+    /// Words of `language` by norm, with their ID and lemma.
+    fn words_of(
+        conn: &mut PgConnection,
+        language: Uuid,
+    ) -> HashMap<String, (Uuid, Option<Uuid>)> {
+        words::table
+            .filter(words::language.eq(language))
+            .select((words::norm, words::id, words::lemma))
+            .load::<(String, Uuid, Option<Uuid>)>(conn)
+            .unwrap()
+            .into_iter()
+            .map(|(norm, id, lemma)| (norm, (id, lemma)))
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn copy_words_remaps_lemmas_and_relations() {
+        let conn = &mut testing::connection();
+        let owner = testing::user(conn);
+        let source = testing::language(conn, &owner);
+        let other = testing::language(conn, &owner);
+        let target = testing::language(conn, &owner);
+        let root = testing::word(conn, source, "root", None);
+        let derived = testing::word(conn, source, "derived", Some(root));
+        let trashed = testing::word(conn, source, "trashed", Some(root));
+        diesel::update(words::table.find(trashed))
+            .set(words::deleted.eq(diesel::dsl::now.nullable()))
+            .execute(conn)
+            .unwrap();
+        let foreign = testing::word(conn, other, "foreign", None);
+        testing::relate(conn, derived, root, WordRelationship::Related);
+        testing::relate(conn, root, foreign, WordRelationship::Definition);
+        testing::relate(conn, root, trashed, WordRelationship::Related);
+        diesel::insert_into(liftentries::table)
+            .values((
+                liftentries::word.eq(root),
+                liftentries::extras.eq(json!({ "note": "kept" })),
+            ))
+            .execute(conn)
+            .unwrap();
+        diesel::insert_into(langtranslatesto::table)
+            .values((
+                langtranslatesto::langfrom.eq(source),
+                langtranslatesto::langto.eq(other),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        assert_eq!(2, LanguageFork::copy_words(conn, source, target).unwrap());
+
+        let copies = words_of(conn, target);
+        assert_eq!(2, copies.len());
+        let (new_root, root_lemma) = copies["root"];
+        let (new_derived, derived_lemma) = copies["derived"];
+        assert_ne!(root, new_root);
+        assert_ne!(derived, new_derived);
+        assert_eq!(None, root_lemma);
+        assert_eq!(Some(new_root), derived_lemma);
+        // The source is left untouched
+        assert_eq!(Some(root), words_of(conn, source)["derived"].1);
+
+        let mut relations = wordrelation::table
+            .filter(wordrelation::wordsource.eq_any([new_root, new_derived]))
+            .select((wordrelation::wordsource, wordrelation::wordtarget))
+            .load::<(Uuid, Uuid)>(conn)
+            .unwrap();
+        relations.sort();
+        let mut expected = vec![(new_derived, new_root), (new_root, foreign)];
+        expected.sort();
+        assert_eq!(expected, relations);
+
+        let extras = liftentries::table
+            .find(new_root)
+            .select(liftentries::extras)
+            .first::<serde_json::Value>(conn)
+            .unwrap();
+        assert_eq!(json!({ "note": "kept" }), extras);
+        let translations = langtranslatesto::table
+            .filter(langtranslatesto::langfrom.eq(target))
+            .select(langtranslatesto::langto)
+            .load::<Uuid>(conn)
+            .unwrap();
+        assert_eq!(vec![other], translations);
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn only_public_and_licensed_languages_allow_derivatives() {
+        let conn = &mut testing::connection();
+        let owner = testing::user(conn);
+        let language = testing::language(conn, &owner);
+        let mut allows = |release: Release, license: Option<&str>| {
+            diesel::update(languages::table.find(language))
+                .set((
+                    languages::release.eq(release),
+                    languages::license.eq(license),
+                ))
+                .execute(conn)
+                .unwrap();
+            languages::table
+                .find(language)
+                .first::<Language>(conn)
+                .unwrap()
+                .allows_derivatives()
+        };
+        assert!(allows(Release::Public, Some("CC BY-SA 4.0")));
+        assert!(!allows(Release::Public, None));
+        assert!(!allows(Release::Public, Some("CC BY-NC-ND 4.0")));
+        assert!(!allows(Release::NonCommercial, Some("CC BY-NC 4.0")));
+        assert!(!allows(Release::Research, Some("CC BY 4.0")));
+        assert!(!allows(Release::Private, Some("CC BY 4.0")));
+    }
+}
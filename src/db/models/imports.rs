@@ -0,0 +1,1567 @@
+use super::super::schema;
+use super::{
+    exports::{CSV_COLUMNS, EXPORT_FAILED, EXPORT_ONLY_COLUMNS},
+    languages::{Language, Release},
+    lift::{LiftEntry, LiftHeader},
+    revisions::{to_json, NewWordRevision, RevisionAction},
+    words::{
+        NewWordRelation, PartOfSpeech, Word, WordRelation, WordRelationship,
+        WordSnapshot,
+    },
+};
+use crate::{
+    db::{DatabaseError, ErrorKind},
+    graphql::{subscription::LiveEvent, Context},
+};
+
+use diesel::prelude::*;
+use juniper::{GraphQLEnum, GraphQLInputObject, GraphQLObject};
+use tracing::info;
+use uuid::Uuid;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use schema::{languages, liftentries, wordrelation, wordrevisions, words};
+
+/// Maximum amount of rows accepted in a single import.
+pub const MAX_ROWS: usize = 20_000;
+/// Amount of rows inserted per statement, well below the 65535 bind
+/// parameters a PostgreSQL statement accepts.
+const BATCH_SIZE: usize = 1000;
+/// Maximum length of the norm and native forms of a word, in
+/// characters.
+const MAX_FORM_LENGTH: usize = 255;
+/// Maximum length of the path or URL of a media file, in characters.
+const MAX_MEDIA_LENGTH: usize = 511;
+
+#[derive(GraphQLEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    #[graphql(description = "Comma-separated values")]
+    Csv,
+    #[graphql(description = "Tab-separated values")]
+    Tsv,
+    #[graphql(description = "JSON document, as exported by Ordabok")]
+    Json,
+    #[graphql(description = "JSON Lines, as exported by Ordabok")]
+    Jsonl,
+}
+
+#[derive(GraphQLEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WordField {
+    Norm,
+    Native,
+    #[graphql(
+        description = "Norm of the word's lemma, either imported along with it or already in the language"
+    )]
+    Lemma,
+    PartOfSpeech,
+    Audio,
+    Video,
+    Image,
+    Description,
+    Etymology,
+    #[graphql(name = "LUSAGE")]
+    Usage,
+    Morphology,
+}
+
+impl WordField {
+    pub const ALL: [WordField; 11] = [
+        Self::Norm,
+        Self::Native,
+        Self::Lemma,
+        Self::PartOfSpeech,
+        Self::Audio,
+        Self::Video,
+        Self::Image,
+        Self::Description,
+        Self::Etymology,
+        Self::Usage,
+        Self::Morphology,
+    ];
+
+    /// Header of the column holding the field, named after the
+    /// matching field of `NewWord`.
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::Norm => "norm",
+            Self::Native => "native",
+            Self::Lemma => "lemma",
+            Self::PartOfSpeech => "partofspeech",
+            Self::Audio => "audio",
+            Self::Video => "video",
+            Self::Image => "image",
+            Self::Description => "description",
+            Self::Etymology => "etymology",
+            Self::Usage => "lusage",
+            Self::Morphology => "morphology",
+        }
+    }
+
+    /// Field whose column is named `header`, ignoring case, spaces,
+    /// dashes and underscores.
+    fn from_header(header: &str) -> Option<Self> {
+        let header = header
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        match header.as_str() {
+            "pos" => Some(Self::PartOfSpeech),
+            "usage" => Some(Self::Usage),
+            header => {
+                Self::ALL.into_iter().find(|field| field.column() == header)
+            }
+        }
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct ColumnMapping {
+    #[graphql(description = "Header of the column in the imported data")]
+    column: String,
+    #[graphql(description = "Field of the words the column holds")]
+    field: WordField,
+}
+
+#[derive(GraphQLObject, Debug, Clone)]
+pub struct ImportIssue {
+    #[graphql(
+        description = "Line of the row in CSV, TSV and JSON Lines data, the header being on line 1, or rank of the entry in JSON and LIFT data"
+    )]
+    pub row: i32,
+    #[graphql(description = "Column the issue was found in, if any")]
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(GraphQLObject, Debug, Clone, Default)]
+#[graphql(description = "Outcome of an import of words")]
+pub struct ImportReport {
+    #[graphql(description = "Amount of rows read from the imported data")]
+    pub rows: i32,
+    #[graphql(
+        description = "Amount of words added to the language, zero for dry runs and imports with errors"
+    )]
+    pub imported: i32,
+    #[graphql(description = "Issues preventing the import")]
+    pub errors: Vec<ImportIssue>,
+    #[graphql(
+        description = "Issues that do not prevent the import, such as unresolved lemmas"
+    )]
+    pub warnings: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    pub fn error(&mut self, row: usize, column: Option<&str>, message: String) {
+        self.errors.push(ImportIssue {
+            row: i32::try_from(row).unwrap_or(i32::MAX),
+            column: column.map(String::from),
+            message,
+        });
+    }
+
+    pub fn warning(
+        &mut self,
+        row: usize,
+        column: Option<&str>,
+        message: String,
+    ) {
+        self.warnings.push(ImportIssue {
+            row: i32::try_from(row).unwrap_or(i32::MAX),
+            column: column.map(String::from),
+            message,
+        });
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = words)]
+pub struct ImportedWord {
+    pub id: Uuid,
+    pub norm: String,
+    pub native: Option<String>,
+    pub lemma: Option<Uuid>,
+    pub language: Uuid,
+    pub partofspeech: PartOfSpeech,
+    pub audio: Option<String>,
+    pub video: Option<String>,
+    pub image: Option<String>,
+    pub description: Option<String>,
+    pub etymology: Option<String>,
+    pub lusage: Option<String>,
+    pub morphology: Option<String>,
+}
+
+impl ImportedWord {
+    /// Issue with the length of the word's forms and media, if any.
+    pub fn length_issue(&self) -> Option<String> {
+        let too_long = |value: Option<&str>, max: usize| {
+            value.map_or(false, |value| value.chars().count() > max)
+        };
+        [
+            ("norm", Some(self.norm.as_str()), MAX_FORM_LENGTH),
+            ("native", self.native.as_deref(), MAX_FORM_LENGTH),
+            ("audio", self.audio.as_deref(), MAX_MEDIA_LENGTH),
+            ("video", self.video.as_deref(), MAX_MEDIA_LENGTH),
+            ("image", self.image.as_deref(), MAX_MEDIA_LENGTH),
+        ]
+        .into_iter()
+        .find(|(_, value, max)| too_long(*value, *max))
+        .map(|(field, _, max)| {
+            format!("{field} is longer than {max} characters")
+        })
+    }
+}
+
+/// Word an imported row refers to.
+#[derive(Debug, Clone)]
+pub enum Reference {
+    /// Word with this norm, imported along with the row or already in
+    /// the language
+    Norm(String),
+    /// Word imported along with the row
+    Imported(Uuid),
+    /// Word already in the database
+    Existing(Uuid),
+    /// Word with this ID in the exported data, imported along with the
+    /// row or already in the database
+    Exported(Uuid),
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Norm(norm) => write!(f, "{norm}"),
+            Self::Imported(id) | Self::Existing(id) | Self::Exported(id) => {
+                write!(f, "{id}")
+            }
+        }
+    }
+}
+
+/// Word a reference resolved to.
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    /// Index of an imported row
+    Row(usize),
+    Word(Uuid),
+}
+
+/// Row of the imported data along with where it was read from.
+pub struct Row {
+    pub line: usize,
+    pub word: ImportedWord,
+    /// Lemma of the word, still to be resolved.
+    pub lemma: Option<Reference>,
+    /// Relations from the word to other words, still to be resolved.
+    pub relations: Vec<(Reference, WordRelationship)>,
+    /// Parts of the LIFT entry of the word Ordabok has no field for.
+    pub lift: Option<serde_json::Value>,
+    /// ID of the word in the export the row comes from, which other
+    /// rows may refer to.
+    pub source: Option<Uuid>,
+}
+
+/// Columns of exported data linking words to each other.
+struct ExportColumns {
+    id: Option<usize>,
+    definitions: Option<usize>,
+    related: Option<usize>,
+}
+
+impl ExportColumns {
+    /// Export columns among `headers`, unless mapped to a field in
+    /// `columns`.
+    fn of(
+        headers: &csv::StringRecord,
+        columns: &HashMap<WordField, usize>,
+    ) -> Self {
+        let position = |name: &str| {
+            headers.iter().enumerate().position(|(index, header)| {
+                header.eq_ignore_ascii_case(name)
+                    && !columns.values().any(|column| *column == index)
+            })
+        };
+        Self {
+            id: position("id"),
+            definitions: position("definitions"),
+            related: position("related"),
+        }
+    }
+
+    /// Set the exported ID and the relations of `row` from `record`.
+    fn read(
+        &self,
+        record: &csv::StringRecord,
+        headers: &csv::StringRecord,
+        row: &mut Row,
+        report: &mut ImportReport,
+    ) {
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .filter(|value| !value.is_empty())
+        };
+        let header =
+            |index: Option<usize>| index.and_then(|index| headers.get(index));
+        if let Some(id) = cell(self.id) {
+            match Uuid::from_str(id) {
+                Ok(id) => row.source = Some(id),
+                Err(_) => report.warning(
+                    row.line,
+                    header(self.id),
+                    format!(
+                        "{id} is not a word ID, other words cannot refer to it"
+                    ),
+                ),
+            }
+        }
+        for (index, relationship) in [
+            (self.definitions, WordRelationship::Definition),
+            (self.related, WordRelationship::Related),
+        ] {
+            for target in
+                cell(index).into_iter().flat_map(str::split_whitespace)
+            {
+                match Uuid::from_str(target) {
+                    Ok(id) => row
+                        .relations
+                        .push((Reference::Exported(id), relationship.clone())),
+                    Err(_) => report.warning(
+                        row.line,
+                        header(index),
+                        format!("{target} is not a word ID, the relation is not imported"),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+fn too_many_rows() -> DatabaseError {
+    DatabaseError::new(
+        format!("Cannot import more than {MAX_ROWS} words at once"),
+        ErrorKind::InvalidInput,
+    )
+}
+
+fn failed_export() -> String {
+    "The data comes from an export that failed, it is incomplete".to_string()
+}
+
+/// Words read from imported data, ready to be added to a language.
+pub struct WordImport {
+    language: Uuid,
+    words: Vec<ImportedWord>,
+    relations: Vec<NewWordRelation>,
+    lift: Vec<LiftEntry>,
+    lift_header: Option<LiftHeader>,
+    report: ImportReport,
+}
+
+impl WordImport {
+    /// Which field each column of `headers` holds, according to
+    /// `mapping` or, without one, to the name of the columns.
+    fn columns(
+        headers: &csv::StringRecord,
+        mapping: Option<&[ColumnMapping]>,
+        report: &mut ImportReport,
+    ) -> Result<HashMap<WordField, usize>, DatabaseError> {
+        let mut columns = HashMap::new();
+        let mut map = |field: WordField, index: usize| {
+            if columns.insert(field, index).is_some() {
+                Err(DatabaseError::new(
+                    format!(
+                        "Several columns are mapped to the field {}",
+                        field.column()
+                    ),
+                    ErrorKind::InvalidInput,
+                ))
+            } else {
+                Ok(())
+            }
+        };
+        if let Some(mapping) = mapping {
+            for ColumnMapping { column, field } in mapping {
+                let index = headers
+                    .iter()
+                    .position(|header| header == column)
+                    .ok_or_else(|| {
+                        DatabaseError::new(
+                            format!(
+                                "No column named {column} in imported data"
+                            ),
+                            ErrorKind::InvalidInput,
+                        )
+                    })?;
+                map(*field, index)?;
+            }
+        } else {
+            for (index, header) in headers.iter().enumerate() {
+                if let Some(field) = WordField::from_header(header) {
+                    map(field, index)?;
+                }
+            }
+        }
+        if !columns.contains_key(&WordField::Norm) {
+            return Err(DatabaseError::new(
+                "No column of the imported data holds the norm of the words",
+                ErrorKind::InvalidInput,
+            ));
+        }
+        for (index, header) in headers.iter().enumerate() {
+            if !columns.values().any(|column| *column == index)
+                && !EXPORT_ONLY_COLUMNS.contains(&header)
+            {
+                report.warning(
+                    1,
+                    Some(header),
+                    format!("Column {header} is not imported"),
+                );
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Read the row `record` found on line `line`, reporting its
+    /// issues in `report`.
+    fn row(
+        record: &csv::StringRecord,
+        line: usize,
+        language: Uuid,
+        columns: &HashMap<WordField, usize>,
+        headers: &csv::StringRecord,
+        report: &mut ImportReport,
+    ) -> Option<Row> {
+        let cell = |field: WordField| {
+            columns
+                .get(&field)
+                .and_then(|index| record.get(*index))
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+        };
+        let header = |field: WordField| {
+            columns.get(&field).and_then(|index| headers.get(*index))
+        };
+        let errors = report.errors.len();
+        let mut check_length = |field: WordField, max: usize| {
+            if let Some(value) = cell(field) {
+                if value.chars().count() > max {
+                    report.error(
+                        line,
+                        header(field),
+                        format!(
+                            "{} is longer than {max} characters",
+                            field.column()
+                        ),
+                    );
+                }
+            }
+        };
+        check_length(WordField::Norm, MAX_FORM_LENGTH);
+        check_length(WordField::Native, MAX_FORM_LENGTH);
+        check_length(WordField::Audio, MAX_MEDIA_LENGTH);
+        check_length(WordField::Video, MAX_MEDIA_LENGTH);
+        check_length(WordField::Image, MAX_MEDIA_LENGTH);
+        let norm = cell(WordField::Norm);
+        if norm.is_none() {
+            report.error(
+                line,
+                header(WordField::Norm),
+                "Missing norm".to_string(),
+            );
+        }
+        let partofspeech = match cell(WordField::PartOfSpeech) {
+            Some(value) => match value.parse::<PartOfSpeech>() {
+                Ok(partofspeech) => partofspeech,
+                Err(e) => {
+                    report.error(line, header(WordField::PartOfSpeech), e);
+                    PartOfSpeech::default()
+                }
+            },
+            None => {
+                report.warning(
+                    line,
+                    header(WordField::PartOfSpeech),
+                    format!(
+                        "Missing part of speech, defaulting to {:?}",
+                        PartOfSpeech::default()
+                    ),
+                );
+                PartOfSpeech::default()
+            }
+        };
+        if report.errors.len() > errors {
+            return None;
+        }
+        Some(Row {
+            line,
+            word: ImportedWord {
+                id: Uuid::new_v4(),
+                norm: norm.unwrap_or_default(),
+                native: cell(WordField::Native),
+                lemma: None,
+                language,
+                partofspeech,
+                audio: cell(WordField::Audio),
+                video: cell(WordField::Video),
+                image: cell(WordField::Image),
+                description: cell(WordField::Description),
+                etymology: cell(WordField::Etymology),
+                lusage: cell(WordField::Usage),
+                morphology: cell(WordField::Morphology),
+            },
+            lemma: cell(WordField::Lemma).map(Reference::Norm),
+            relations: Vec::new(),
+            lift: None,
+            source: None,
+        })
+    }
+
+    /// Link the rows to their lemma and to the targets of their
+    /// relations, looked up among the imported rows first, then among
+    /// the words already in the database.
+    ///
+    /// Existing lemmas must belong to the language, existing targets
+    /// of relations to a language its owner may read.
+    ///
+    /// The words are ordered so that the lemmas they import come
+    /// before the words using them.
+    pub fn from_rows(
+        conn: &mut PgConnection,
+        language: Uuid,
+        mut rows: Vec<Row>,
+        lemma_header: Option<&str>,
+        mut report: ImportReport,
+    ) -> Result<WordImport, DatabaseError> {
+        let mut imported_norms: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut imported_ids: HashMap<Uuid, usize> = HashMap::new();
+        let mut exported_ids: HashMap<Uuid, usize> = HashMap::new();
+        for (index, row) in rows.iter().enumerate() {
+            imported_norms
+                .entry(row.word.norm.clone())
+                .or_default()
+                .push(index);
+            imported_ids.insert(row.word.id, index);
+            if let Some(source) = row.source {
+                exported_ids.insert(source, index);
+            }
+        }
+        let references = rows
+            .iter()
+            .flat_map(|row| {
+                row.lemma
+                    .iter()
+                    .chain(row.relations.iter().map(|(target, _)| target))
+            })
+            .collect::<Vec<_>>();
+        let mut existing_norms: HashMap<String, Vec<Uuid>> = HashMap::new();
+        if references
+            .iter()
+            .any(|reference| matches!(reference, Reference::Norm(_)))
+        {
+            for (norm, id) in Word::visible()
+                .filter(words::language.eq(language))
+                .select((words::norm, words::id))
+                .load::<(String, Uuid)>(conn)?
+            {
+                existing_norms.entry(norm).or_default().push(id);
+            }
+        }
+        let existing_ids = references
+            .iter()
+            .filter_map(|reference| match reference {
+                Reference::Existing(id) => Some(*id),
+                Reference::Exported(id) if !exported_ids.contains_key(id) => {
+                    Some(*id)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let existing_ids = if existing_ids.is_empty() {
+            HashMap::new()
+        } else {
+            let owner = languages::table
+                .find(language)
+                .select(languages::owner)
+                .first::<String>(conn)?;
+            Word::visible()
+                    .filter(words::id.eq_any(existing_ids))
+                    .filter(
+                        words::language.eq_any(
+                            languages::table
+                                .filter(languages::owner.eq(owner).or(
+                                    languages::release.ne(Release::Private),
+                                ))
+                                .select(languages::id),
+                        ),
+                    )
+                    .select((words::id, words::language))
+                    .load::<(Uuid, Uuid)>(conn)?
+                    .into_iter()
+                    .collect::<HashMap<_, _>>()
+        };
+        drop(references);
+        let existing = |id: &Uuid, lemma: bool| match existing_ids.get(id) {
+            Some(target) if lemma && *target != language => {
+                Err(format!("Word {id} is not in the language"))
+            }
+            Some(_) => Ok(Target::Word(*id)),
+            None => Err(format!("Word {id} not found")),
+        };
+        let resolve = |reference: &Reference, lemma: bool| match reference {
+            Reference::Norm(norm) => match (
+                imported_norms.get(norm).map(Vec::as_slice),
+                existing_norms.get(norm).map(Vec::as_slice),
+            ) {
+                (Some([index]), _) => Ok(Target::Row(*index)),
+                (None, Some([id])) => Ok(Target::Word(*id)),
+                (Some(_), _) | (None, Some(_)) => {
+                    Err(format!("Several words are named {norm}"))
+                }
+                (None, None) => Err(format!("Word {norm} not found")),
+            },
+            Reference::Imported(id) => imported_ids
+                .get(id)
+                .map(|index| Target::Row(*index))
+                .ok_or_else(|| format!("Word {id} is not imported")),
+            Reference::Existing(id) => existing(id, lemma),
+            Reference::Exported(id) => match exported_ids.get(id) {
+                Some(index) => Ok(Target::Row(*index)),
+                None => existing(id, lemma),
+            },
+        };
+        let mut links: Vec<Option<usize>> = vec![None; rows.len()];
+        let mut lemmas: Vec<Option<Uuid>> = vec![None; rows.len()];
+        let mut relations = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            if let Some(lemma) = &row.lemma {
+                match resolve(lemma, true) {
+                    Ok(Target::Row(target)) if target == index => report
+                        .warning(
+                            row.line,
+                            lemma_header,
+                            format!("Word {lemma} cannot be its own lemma"),
+                        ),
+                    Ok(Target::Row(target)) => links[index] = Some(target),
+                    Ok(Target::Word(id)) => lemmas[index] = Some(id),
+                    Err(e) => report.warning(
+                        row.line,
+                        lemma_header,
+                        format!("{e}, the word is imported without lemma"),
+                    ),
+                }
+            }
+            for (target, relationship) in &row.relations {
+                let wordtarget = match resolve(target, false) {
+                    Ok(Target::Row(target)) => rows[target].word.id,
+                    Ok(Target::Word(id)) => id,
+                    Err(e) => {
+                        report.warning(
+                            row.line,
+                            None,
+                            format!("{e}, the relation is not imported"),
+                        );
+                        continue;
+                    }
+                };
+                relations.push(NewWordRelation {
+                    wordsource: row.word.id,
+                    wordtarget,
+                    relationship: relationship.clone(),
+                });
+            }
+        }
+        // Depth of each row in its chain of lemmas, breaking cycles
+        let mut depths: Vec<Option<usize>> = vec![None; rows.len()];
+        let mut visiting = vec![false; rows.len()];
+        for start in 0..rows.len() {
+            let mut path = Vec::new();
+            let mut current = start;
+            let mut depth = loop {
+                if let Some(depth) = depths[current] {
+                    break depth + 1;
+                }
+                visiting[current] = true;
+                path.push(current);
+                match links[current] {
+                    Some(next) if visiting[next] => {
+                        links[current] = None;
+                        report.warning(
+                            rows[current].line,
+                            lemma_header,
+                            format!(
+                                "Lemma {} forms a cycle, the word is imported without lemma",
+                                rows[next].word.norm
+                            ),
+                        );
+                        break 0;
+                    }
+                    Some(next) => current = next,
+                    None => break 0,
+                }
+            };
+            for index in path.into_iter().rev() {
+                visiting[index] = false;
+                depths[index] = Some(depth);
+                depth += 1;
+            }
+        }
+        let ids = rows.iter().map(|row| row.word.id).collect::<Vec<_>>();
+        for (index, row) in rows.iter_mut().enumerate() {
+            row.word.lemma =
+                links[index].map(|target| ids[target]).or(lemmas[index]);
+        }
+        let lift = rows
+            .iter_mut()
+            .filter_map(|row| {
+                row.lift.take().map(|extras| LiftEntry {
+                    word: row.word.id,
+                    extras,
+                })
+            })
+            .collect();
+        let mut order = (0..rows.len()).collect::<Vec<_>>();
+        order.sort_by_key(|index| depths[*index]);
+        let mut rows = rows.into_iter().map(Some).collect::<Vec<_>>();
+        let words = order
+            .into_iter()
+            .filter_map(|index| rows[index].take().map(|row| row.word))
+            .collect();
+        Ok(WordImport {
+            language,
+            words,
+            relations,
+            lift,
+            lift_header: None,
+            report,
+        })
+    }
+
+    /// Keep `header` as the LIFT header of the language once the
+    /// words are imported.
+    pub fn with_lift_header(self, header: LiftHeader) -> Self {
+        Self {
+            lift_header: Some(header),
+            ..self
+        }
+    }
+
+    /// Read the words of `data` for the language `language`.
+    ///
+    /// Issues with the data are collected in the import's report,
+    /// errors are only returned when the data cannot be read at all.
+    pub fn parse(
+        conn: &mut PgConnection,
+        language: Uuid,
+        data: &str,
+        format: ImportFormat,
+        mapping: Option<&[ColumnMapping]>,
+    ) -> Result<WordImport, DatabaseError> {
+        let mut report = ImportReport::default();
+        let (rows, lemma_header) = match format {
+            ImportFormat::Csv | ImportFormat::Tsv => {
+                WordImport::read_delimited(
+                    language,
+                    data,
+                    if format == ImportFormat::Csv {
+                        b','
+                    } else {
+                        b'\t'
+                    },
+                    mapping,
+                    &mut report,
+                )?
+            }
+            ImportFormat::Json | ImportFormat::Jsonl => {
+                if mapping.is_some() {
+                    return Err(DatabaseError::new(
+                        "Columns can only be mapped in CSV and TSV data",
+                        ErrorKind::InvalidInput,
+                    ));
+                }
+                let words = if format == ImportFormat::Json {
+                    WordImport::json_words(data, &mut report)?
+                } else {
+                    WordImport::json_lines_words(data, &mut report)?
+                };
+                let rows = WordImport::json_rows(language, words, &mut report)?;
+                (rows, Some(WordField::Lemma.column().to_string()))
+            }
+        };
+        WordImport::from_rows(
+            conn,
+            language,
+            rows,
+            lemma_header.as_deref(),
+            report,
+        )
+    }
+
+    /// Read the rows of CSV or TSV `data` whose values are separated
+    /// by `delimiter`, along with the header of their lemmas.
+    fn read_delimited(
+        language: Uuid,
+        data: &str,
+        delimiter: u8,
+        mapping: Option<&[ColumnMapping]>,
+        report: &mut ImportReport,
+    ) -> Result<(Vec<Row>, Option<String>), DatabaseError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes());
+        let headers = reader
+            .headers()
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to read header of imported data: {e}"),
+                    ErrorKind::InvalidInput,
+                )
+            })?
+            .clone();
+        let columns = WordImport::columns(&headers, mapping, report)?;
+        let exported = ExportColumns::of(&headers, &columns);
+        let failure = format!("# {EXPORT_FAILED}");
+        let mut rows = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            if index >= MAX_ROWS {
+                return Err(too_many_rows());
+            }
+            report.rows += 1;
+            match record {
+                Ok(record) => {
+                    let line =
+                        record.position().map_or(index + 2, |position| {
+                            usize::try_from(position.line())
+                                .unwrap_or(usize::MAX)
+                        });
+                    if record.len() == 1 && record[0] == failure {
+                        report.error(line, None, failed_export());
+                        continue;
+                    }
+                    if let Some(mut row) = WordImport::row(
+                        &record, line, language, &columns, &headers, report,
+                    ) {
+                        exported.read(&record, &headers, &mut row, report);
+                        rows.push(row);
+                    }
+                }
+                Err(e) => {
+                    let line = e.position().map_or(index + 2, |position| {
+                        usize::try_from(position.line()).unwrap_or(usize::MAX)
+                    });
+                    report.error(line, None, format!("Malformed row: {e}"));
+                }
+            }
+        }
+        let lemma_header = columns
+            .get(&WordField::Lemma)
+            .and_then(|index| headers.get(*index))
+            .map(String::from);
+        Ok((rows, lemma_header))
+    }
+
+    /// Words of a JSON export along with their rank.
+    fn json_words(
+        data: &str,
+        report: &mut ImportReport,
+    ) -> Result<Vec<(usize, serde_json::Value)>, DatabaseError> {
+        // The failure marker makes the document invalid, which would
+        // hide why
+        if data.contains(&format!("/* {EXPORT_FAILED} */")) {
+            report.error(1, None, failed_export());
+            return Ok(Vec::new());
+        }
+        let mut document = serde_json::from_str::<serde_json::Value>(data)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to read imported JSON: {e}"),
+                    ErrorKind::InvalidInput,
+                )
+            })?;
+        let Some(serde_json::Value::Array(words)) =
+            document.get_mut("words").map(serde_json::Value::take)
+        else {
+            return Err(DatabaseError::new(
+                "Imported JSON has no array of words",
+                ErrorKind::InvalidInput,
+            ));
+        };
+        if words.len() > MAX_ROWS {
+            return Err(too_many_rows());
+        }
+        Ok(words
+            .into_iter()
+            .enumerate()
+            .map(|(index, word)| (index + 1, word))
+            .collect())
+    }
+
+    /// Words of a JSON Lines export along with their line, the first
+    /// line describing the language.
+    fn json_lines_words(
+        data: &str,
+        report: &mut ImportReport,
+    ) -> Result<Vec<(usize, serde_json::Value)>, DatabaseError> {
+        let failure = format!("# {EXPORT_FAILED}");
+        let mut words = Vec::new();
+        for (index, line) in data.lines().enumerate().skip(1) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if words.len() >= MAX_ROWS {
+                return Err(too_many_rows());
+            }
+            if line == failure {
+                report.error(index + 1, None, failed_export());
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(word) => words.push((index + 1, word)),
+                Err(e) => {
+                    report.rows += 1;
+                    report.error(
+                        index + 1,
+                        None,
+                        format!("Malformed word: {e}"),
+                    );
+                }
+            }
+        }
+        Ok(words)
+    }
+
+    /// Rows of the exported JSON `words`, read like CSV rows with the
+    /// columns of CSV exports.
+    fn json_rows(
+        language: Uuid,
+        words: Vec<(usize, serde_json::Value)>,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Row>, DatabaseError> {
+        use serde_json::Value;
+        let headers = csv::StringRecord::from(CSV_COLUMNS.to_vec());
+        let columns = WordImport::columns(&headers, None, report)?;
+        let exported = ExportColumns::of(&headers, &columns);
+        let mut rows = Vec::new();
+        for (line, word) in words {
+            report.rows += 1;
+            let Value::Object(word) = word else {
+                report.error(line, None, "Word is not a JSON object".into());
+                continue;
+            };
+            let record = CSV_COLUMNS
+                .iter()
+                .map(|column| match word.get(*column) {
+                    Some(Value::String(text)) => text.clone(),
+                    Some(Value::Array(ids)) => ids
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    // Lemmas are described by their ID and norm
+                    Some(Value::Object(lemma)) => lemma
+                        .get("norm")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    Some(Value::Null) | None => String::new(),
+                    Some(value) => value.to_string(),
+                })
+                .collect::<csv::StringRecord>();
+            if let Some(mut row) = WordImport::row(
+                &record, line, language, &columns, &headers, report,
+            ) {
+                exported.read(&record, &headers, &mut row, report);
+                if let Some(id) = word
+                    .get("lemma")
+                    .and_then(|lemma| lemma.get("id"))
+                    .and_then(Value::as_str)
+                    .and_then(|id| Uuid::from_str(id).ok())
+                {
+                    row.lemma = Some(Reference::Exported(id));
+                }
+                rows.push(row);
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Insert the words on behalf of `author` in a single
+    /// transaction, along with their relations and revisions.
+    fn commit(
+        &self,
+        conn: &mut PgConnection,
+        author: &str,
+    ) -> QueryResult<Vec<Uuid>> {
+        let mut lemma_of: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for word in &self.words {
+            if let Some(lemma) = word.lemma {
+                lemma_of.entry(lemma).or_default().push(word.id);
+            }
+        }
+        conn.transaction(|conn| {
+            let mut inserted = Vec::with_capacity(self.words.len());
+            for batch in self.words.chunks(BATCH_SIZE) {
+                inserted.extend(
+                    diesel::insert_into(words::table)
+                        .values(batch)
+                        .get_results::<Word>(conn)?,
+                );
+            }
+            let mut relations: HashMap<Uuid, Vec<WordRelation>> =
+                HashMap::new();
+            for batch in self.relations.chunks(BATCH_SIZE) {
+                for relation in diesel::insert_into(wordrelation::table)
+                    .values(batch)
+                    .get_results::<WordRelation>(conn)?
+                {
+                    if relation.target() != relation.source() {
+                        relations
+                            .entry(relation.target())
+                            .or_default()
+                            .push(relation.clone());
+                    }
+                    relations
+                        .entry(relation.source())
+                        .or_default()
+                        .push(relation);
+                }
+            }
+            for batch in self.lift.chunks(BATCH_SIZE) {
+                diesel::insert_into(liftentries::table)
+                    .values(batch)
+                    .execute(conn)?;
+            }
+            if let Some(header) = &self.lift_header {
+                header.save(conn)?;
+            }
+            let ids = inserted.iter().map(Word::uuid).collect::<Vec<_>>();
+            let revisions = inserted
+                .into_iter()
+                .map(|word| {
+                    Ok(NewWordRevision {
+                        word: word.uuid(),
+                        language: self.language,
+                        author: Some(author.to_string()),
+                        action: RevisionAction::Create,
+                        before: None,
+                        after: Some(to_json(&WordSnapshot {
+                            lemma_of: lemma_of
+                                .remove(&word.uuid())
+                                .unwrap_or_default(),
+                            relations: relations
+                                .remove(&word.uuid())
+                                .unwrap_or_default(),
+                            word,
+                        })?),
+                    })
+                })
+                .collect::<QueryResult<Vec<_>>>()?;
+            for batch in revisions.chunks(BATCH_SIZE) {
+                diesel::insert_into(wordrevisions::table)
+                    .values(batch)
+                    .execute(conn)?;
+            }
+            Ok(ids)
+        })
+    }
+
+    /// Insert the words on behalf of `author` unless `dry_run` is set
+    /// or the data has errors, returning the report of the import
+    /// along with the IDs of the inserted words.
+    pub fn apply(
+        self,
+        conn: &mut PgConnection,
+        author: &str,
+        dry_run: bool,
+    ) -> QueryResult<(ImportReport, Vec<Uuid>)> {
+        if dry_run || !self.report.errors.is_empty() {
+            return Ok((self.report, Vec::new()));
+        }
+        let ids = self.commit(conn, author)?;
+        let report = ImportReport {
+            imported: i32::try_from(ids.len()).unwrap_or(i32::MAX),
+            ..self.report
+        };
+        Ok((report, ids))
+    }
+
+    /// Import the words read by `parse` into the language `language`
+    /// of `user`, unless `dry_run` is set or the data has errors.
+    ///
+    /// Either every word is added or none is. Followers of the
+    /// language are not notified of each imported word.
+    pub fn import<F>(
+        context: &Context,
+        user: &str,
+        language: Uuid,
+        dry_run: bool,
+        parse: F,
+    ) -> Result<ImportReport, DatabaseError>
+    where
+        F: FnOnce(&mut PgConnection, Uuid) -> Result<WordImport, DatabaseError>,
+    {
+        let language = Language::find(&context.db, language)?;
+        if !language.is_owned_by(user) || !context.may_write(language.uuid()) {
+            return Err(DatabaseError::new(
+                format!("Language {language} is not owned by user {user}"),
+                ErrorKind::Forbidden,
+            ));
+        }
+        let conn = &mut context.db.conn()?;
+        let import = parse(conn, language.uuid())?;
+        let (report, ids) = import.apply(conn, user, dry_run).map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to import words into {language}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })?;
+        if !ids.is_empty() {
+            info!("User {user} imported {} words into {language}", ids.len());
+        }
+        for word in &ids {
+            context.live.publish(LiveEvent::Word {
+                language: language.uuid(),
+                word: *word,
+                action: RevisionAction::Create,
+            });
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::super::exports::{ExportFormat, WordExport};
+    use super::*;
+    use crate::db::testing;
+
+    /// Words of `language` sorted by norm, with the norm of their
+    /// lemma and the fields exports and imports carry.
+    #[allow(clippy::type_complexity)]
+    fn contents(
+        conn: &mut PgConnection,
+        language: Uuid,
+    ) -> Vec<(
+        String,
+        Option<String>,
+        Option<String>,
+        PartOfSpeech,
+        Option<String>,
+        Option<String>,
+    )> {
+        let words = words::table
+            .filter(words::language.eq(language))
+            .select((
+                words::id,
+                words::norm,
+                words::native,
+                words::lemma,
+                words::partofspeech,
+                words::description,
+                words::lusage,
+            ))
+            .order(words::norm)
+            .load::<(
+                Uuid,
+                String,
+                Option<String>,
+                Option<Uuid>,
+                PartOfSpeech,
+                Option<String>,
+                Option<String>,
+            )>(conn)
+            .unwrap();
+        let norms = words
+            .iter()
+            .map(|word| (word.0, word.1.clone()))
+            .collect::<HashMap<_, _>>();
+        words
+            .into_iter()
+            .map(|(_, norm, native, lemma, pos, description, usage)| {
+                let lemma = lemma.map(|lemma| norms[&lemma].clone());
+                (norm, native, lemma, pos, description, usage)
+            })
+            .collect()
+    }
+
+    /// Relations from the words of `language`, as the norms of their
+    /// source and target along with their kind.
+    fn relations_of(
+        conn: &mut PgConnection,
+        language: Uuid,
+    ) -> Vec<(String, String, String)> {
+        let sources = words::table
+            .filter(words::language.eq(language))
+            .select((words::id, words::norm))
+            .load::<(Uuid, String)>(conn)
+            .unwrap()
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let mut relations = wordrelation::table
+            .inner_join(words::table.on(words::id.eq(wordrelation::wordtarget)))
+            .filter(
+                wordrelation::wordsource
+                    .eq_any(sources.keys().copied().collect::<Vec<_>>()),
+            )
+            .select((
+                wordrelation::wordsource,
+                words::norm,
+                wordrelation::relationship,
+            ))
+            .load::<(Uuid, String, WordRelationship)>(conn)
+            .unwrap()
+            .into_iter()
+            .map(|(source, target, relationship)| {
+                (
+                    sources[&source].clone(),
+                    target,
+                    format!("{relationship:?}"),
+                )
+            })
+            .collect::<Vec<_>>();
+        relations.sort();
+        relations
+    }
+
+    /// Words of `language` by norm, with their lemma.
+    fn lemmas_of(
+        conn: &mut PgConnection,
+        language: Uuid,
+    ) -> HashMap<String, Option<Uuid>> {
+        words::table
+            .filter(words::language.eq(language))
+            .select((words::norm, words::lemma))
+            .load::<(String, Option<Uuid>)>(conn)
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    fn id_of(conn: &mut PgConnection, language: Uuid, norm: &str) -> Uuid {
+        words::table
+            .filter(words::language.eq(language))
+            .filter(words::norm.eq(norm))
+            .select(words::id)
+            .first(conn)
+            .unwrap()
+    }
+
+    fn lines(issues: &[ImportIssue]) -> Vec<i32> {
+        issues.iter().map(|issue| issue.row).collect()
+    }
+
+    /// Row importing the noun `norm` into `language`.
+    fn row(language: Uuid, norm: &str) -> Row {
+        Row {
+            line: 0,
+            word: ImportedWord {
+                id: Uuid::new_v4(),
+                norm: norm.to_string(),
+                native: None,
+                lemma: None,
+                language,
+                partofspeech: PartOfSpeech::Noun,
+                audio: None,
+                video: None,
+                image: None,
+                description: None,
+                etymology: None,
+                lusage: None,
+                morphology: None,
+            },
+            lemma: None,
+            relations: Vec::new(),
+            lift: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn reads_headers_case_and_separator_insensitively() {
+        assert_eq!(Some(WordField::Norm), WordField::from_header("Norm"));
+        assert_eq!(
+            Some(WordField::PartOfSpeech),
+            WordField::from_header("part-of speech")
+        );
+        assert_eq!(
+            Some(WordField::PartOfSpeech),
+            WordField::from_header("POS")
+        );
+        assert_eq!(Some(WordField::Usage), WordField::from_header("usage"));
+        assert_eq!(None, WordField::from_header("comment"));
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn dry_runs_report_issues_without_importing() {
+        let conn = &mut testing::connection();
+        let user = testing::user(conn);
+        let language = testing::language(conn, &user);
+        let data = "norm,partofspeech,lemma,comment\n\
+                    cat,noun,,\n\
+                    ,noun,,\n\
+                    dog,thing,,\n\
+                    cats,,cat,\n\
+                    dogs,noun,wolf,\n";
+
+        let import =
+            WordImport::parse(conn, language, data, ImportFormat::Csv, None)
+                .unwrap();
+        let (report, ids) = import.apply(conn, &user, true).unwrap();
+        assert!(ids.is_empty());
+        assert_eq!(5, report.rows);
+        assert_eq!(0, report.imported);
+        assert_eq!(vec![3, 4], lines(&report.errors));
+        assert_eq!(Some("norm"), report.errors[0].column.as_deref());
+        assert_eq!(Some("partofspeech"), report.errors[1].column.as_deref());
+        assert_eq!(vec![1, 5, 6], lines(&report.warnings));
+        assert_eq!(Some("comment"), report.warnings[0].column.as_deref());
+        assert_eq!(Some("lemma"), report.warnings[2].column.as_deref());
+        assert!(report.warnings[2].message.contains("wolf not found"));
+
+        // Errors prevent the import even without a dry run
+        let import =
+            WordImport::parse(conn, language, data, ImportFormat::Csv, None)
+                .unwrap();
+        let (report, ids) = import.apply(conn, &user, false).unwrap();
+        assert!(ids.is_empty());
+        assert_eq!(0, report.imported);
+        assert!(lemmas_of(conn, language).is_empty());
+
+        let data = "norm\tlemma\ncat\t\ncats\tcat\n";
+        let import =
+            WordImport::parse(conn, language, data, ImportFormat::Tsv, None)
+                .unwrap();
+        let (report, _) = import.apply(conn, &user, true).unwrap();
+        assert_eq!(2, report.rows);
+        assert_eq!(0, report.imported);
+        assert!(report.errors.is_empty());
+        assert!(lemmas_of(conn, language).is_empty());
+
+        let import =
+            WordImport::parse(conn, language, data, ImportFormat::Tsv, None)
+                .unwrap();
+        let (report, ids) = import.apply(conn, &user, false).unwrap();
+        assert_eq!(2, ids.len());
+        assert_eq!(2, report.imported);
+        let cat = id_of(conn, language, "cat");
+        assert_eq!(Some(cat), lemmas_of(conn, language)["cats"]);
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn exports_round_trip_through_the_importer() {
+        let db = testing::database();
+        let (user, source) = {
+            let conn = &mut db.conn().unwrap();
+            let user = testing::user(conn);
+            let source = testing::language(conn, &user);
+            let root = testing::word(conn, source, "root", None);
+            let derived = testing::word(conn, source, "derived", Some(root));
+            let other = testing::language(conn, &user);
+            let translation = testing::word(conn, other, "translation", None);
+            testing::relate(
+                conn,
+                root,
+                translation,
+                WordRelationship::Definition,
+            );
+            testing::relate(conn, derived, root, WordRelationship::Related);
+            diesel::update(words::table.find(derived))
+                .set((
+                    words::native.eq("dérivé"),
+                    words::partofspeech.eq(PartOfSpeech::Verb),
+                    words::description.eq("Quoted \"text\",\tand\nlines"),
+                    words::lusage.eq("rare"),
+                ))
+                .execute(conn)
+                .unwrap();
+            (user, source)
+        };
+        for (export, import) in [
+            (ExportFormat::Csv, ImportFormat::Csv),
+            (ExportFormat::Tsv, ImportFormat::Tsv),
+            (ExportFormat::Json, ImportFormat::Json),
+            (ExportFormat::Jsonl, ImportFormat::Jsonl),
+        ] {
+            let mut data = Vec::new();
+            let count = WordExport::new(&db, source, export)
+                .unwrap()
+                .write(&db, &mut data)
+                .unwrap();
+            assert_eq!(2, count);
+            let data = String::from_utf8(data).unwrap();
+
+            let conn = &mut db.conn().unwrap();
+            let copy = testing::language(conn, &user);
+            let (report, ids) =
+                WordImport::parse(conn, copy, &data, import, None)
+                    .unwrap()
+                    .apply(conn, &user, false)
+                    .unwrap();
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+            assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+            assert_eq!(2, ids.len());
+            assert_eq!(contents(conn, source), contents(conn, copy));
+            let relations = relations_of(conn, copy);
+            assert_eq!(2, relations.len(), "{export:?}");
+            assert_eq!(relations_of(conn, source), relations);
+
+            // A failed export is never imported, even partially
+            let mut failed = data.into_bytes();
+            export.write_failure(&mut failed).unwrap();
+            let failed = String::from_utf8(failed).unwrap();
+            let copy = testing::language(conn, &user);
+            let (report, ids) =
+                WordImport::parse(conn, copy, &failed, import, None)
+                    .unwrap()
+                    .apply(conn, &user, false)
+                    .unwrap();
+            assert!(ids.is_empty());
+            assert_eq!(1, report.errors.len());
+            assert!(report.errors[0].message.contains("export that failed"));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn lemma_cycles_are_broken() {
+        let conn = &mut testing::connection();
+        let user = testing::user(conn);
+        let language = testing::language(conn, &user);
+        let data = "norm,lemma\na,b\nb,c\nc,a\nd,d\ne,a\n";
+
+        let import =
+            WordImport::parse(conn, language, data, ImportFormat::Csv, None)
+                .unwrap();
+        let (report, _) = import.apply(conn, &user, false).unwrap();
+        assert_eq!(5, report.imported);
+        let warnings = report
+            .warnings
+            .iter()
+            .filter(|issue| issue.column.as_deref() == Some("lemma"))
+            .map(|issue| issue.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(2, warnings.len(), "{warnings:?}");
+        assert_eq!(1, warnings.iter().filter(|w| w.contains("cycle")).count());
+        assert!(warnings.contains(&"Word d cannot be its own lemma"));
+
+        let lemmas = lemmas_of(conn, language);
+        let cycle = ["a", "b", "c"];
+        assert_eq!(
+            1,
+            cycle.iter().filter(|norm| lemmas[**norm].is_none()).count()
+        );
+        assert_eq!(None, lemmas["d"]);
+        let a = id_of(conn, language, "a");
+        assert_eq!(Some(a), lemmas["e"]);
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn imports_more_rows_than_a_batch() {
+        let conn = &mut testing::connection();
+        let user = testing::user(conn);
+        let language = testing::language(conn, &user);
+        let count = 2 * BATCH_SIZE + 1;
+        // A single chain of lemmas going through every batch, only w0
+        // has no lemma
+        let mut data = String::from("norm,lemma\nw0,\n");
+        for index in 1..count {
+            let lemma = (index + BATCH_SIZE) % count;
+            writeln!(data, "w{index},w{lemma}").unwrap();
+        }
+
+        let import =
+            WordImport::parse(conn, language, &data, ImportFormat::Csv, None)
+                .unwrap();
+        let (report, ids) = import.apply(conn, &user, false).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(count, ids.len());
+        let lemmas = lemmas_of(conn, language);
+        assert_eq!(count, lemmas.len());
+        assert_eq!(1, lemmas.values().filter(|lemma| lemma.is_none()).count());
+        let revisions = wordrevisions::table
+            .filter(wordrevisions::language.eq(language))
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap();
+        assert_eq!(i64::try_from(count).unwrap(), revisions);
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn existing_references_are_scoped() {
+        let conn = &mut testing::connection();
+        let user = testing::user(conn);
+        let other = testing::user(conn);
+        let language = testing::language(conn, &user);
+        let public = testing::language(conn, &other);
+        let private = testing::language(conn, &other);
+        diesel::update(languages::table.find(private))
+            .set(languages::release.eq(Release::Private))
+            .execute(conn)
+            .unwrap();
+        let own = testing::word(conn, language, "own", None);
+        let shared = testing::word(conn, public, "shared", None);
+        let hidden = testing::word(conn, private, "hidden", None);
+
+        let mut rows =
+            vec![row(language, "a"), row(language, "b"), row(language, "c")];
+        rows[0].lemma = Some(Reference::Existing(own));
+        rows[1].lemma = Some(Reference::Existing(shared));
+        rows[2].lemma = Some(Reference::Existing(hidden));
+        rows[0].relations = vec![
+            (Reference::Existing(shared), WordRelationship::Definition),
+            (Reference::Existing(hidden), WordRelationship::Definition),
+        ];
+        let a = rows[0].word.id;
+        let import = WordImport::from_rows(
+            conn,
+            language,
+            rows,
+            None,
+            ImportReport::default(),
+        )
+        .unwrap();
+        let warnings = import
+            .report
+            .warnings
+            .iter()
+            .map(|issue| issue.message.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                format!("Word {hidden} not found, the relation is not imported"),
+                format!(
+                    "Word {shared} is not in the language, the word is imported without lemma"
+                ),
+                format!(
+                    "Word {hidden} not found, the word is imported without lemma"
+                ),
+            ],
+            warnings
+        );
+        let (_, ids) = import.apply(conn, &user, false).unwrap();
+        assert_eq!(3, ids.len());
+        let lemmas = lemmas_of(conn, language);
+        assert_eq!(Some(own), lemmas["a"]);
+        assert_eq!(None, lemmas["b"]);
+        assert_eq!(None, lemmas["c"]);
+        let targets = wordrelation::table
+            .filter(wordrelation::wordsource.eq(a))
+            .select(wordrelation::wordtarget)
+            .load::<Uuid>(conn)
+            .unwrap();
+        assert_eq!(vec![shared], targets);
+    }
+}
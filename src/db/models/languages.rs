@@ -1,22 +1,64 @@
 use crate::{
-    db::{Database, DatabaseError},
-    graphql::Context,
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::{subscription::LiveEvent, Context},
+};
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    result::{DatabaseErrorKind, Error},
 };
-use diesel::prelude::*;
 use juniper::{FieldResult, GraphQLEnum};
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use uuid::Uuid;
 
 use super::super::schema;
+use super::comments::Comment;
+use super::feed::FeedEvent;
+use super::forks::LanguageFork;
+use super::notifications::Event;
+use super::revisions::{
+    to_json, LanguageRevision, NewLanguageRevision, RevisionAction,
+};
 use super::users::User;
 
 use std::{convert::Into, fmt::Display};
 
 use schema::{langandagents, langtranslatesto, languages, userfollowlanguage};
 
+/// Index keeping users from owning two languages with the same name
+/// outside of the trash.
+const NAME_INDEX: &str = "languages_owner_name";
+
+/// Error telling `owner` they already own a language named `name`.
+pub fn name_taken(owner: &str, name: &str) -> DatabaseError {
+    DatabaseError::new(
+        format!("User {owner} already owns a language named {name}"),
+        ErrorKind::InvalidInput,
+    )
+}
+
+/// Whether `e` is due to a user owning two languages with the same
+/// name, which [`Language::collides_for`] cannot rule out when
+/// concurrent requests race each other.
+pub fn is_name_taken(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info)
+            if info.constraint_name() == Some(NAME_INDEX)
+    )
+}
+
 #[derive(
-    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+    diesel_derive_enum::DbEnum,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    GraphQLEnum,
+    Serialize,
+    Deserialize,
 )]
 #[DieselTypePath = "crate::db::schema::sql_types::Release"]
 pub enum Release {
@@ -34,7 +76,14 @@ impl Default for Release {
 }
 
 #[derive(
-    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+    diesel_derive_enum::DbEnum,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    GraphQLEnum,
+    Serialize,
+    Deserialize,
 )]
 #[DieselTypePath = "crate::db::schema::sql_types::Dictgenre"]
 pub enum DictGenre {
@@ -54,6 +103,7 @@ pub enum DictGenre {
 pub enum AgentLanguageRelation {
     Publisher,
     Author,
+    Maintainer,
 }
 
 #[derive(Default, Debug, Clone, juniper::GraphQLInputObject)]
@@ -105,43 +155,109 @@ impl From<NewLanguage> for NewLanguageInternal {
 impl NewLanguage {
     pub fn insert(
         &self,
-        db: &Database,
+        context: &Context,
         owner: &str,
     ) -> Result<Language, DatabaseError> {
         use languages::dsl;
-        let conn = &mut db.conn()?;
-        match diesel::insert_into(dsl::languages)
-            .values(NewLanguageInternal {
-                owner: owner.to_string(),
-                ..self.clone().into()
-            })
-            .execute(conn)
-        {
-            Ok(_) => dsl::languages
-                .filter(dsl::name.eq(self.name.clone()))
-                .filter(dsl::owner.eq(owner))
-                .first::<Language>(conn)
-                .map_err(|e| {
-                    DatabaseError::new(
-                        format!(
-                            "Failed to find language {} by user {owner}: {e:?}",
-                            self.name
-                        ),
-                        "Database Error",
-                    )
-                }),
-            Err(e) => Err(DatabaseError::new(
+        let conn = &mut context.db.conn()?;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let language = diesel::insert_into(dsl::languages)
+                .values(NewLanguageInternal {
+                    owner: owner.to_string(),
+                    ..self.clone().into()
+                })
+                .get_result::<Language>(conn)?;
+            NewLanguageRevision {
+                language: language.id,
+                author: Some(owner.to_string()),
+                action: RevisionAction::Create,
+                before: None,
+                after: Some(to_json(&language)?),
+            }
+            .record(conn)?;
+            Ok(language)
+        })
+        .map_err(|e| {
+            if is_name_taken(&e) {
+                return name_taken(owner, &self.name);
+            }
+            DatabaseError::new(
                 format!(
                     "Failed to insert language {} by user {owner}: {e:?}",
                     self.name
                 ),
-                "Database Error",
-            )),
+                ErrorKind::Internal,
+            )
+        })
+        .map(|language| {
+            Event::LanguageCreated {
+                actor: owner.to_string(),
+                language: language.id,
+            }
+            .dispatch(context);
+            language
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, juniper::GraphQLInputObject)]
+pub struct LanguageUpdate {
+    name: Option<String>,
+    native: Option<String>,
+    release: Option<Release>,
+    genre: Option<Vec<DictGenre>>,
+    abstract_: Option<String>,
+    description: Option<String>,
+    rights: Option<String>,
+    license: Option<String>,
+}
+
+#[derive(AsChangeset, Debug, Clone)]
+#[diesel(table_name = languages)]
+struct LanguageChangeset {
+    name: Option<String>,
+    native: Option<String>,
+    release: Option<Release>,
+    genre: Option<Vec<DictGenre>>,
+    abstract_: Option<String>,
+    description: Option<String>,
+    rights: Option<String>,
+    license: Option<String>,
+}
+
+impl From<LanguageUpdate> for LanguageChangeset {
+    fn from(val: LanguageUpdate) -> Self {
+        LanguageChangeset {
+            name: val.name,
+            native: val.native,
+            release: val.release,
+            genre: val.genre,
+            abstract_: val.abstract_,
+            description: val.description,
+            rights: val.rights,
+            license: val.license,
         }
     }
 }
 
-#[derive(Queryable, Insertable, Debug, Clone)]
+/// Description of a language written at the top of scholarly
+/// exports of its dictionary.
+#[derive(Debug, Clone)]
+pub struct LanguageMetadata {
+    pub id: Uuid,
+    pub name: String,
+    pub native: Option<String>,
+    pub genre: Vec<DictGenre>,
+    pub abstract_: Option<String>,
+    pub created: chrono::NaiveDateTime,
+    pub rights: Option<String>,
+    pub license: Option<String>,
+    pub owner: User,
+    pub authors: Vec<User>,
+    pub publishers: Vec<User>,
+}
+
+#[derive(Queryable, Insertable, Debug, Clone, Serialize, Deserialize)]
 pub struct Language {
     id: Uuid,
     name: String,
@@ -154,6 +270,8 @@ pub struct Language {
     rights: Option<String>,
     license: Option<String>,
     owner: String,
+    #[serde(default)]
+    deleted: Option<chrono::NaiveDateTime>,
 }
 
 impl Display for Language {
@@ -167,66 +285,296 @@ impl Language {
         self.owner == owner
     }
 
+    pub fn uuid(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn is_trashed(&self) -> bool {
+        self.deleted.is_some()
+    }
+
+    /// Whether the language can be seen by `user`, anonymous if
+    /// `None`: private languages are only visible to their owner.
+    pub fn is_visible_to(&self, user: Option<&str>) -> bool {
+        self.release != Release::Private
+            || user.map_or(false, |user| self.is_owned_by(user))
+    }
+
+    /// Languages that are not in the trash.
+    ///
+    /// Every read path should start from this query rather than from
+    /// the `languages` table itself.
+    pub fn visible() -> languages::BoxedQuery<'static, Pg> {
+        languages::table
+            .filter(languages::deleted.is_null())
+            .into_boxed()
+    }
+
     pub fn find(
         db: &Database,
         language: Uuid,
     ) -> Result<Language, DatabaseError> {
-        use languages::dsl;
-        dsl::languages.find(language).first::<Language>(&mut db.conn()?).map_err(|e| match e {
+        Language::fetch(&mut db.conn()?, language)
+    }
+
+    /// Same as [`Language::find`], through a connection already
+    /// checked out.
+    pub fn fetch(
+        conn: &mut PgConnection,
+        language: Uuid,
+    ) -> Result<Language, DatabaseError> {
+        Language::visible().filter(languages::id.eq(language)).first::<Language>(conn).map_err(|e| match e {
             diesel::NotFound => DatabaseError::new(
                 format!("Language {language} not found"),
-                "Not Found"
+                ErrorKind::NotFound
             ),
             e => DatabaseError::new(
                 format!("Error fetching language {language} from database: {e:?}"),
-                "Database Error"
+                ErrorKind::Internal
             )
         })
     }
 
+    /// Language `language` even if it is in the trash, `None` if it
+    /// does not exist.
+    pub fn find_with_trashed(
+        db: &Database,
+        language: Uuid,
+    ) -> Result<Option<Language>, DatabaseError> {
+        languages::table
+            .find(language)
+            .first::<Language>(&mut db.conn()?)
+            .optional()
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Error fetching language {language} from database: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Whether `user`, anonymous if `None`, may see the language and
+    /// what happens to it: private languages and languages in the
+    /// trash are only visible to their owner.
+    pub fn is_readable_by(&self, user: Option<&str>) -> bool {
+        self.is_visible_to(user)
+            && (!self.is_trashed()
+                || user.map_or(false, |user| self.is_owned_by(user)))
+    }
+
     pub fn delete(
         context: &Context,
         language_id: Uuid,
     ) -> Result<(), DatabaseError> {
         use languages::dsl;
         let conn = &mut context.db.conn()?;
-        match dsl::languages.find(language_id)
-                            .first::<Language>(conn)
+        match Language::visible()
+            .filter(dsl::id.eq(language_id))
+            .first::<Language>(conn)
         {
-            Ok(language) if context.user_auth == Some(language.owner.clone()) => {
-                match diesel::delete(dsl::languages.find(language_id))
-                    .execute(conn) {
-                        Ok(_) => Ok(()),
-                        Err(e) => Err(DatabaseError::new(
-                            format!("Failed to delete language {language_id}: {e:?}"),
-                            "Database Error"
-                        ))
+            Ok(language)
+                if context.language_writer() == Some(&language.owner)
+                    && context.may_write(language_id) =>
+            {
+                conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                    diesel::update(dsl::languages.find(language_id))
+                        .set(dsl::deleted.eq(diesel::dsl::now.nullable()))
+                        .execute(conn)?;
+                    NewLanguageRevision {
+                        language: language_id,
+                        author: context.user_auth().cloned(),
+                        action: RevisionAction::Delete,
+                        before: Some(to_json(&language)?),
+                        after: None,
                     }
-            },
-            Ok(language) => {
-                Err(DatabaseError::new(
+                    .record(conn)?;
+                    Ok(())
+                })
+                .map_err(|e| {
+                    DatabaseError::new(
+                        format!(
+                            "Failed to delete language {language_id}: {e:?}"
+                        ),
+                        ErrorKind::Internal,
+                    )
+                })?;
+                context.live.publish(LiveEvent::Language {
+                    id: language_id,
+                    action: RevisionAction::Delete,
+                });
+                Ok(())
+            }
+            Ok(_) => Err(DatabaseError::new(
+                format!(
+                    "Not allowed to delete other user's language {language_id}"
+                ),
+                ErrorKind::Forbidden,
+            )),
+            Err(diesel::NotFound) => Err(DatabaseError::new(
+                format!("Language {language_id} not found"),
+                ErrorKind::NotFound,
+            )),
+            Err(e) => Err(DatabaseError::new(
+                format!("Failed to delete language {language_id}: {e:?}"),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+
+    /// Take a language owned by `user` out of the trash.
+    pub fn restore(
+        context: &Context,
+        language_id: Uuid,
+        user: &str,
+    ) -> Result<Language, DatabaseError> {
+        use languages::dsl;
+        let conn = &mut context.db.conn()?;
+        let language = match dsl::languages
+            .find(language_id)
+            .first::<Language>(conn)
+        {
+            Ok(language) if !language.is_trashed() => {
+                return Err(DatabaseError::new(
+                    format!("Language {language_id} is not in the trash"),
+                    ErrorKind::Conflict,
+                ))
+            }
+            Ok(language)
+                if !language.is_owned_by(user)
+                    || !context.may_write(language_id) =>
+            {
+                return Err(DatabaseError::new(
                     format!(
-                        "User {} not allowed to delete other user's language {language_id}",
-                        language.owner),
-                    "Unauthorized"
+                        "User {user} not allowed to restore other user's language {language_id}"
+                    ),
+                    ErrorKind::Forbidden,
                 ))
-            },
+            }
+            Ok(language) => language,
+            Err(diesel::NotFound) => {
+                return Err(DatabaseError::new(
+                    format!("Language {language_id} not found"),
+                    ErrorKind::NotFound,
+                ))
+            }
             Err(e) => {
-                Err(DatabaseError::new(
-                    format!("Failed to delete language {language_id}: {e:?}"),
-                    "Database Error"
+                return Err(DatabaseError::new(
+                    format!(
+                        "Error fetching language {language_id} from database: {e:?}"
+                    ),
+                    ErrorKind::Internal,
                 ))
             }
+        };
+        // A language named like it may have been created while it was
+        // in the trash
+        if language.collides_for(conn, user)? {
+            return Err(language.collision(user));
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let restored = diesel::update(dsl::languages.find(language_id))
+                .set(dsl::deleted.eq(None::<chrono::NaiveDateTime>))
+                .get_result::<Language>(conn)?;
+            NewLanguageRevision {
+                language: language_id,
+                author: Some(user.to_string()),
+                action: RevisionAction::Restore,
+                before: Some(to_json(&language)?),
+                after: Some(to_json(&restored)?),
+            }
+            .record(conn)?;
+            Ok(restored)
+        })
+        .map_err(|e| {
+            if is_name_taken(&e) {
+                return language.collision(user);
+            }
+            DatabaseError::new(
+                format!("Failed to restore language {language_id}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+        .map(|language| {
+            context.live.publish(LiveEvent::Language {
+                id: language_id,
+                action: RevisionAction::Restore,
+            });
+            language
+        })
+    }
+
+    /// Update the metadata of a language owned by `user`, recording
+    /// the change in the language's history.
+    pub fn update(
+        context: &Context,
+        language_id: Uuid,
+        user: &str,
+        changes: LanguageUpdate,
+    ) -> Result<Language, DatabaseError> {
+        use languages::dsl;
+        let language = Language::find(&context.db, language_id)?;
+        if !language.is_owned_by(user) || !context.may_write(language_id) {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} not allowed to edit other user's language {language_id}"
+                ),
+                ErrorKind::Forbidden,
+            ));
         }
+        let name = changes
+            .name
+            .clone()
+            .unwrap_or_else(|| language.name.clone());
+        let changes: LanguageChangeset = changes.into();
+        let conn = &mut context.db.conn()?;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = diesel::update(dsl::languages.find(language_id))
+                .set(&changes)
+                .get_result::<Language>(conn)?;
+            NewLanguageRevision {
+                language: language_id,
+                author: Some(user.to_string()),
+                action: RevisionAction::Update,
+                before: Some(to_json(&language)?),
+                after: Some(to_json(&updated)?),
+            }
+            .record(conn)?;
+            Ok(updated)
+        })
+        .map_err(|e| {
+            if is_name_taken(&e) {
+                return name_taken(user, &name);
+            }
+            DatabaseError::new(
+                format!("Failed to update language {language_id}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+        .map(|language| {
+            context.live.publish(LiveEvent::Language {
+                id: language_id,
+                action: RevisionAction::Update,
+            });
+            language
+        })
     }
 
     fn relationship(
         &self,
         db: &Database,
         relationship: AgentLanguageRelation,
+    ) -> Result<Vec<User>, DatabaseError> {
+        self.agents(&mut db.conn()?, relationship)
+    }
+
+    fn agents(
+        &self,
+        conn: &mut PgConnection,
+        relationship: AgentLanguageRelation,
     ) -> Result<Vec<User>, DatabaseError> {
         use schema::langandagents::dsl;
-        let conn = &mut db.conn()?;
         Ok(dsl::langandagents
             .filter(dsl::language.eq(self.id))
             .filter(dsl::relationship.eq(relationship))
@@ -234,7 +582,7 @@ impl Language {
             .map_err(|e| {
                 DatabaseError::new(
                     format!("Failed to retrieve language relationship: {e:?}"),
-                    "Database reading error",
+                    ErrorKind::Internal,
                 )
             })?
             .iter()
@@ -251,6 +599,254 @@ impl Language {
             })
             .collect::<Vec<User>>())
     }
+
+    fn has_agent(
+        &self,
+        conn: &mut PgConnection,
+        agent: &str,
+        relationship: AgentLanguageRelation,
+    ) -> QueryResult<bool> {
+        use schema::langandagents::dsl;
+        diesel::select(diesel::dsl::exists(
+            dsl::langandagents
+                .filter(dsl::language.eq(self.id))
+                .filter(dsl::agent.eq(agent))
+                .filter(dsl::relationship.eq(relationship)),
+        ))
+        .get_result::<bool>(conn)
+    }
+
+    /// Give `agent` a relationship with the language unless they
+    /// already have it.
+    pub fn add_agent(
+        &self,
+        conn: &mut PgConnection,
+        agent: &str,
+        relationship: AgentLanguageRelation,
+    ) -> QueryResult<()> {
+        use schema::langandagents::dsl;
+        if !self.has_agent(conn, agent, relationship.clone())? {
+            diesel::insert_into(dsl::langandagents)
+                .values((
+                    dsl::agent.eq(agent),
+                    dsl::language.eq(self.id),
+                    dsl::relationship.eq(relationship),
+                ))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Whether users other than the owner may make derivative works
+    /// of the language, such as forks.
+    ///
+    /// Only public languages qualify, non-commercial and research
+    /// releases are only shared for the uses they name. Their license
+    /// must also be set, without it nothing grants the right to adapt
+    /// the language, and allow derivative works, which licenses such
+    /// as CC BY-ND forbid.
+    pub fn allows_derivatives(&self) -> bool {
+        self.release == Release::Public
+            && self.license.as_deref().map_or(false, |license| {
+                let license = license.to_lowercase();
+                !(license.contains("-nd")
+                    || license.contains("noderivatives")
+                    || license.contains("no derivatives"))
+            })
+    }
+
+    /// Metadata of the language along with its owner, authors and
+    /// publishers.
+    pub fn metadata(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<LanguageMetadata, DatabaseError> {
+        use schema::users::dsl;
+        let owner =
+            dsl::users
+                .find(&self.owner)
+                .first::<User>(conn)
+                .map_err(|e| {
+                    DatabaseError::new(
+                        format!("Failed to retrieve owner of {self}: {e:?}"),
+                        ErrorKind::Internal,
+                    )
+                })?;
+        Ok(LanguageMetadata {
+            id: self.id,
+            name: self.name.clone(),
+            native: self.native.clone(),
+            genre: self.genre.iter().flatten().cloned().collect(),
+            abstract_: self.abstract_.clone(),
+            created: self.created,
+            rights: self.rights.clone(),
+            license: self.license.clone(),
+            owner,
+            authors: self.agents(conn, AgentLanguageRelation::Author)?,
+            publishers: self.agents(conn, AgentLanguageRelation::Publisher)?,
+        })
+    }
+
+    /// Insert a copy of the language's metadata, but not its words,
+    /// named `name` and owned by `owner`.
+    ///
+    /// Its release, rights and license are kept so the copy is under
+    /// the same terms as the original.
+    pub fn insert_copy(
+        &self,
+        conn: &mut PgConnection,
+        owner: &str,
+        name: &str,
+    ) -> QueryResult<Language> {
+        let language = diesel::insert_into(languages::table)
+            .values(NewLanguageInternal {
+                name: name.to_string(),
+                native: self.native.clone(),
+                release: self.release.clone(),
+                genre: self.genre.iter().flatten().cloned().collect(),
+                abstract_: self.abstract_.clone(),
+                description: self.description.clone(),
+                rights: self.rights.clone(),
+                license: self.license.clone(),
+                owner: owner.to_string(),
+            })
+            .get_result::<Language>(conn)?;
+        NewLanguageRevision {
+            language: language.id,
+            author: Some(owner.to_string()),
+            action: RevisionAction::Create,
+            before: None,
+            after: Some(to_json(&language)?),
+        }
+        .record(conn)?;
+        Ok(language)
+    }
+
+    /// Whether `agent` has any relationship with the language.
+    pub fn is_collaborator(
+        &self,
+        conn: &mut PgConnection,
+        agent: &str,
+    ) -> QueryResult<bool> {
+        use schema::langandagents::dsl;
+        diesel::select(diesel::dsl::exists(
+            dsl::langandagents
+                .filter(dsl::language.eq(self.id))
+                .filter(dsl::agent.eq(agent)),
+        ))
+        .get_result::<bool>(conn)
+    }
+
+    /// Whether `owner` already owns a language named like this one,
+    /// which would make it ambiguous for them.
+    pub fn collides_for(
+        &self,
+        conn: &mut PgConnection,
+        owner: &str,
+    ) -> QueryResult<bool> {
+        use languages::dsl;
+        diesel::select(diesel::dsl::exists(
+            dsl::languages
+                .filter(dsl::owner.eq(owner))
+                .filter(dsl::name.eq(&self.name))
+                .filter(dsl::deleted.is_null())
+                .filter(dsl::id.ne(self.id)),
+        ))
+        .get_result::<bool>(conn)
+    }
+
+    /// Error telling `owner` they already own a language named like
+    /// this one, see [`Language::collides_for`].
+    pub fn collision(&self, owner: &str) -> DatabaseError {
+        name_taken(owner, &self.name)
+    }
+
+    /// Make `new_owner` the owner of the language on behalf of
+    /// `author` without checking whether they are allowed to.
+    pub fn transfer_unchecked(
+        &self,
+        conn: &mut PgConnection,
+        author: &str,
+        new_owner: &str,
+    ) -> Result<Language, DatabaseError> {
+        use languages::dsl;
+        if self.collides_for(conn, new_owner)? {
+            return Err(self.collision(new_owner));
+        }
+        let updated = diesel::update(dsl::languages.find(self.id))
+            .set(dsl::owner.eq(new_owner))
+            .get_result::<Language>(conn)
+            .map_err(|e| {
+                if is_name_taken(&e) {
+                    self.collision(new_owner)
+                } else {
+                    e.into()
+                }
+            })?;
+        NewLanguageRevision {
+            language: self.id,
+            author: Some(author.to_string()),
+            action: RevisionAction::Update,
+            before: Some(to_json(self)?),
+            after: Some(to_json(&updated)?),
+        }
+        .record(conn)?;
+        Ok(updated)
+    }
+
+    /// Whether `user` can review changes suggested for the language,
+    /// that is whether they own or maintain it.
+    pub fn can_review(
+        &self,
+        db: &Database,
+        user: &str,
+    ) -> Result<bool, DatabaseError> {
+        Ok(self.is_owned_by(user)
+            || self.has_agent(
+                &mut db.conn()?,
+                user,
+                AgentLanguageRelation::Maintainer,
+            )?)
+    }
+
+    /// Add or remove a maintainer of a language owned by `user`.
+    pub fn set_maintainer(
+        context: &Context,
+        language_id: Uuid,
+        user: &str,
+        maintainer: &str,
+        is_maintainer: bool,
+    ) -> Result<Language, DatabaseError> {
+        use schema::langandagents::dsl;
+        let language = Language::find(&context.db, language_id)?;
+        if !language.is_owned_by(user) || !context.may_write(language_id) {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} not allowed to manage maintainers of other user's language {language_id}"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        let conn = &mut context.db.conn()?;
+        if is_maintainer {
+            language.add_agent(
+                conn,
+                maintainer,
+                AgentLanguageRelation::Maintainer,
+            )?;
+        } else {
+            diesel::delete(
+                dsl::langandagents
+                    .filter(dsl::language.eq(language_id))
+                    .filter(dsl::agent.eq(maintainer))
+                    .filter(
+                        dsl::relationship.eq(AgentLanguageRelation::Maintainer),
+                    ),
+            )
+            .execute(conn)?;
+        }
+        Ok(language)
+    }
 }
 
 #[juniper::graphql_object(Context = Context)]
@@ -292,18 +888,19 @@ impl Language {
                         format!(
                             "Failed to retrieve language translations: {e:?}"
                         ),
-                        "Database reading failure",
+                        ErrorKind::Internal,
                     )
                 })?
                 .into_iter()
                 .flat_map(|l| {
-                    use schema::languages::dsl;
-                    dsl::languages.find(l.langto).first::<Language>(conn)
+                    Language::visible()
+                        .filter(languages::id.eq(l.langto))
+                        .first::<Language>(conn)
                 })
                 .collect::<Vec<Language>>()),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to connect to the database: {e:?}"),
-                "Database connection failure",
+                ErrorKind::Internal,
             )
             .into()),
         }
@@ -363,12 +960,12 @@ impl Language {
                             "Failed to retrieve owner {} of language {}: {e:?}",
                             self.owner, self.name
                         ),
-                        "Database error",
+                        ErrorKind::Internal,
                     )
                 })?),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to connect to the database: {e:?}"),
-                "Database connection failure",
+                ErrorKind::Internal,
             )
             .into()),
         }
@@ -390,9 +987,46 @@ impl Language {
             .map_err(Into::into)
     }
 
-    #[graphql(description = "People following the language")]
+    #[graphql(
+        name = "recentChanges",
+        description = "Changes made to the language and its words, most recent first",
+        arguments(
+            first(description = "Maximum amount of changes to return"),
+            after(description = "Cursor of the change to start after")
+        )
+    )]
+    fn recent_changes(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<Vec<FeedEvent>> {
+        if self.is_visible_to(context.user_auth().map(String::as_str)) {
+            FeedEvent::recent_changes(&context.db, self.id, first, after)
+                .map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                format!("Changes of private language {self} are not public"),
+                ErrorKind::Forbidden,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "People who can review changes suggested for the language's dictionary"
+    )]
+    fn maintainers(&self, context: &Context) -> FieldResult<Vec<User>> {
+        self.relationship(&context.db, AgentLanguageRelation::Maintainer)
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "People following the language, except those who hide the languages they follow"
+    )]
     fn followers(&self, context: &Context) -> FieldResult<Vec<User>> {
         use schema::userfollowlanguage::dsl;
+        let requester = context.user_auth();
         match &mut context.db.conn() {
             Ok(conn) => {
                 Ok(dsl::userfollowlanguage
@@ -400,7 +1034,7 @@ impl Language {
                    .load::<UserFollowLanguage>(conn)
                    .map_err(|e| {
                        DatabaseError::new(format!("Failed to retrieve language followers for language {}: {e:?}", self.id),
-                       "Database error")
+                       ErrorKind::Internal)
                    })?
                    .into_iter()
                    .filter_map(|follow| {
@@ -408,7 +1042,9 @@ impl Language {
                        match dsl::users
                            .find(follow.userid.clone())
                            .first::<User>(conn) {
-                               Ok(user) => Some(user),
+                               Ok(user) if user.public_languages_followed
+                                   || requester == Some(&user.id) => Some(user),
+                               Ok(_) => None,
                                Err(e) => {
                                    info!("Failed to retrieve user {} from database: {e:?}", follow.userid);
                                    None
@@ -420,11 +1056,81 @@ impl Language {
             }
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to connect to the database: {e:?}"),
-                "Database connection failure",
+                ErrorKind::Internal,
             )
             .into()),
         }
     }
+
+    #[graphql(
+        description = "Language this one was forked from, null if it was not forked"
+    )]
+    fn forked_from(
+        &self,
+        context: &Context,
+    ) -> FieldResult<Option<LanguageFork>> {
+        LanguageFork::of(&context.db, self.id).map_err(Into::into)
+    }
+
+    #[graphql(
+        name = "deletedAt",
+        description = "Time at which the language was put in the trash, null if it is not in the trash"
+    )]
+    fn deleted_at(&self) -> Option<String> {
+        self.deleted.map(|deleted| deleted.to_string())
+    }
+
+    #[graphql(
+        description = "Changes made to the language's metadata, most recent first"
+    )]
+    fn history(&self, context: &Context) -> FieldResult<Vec<LanguageRevision>> {
+        LanguageRevision::history(
+            &context.db,
+            self.id,
+            context.user_auth().map(String::as_str),
+        )
+        .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Discussion threads about the language itself, oldest first",
+        arguments(
+            first(description = "Maximum amount of threads to return"),
+            after(
+                description = "Only return threads started after this comment"
+            )
+        )
+    )]
+    fn comments(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<Vec<Comment>> {
+        Comment::of_language(context, self.id, first, after).map_err(Into::into)
+    }
+
+    #[graphql(description = "Amount of people learning words of the language")]
+    fn learners(&self, context: &Context) -> FieldResult<i32> {
+        use schema::{wordlearning, words};
+        wordlearning::table
+            .inner_join(words::table)
+            .filter(words::language.eq(self.id))
+            .filter(words::deleted.is_null())
+            .select(diesel::dsl::count_distinct(wordlearning::userid))
+            .first::<i64>(&mut context.db.conn()?)
+            .map(|count| i32::try_from(count).unwrap_or(i32::MAX))
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to count learners of language {}: {e:?}",
+                        self.id
+                    ),
+                    ErrorKind::Internal,
+                )
+                .into()
+            })
+    }
 }
 
 #[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
@@ -466,16 +1172,25 @@ impl UserFollowLanguage {
         lang: Uuid,
     ) -> Result<Language, DatabaseError> {
         let conn = &mut context.db.conn()?;
-        match languages::dsl::languages.find(lang).first::<Language>(conn) {
+        match Language::visible()
+            .filter(languages::id.eq(lang))
+            .first::<Language>(conn)
+        {
+            Ok(language) if !language.is_readable_by(Some(userid)) => {
+                Err(DatabaseError::new(
+                    format!("Cannot follow non-existing language {lang}"),
+                    ErrorKind::NotFound,
+                ))
+            }
             Err(diesel::NotFound) => Err(DatabaseError::new(
                 format!("Cannot follow non-existing language {lang}"),
-                "Invalid Language",
+                ErrorKind::NotFound,
             )),
             Err(e) => Err(DatabaseError::new(
                 format!(
                     "Could not retrieve language {lang} from database: {e:?}"
                 ),
-                "Database error",
+                ErrorKind::Internal,
             )),
             Ok(language) => {
                 use userfollowlanguage::dsl;
@@ -485,7 +1200,7 @@ impl UserFollowLanguage {
                         Ok(_) => Ok(language),
                         Err(e) => Err(DatabaseError::new(
                             format!("Failed to follow language {lang} as user {userid}: {e:?}"),
-                            "Database Error"
+                            ErrorKind::Internal
                         ))
                     }
             }
@@ -509,20 +1224,57 @@ impl UserFollowLanguage {
                             Ok(_) => Language::find(&context.db, lang),
                             Err(e) => Err(DatabaseError::new(
                                 format!("Failed to make user {userid} unfollow language {lang}: {e:?}"),
-                                "Database Error"
+                                ErrorKind::Internal
                             ))
                         }
                 },
                 Err(diesel::NotFound) => {
                     Err(DatabaseError::new(
                         format!("User {userid} does not follow language {lang}"),
-                        "Invalid",
+                        ErrorKind::Conflict,
                     ))
                 }
                 Err(e) => Err(DatabaseError::new(
                     format!("Failed to retrieve relationship between user {userid} and language {lang} from database: {e:?}"),
-                    "Database Error",
+                    ErrorKind::Internal,
                 ))
             }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn languages_named_like_a_restored_one_block_it() {
+        let db = testing::database();
+        let (owner, trashed, other) = {
+            let conn = &mut db.conn().unwrap();
+            let owner = testing::user(conn);
+            (
+                owner.clone(),
+                testing::language(conn, &owner),
+                testing::language(conn, &owner),
+            )
+        };
+        let context = Context::of_user(db.clone(), &owner);
+        Language::delete(&context, trashed).unwrap();
+        diesel::update(languages::table.find(other))
+            .set(languages::name.eq(format!("test-{trashed}")))
+            .execute(&mut db.conn().unwrap())
+            .unwrap();
+        let err = Language::restore(&context, trashed, &owner).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+
+        diesel::update(languages::table.find(other))
+            .set(languages::name.eq(format!("test-{other}")))
+            .execute(&mut db.conn().unwrap())
+            .unwrap();
+        assert!(Language::restore(&context, trashed, &owner)
+            .unwrap()
+            .is_owned_by(&owner));
+    }
+}
@@ -0,0 +1,1215 @@
+use super::super::schema;
+use super::{
+    exports::{escape_xml, WordRecord},
+    imports::{
+        ImportReport, ImportedWord, Reference, Row, WordImport, MAX_ROWS,
+    },
+    words::{PartOfSpeech, WordRelationship},
+};
+use crate::db::{DatabaseError, ErrorKind};
+
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use schema::{liftentries, liftheaders};
+
+/// Writing system of the norm of words when a language has none.
+pub const DEFAULT_NORM_LANG: &str = "qaa-x-norm";
+/// Writing system of definitions and notes when a language has none.
+const DEFAULT_ANALYSIS_LANG: &str = "en";
+/// Relation type written from a word to its lemma.
+const LEMMA_RELATION: &str = "_component-lexeme";
+/// Extensions of media files read as videos rather than audio.
+const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "webm", "ogv", "mov", "mkv", "avi"];
+
+/// Parts of the LIFT entry of a word Ordabok has no field for.
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = liftentries)]
+pub struct LiftEntry {
+    pub word: Uuid,
+    pub extras: serde_json::Value,
+}
+
+/// Header and writing systems of the LIFT file a language was
+/// imported from.
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[diesel(table_name = liftheaders)]
+pub struct LiftHeader {
+    language: Uuid,
+    header: Option<String>,
+    normlang: String,
+    nativelang: Option<String>,
+    analysislang: String,
+}
+
+impl LiftHeader {
+    /// Default header of the language `language`, for languages never
+    /// imported from LIFT.
+    pub fn new(language: Uuid) -> LiftHeader {
+        LiftHeader {
+            language,
+            header: None,
+            normlang: DEFAULT_NORM_LANG.to_string(),
+            nativelang: None,
+            analysislang: DEFAULT_ANALYSIS_LANG.to_string(),
+        }
+    }
+
+    /// Header of the language `language`, or defaults if it was never
+    /// imported from LIFT.
+    pub fn of(
+        conn: &mut PgConnection,
+        language: Uuid,
+    ) -> QueryResult<LiftHeader> {
+        Ok(liftheaders::table
+            .find(language)
+            .first::<LiftHeader>(conn)
+            .optional()?
+            .unwrap_or_else(|| LiftHeader::new(language)))
+    }
+
+    /// Writing system of the norm of words.
+    pub fn normlang(&self) -> &str {
+        &self.normlang
+    }
+
+    /// Writing system of the native spelling of words, that of their
+    /// norm if unknown.
+    pub fn nativelang(&self) -> &str {
+        self.nativelang.as_deref().unwrap_or(&self.normlang)
+    }
+
+    /// Writing system of definitions and notes.
+    pub fn analysislang(&self) -> &str {
+        &self.analysislang
+    }
+
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        use liftheaders::dsl;
+        diesel::insert_into(dsl::liftheaders)
+            .values(self)
+            .on_conflict(dsl::language)
+            .do_update()
+            .set((
+                dsl::header.eq(&self.header),
+                dsl::normlang.eq(&self.normlang),
+                dsl::nativelang.eq(&self.nativelang),
+                dsl::analysislang.eq(&self.analysislang),
+            ))
+            .execute(conn)
+    }
+}
+
+/// Relation of a LIFT entry whose type or content has no equivalent
+/// in Ordabok.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+struct RelationExtras {
+    /// Word the relation points to, absent for lemmas
+    target: Option<Uuid>,
+    kind: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<String>,
+}
+
+/// Parts of a LIFT entry Ordabok has no field for, kept as XML so
+/// that they are written back when exporting.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+struct Extras {
+    /// Attributes of the entry other than its id
+    attributes: Vec<(String, String)>,
+    /// Forms of the lexical unit other than the norm and native form
+    forms: Vec<String>,
+    /// Unmapped children of the entry, including senses after the
+    /// first one
+    entry: Vec<String>,
+    /// Attributes of the first sense
+    sense_attributes: Vec<(String, String)>,
+    /// Unmapped children of the first sense
+    sense: Vec<String>,
+    /// Grammatical info of the first sense matching no part of speech
+    grammatical_info: Option<String>,
+    /// Forms of the definition other than the description
+    definition: Vec<String>,
+    /// Attributes of the etymology
+    etymology_attributes: Vec<(String, String)>,
+    /// Children of the etymology other than its first form
+    etymology: Vec<String>,
+    /// Writing system of mapped texts not in the analysis language,
+    /// by LIFT element
+    langs: HashMap<String, String>,
+    lemma: Option<RelationExtras>,
+    relations: Vec<RelationExtras>,
+}
+
+fn xml(element: &Element) -> String {
+    let mut buffer = Vec::new();
+    let config = EmitterConfig::new()
+        .write_document_declaration(false)
+        .perform_indent(false);
+    match element.write_with_config(&mut buffer, config) {
+        Ok(()) => String::from_utf8_lossy(&buffer).into_owned(),
+        Err(e) => {
+            warn!("Failed to serialize LIFT element {}: {e:?}", element.name);
+            String::new()
+        }
+    }
+}
+
+fn children_xml(element: &Element) -> Vec<String> {
+    element
+        .children
+        .iter()
+        .filter_map(XMLNode::as_element)
+        .map(xml)
+        .collect()
+}
+
+fn attributes(element: &Element, skip: &[&str]) -> Vec<(String, String)> {
+    element
+        .attributes
+        .iter()
+        .filter(|(name, _)| !skip.contains(&name.as_str()))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+fn attribute<'a>(element: &'a Element, name: &str) -> Option<&'a str> {
+    element.attributes.get(name).map(String::as_str)
+}
+
+/// Text of a `<form>`, if it has any.
+fn form_text(form: &Element) -> Option<String> {
+    form.get_child("text")
+        .and_then(Element::get_text)
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Remove the first `<form>` of `element` with a text, in the writing
+/// system `lang` if set, and return its writing system and text.
+fn take_form(
+    element: &mut Element,
+    lang: Option<&str>,
+) -> Option<(String, String)> {
+    let (index, text) = element.children.iter().enumerate().find_map(
+        |(index, node)| match node {
+            XMLNode::Element(form)
+                if form.name == "form"
+                    && lang.map_or(true, |lang| {
+                        attribute(form, "lang") == Some(lang)
+                    }) =>
+            {
+                form_text(form).map(|text| (index, text))
+            }
+            _ => None,
+        },
+    )?;
+    let lang = element
+        .children
+        .remove(index)
+        .as_element()
+        .and_then(|form| attribute(form, "lang").map(String::from));
+    Some((lang.unwrap_or_default(), text))
+}
+
+/// Writing systems of the forms of the `child` elements of `element`.
+fn form_langs<'a>(
+    element: &'a Element,
+    child: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    element
+        .children
+        .iter()
+        .filter_map(XMLNode::as_element)
+        .filter(move |element| element.name == child)
+        .flat_map(|element| element.children.iter())
+        .filter_map(XMLNode::as_element)
+        .filter(|form| form.name == "form")
+        .filter_map(|form| attribute(form, "lang"))
+}
+
+/// Whether LIFT relations of type `kind` point to the main entry of a
+/// variant, as FieldWorks, WeSay and Lexique Pro write them.
+fn is_lemma_relation(kind: &str) -> bool {
+    matches!(
+        kind.to_lowercase().replace(['_', '-'], " ").trim(),
+        "component lexeme"
+            | "main entry"
+            | "base form"
+            | "baseform"
+            | "variant of"
+    )
+}
+
+fn is_video(href: &str) -> bool {
+    href.rsplit_once('.').map_or(false, |(_, extension)| {
+        VIDEO_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+    })
+}
+
+/// Entry of a LIFT file being read.
+struct EntryReader<'a> {
+    rank: usize,
+    header: &'a LiftHeader,
+    /// UUIDs given to the entries of the file, by LIFT id
+    ids: &'a HashMap<String, Uuid>,
+    report: &'a mut ImportReport,
+    word: ImportedWord,
+    extras: Extras,
+    lemma: Option<Reference>,
+    relations: Vec<(Reference, WordRelationship)>,
+    variants: Vec<Element>,
+}
+
+impl EntryReader<'_> {
+    /// Word the LIFT id or UUID `id` refers to.
+    fn reference(&mut self, id: &str) -> Option<(Reference, Uuid)> {
+        if let Some(uuid) = self.ids.get(id) {
+            Some((Reference::Imported(*uuid), *uuid))
+        } else if let Ok(uuid) = Uuid::from_str(id) {
+            Some((Reference::Existing(uuid), uuid))
+        } else {
+            self.report.warning(
+                self.rank,
+                None,
+                format!("Entry {id} not found, the relation is not imported"),
+            );
+            None
+        }
+    }
+
+    /// Text of the first form of `element`, remembering its writing
+    /// system if it is not the analysis language.
+    fn text(&mut self, element: &mut Element) -> Option<String> {
+        let (lang, text) = take_form(element, None)?;
+        if lang != self.header.analysislang {
+            self.extras.langs.insert(element.name.clone(), lang);
+        }
+        Some(text)
+    }
+
+    fn lexical_unit(&mut self, mut unit: Element) {
+        let norm = take_form(&mut unit, Some(self.header.normlang.as_str()))
+            .or_else(|| take_form(&mut unit, None));
+        if let Some((_, norm)) = norm {
+            self.word.norm = norm;
+        }
+        if let Some(nativelang) = &self.header.nativelang {
+            self.word.native = take_form(&mut unit, Some(nativelang.as_str()))
+                .map(|(_, text)| text);
+        }
+        self.extras.forms = children_xml(&unit);
+    }
+
+    fn sense(&mut self, mut sense: Element) {
+        self.extras.sense_attributes = attributes(&sense, &[]);
+        let mut grammatical_info = false;
+        for child in std::mem::take(&mut sense.children) {
+            let XMLNode::Element(mut child) = child else {
+                continue;
+            };
+            match child.name.as_str() {
+                "grammatical-info" if !grammatical_info => {
+                    grammatical_info = true;
+                    let value = attribute(&child, "value").unwrap_or_default();
+                    match PartOfSpeech::from_str(value) {
+                        Ok(partofspeech) => {
+                            self.word.partofspeech = partofspeech
+                        }
+                        Err(_) => {
+                            self.report.warning(
+                                self.rank,
+                                Some("grammatical-info"),
+                                format!(
+                                    "Unknown part of speech {value}, imported as {:?}",
+                                    PartOfSpeech::Other
+                                ),
+                            );
+                            self.word.partofspeech = PartOfSpeech::Other;
+                            self.extras.grammatical_info =
+                                Some(value.to_string());
+                        }
+                    }
+                }
+                "definition" if self.word.description.is_none() => {
+                    self.word.description = self.text(&mut child);
+                    self.extras.definition = children_xml(&child);
+                }
+                "illustration"
+                    if self.word.image.is_none()
+                        && child.children.is_empty() =>
+                {
+                    self.word.image =
+                        attribute(&child, "href").map(String::from);
+                }
+                "note"
+                    if self.word.lusage.is_none()
+                        && attribute(&child, "type") == Some("usage")
+                        && child.children.len() == 1 =>
+                {
+                    self.word.lusage = self.text(&mut child);
+                }
+                _ => self.extras.sense.push(xml(&child)),
+            }
+        }
+        if !grammatical_info {
+            self.report.warning(
+                self.rank,
+                Some("grammatical-info"),
+                format!(
+                    "Missing part of speech, defaulting to {:?}",
+                    PartOfSpeech::default()
+                ),
+            );
+        }
+    }
+
+    fn relation(&mut self, relation: &Element) {
+        let kind = attribute(relation, "type").unwrap_or_default().to_string();
+        let Some((target, uuid)) = attribute(relation, "ref")
+            .filter(|id| !id.is_empty())
+            .and_then(|id| self.reference(id))
+        else {
+            self.extras.entry.push(xml(relation));
+            return;
+        };
+        let mut extras = RelationExtras {
+            target: Some(uuid),
+            kind: kind.clone(),
+            attributes: attributes(relation, &["type", "ref"]),
+            children: children_xml(relation),
+        };
+        if is_lemma_relation(&kind) && self.lemma.is_none() {
+            self.lemma = Some(target);
+            extras.target = None;
+            self.extras.lemma = Some(extras);
+        } else {
+            let relationship = if kind.eq_ignore_ascii_case("definition") {
+                WordRelationship::Definition
+            } else {
+                WordRelationship::Related
+            };
+            self.relations.push((target, relationship));
+            self.extras.relations.push(extras);
+        }
+    }
+
+    /// Map the children of `entry` onto the word, keeping what has no
+    /// equivalent in its extras.
+    fn read(&mut self, mut entry: Element) {
+        self.extras.attributes = attributes(&entry, &["id"]);
+        let mut sense = false;
+        for child in std::mem::take(&mut entry.children) {
+            let XMLNode::Element(mut child) = child else {
+                continue;
+            };
+            match child.name.as_str() {
+                "lexical-unit" if self.word.norm.is_empty() => {
+                    self.lexical_unit(child);
+                }
+                "sense" if !sense => {
+                    sense = true;
+                    self.sense(child);
+                }
+                "etymology" if self.word.etymology.is_none() => {
+                    self.extras.etymology_attributes = attributes(&child, &[]);
+                    self.word.etymology = self.text(&mut child);
+                    self.extras.etymology = children_xml(&child);
+                }
+                "pronunciation"
+                    if child.children.len() == 1
+                        && child.get_child("media").is_some() =>
+                {
+                    let href = child
+                        .get_child("media")
+                        .and_then(|media| attribute(media, "href"))
+                        .map(String::from);
+                    match href {
+                        Some(href)
+                            if is_video(&href) && self.word.video.is_none() =>
+                        {
+                            self.word.video = Some(href);
+                        }
+                        Some(href)
+                            if !is_video(&href)
+                                && self.word.audio.is_none() =>
+                        {
+                            self.word.audio = Some(href);
+                        }
+                        _ => self.extras.entry.push(xml(&child)),
+                    }
+                }
+                "note"
+                    if self.word.lusage.is_none()
+                        && attribute(&child, "type") == Some("usage")
+                        && child.children.len() == 1 =>
+                {
+                    self.word.lusage = self.text(&mut child);
+                }
+                "field"
+                    if self.word.morphology.is_none()
+                        && attribute(&child, "type") == Some("morphology")
+                        && child.children.len() == 1 =>
+                {
+                    self.word.morphology = self.text(&mut child);
+                }
+                "relation" => self.relation(&child),
+                "variant" => self.variants.push(child),
+                _ => self.extras.entry.push(xml(&child)),
+            }
+        }
+        if !sense {
+            self.report.warning(
+                self.rank,
+                None,
+                format!(
+                    "Entry has no sense, its part of speech defaults to {:?}",
+                    PartOfSpeech::default()
+                ),
+            );
+        }
+    }
+
+    /// Words of the `<variant>` elements of the entry, whose lemma is
+    /// the entry.
+    fn variants(&mut self) -> Vec<Row> {
+        std::mem::take(&mut self.variants)
+            .into_iter()
+            .filter_map(|mut variant| {
+                let Some((_, norm)) = take_form(
+                    &mut variant,
+                    Some(self.header.normlang.as_str()),
+                )
+                .or_else(|| take_form(&mut variant, None)) else {
+                    self.extras.entry.push(xml(&variant));
+                    return None;
+                };
+                let native = self.header.nativelang.as_ref().and_then(|lang| {
+                    take_form(&mut variant, Some(lang.as_str()))
+                        .map(|(_, text)| text)
+                });
+                let extras = Extras {
+                    attributes: attributes(&variant, &[]),
+                    entry: children_xml(&variant),
+                    ..Extras::default()
+                };
+                Some(Row {
+                    line: self.rank,
+                    word: ImportedWord {
+                        id: Uuid::new_v4(),
+                        norm,
+                        native,
+                        lemma: None,
+                        language: self.word.language,
+                        partofspeech: self.word.partofspeech.clone(),
+                        audio: None,
+                        video: None,
+                        image: None,
+                        description: None,
+                        etymology: None,
+                        lusage: None,
+                        morphology: None,
+                    },
+                    lemma: Some(Reference::Imported(self.word.id)),
+                    relations: Vec::new(),
+                    lift: serde_json::to_value(extras).ok(),
+                    source: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Rows of the entry and its variants, none if the entry has
+    /// errors.
+    fn rows(mut self) -> Vec<Row> {
+        let mut rows = self.variants();
+        let mut valid = true;
+        if self.word.norm.is_empty() {
+            valid = false;
+            self.report.error(
+                self.rank,
+                Some("lexical-unit"),
+                "Entry has no lexical unit".to_string(),
+            );
+        }
+        for word in
+            std::iter::once(&self.word).chain(rows.iter().map(|row| &row.word))
+        {
+            if let Some(issue) = word.length_issue() {
+                valid = false;
+                self.report.error(self.rank, None, issue);
+            }
+        }
+        if valid {
+            rows.insert(
+                0,
+                Row {
+                    line: self.rank,
+                    lift: serde_json::to_value(&self.extras).ok(),
+                    word: self.word,
+                    lemma: self.lemma,
+                    relations: self.relations,
+                    source: None,
+                },
+            );
+            rows
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Writing systems of `entries`, falling back on those of `stored`.
+fn detect_langs(
+    entries: &[Element],
+    header: Option<String>,
+    stored: LiftHeader,
+) -> LiftHeader {
+    let mut lexical_langs = entries
+        .iter()
+        .flat_map(|entry| form_langs(entry, "lexical-unit"));
+    let normlang = lexical_langs.next().map(String::from);
+    let nativelang = normlang.as_deref().and_then(|normlang| {
+        lexical_langs
+            .find(|lang| *lang != normlang)
+            .map(String::from)
+    });
+    let analysislang = entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .children
+                .iter()
+                .filter_map(XMLNode::as_element)
+                .filter(|child| child.name == "sense")
+        })
+        .flat_map(|sense| form_langs(sense, "definition"))
+        .next()
+        .map(String::from);
+    LiftHeader {
+        language: stored.language,
+        header: header.or(stored.header),
+        normlang: normlang.unwrap_or(stored.normlang),
+        nativelang: nativelang.or(stored.nativelang),
+        analysislang: analysislang.unwrap_or(stored.analysislang),
+    }
+}
+
+/// Read the entries of the LIFT document `data` as rows of the
+/// language of `stored`, its header so far.
+///
+/// Returns the header of the document along with the rows and the
+/// report of their issues.
+fn read(
+    data: &str,
+    stored: LiftHeader,
+) -> Result<(LiftHeader, Vec<Row>, ImportReport), DatabaseError> {
+    let language = stored.language;
+    let mut root = Element::parse(data.as_bytes()).map_err(|e| {
+        DatabaseError::new(
+            format!("Failed to read LIFT document: {e}"),
+            ErrorKind::InvalidInput,
+        )
+    })?;
+    if root.name != "lift" {
+        return Err(DatabaseError::new(
+            format!(
+                "Expected a LIFT document, found a <{}> element",
+                root.name
+            ),
+            ErrorKind::InvalidInput,
+        ));
+    }
+    let mut header = None;
+    let mut entries = Vec::new();
+    for node in std::mem::take(&mut root.children) {
+        match node {
+            XMLNode::Element(element) if element.name == "header" => {
+                header = Some(xml(&element));
+            }
+            XMLNode::Element(element) if element.name == "entry" => {
+                entries.push(element);
+            }
+            _ => {}
+        }
+    }
+    if entries.len() > MAX_ROWS {
+        return Err(DatabaseError::new(
+            format!("Cannot import more than {MAX_ROWS} words at once"),
+            ErrorKind::InvalidInput,
+        ));
+    }
+    let header = detect_langs(&entries, header, stored);
+    let uuids = entries.iter().map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+    let ids = entries
+        .iter()
+        .zip(&uuids)
+        .filter_map(|(entry, uuid)| {
+            attribute(entry, "id").map(|id| (id.to_string(), *uuid))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut report = ImportReport::default();
+    let mut rows = Vec::new();
+    for (index, (entry, id)) in entries.into_iter().zip(uuids).enumerate() {
+        let rank = index + 1;
+        report.rows += 1;
+        if entry.attributes.contains_key("dateDeleted") {
+            report.warning(
+                rank,
+                None,
+                "Entry is marked as deleted, it is not imported".to_string(),
+            );
+            continue;
+        }
+        let mut reader = EntryReader {
+            rank,
+            header: &header,
+            ids: &ids,
+            report: &mut report,
+            word: ImportedWord {
+                id,
+                norm: String::new(),
+                native: None,
+                lemma: None,
+                language,
+                partofspeech: PartOfSpeech::default(),
+                audio: None,
+                video: None,
+                image: None,
+                description: None,
+                etymology: None,
+                lusage: None,
+                morphology: None,
+            },
+            extras: Extras::default(),
+            lemma: None,
+            relations: Vec::new(),
+            variants: Vec::new(),
+        };
+        reader.read(entry);
+        rows.extend(reader.rows());
+    }
+    Ok((header, rows, report))
+}
+
+/// Read the entries of the LIFT document `data` as words of the
+/// language `language`.
+///
+/// Each entry becomes a word, its `<variant>` elements become words
+/// whose lemma is the entry. The first sense gives the part of speech
+/// and description of the word, the first etymology its etymology,
+/// pronunciation media its audio or video, and illustrations of the
+/// first sense its image. Relations become `lemma` links for main
+/// entry and variant relations, `definitions` for `definition`
+/// relations and `related` links otherwise. Everything else is kept
+/// as is and written back when exporting to LIFT.
+pub fn parse(
+    conn: &mut PgConnection,
+    language: Uuid,
+    data: &str,
+) -> Result<WordImport, DatabaseError> {
+    let (header, rows, report) = read(data, LiftHeader::of(conn, language)?)?;
+    Ok(WordImport::from_rows(conn, language, rows, None, report)?
+        .with_lift_header(header))
+}
+
+/// Write the beginning of a LIFT document, up to its header.
+pub fn write_start<W: Write>(
+    out: &mut W,
+    header: &LiftHeader,
+) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<lift version=\"0.13\" producer=\"Ordabok\">")?;
+    if let Some(header) = &header.header {
+        writeln!(out, "{header}")?;
+    }
+    Ok(())
+}
+
+pub fn write_end<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, "</lift>")
+}
+
+fn write_attributes<W: Write>(
+    out: &mut W,
+    attributes: &[(String, String)],
+) -> io::Result<()> {
+    for (name, value) in attributes {
+        write!(out, " {name}=\"{}\"", escape_xml(value))?;
+    }
+    Ok(())
+}
+
+fn write_form<W: Write>(out: &mut W, lang: &str, text: &str) -> io::Result<()> {
+    write!(
+        out,
+        "<form lang=\"{}\"><text>{}</text></form>",
+        escape_xml(lang),
+        escape_xml(text)
+    )
+}
+
+/// Write `word` as a LIFT entry, along with the `extras` kept from the
+/// entry it was imported from.
+pub fn write_entry<W: Write>(
+    out: &mut W,
+    header: &LiftHeader,
+    word: &WordRecord,
+    extras: Option<&serde_json::Value>,
+) -> io::Result<()> {
+    let mut extras = extras
+        .map(|extras| {
+            serde_json::from_value::<Extras>(extras.clone()).unwrap_or_else(
+                |e| {
+                    warn!("Invalid LIFT extras of word {}: {e:?}", word.id);
+                    Extras::default()
+                },
+            )
+        })
+        .unwrap_or_default();
+    let lang = |element: &str| {
+        extras
+            .langs
+            .get(element)
+            .map_or(header.analysislang.as_str(), String::as_str)
+            .to_string()
+    };
+    write!(out, "<entry id=\"{}\"", word.id)?;
+    write_attributes(out, &extras.attributes)?;
+    write!(out, "><lexical-unit>")?;
+    write_form(out, &header.normlang, word.norm)?;
+    if let Some(native) = word.native {
+        write_form(out, header.nativelang(), native)?;
+    }
+    write!(out, "{}</lexical-unit>", extras.forms.concat())?;
+    for media in [word.audio, word.video].into_iter().flatten() {
+        write!(
+            out,
+            "<pronunciation><media href=\"{}\"/></pronunciation>",
+            escape_xml(media)
+        )?;
+    }
+    if let Some(morphology) = word.morphology {
+        write!(out, "<field type=\"morphology\">")?;
+        write_form(out, &lang("field"), morphology)?;
+        write!(out, "</field>")?;
+    }
+    if let Some(etymology) = word.etymology {
+        write!(out, "<etymology")?;
+        if extras.etymology_attributes.is_empty() {
+            write!(out, " type=\"proto\" source=\"\"")?;
+        }
+        write_attributes(out, &extras.etymology_attributes)?;
+        write!(out, ">")?;
+        write_form(out, &lang("etymology"), etymology)?;
+        write!(out, "{}</etymology>", extras.etymology.concat())?;
+    }
+    if let Some(lemma) = &word.lemma {
+        let relation = extras.lemma.take().unwrap_or_else(|| RelationExtras {
+            kind: LEMMA_RELATION.to_string(),
+            ..RelationExtras::default()
+        });
+        write!(
+            out,
+            "<relation type=\"{}\" ref=\"{}\"",
+            escape_xml(&relation.kind),
+            lemma.id
+        )?;
+        write_attributes(out, &relation.attributes)?;
+        write!(out, ">{}</relation>", relation.children.concat())?;
+    }
+    for (targets, default) in
+        [(word.definitions, "definition"), (word.related, "related")]
+    {
+        for target in targets {
+            let relation = extras
+                .relations
+                .iter()
+                .position(|relation| {
+                    relation.target == Some(*target)
+                        && (default == "definition")
+                            == relation.kind.eq_ignore_ascii_case("definition")
+                })
+                .map_or_else(
+                    || RelationExtras {
+                        kind: default.to_string(),
+                        ..RelationExtras::default()
+                    },
+                    |index| extras.relations.remove(index),
+                );
+            write!(
+                out,
+                "<relation type=\"{}\" ref=\"{target}\"",
+                escape_xml(&relation.kind)
+            )?;
+            write_attributes(out, &relation.attributes)?;
+            write!(out, ">{}</relation>", relation.children.concat())?;
+        }
+    }
+    write!(out, "<sense")?;
+    write_attributes(out, &extras.sense_attributes)?;
+    write!(
+        out,
+        "><grammatical-info value=\"{}\"/>",
+        escape_xml(
+            extras
+                .grammatical_info
+                .as_deref()
+                .filter(|_| word.partofspeech == &PartOfSpeech::Other)
+                .unwrap_or_else(|| word.partofspeech.name())
+        )
+    )?;
+    if let Some(description) = word.description {
+        write!(out, "<definition>")?;
+        write_form(out, &lang("definition"), description)?;
+        write!(out, "{}</definition>", extras.definition.concat())?;
+    }
+    if let Some(image) = word.image {
+        write!(out, "<illustration href=\"{}\"/>", escape_xml(image))?;
+    }
+    if let Some(usage) = word.lusage {
+        write!(out, "<note type=\"usage\">")?;
+        write_form(out, &lang("note"), usage)?;
+        write!(out, "</note>")?;
+    }
+    write!(out, "{}</sense>", extras.sense.concat())?;
+    writeln!(out, "{}</entry>", extras.entry.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::exports::LemmaReference;
+    use super::*;
+    use crate::db::{
+        schema::{wordrelation, words},
+        testing,
+    };
+
+    const SAMPLE: &str = include_str!("../../../tests/fixtures/sample.lift");
+
+    fn extras(row: &Row) -> Extras {
+        serde_json::from_value(row.lift.clone().unwrap()).unwrap()
+    }
+
+    fn row<'a>(rows: &'a [Row], norm: &str) -> &'a Row {
+        rows.iter().find(|row| row.word.norm == norm).unwrap()
+    }
+
+    /// ID of the imported word `reference` points to.
+    fn imported(reference: &Reference) -> Uuid {
+        match reference {
+            Reference::Imported(id) => *id,
+            reference => panic!("Unexpected reference to {reference}"),
+        }
+    }
+
+    /// Norm of the imported word `reference` points to.
+    fn norm<'a>(rows: &'a [Row], reference: &Reference) -> &'a str {
+        rows.iter()
+            .find(|row| row.word.id == imported(reference))
+            .map(|row| row.word.norm.as_str())
+            .unwrap()
+    }
+
+    /// Words of `rows` with the norms of the words they point to
+    /// rather than their IDs, which change on each import.
+    fn summary(rows: &[Row]) -> Vec<String> {
+        rows.iter()
+            .map(|row| {
+                let word = ImportedWord {
+                    id: Uuid::nil(),
+                    ..row.word.clone()
+                };
+                let lemma = row.lemma.as_ref().map(|lemma| norm(rows, lemma));
+                let relations = row
+                    .relations
+                    .iter()
+                    .map(|(target, relationship)| {
+                        (norm(rows, target), relationship)
+                    })
+                    .collect::<Vec<_>>();
+                format!("{word:?} {lemma:?} {relations:?}")
+            })
+            .collect()
+    }
+
+    /// `extras` without the IDs of the words relations point to, nor
+    /// the lemma relation exports add to variants.
+    fn normalized(mut extras: Extras) -> Extras {
+        for relation in &mut extras.relations {
+            relation.target = None;
+        }
+        let added = RelationExtras {
+            kind: LEMMA_RELATION.to_string(),
+            ..RelationExtras::default()
+        };
+        if extras.lemma.as_ref() == Some(&added) {
+            extras.lemma = None;
+        }
+        extras
+    }
+
+    /// Write `row` as its word would be exported.
+    fn write_row(
+        out: &mut Vec<u8>,
+        header: &LiftHeader,
+        rows: &[Row],
+        row: &Row,
+    ) {
+        let targets = |kind: WordRelationship| {
+            row.relations
+                .iter()
+                .filter(|(_, relationship)| *relationship == kind)
+                .map(|(target, _)| imported(target))
+                .collect::<Vec<_>>()
+        };
+        let definitions = targets(WordRelationship::Definition);
+        let related = targets(WordRelationship::Related);
+        let word = &row.word;
+        let record = WordRecord {
+            id: word.id,
+            norm: &word.norm,
+            native: word.native.as_deref(),
+            lemma: row.lemma.as_ref().map(|lemma| LemmaReference {
+                id: imported(lemma),
+                norm: norm(rows, lemma),
+            }),
+            partofspeech: &word.partofspeech,
+            audio: word.audio.as_deref(),
+            video: word.video.as_deref(),
+            image: word.image.as_deref(),
+            description: word.description.as_deref(),
+            etymology: word.etymology.as_deref(),
+            lusage: word.lusage.as_deref(),
+            morphology: word.morphology.as_deref(),
+            definitions: &definitions,
+            related: &related,
+        };
+        write_entry(out, header, &record, row.lift.as_ref()).unwrap();
+    }
+
+    #[test]
+    fn recognises_lemma_relations_and_videos() {
+        assert!(is_lemma_relation("_component-lexeme"));
+        assert!(is_lemma_relation("Main Entry"));
+        assert!(is_lemma_relation("variant_of"));
+        assert!(!is_lemma_relation("synonym"));
+        assert!(is_video("media/meow.WEBM"));
+        assert!(!is_video("media/meow.mp3"));
+        assert!(!is_video("meow"));
+    }
+
+    #[test]
+    fn reads_sample_entries() {
+        let (header, rows, report) =
+            read(SAMPLE, LiftHeader::new(Uuid::new_v4())).unwrap();
+        assert_eq!("is", header.normlang);
+        assert_eq!(Some("is-Runr"), header.nativelang.as_deref());
+        assert_eq!("en", header.analysislang);
+        assert!(header.header.unwrap().contains("literal-meaning"));
+
+        assert_eq!(5, report.rows);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        let warnings = report
+            .warnings
+            .iter()
+            .map(|issue| (issue.row, issue.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (
+                    3,
+                    "Unknown part of speech Intransitive verb, imported as Other"
+                ),
+                (4, "Entry is marked as deleted, it is not imported"),
+                (5, "Entry hundur_9 not found, the relation is not imported"),
+                (5, "Entry has no sense, its part of speech defaults to Noun"),
+            ],
+            warnings
+        );
+        let norms = rows
+            .iter()
+            .map(|row| row.word.norm.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["köttur", "kattur", "kisa", "mjálma", "mús"], norms);
+
+        let cat = row(&rows, "köttur");
+        let word = &cat.word;
+        assert_eq!(Some("ᚴᚢᛏᛏᚢᚱ"), word.native.as_deref());
+        assert_eq!(PartOfSpeech::Noun, word.partofspeech);
+        assert_eq!(
+            Some("A small domesticated feline"),
+            word.description.as_deref()
+        );
+        assert_eq!(Some("kottur.mp3"), word.audio.as_deref());
+        assert_eq!(Some("kottur.jpg"), word.image.as_deref());
+        assert_eq!(Some("Common"), word.lusage.as_deref());
+        assert_eq!(
+            Some("köttur, kött, ketti, kattar"),
+            word.morphology.as_deref()
+        );
+        assert_eq!(Some("kǫttr"), word.etymology.as_deref());
+        assert!(cat.lemma.is_none());
+        assert_eq!(1, cat.relations.len());
+        assert_eq!("kisa", norm(&rows, &cat.relations[0].0));
+        assert_eq!(WordRelationship::Related, cat.relations[0].1);
+        let kept = extras(cat);
+        assert_eq!(
+            vec![
+                (
+                    "guid".to_string(),
+                    "5c1f4b9e-6c3a-4a57-9f0e-2b8d1d7c6a01".to_string()
+                ),
+                (
+                    "dateCreated".to_string(),
+                    "2023-01-04T10:00:00Z".to_string()
+                ),
+            ],
+            kept.attributes
+        );
+        assert_eq!(1, kept.forms.len());
+        assert!(kept.forms[0].contains("kʰœhtʏr"));
+        // The second sense and unknown elements are kept as they are
+        assert_eq!(3, kept.entry.len());
+        assert!(kept.entry[0].contains("morph-type"));
+        assert!(kept.entry[1].contains("A whip with several lashes"));
+        assert!(kept.entry[2].contains("little tiger"));
+        assert_eq!(
+            vec![
+                ("id".to_string(), "kottur_1_s1".to_string()),
+                ("order".to_string(), "1".to_string()),
+            ],
+            kept.sense_attributes
+        );
+        assert_eq!(1, kept.sense.len());
+        assert!(kept.sense[0].contains("The cat sleeps."));
+        assert_eq!(1, kept.definition.len());
+        assert!(kept.definition[0].contains("Un petit félin domestique"));
+        assert_eq!(
+            Some("non"),
+            kept.langs.get("etymology").map(String::as_str)
+        );
+        assert_eq!(1, kept.etymology.len());
+        assert!(kept.etymology[0].contains("<gloss"));
+        assert_eq!(1, kept.relations.len());
+        assert_eq!("synonym", kept.relations[0].kind);
+        assert!(kept.relations[0].children[0].contains("is-primary"));
+
+        let variant = row(&rows, "kattur");
+        assert_eq!("köttur", norm(&rows, variant.lemma.as_ref().unwrap()));
+        assert_eq!(PartOfSpeech::Noun, variant.word.partofspeech);
+        assert!(extras(variant).entry[0].contains("dialectal"));
+
+        let kitty = row(&rows, "kisa");
+        assert_eq!("köttur", norm(&rows, kitty.lemma.as_ref().unwrap()));
+        let lemma = extras(kitty).lemma.unwrap();
+        assert_eq!("_component-lexeme", lemma.kind);
+        assert_eq!(None, lemma.target);
+        assert!(lemma.children[0].contains("Diminutive"));
+
+        let meow = row(&rows, "mjálma");
+        assert_eq!(Some("mjalma.webm"), meow.word.video.as_deref());
+        assert_eq!(None, meow.word.audio);
+        assert_eq!(PartOfSpeech::Other, meow.word.partofspeech);
+        assert_eq!(
+            Some("Intransitive verb"),
+            extras(meow).grammatical_info.as_deref()
+        );
+        assert_eq!("köttur", norm(&rows, &meow.relations[0].0));
+        assert_eq!("related", extras(meow).relations[0].kind);
+
+        let mouse = row(&rows, "mús");
+        assert!(mouse.relations.is_empty());
+        assert!(extras(mouse).entry[0].contains("hundur_9"));
+    }
+
+    #[test]
+    fn round_trips_through_write_entry() {
+        let stored = LiftHeader::new(Uuid::new_v4());
+        let (header, rows, _) = read(SAMPLE, stored.clone()).unwrap();
+        let mut out = Vec::new();
+        write_start(&mut out, &header).unwrap();
+        for row in &rows {
+            write_row(&mut out, &header, &rows, row);
+        }
+        write_end(&mut out).unwrap();
+        let data = String::from_utf8(out).unwrap();
+
+        let (reread_header, reread, report) = read(&data, stored).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(header.header, reread_header.header);
+        assert_eq!(header.normlang, reread_header.normlang);
+        assert_eq!(header.nativelang, reread_header.nativelang);
+        assert_eq!(header.analysislang, reread_header.analysislang);
+        assert_eq!(summary(&rows), summary(&reread));
+        for (row, reread) in rows.iter().zip(&reread) {
+            assert_eq!(
+                normalized(extras(row)),
+                normalized(extras(reread)),
+                "Extras of {} differ",
+                row.word.norm
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn imports_sample_with_its_extras() {
+        let conn = &mut testing::connection();
+        let user = testing::user(conn);
+        let language = testing::language(conn, &user);
+
+        let (report, ids) = parse(conn, language, SAMPLE)
+            .unwrap()
+            .apply(conn, &user, false)
+            .unwrap();
+        assert_eq!(5, report.imported);
+        assert_eq!(5, ids.len());
+
+        let header = LiftHeader::of(conn, language).unwrap();
+        assert_eq!("is", header.normlang);
+        assert_eq!(Some("is-Runr"), header.nativelang.as_deref());
+        assert!(header.header.unwrap().contains("literal-meaning"));
+
+        let id_of = |conn: &mut PgConnection, norm: &str| {
+            words::table
+                .filter(words::language.eq(language))
+                .filter(words::norm.eq(norm))
+                .select(words::id)
+                .first::<Uuid>(conn)
+                .unwrap()
+        };
+        let cat = id_of(conn, "köttur");
+        let kitty = id_of(conn, "kisa");
+        let kept = liftentries::table
+            .find(cat)
+            .select(liftentries::extras)
+            .first::<serde_json::Value>(conn)
+            .unwrap();
+        let kept = serde_json::from_value::<Extras>(kept).unwrap();
+        assert_eq!(3, kept.entry.len());
+        assert!(kept.entry[1].contains("A whip with several lashes"));
+        assert_eq!(Some(kitty), kept.relations[0].target);
+
+        let lemma = words::table
+            .find(kitty)
+            .select(words::lemma)
+            .first::<Option<Uuid>>(conn)
+            .unwrap();
+        assert_eq!(Some(cat), lemma);
+        let targets = wordrelation::table
+            .filter(wordrelation::wordsource.eq(cat))
+            .select(wordrelation::wordtarget)
+            .load::<Uuid>(conn)
+            .unwrap();
+        assert_eq!(vec![kitty], targets);
+    }
+}
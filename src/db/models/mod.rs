@@ -1,3 +1,35 @@
+pub mod accounts;
+pub mod api_tokens;
+pub mod audit;
+pub mod comments;
+pub mod exports;
+pub mod feed;
+pub mod forks;
+pub mod imports;
 pub mod languages;
+pub mod lift;
+pub mod notifications;
+pub mod ontolex;
+pub mod persisted_queries;
+pub mod profiles;
+pub mod revisions;
+pub mod stats;
+pub mod suggestions;
+pub mod tei;
+pub mod transfers;
+pub mod trash;
 pub mod users;
 pub mod words;
+
+/// Amount of items returned by paginated fields when no page size is
+/// requested.
+const DEFAULT_PAGE_SIZE: i64 = 20;
+/// Maximum amount of items returned at once by paginated fields.
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// Amount of items to return for a page of at most `first` items.
+pub fn page_size(first: Option<i32>) -> i64 {
+    first
+        .map_or(DEFAULT_PAGE_SIZE, i64::from)
+        .clamp(0, MAX_PAGE_SIZE)
+}
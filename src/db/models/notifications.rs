@@ -0,0 +1,414 @@
+use super::super::schema;
+use super::revisions::RevisionAction;
+use super::{languages::Language, page_size, users::User, words::Word};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::{
+        subscription::{Live, LiveEvent},
+        Context,
+    },
+};
+
+use diesel::prelude::*;
+use juniper::{FieldResult, GraphQLEnum};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use schema::{languages, notifications, userfollowlanguage, userfollows};
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Notificationkind"]
+pub enum NotificationKind {
+    WordAdded,
+    WordChanged,
+    WordDeleted,
+    LanguageCreated,
+    NewFollower,
+}
+
+/// Something that happened and that users may want to be notified of.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// `actor` added, changed or deleted a word of a language.
+    Word {
+        kind: NotificationKind,
+        actor: String,
+        language: Uuid,
+        word: Uuid,
+    },
+    /// `actor` created a new language.
+    LanguageCreated { actor: String, language: Uuid },
+    /// `actor` started following `followed`.
+    NewFollower { actor: String, followed: String },
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = notifications)]
+struct NewNotification {
+    recipient: String,
+    kind: NotificationKind,
+    actor: Option<String>,
+    language: Option<Uuid>,
+    word: Option<Uuid>,
+}
+
+impl Event {
+    pub fn word(kind: NotificationKind, actor: &str, word: &Word) -> Self {
+        Self::Word {
+            kind,
+            actor: actor.to_string(),
+            language: word.language_id(),
+            word: word.uuid(),
+        }
+    }
+
+    /// Users to notify of the event, never including its actor nor
+    /// users who cannot read the language it happened to.
+    fn recipients(&self, conn: &mut PgConnection) -> QueryResult<Vec<String>> {
+        match self {
+            Event::Word {
+                actor, language, ..
+            } => {
+                let followers = userfollowlanguage::table
+                    .filter(userfollowlanguage::lang.eq(language))
+                    .filter(userfollowlanguage::userid.ne(actor))
+                    .select(userfollowlanguage::userid)
+                    .distinct()
+                    .load::<String>(conn)?;
+                Event::readers(conn, *language, followers)
+            }
+            Event::LanguageCreated { actor, language } => {
+                let followers = userfollows::table
+                    .filter(userfollows::following.eq(actor))
+                    .filter(userfollows::follower.ne(actor))
+                    .select(userfollows::follower)
+                    .load::<String>(conn)?;
+                Event::readers(conn, *language, followers)
+            }
+            Event::NewFollower { actor, followed } if actor != followed => {
+                Ok(vec![followed.clone()])
+            }
+            Event::NewFollower { .. } => Ok(Vec::new()),
+        }
+    }
+
+    /// Users among `users` who can read `language`, as
+    /// [`Language::is_readable_by`] tells.
+    fn readers(
+        conn: &mut PgConnection,
+        language: Uuid,
+        users: Vec<String>,
+    ) -> QueryResult<Vec<String>> {
+        let Some(language) = languages::table
+            .find(language)
+            .first::<Language>(conn)
+            .optional()?
+        else {
+            return Ok(Vec::new());
+        };
+        Ok(users
+            .into_iter()
+            .filter(|user| language.is_readable_by(Some(user)))
+            .collect())
+    }
+
+    fn notification(&self, recipient: String) -> NewNotification {
+        match self {
+            Event::Word {
+                kind,
+                actor,
+                language,
+                word,
+            } => NewNotification {
+                recipient,
+                kind: kind.clone(),
+                actor: Some(actor.clone()),
+                language: Some(*language),
+                word: Some(*word),
+            },
+            Event::LanguageCreated { actor, language } => NewNotification {
+                recipient,
+                kind: NotificationKind::LanguageCreated,
+                actor: Some(actor.clone()),
+                language: Some(*language),
+                word: None,
+            },
+            Event::NewFollower { actor, .. } => NewNotification {
+                recipient,
+                kind: NotificationKind::NewFollower,
+                actor: Some(actor.clone()),
+                language: None,
+                word: None,
+            },
+        }
+    }
+
+    /// Create a notification for each recipient of the event and push
+    /// them to their subscribers.
+    fn fan_out(
+        &self,
+        db: &Database,
+        live: &Live,
+    ) -> Result<usize, DatabaseError> {
+        let conn = &mut db.conn()?;
+        let notifications = self
+            .recipients(conn)?
+            .into_iter()
+            .map(|recipient| self.notification(recipient))
+            .collect::<Vec<_>>();
+        if notifications.is_empty() {
+            return Ok(0);
+        }
+        let notifications =
+            diesel::insert_into(notifications::dsl::notifications)
+                .values(notifications)
+                .get_results::<Notification>(conn)?;
+        let count = notifications.len();
+        for notification in notifications {
+            live.publish(LiveEvent::Notification(notification));
+        }
+        Ok(count)
+    }
+
+    /// Notify users of the event in the background.
+    ///
+    /// Must be called once the change the event describes has been
+    /// committed, from within Rocket's runtime.
+    pub fn dispatch(self, context: &Context) {
+        if let Event::Word {
+            kind,
+            language,
+            word,
+            ..
+        } = &self
+        {
+            let action = match kind {
+                NotificationKind::WordDeleted => RevisionAction::Delete,
+                NotificationKind::WordAdded => RevisionAction::Create,
+                _ => RevisionAction::Update,
+            };
+            context.live.publish(LiveEvent::Word {
+                language: *language,
+                word: *word,
+                action,
+            });
+        }
+        let db = context.db.clone();
+        let live = context.live.clone();
+        rocket::tokio::task::spawn_blocking(move || {
+            match self.fan_out(&db, &live) {
+                Ok(count) => debug!("Sent {count} notifications for {self:?}"),
+                Err(e) => {
+                    warn!("Failed to send notifications for {self:?}: {e:?}")
+                }
+            }
+        });
+    }
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct Notification {
+    id: i32,
+    recipient: String,
+    kind: NotificationKind,
+    actor: Option<String>,
+    language: Option<Uuid>,
+    word: Option<Uuid>,
+    created: chrono::NaiveDateTime,
+    read: bool,
+}
+
+impl Notification {
+    pub fn is_for(&self, user: &str) -> bool {
+        self.recipient == user
+    }
+
+    /// Notifications of `recipient`, most recent first.
+    ///
+    /// Only notifications older than the notification `after` are
+    /// returned, at most `first` of them.
+    pub fn list(
+        db: &Database,
+        recipient: &str,
+        unread_only: bool,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> Result<Vec<Notification>, DatabaseError> {
+        use notifications::dsl;
+        let mut query = dsl::notifications
+            .filter(dsl::recipient.eq(recipient))
+            .into_boxed();
+        if unread_only {
+            query = query.filter(dsl::read.eq(false));
+        }
+        if let Some(after) = after {
+            query = query.filter(dsl::id.lt(after));
+        }
+        query
+            .order(dsl::id.desc())
+            .limit(page_size(first))
+            .load::<Notification>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve notifications of user {recipient}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Mark notifications of `recipient` as read, all of them if `ids`
+    /// is `None`.
+    ///
+    /// Returns the amount of notifications marked as read.
+    pub fn mark_read(
+        db: &Database,
+        recipient: &str,
+        ids: Option<Vec<i32>>,
+    ) -> Result<i32, DatabaseError> {
+        use notifications::dsl;
+        let mut query = diesel::update(dsl::notifications)
+            .filter(dsl::recipient.eq(recipient))
+            .filter(dsl::read.eq(false))
+            .into_boxed();
+        if let Some(ids) = ids {
+            query = query.filter(dsl::id.eq_any(ids));
+        }
+        query
+            .set(dsl::read.eq(true))
+            .execute(&mut db.conn()?)
+            .map(|count| i32::try_from(count).unwrap_or(i32::MAX))
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to mark notifications of user {recipient} as read: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl Notification {
+    #[graphql(description = "Number of the notification")]
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[graphql(description = "What the notification is about")]
+    fn kind(&self) -> NotificationKind {
+        self.kind.clone()
+    }
+
+    #[graphql(description = "User who triggered the notification")]
+    fn actor(&self, context: &Context) -> FieldResult<Option<User>> {
+        match &self.actor {
+            Some(actor) => context.db.user(actor).map_err(Into::into),
+            None => Ok(None),
+        }
+    }
+
+    #[graphql(description = "Language concerned by the notification")]
+    fn language(&self, context: &Context) -> Option<Language> {
+        match self.language {
+            Some(language) => match Language::find(&context.db, language) {
+                Ok(language) => Some(language),
+                Err(e) => {
+                    info!("Failed to retrieve language {language}: {e:?}");
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    #[graphql(
+        description = "Word concerned by the notification, null if it was deleted since"
+    )]
+    fn word(&self, context: &Context) -> Option<Word> {
+        match self.word {
+            Some(word) => match Word::find(&context.db, word) {
+                Ok(word) => Some(word),
+                Err(e) => {
+                    info!("Failed to retrieve word {word}: {e:?}");
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    #[graphql(description = "Time at which the notification was created")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(description = "Whether the notification was marked as read")]
+    fn read(&self) -> bool {
+        self.read
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::languages::Release;
+    use super::*;
+    use crate::db::testing;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn only_readers_are_notified() {
+        let conn = &mut testing::connection();
+        let owner = testing::user(conn);
+        let follower = testing::user(conn);
+        let language = testing::language(conn, &owner);
+        let word = testing::word(conn, language, "test", None);
+        diesel::insert_into(userfollowlanguage::table)
+            .values(vec![
+                (
+                    userfollowlanguage::lang.eq(language),
+                    userfollowlanguage::userid.eq(&owner),
+                ),
+                (
+                    userfollowlanguage::lang.eq(language),
+                    userfollowlanguage::userid.eq(&follower),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+        diesel::insert_into(userfollows::table)
+            .values((
+                userfollows::follower.eq(&follower),
+                userfollows::following.eq(&owner),
+            ))
+            .execute(conn)
+            .unwrap();
+        let changed = Event::Word {
+            kind: NotificationKind::WordChanged,
+            actor: testing::user(conn),
+            language,
+            word,
+        };
+        let created = Event::LanguageCreated {
+            actor: owner.clone(),
+            language,
+        };
+        let mut recipients = changed.recipients(conn).unwrap();
+        recipients.sort();
+        let mut expected = vec![owner.clone(), follower.clone()];
+        expected.sort();
+        assert_eq!(expected, recipients);
+        assert_eq!(vec![follower], created.recipients(conn).unwrap());
+
+        diesel::update(languages::table.find(language))
+            .set(languages::release.eq(Release::Private))
+            .execute(conn)
+            .unwrap();
+        assert_eq!(vec![owner], changed.recipients(conn).unwrap());
+        assert!(created.recipients(conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,613 @@
+use super::{
+    exports::WordRecord, languages::LanguageMetadata, lift::LiftHeader,
+    users::User, words::PartOfSpeech,
+};
+
+use tracing::warn;
+use uuid::Uuid;
+
+use std::env;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+/// Prefixes of the vocabularies used by exports, written in Turtle
+/// prefix declarations and in the JSON-LD context.
+const PREFIXES: [(&str, &str); 8] = [
+    ("ontolex", "http://www.w3.org/ns/lemon/ontolex#"),
+    ("lime", "http://www.w3.org/ns/lemon/lime#"),
+    ("vartrans", "http://www.w3.org/ns/lemon/vartrans#"),
+    ("lexinfo", "http://www.lexinfo.net/ontology/3.0/lexinfo#"),
+    ("dct", "http://purl.org/dc/terms/"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("skos", "http://www.w3.org/2004/02/skos/core#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+];
+
+/// Public URL of the server, from which the IRIs of languages and
+/// words are made.
+///
+/// Without `ORDABOK_BASE_URL`, IRIs point to `http://localhost:8000`
+/// and are neither stable nor dereferenceable, hence the warning,
+/// given once since `ORDABOK_BASE_URL` is only read once.
+pub fn base_iri() -> &'static str {
+    static BASE_IRI: OnceLock<String> = OnceLock::new();
+    BASE_IRI.get_or_init(|| {
+        env::var("ORDABOK_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| {
+                warn!("ORDABOK_BASE_URL is not set, IRIs will point to http://localhost:8000");
+                "http://localhost:8000".to_string()
+            })
+    })
+}
+
+/// Whether `tag` can be written as the language tag of a Turtle
+/// literal.
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    subtags.next().map_or(false, |primary| {
+        !primary.is_empty() && primary.chars().all(|c| c.is_ascii_alphabetic())
+    }) && subtags.all(|subtag| {
+        !subtag.is_empty() && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// Whether `text` is an absolute HTTP(S) IRI that can be written
+/// between angle brackets in Turtle.
+fn is_iri(text: &str) -> bool {
+    (text.starts_with("http://") || text.starts_with("https://"))
+        && !text.chars().any(|c| {
+            c.is_whitespace()
+                || c.is_control()
+                || matches!(
+                    c,
+                    '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\'
+                )
+        })
+}
+
+/// IRI of the lexicon of a language, served by the server.
+pub fn language_iri(base: &str, language: Uuid) -> String {
+    format!("{base}/languages/{language}")
+}
+
+/// IRI of the lexical entry of a word, served by the server.
+pub fn word_iri(base: &str, word: Uuid) -> String {
+    format!("{base}/words/{word}")
+}
+
+/// LexInfo part of speech of a Universal Dependencies one, if any.
+fn lexinfo(partofspeech: &PartOfSpeech) -> Option<&'static str> {
+    match partofspeech {
+        PartOfSpeech::Adjective => Some("lexinfo:adjective"),
+        PartOfSpeech::Adposition => Some("lexinfo:adposition"),
+        PartOfSpeech::Adverb => Some("lexinfo:adverb"),
+        PartOfSpeech::Auxilliary => Some("lexinfo:auxiliary"),
+        PartOfSpeech::CoordConj => Some("lexinfo:coordinatingConjunction"),
+        PartOfSpeech::Determiner => Some("lexinfo:determiner"),
+        PartOfSpeech::Interjection => Some("lexinfo:interjection"),
+        PartOfSpeech::Noun => Some("lexinfo:noun"),
+        PartOfSpeech::Numeral => Some("lexinfo:numeral"),
+        PartOfSpeech::Particle => Some("lexinfo:particle"),
+        PartOfSpeech::Pronoun => Some("lexinfo:pronoun"),
+        PartOfSpeech::ProperNoun => Some("lexinfo:properNoun"),
+        PartOfSpeech::Punctuation => Some("lexinfo:punctuation"),
+        PartOfSpeech::SubjConj => Some("lexinfo:subordinatingConjunction"),
+        PartOfSpeech::Symbol => Some("lexinfo:symbol"),
+        PartOfSpeech::Verb => Some("lexinfo:verb"),
+        PartOfSpeech::Other => None,
+    }
+}
+
+/// Object of an RDF statement.
+enum Term {
+    Iri(String),
+    /// IRI abbreviated with one of the `PREFIXES`
+    Name(&'static str),
+    Literal(String),
+    /// Literal in the language given by its tag
+    Text(String, String),
+    Date(String),
+}
+
+impl Term {
+    /// Link to `text` if it is an IRI, `text` itself otherwise.
+    fn link(text: &str) -> Self {
+        if is_iri(text) {
+            Term::Iri(text.to_string())
+        } else {
+            Term::Literal(text.to_string())
+        }
+    }
+
+    fn turtle(&self) -> String {
+        match self {
+            Term::Iri(iri) if is_iri(iri) => format!("<{iri}>"),
+            // Never let an invalid IRI break out of its brackets
+            Term::Iri(iri) => format!("\"{}\"", escape_turtle(iri)),
+            Term::Name(name) => (*name).to_string(),
+            Term::Literal(text) => format!("\"{}\"", escape_turtle(text)),
+            Term::Text(text, lang) if is_language_tag(lang) => {
+                format!("\"{}\"@{lang}", escape_turtle(text))
+            }
+            Term::Text(text, _) => format!("\"{}\"", escape_turtle(text)),
+            Term::Date(date) => format!("\"{date}\"^^xsd:date"),
+        }
+    }
+
+    fn json(&self) -> serde_json::Value {
+        match self {
+            Term::Iri(iri) => serde_json::json!({ "@id": iri }),
+            Term::Name(name) => serde_json::json!({ "@id": name }),
+            Term::Literal(text) => serde_json::json!(text),
+            Term::Text(text, lang) if is_language_tag(lang) => {
+                serde_json::json!({ "@value": text, "@language": lang })
+            }
+            Term::Text(text, _) => serde_json::json!(text),
+            Term::Date(date) => {
+                serde_json::json!({ "@value": date, "@type": "xsd:date" })
+            }
+        }
+    }
+}
+
+fn escape_turtle(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+/// Resource described by the statements of which it is the subject.
+struct Node {
+    id: String,
+    types: Vec<&'static str>,
+    properties: Vec<(&'static str, Term)>,
+}
+
+impl Node {
+    fn new(id: String, types: &[&'static str]) -> Self {
+        Self {
+            id,
+            types: types.to_vec(),
+            properties: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, property: &'static str, term: Term) -> &mut Self {
+        self.properties.push((property, term));
+        self
+    }
+
+    fn literal(
+        &mut self,
+        property: &'static str,
+        text: Option<&str>,
+    ) -> &mut Self {
+        if let Some(text) = text {
+            self.add(property, Term::Literal(text.to_string()));
+        }
+        self
+    }
+
+    fn turtle(&self) -> String {
+        let mut statements = Vec::new();
+        if !self.types.is_empty() {
+            statements.push(format!("a {}", self.types.join(", ")));
+        }
+        statements.extend(
+            self.properties.iter().map(|(property, term)| {
+                format!("{property} {}", term.turtle())
+            }),
+        );
+        format!("<{}> {} .\n", self.id, statements.join(" ;\n    "))
+    }
+
+    fn json(&self) -> serde_json::Value {
+        let mut node = serde_json::Map::new();
+        node.insert("@id".to_string(), serde_json::json!(self.id));
+        if !self.types.is_empty() {
+            node.insert("@type".to_string(), serde_json::json!(self.types));
+        }
+        for (property, term) in &self.properties {
+            match node.get_mut(*property) {
+                Some(serde_json::Value::Array(values)) => {
+                    values.push(term.json())
+                }
+                _ => {
+                    node.insert(
+                        (*property).to_string(),
+                        serde_json::json!([term.json()]),
+                    );
+                }
+            }
+        }
+        serde_json::Value::Object(node)
+    }
+}
+
+fn display_name(user: &User) -> String {
+    user.display_name
+        .clone()
+        .unwrap_or_else(|| user.username.clone())
+}
+
+/// Lexicon of the language `metadata`, described with Dublin Core
+/// terms.
+fn lexicon(base: &str, metadata: &LanguageMetadata) -> Node {
+    let mut node =
+        Node::new(language_iri(base, metadata.id), &["lime:Lexicon"]);
+    node.literal("rdfs:label", Some(metadata.name.as_str()))
+        .literal("dct:title", Some(metadata.name.as_str()))
+        .literal("dct:alternative", metadata.native.as_deref())
+        .literal("dct:identifier", Some(metadata.id.to_string().as_str()))
+        .literal("dct:abstract", metadata.abstract_.as_deref())
+        .literal("dct:rights", metadata.rights.as_deref())
+        .add(
+            "dct:created",
+            Term::Date(metadata.created.format("%Y-%m-%d").to_string()),
+        )
+        .add("dct:creator", Term::Literal(display_name(&metadata.owner)));
+    if let Some(license) = &metadata.license {
+        // Licenses given as a URL are linked to rather than quoted
+        node.add("dct:license", Term::link(license));
+    }
+    for author in &metadata.authors {
+        node.add("dct:contributor", Term::Literal(display_name(author)));
+    }
+    for publisher in &metadata.publishers {
+        node.add("dct:publisher", Term::Literal(display_name(publisher)));
+    }
+    node
+}
+
+/// Lexical entry of `word` in the lexicon `language`, along with its
+/// forms, its sense and its translations.
+///
+/// The norm of the word is its canonical form and its native
+/// spelling another form, written in the writing systems given by
+/// `header`. The words its `definitions` point to are translations of
+/// its sense, its lemma a lexical relation and related words are
+/// linked with `rdfs:seeAlso`.
+fn entry(
+    base: &str,
+    language: Uuid,
+    header: &LiftHeader,
+    word: &WordRecord,
+) -> Vec<Node> {
+    let iri = word_iri(base, word.id);
+    let sense = format!("{iri}#sense");
+    let mut nodes = Vec::new();
+    let mut lexicon = Node::new(language_iri(base, language), &[]);
+    lexicon.add("lime:entry", Term::Iri(iri.clone()));
+    nodes.push(lexicon);
+    let mut entry = Node::new(iri.clone(), &["ontolex:LexicalEntry"]);
+    entry
+        .literal("rdfs:label", Some(word.norm))
+        .add(
+            "ontolex:canonicalForm",
+            Term::Iri(format!("{iri}#canonicalForm")),
+        )
+        .add("ontolex:sense", Term::Iri(sense.clone()))
+        .literal("lexinfo:etymology", word.etymology);
+    if let Some(partofspeech) = lexinfo(word.partofspeech) {
+        entry.add("lexinfo:partOfSpeech", Term::Name(partofspeech));
+    }
+    if word.native.is_some() {
+        entry.add("ontolex:otherForm", Term::Iri(format!("{iri}#nativeForm")));
+    }
+    if let Some(lemma) = &word.lemma {
+        entry.add("vartrans:lexicalRel", Term::Iri(word_iri(base, lemma.id)));
+    }
+    for target in word.definitions {
+        entry.add(
+            "vartrans:translatableAs",
+            Term::Iri(word_iri(base, *target)),
+        );
+    }
+    for target in word.related {
+        entry.add("rdfs:seeAlso", Term::Iri(word_iri(base, *target)));
+    }
+    nodes.push(entry);
+    let mut canonical =
+        Node::new(format!("{iri}#canonicalForm"), &["ontolex:Form"]);
+    canonical.add(
+        "ontolex:writtenRep",
+        Term::Text(word.norm.to_string(), header.normlang().to_string()),
+    );
+    nodes.push(canonical);
+    if let Some(native) = word.native {
+        let mut form =
+            Node::new(format!("{iri}#nativeForm"), &["ontolex:Form"]);
+        form.add(
+            "ontolex:writtenRep",
+            Term::Text(native.to_string(), header.nativelang().to_string()),
+        );
+        nodes.push(form);
+    }
+    let mut lexical_sense = Node::new(sense.clone(), &["ontolex:LexicalSense"]);
+    lexical_sense
+        .add("ontolex:isSenseOf", Term::Iri(iri.clone()))
+        .literal("skos:definition", word.description)
+        .literal("skos:scopeNote", word.lusage);
+    nodes.push(lexical_sense);
+    for target in word.definitions {
+        let mut translation = Node::new(
+            format!("{iri}#translation-{target}"),
+            &["vartrans:Translation"],
+        );
+        translation
+            .add("vartrans:source", Term::Iri(sense.clone()))
+            .add(
+                "vartrans:target",
+                Term::Iri(format!("{}#sense", word_iri(base, *target))),
+            );
+        nodes.push(translation);
+    }
+    nodes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Turtle,
+    JsonLd,
+}
+
+/// Writer of OntoLex-Lemon descriptions of a lexicon and its entries,
+/// keeping track of the JSON-LD nodes already written.
+pub struct LexiconWriter {
+    syntax: Syntax,
+    base: &'static str,
+    /// Writing systems of the forms of the entries
+    header: LiftHeader,
+    first: bool,
+}
+
+impl LexiconWriter {
+    pub fn new(syntax: Syntax, header: LiftHeader) -> Self {
+        Self {
+            syntax,
+            base: base_iri(),
+            header,
+            first: true,
+        }
+    }
+
+    fn write_node<W: Write>(
+        &mut self,
+        out: &mut W,
+        node: &Node,
+    ) -> io::Result<()> {
+        match self.syntax {
+            Syntax::Turtle => writeln!(out, "{}", node.turtle()),
+            Syntax::JsonLd => {
+                if !self.first {
+                    out.write_all(b",\n")?;
+                }
+                self.first = false;
+                serde_json::to_writer(&mut *out, &node.json())?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the prefixes of the document, then the description of
+    /// the lexicon `metadata` if any.
+    pub fn write_start<W: Write>(
+        &mut self,
+        out: &mut W,
+        metadata: Option<&LanguageMetadata>,
+    ) -> io::Result<()> {
+        match self.syntax {
+            Syntax::Turtle => {
+                for (prefix, iri) in PREFIXES {
+                    writeln!(out, "@prefix {prefix}: <{iri}> .")?;
+                }
+                writeln!(out)?;
+            }
+            Syntax::JsonLd => {
+                let context = PREFIXES
+                    .iter()
+                    .map(|(prefix, iri)| {
+                        ((*prefix).to_string(), serde_json::json!(iri))
+                    })
+                    .collect::<serde_json::Map<_, _>>();
+                write!(
+                    out,
+                    "{{\"@context\":{},\"@graph\":[\n",
+                    serde_json::Value::Object(context)
+                )?;
+            }
+        }
+        if let Some(metadata) = metadata {
+            self.write_node(out, &lexicon(self.base, metadata))?;
+        }
+        Ok(())
+    }
+
+    /// Write the lexical entry of `word` in the lexicon `language`.
+    pub fn write_entry<W: Write>(
+        &mut self,
+        out: &mut W,
+        language: Uuid,
+        word: &WordRecord,
+    ) -> io::Result<()> {
+        for node in entry(self.base, language, &self.header, word) {
+            self.write_node(out, &node)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_end<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        match self.syntax {
+            Syntax::Turtle => Ok(()),
+            Syntax::JsonLd => out.write_all(b"\n]}\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn licenses_are_linked_only_when_valid_iris() {
+        let link = |text: &str| Term::link(text).turtle();
+        assert_eq!(
+            "<https://creativecommons.org/licenses/by/4.0/>",
+            link("https://creativecommons.org/licenses/by/4.0/")
+        );
+        assert_eq!("\"CC BY 4.0\"", link("CC BY 4.0"));
+        assert_eq!(
+            "\"http://a> . <http://b> <http://c> <http://d\"",
+            link("http://a> . <http://b> <http://c> <http://d")
+        );
+        assert_eq!(
+            "\"https://example.com/my license\"",
+            link("https://example.com/my license")
+        );
+        assert_eq!(
+            "\"https://example.com/\\\"quoted\\\"\"",
+            link("https://example.com/\"quoted\"")
+        );
+    }
+
+    #[test]
+    fn invalid_iris_do_not_break_out_of_their_brackets() {
+        assert_eq!(
+            "\"http://a> <http://b\"",
+            Term::Iri("http://a> <http://b".to_string()).turtle()
+        );
+    }
+
+    #[test]
+    fn written_forms_are_tagged_only_with_valid_languages() {
+        let text = |lang: &str| Term::Text("mjá".to_string(), lang.to_string());
+        assert_eq!("\"mjá\"@qaa-x-norm", text("qaa-x-norm").turtle());
+        assert_eq!("\"mjá\"", text("en US").turtle());
+        assert_eq!("\"mjá\"", text("en-").turtle());
+        assert_eq!(
+            serde_json::json!({ "@value": "mjá", "@language": "is" }),
+            text("is").json()
+        );
+        assert_eq!(serde_json::json!("mjá"), text("").json());
+    }
+
+    /// Export of the noun `word` of `language`, with a native spelling
+    /// and translated as `translation`.
+    fn export(
+        syntax: Syntax,
+        language: Uuid,
+        word: Uuid,
+        translation: Uuid,
+    ) -> String {
+        let mut writer = LexiconWriter::new(syntax, LiftHeader::new(language));
+        let mut out = Vec::new();
+        writer.write_start(&mut out, None).unwrap();
+        writer
+            .write_entry(
+                &mut out,
+                language,
+                &WordRecord {
+                    id: word,
+                    norm: "mjámjá",
+                    native: Some("ᛗᛃᚨᛗᛃᚨ"),
+                    lemma: None,
+                    partofspeech: &PartOfSpeech::Noun,
+                    audio: None,
+                    video: None,
+                    image: None,
+                    description: None,
+                    etymology: None,
+                    lusage: None,
+                    morphology: None,
+                    definitions: &[translation],
+                    related: &[],
+                },
+            )
+            .unwrap();
+        writer.write_end(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn entries_are_exported_as_turtle() {
+        let (language, word, translation) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let turtle = export(Syntax::Turtle, language, word, translation);
+        let iri = word_iri(base_iri(), word);
+        assert!(turtle.starts_with(
+            "@prefix ontolex: <http://www.w3.org/ns/lemon/ontolex#> ."
+        ));
+        assert!(turtle.contains(&format!(
+            "<{}> lime:entry <{iri}> .",
+            language_iri(base_iri(), language)
+        )));
+        assert!(turtle.contains(&format!("<{iri}> a ontolex:LexicalEntry ;")));
+        assert!(turtle.contains("lexinfo:partOfSpeech lexinfo:noun"));
+        assert!(turtle.contains(&format!(
+            "vartrans:translatableAs <{}>",
+            word_iri(base_iri(), translation)
+        )));
+        assert!(turtle.contains(&format!(
+            "<{iri}#canonicalForm> a ontolex:Form ;\n    \
+             ontolex:writtenRep \"mjámjá\"@qaa-x-norm ."
+        )));
+        assert!(turtle.contains(&format!(
+            "<{iri}#nativeForm> a ontolex:Form ;\n    \
+             ontolex:writtenRep \"ᛗᛃᚨᛗᛃᚨ\"@qaa-x-norm ."
+        )));
+    }
+
+    #[test]
+    fn entries_are_exported_as_json_ld() {
+        let (language, word, translation) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let document: serde_json::Value = serde_json::from_str(&export(
+            Syntax::JsonLd,
+            language,
+            word,
+            translation,
+        ))
+        .unwrap();
+        assert_eq!(
+            "http://www.w3.org/ns/lemon/ontolex#",
+            document["@context"]["ontolex"]
+        );
+        let iri = word_iri(base_iri(), word);
+        let node = |id: &str| {
+            document["@graph"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|node| node["@id"] == id)
+                .unwrap_or_else(|| panic!("{id} was not exported"))
+                .clone()
+        };
+        assert_eq!(
+            serde_json::json!([{ "@id": iri }]),
+            node(&language_iri(base_iri(), language))["lime:entry"]
+        );
+        let entry = node(&iri);
+        assert_eq!(serde_json::json!(["ontolex:LexicalEntry"]), entry["@type"]);
+        assert_eq!(
+            serde_json::json!([{ "@id": word_iri(base_iri(), translation) }]),
+            entry["vartrans:translatableAs"]
+        );
+        assert_eq!(
+            serde_json::json!([
+                { "@value": "mjámjá", "@language": "qaa-x-norm" }
+            ]),
+            node(&format!("{iri}#canonicalForm"))["ontolex:writtenRep"]
+        );
+        assert_eq!(
+            serde_json::json!([
+                { "@value": "ᛗᛃᚨᛗᛃᚨ", "@language": "qaa-x-norm" }
+            ]),
+            node(&format!("{iri}#nativeForm"))["ontolex:writtenRep"]
+        );
+    }
+}
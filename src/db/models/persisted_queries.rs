@@ -0,0 +1,170 @@
+use super::super::schema;
+use crate::db::{Database, DatabaseError, ErrorKind};
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bpchar, Text};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use std::env;
+
+use schema::persistedqueries;
+
+/// Maximum size of a registered query document, in bytes.
+pub const MAX_QUERY_SIZE: usize = 16 * 1024;
+
+/// Amount of queries that can be registered if
+/// `GRAPHQL_MAX_PERSISTED_QUERIES` is not set.
+const DEFAULT_MAX_QUERIES: i64 = 10_000;
+
+fn max_queries() -> i64 {
+    match env::var("GRAPHQL_MAX_PERSISTED_QUERIES") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid GRAPHQL_MAX_PERSISTED_QUERIES {val}, defaulting to {DEFAULT_MAX_QUERIES}: {e:?}"
+            );
+            DEFAULT_MAX_QUERIES
+        }),
+        Err(_) => DEFAULT_MAX_QUERIES,
+    }
+}
+
+/// Hex-encoded SHA-256 hash of `content`.
+pub fn sha256(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Query documents registered by clients through automatic persisted
+/// queries, identified by the SHA-256 hash of the document.
+pub struct PersistedQuery;
+
+impl PersistedQuery {
+    /// Find the query document with the hash `hash`, if it was
+    /// registered.
+    pub fn find(
+        db: &Database,
+        hash: &str,
+    ) -> Result<Option<String>, DatabaseError> {
+        use persistedqueries::dsl;
+        dsl::persistedqueries
+            .find(hash.to_lowercase())
+            .select(dsl::query)
+            .first::<String>(&mut db.conn()?)
+            .optional()
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to retrieve persisted query {hash}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Register `query` under `hash`, provided it is indeed its hash
+    /// and it is no larger than [`MAX_QUERY_SIZE`].
+    ///
+    /// Anybody can register queries, so only queries that were
+    /// successfully parsed and checked against the query limits should
+    /// be registered, and no more than `GRAPHQL_MAX_PERSISTED_QUERIES`
+    /// of them are kept. Queries which cannot be registered can still
+    /// be sent in full.
+    pub fn register(
+        db: &Database,
+        hash: &str,
+        query: &str,
+    ) -> Result<(), DatabaseError> {
+        if query.len() > MAX_QUERY_SIZE {
+            return Err(DatabaseError::new(
+                format!(
+                    "Persisted queries cannot be larger than {MAX_QUERY_SIZE} bytes"
+                ),
+                ErrorKind::InvalidInput,
+            ));
+        }
+        if sha256(query) != hash.to_lowercase() {
+            return Err(DatabaseError::new(
+                format!("Hash {hash} does not match the provided query"),
+                ErrorKind::InvalidInput,
+            ));
+        }
+        PersistedQuery::insert(db, &hash.to_lowercase(), query, max_queries())
+    }
+
+    /// Insert `query` under `hash` unless `max` queries are already
+    /// registered.
+    fn insert(
+        db: &Database,
+        hash: &str,
+        query: &str,
+        max: i64,
+    ) -> Result<(), DatabaseError> {
+        use persistedqueries::dsl;
+        let conn = &mut db.conn()?;
+        let inserted = diesel::sql_query(
+            "INSERT INTO persistedqueries (hash, query)
+             SELECT $1, $2
+             WHERE (SELECT COUNT(*) FROM persistedqueries) < $3
+             ON CONFLICT (hash) DO NOTHING",
+        )
+        .bind::<Bpchar, _>(hash)
+        .bind::<Text, _>(query)
+        .bind::<BigInt, _>(max)
+        .execute(conn)
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to register persisted query {hash}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })?;
+        if inserted == 0
+            && !diesel::select(diesel::dsl::exists(
+                dsl::persistedqueries.find(hash),
+            ))
+            .get_result::<bool>(conn)?
+        {
+            return Err(DatabaseError::new(
+                "Too many persisted queries, send the full query instead",
+                ErrorKind::InvalidInput,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn registered_queries_are_capped() {
+        let db = testing::database();
+        let registered = persistedqueries::table
+            .count()
+            .get_result::<i64>(&mut db.conn().unwrap())
+            .unwrap();
+        let query = format!("{{ test{registered} }}");
+        let hash = sha256(&query);
+        PersistedQuery::insert(&db, &hash, &query, registered + 1).unwrap();
+        assert_eq!(
+            Some(query.clone()),
+            PersistedQuery::find(&db, &hash).unwrap()
+        );
+        // Known queries can be sent again once the cap is reached
+        PersistedQuery::insert(&db, &hash, &query, registered + 1).unwrap();
+
+        let other = format!("{{ other{registered} }}");
+        let err = PersistedQuery::insert(
+            &db,
+            &sha256(&other),
+            &other,
+            registered + 1,
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        assert_eq!(None, PersistedQuery::find(&db, &sha256(&other)).unwrap());
+    }
+}
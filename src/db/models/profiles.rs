@@ -0,0 +1,323 @@
+use super::super::schema;
+use super::users::User;
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use diesel::prelude::*;
+use juniper::{GraphQLEnum, GraphQLInputObject, GraphQLObject};
+
+use schema::{userlanguages, users};
+
+/// Maximum length of a display name, in characters.
+const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+/// Maximum length of a bio, in characters.
+const MAX_BIO_LENGTH: usize = 10_000;
+/// Maximum length of the path or URL of an avatar.
+const MAX_AVATAR_LENGTH: usize = 511;
+/// Maximum length of a BCP 47 language tag.
+const MAX_LANGUAGE_TAG_LENGTH: usize = 35;
+/// Maximum length of the name of a spoken language.
+const MAX_SPOKEN_LANGUAGE_LENGTH: usize = 64;
+/// Maximum amount of spoken languages on a profile.
+const MAX_SPOKEN_LANGUAGES: usize = 50;
+/// Maximum amount of links on a profile.
+const MAX_LINKS: usize = 10;
+/// Maximum length of a link.
+const MAX_LINK_LENGTH: usize = 2048;
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Proficiency"]
+pub enum Proficiency {
+    Beginner,
+    Intermediate,
+    Advanced,
+    Fluent,
+    Native,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct SpokenLanguage {
+    #[allow(dead_code)]
+    id: i32,
+    #[allow(dead_code)]
+    userid: String,
+    language: String,
+    proficiency: Proficiency,
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl SpokenLanguage {
+    #[graphql(description = "Name or tag of the language")]
+    fn language(&self) -> String {
+        self.language.clone()
+    }
+
+    #[graphql(description = "How well the user speaks the language")]
+    fn proficiency(&self) -> Proficiency {
+        self.proficiency
+    }
+}
+
+impl SpokenLanguage {
+    /// Languages spoken by `user`, best spoken first.
+    pub fn of(
+        db: &Database,
+        user: &str,
+    ) -> Result<Vec<SpokenLanguage>, DatabaseError> {
+        use userlanguages::dsl;
+        dsl::userlanguages
+            .filter(dsl::userid.eq(user))
+            .order((dsl::proficiency.desc(), dsl::language.asc()))
+            .load::<SpokenLanguage>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve languages spoken by {user}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct SpokenLanguageInput {
+    #[graphql(description = "Name or tag of the language")]
+    pub language: String,
+    #[graphql(description = "How well the user speaks the language")]
+    pub proficiency: Proficiency,
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+#[graphql(
+    description = "Changes to a profile, null fields are left unchanged and empty strings remove a value"
+)]
+pub struct ProfileUpdate {
+    #[graphql(description = "Name displayed instead of the username")]
+    pub display_name: Option<String>,
+    #[graphql(description = "Presentation of the user, in Markdown format")]
+    pub bio: Option<String>,
+    #[graphql(description = "Path to the user's avatar picture")]
+    pub avatar: Option<String>,
+    #[graphql(
+        description = "BCP 47 tag of the language the interface should be displayed in"
+    )]
+    pub interface_language: Option<String>,
+    #[graphql(
+        description = "Languages the user speaks, replacing the current ones"
+    )]
+    pub spoken_languages: Option<Vec<SpokenLanguageInput>>,
+    #[graphql(
+        description = "Links to the user's websites, replacing the current ones"
+    )]
+    pub links: Option<Vec<String>>,
+    #[graphql(description = "Whether others can see who follows the user")]
+    pub public_followers: Option<bool>,
+    #[graphql(
+        description = "Whether others can see which languages the user follows"
+    )]
+    pub public_languages_followed: Option<bool>,
+    #[graphql(description = "Whether others can see what the user learns")]
+    pub public_words_learning: Option<bool>,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default, PartialEq, Eq)]
+#[diesel(table_name = users)]
+struct ProfileChangeset {
+    display_name: Option<Option<String>>,
+    bio: Option<Option<String>>,
+    avatar: Option<Option<String>>,
+    interface_language: Option<Option<String>>,
+    links: Option<Vec<String>>,
+    public_followers: Option<bool>,
+    public_languages_followed: Option<bool>,
+    public_words_learning: Option<bool>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = userlanguages)]
+struct NewSpokenLanguage<'a> {
+    userid: &'a str,
+    language: &'a str,
+    proficiency: Proficiency,
+}
+
+/// Trimmed `value`, `Some(None)` if it is empty to remove the current
+/// value.
+fn clearable(value: Option<&String>) -> Option<Option<String>> {
+    value.map(|value| {
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+fn check_length(
+    field: &str,
+    value: Option<&str>,
+    max: usize,
+) -> Result<(), DatabaseError> {
+    match value {
+        Some(value) if value.chars().count() > max => Err(DatabaseError::new(
+            format!("{field} cannot be longer than {max} characters"),
+            ErrorKind::InvalidInput,
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl ProfileUpdate {
+    fn validate(&self) -> Result<ProfileChangeset, DatabaseError> {
+        let changes = ProfileChangeset {
+            display_name: clearable(self.display_name.as_ref()),
+            bio: clearable(self.bio.as_ref()),
+            avatar: clearable(self.avatar.as_ref()),
+            interface_language: clearable(self.interface_language.as_ref()),
+            links: self.links.as_ref().map(|links| {
+                links
+                    .iter()
+                    .map(|link| link.trim().to_string())
+                    .filter(|link| !link.is_empty())
+                    .collect()
+            }),
+            public_followers: self.public_followers,
+            public_languages_followed: self.public_languages_followed,
+            public_words_learning: self.public_words_learning,
+        };
+        check_length(
+            "Display name",
+            changes.display_name.clone().flatten().as_deref(),
+            MAX_DISPLAY_NAME_LENGTH,
+        )?;
+        check_length(
+            "Bio",
+            changes.bio.clone().flatten().as_deref(),
+            MAX_BIO_LENGTH,
+        )?;
+        check_length(
+            "Avatar",
+            changes.avatar.clone().flatten().as_deref(),
+            MAX_AVATAR_LENGTH,
+        )?;
+        if let Some(Some(tag)) = &changes.interface_language {
+            if tag.len() > MAX_LANGUAGE_TAG_LENGTH
+                || !tag.split('-').all(|subtag| {
+                    !subtag.is_empty()
+                        && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+                })
+            {
+                return Err(DatabaseError::new(
+                    format!("{tag} is not a valid BCP 47 language tag"),
+                    ErrorKind::InvalidInput,
+                ));
+            }
+        }
+        if let Some(links) = &changes.links {
+            if links.len() > MAX_LINKS {
+                return Err(DatabaseError::new(
+                    format!(
+                        "A profile cannot have more than {MAX_LINKS} links"
+                    ),
+                    ErrorKind::InvalidInput,
+                ));
+            }
+            for link in links {
+                check_length("Link", Some(link), MAX_LINK_LENGTH)?;
+                if !link.starts_with("https://") && !link.starts_with("http://")
+                {
+                    return Err(DatabaseError::new(
+                        format!("Link {link} is not an HTTP(S) URL"),
+                        ErrorKind::InvalidInput,
+                    ));
+                }
+            }
+        }
+        if let Some(languages) = &self.spoken_languages {
+            if languages.len() > MAX_SPOKEN_LANGUAGES {
+                return Err(DatabaseError::new(
+                    format!("A profile cannot list more than {MAX_SPOKEN_LANGUAGES} languages"),
+                    ErrorKind::InvalidInput,
+                ));
+            }
+            for (i, spoken) in languages.iter().enumerate() {
+                let language = spoken.language.trim();
+                if language.is_empty() {
+                    return Err(DatabaseError::new(
+                        "Spoken languages cannot have an empty name",
+                        ErrorKind::InvalidInput,
+                    ));
+                }
+                check_length(
+                    "Spoken language",
+                    Some(language),
+                    MAX_SPOKEN_LANGUAGE_LENGTH,
+                )?;
+                if languages[..i]
+                    .iter()
+                    .any(|other| other.language.trim() == language)
+                {
+                    return Err(DatabaseError::new(
+                        format!("Language {language} is listed more than once"),
+                        ErrorKind::InvalidInput,
+                    ));
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Apply the changes to the profile of `user`.
+    pub fn apply(
+        &self,
+        db: &Database,
+        user: &str,
+    ) -> Result<User, DatabaseError> {
+        let changes = self.validate()?;
+        db.conn()?.transaction::<_, DatabaseError, _>(|conn| {
+            let profile = if changes == ProfileChangeset::default() {
+                users::dsl::users.find(user).first::<User>(conn)?
+            } else {
+                diesel::update(users::dsl::users.find(user))
+                    .set(&changes)
+                    .get_result::<User>(conn)?
+            };
+            if let Some(languages) = &self.spoken_languages {
+                use userlanguages::dsl;
+                diesel::delete(dsl::userlanguages.filter(dsl::userid.eq(user)))
+                    .execute(conn)?;
+                if languages.is_empty() {
+                    return Ok(profile);
+                }
+                diesel::insert_into(dsl::userlanguages)
+                    .values(
+                        languages
+                            .iter()
+                            .map(|spoken| NewSpokenLanguage {
+                                userid: user,
+                                language: spoken.language.trim(),
+                                proficiency: spoken.proficiency,
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .execute(conn)?;
+            }
+            Ok(profile)
+        })
+    }
+}
+
+#[derive(GraphQLObject, Debug, Clone, Copy)]
+#[graphql(description = "Who can see the lists of a user")]
+pub struct Privacy {
+    #[graphql(description = "Whether others can see who follows the user")]
+    pub followers: bool,
+    #[graphql(
+        description = "Whether others can see which languages the user follows"
+    )]
+    pub languages_followed: bool,
+    #[graphql(description = "Whether others can see what the user learns")]
+    pub words_learning: bool,
+}
@@ -0,0 +1,349 @@
+use super::super::schema;
+use super::{languages::Language, users::User};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use diesel::prelude::*;
+use juniper::GraphQLEnum;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use schema::{languagerevisions, languages, wordrevisions, words};
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Revisionaction"]
+pub enum RevisionAction {
+    Create,
+    Update,
+    Delete,
+    Revert,
+    Restore,
+}
+
+/// Serialize a snapshot of a database row so it can be stored in a
+/// revision.
+pub fn to_json<T: Serialize>(value: &T) -> QueryResult<serde_json::Value> {
+    serde_json::to_value(value)
+        .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))
+}
+
+/// Deserialize a snapshot stored in a revision.
+pub fn from_json<T: DeserializeOwned>(
+    value: serde_json::Value,
+) -> QueryResult<T> {
+    serde_json::from_value(value)
+        .map_err(|e| diesel::result::Error::DeserializationError(Box::new(e)))
+}
+
+fn author(context: &Context, author: Option<&str>) -> Option<User> {
+    match author {
+        Some(id) => match context.db.user(id) {
+            Ok(user) => user,
+            Err(e) => {
+                info!("Failed to retrieve revision author {id}: {e:?}");
+                None
+            }
+        },
+        None => None,
+    }
+}
+
+/// Check that `user`, anonymous if `None`, may read the history of
+/// the language `language` or of one of its words.
+///
+/// Private languages only show their history to their owner, as do
+/// languages in the trash and, if `trashed` is set, the words of a
+/// language in the trash.
+fn check_readable(
+    conn: &mut PgConnection,
+    language: Uuid,
+    user: Option<&str>,
+    trashed: bool,
+) -> Result<(), DatabaseError> {
+    let language = languages::table
+        .find(language)
+        .first::<Language>(conn)
+        .optional()?;
+    match language {
+        Some(language)
+            if language.is_visible_to(user)
+                && (!(trashed || language.is_trashed())
+                    || user
+                        .map_or(false, |user| language.is_owned_by(user))) =>
+        {
+            Ok(())
+        }
+        _ => Err(DatabaseError::new(
+            "History not found or not public",
+            ErrorKind::Forbidden,
+        )),
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = wordrevisions)]
+pub struct NewWordRevision {
+    pub word: Uuid,
+    pub language: Uuid,
+    pub author: Option<String>,
+    pub action: RevisionAction,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl NewWordRevision {
+    pub fn record(self, conn: &mut PgConnection) -> QueryResult<WordRevision> {
+        diesel::insert_into(wordrevisions::dsl::wordrevisions)
+            .values(self)
+            .get_result::<WordRevision>(conn)
+    }
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct WordRevision {
+    pub id: i32,
+    pub word: Uuid,
+    pub language: Uuid,
+    pub author: Option<String>,
+    pub action: RevisionAction,
+    pub created: chrono::NaiveDateTime,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl WordRevision {
+    /// List the revisions of a word, most recent first, as `user`
+    /// can see them.
+    ///
+    /// Revisions record the language of the word, so the history of a
+    /// word deleted for good stays readable by whoever can see its
+    /// language.
+    pub fn history(
+        db: &Database,
+        word: Uuid,
+        user: Option<&str>,
+    ) -> Result<Vec<WordRevision>, DatabaseError> {
+        use wordrevisions::dsl;
+        let conn = &mut db.conn()?;
+        let revisions = dsl::wordrevisions
+            .filter(dsl::word.eq(word))
+            .order(dsl::id.desc())
+            .load::<WordRevision>(conn)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to retrieve history of word {word}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })?;
+        let current = words::table
+            .find(word)
+            .select((words::language, words::deleted))
+            .first::<(Uuid, Option<chrono::NaiveDateTime>)>(conn)
+            .optional()?;
+        let trashed = current.map_or(false, |(_, deleted)| deleted.is_some());
+        let mut languages = revisions
+            .iter()
+            .map(|revision| revision.language)
+            .chain(current.map(|(language, _)| language))
+            .collect::<Vec<_>>();
+        languages.sort_unstable();
+        languages.dedup();
+        for language in languages {
+            check_readable(conn, language, user, trashed)?;
+        }
+        Ok(revisions)
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl WordRevision {
+    #[graphql(description = "Number of the revision")]
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[graphql(description = "Unique identifier of the revised word")]
+    fn word(&self) -> String {
+        self.word.to_string()
+    }
+
+    #[graphql(description = "User who made the change, if they still exist")]
+    fn author(&self, context: &Context) -> Option<User> {
+        author(context, self.author.as_deref())
+    }
+
+    #[graphql(description = "What kind of change was made")]
+    fn action(&self) -> RevisionAction {
+        self.action.clone()
+    }
+
+    #[graphql(description = "Time at which the change was made")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(
+        description = "JSON representation of the word before the change, null if it was created"
+    )]
+    fn before(&self) -> Option<String> {
+        self.before.as_ref().map(ToString::to_string)
+    }
+
+    #[graphql(
+        description = "JSON representation of the word after the change, null if it was deleted"
+    )]
+    fn after(&self) -> Option<String> {
+        self.after.as_ref().map(ToString::to_string)
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = languagerevisions)]
+pub struct NewLanguageRevision {
+    pub language: Uuid,
+    pub author: Option<String>,
+    pub action: RevisionAction,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl NewLanguageRevision {
+    pub fn record(
+        self,
+        conn: &mut PgConnection,
+    ) -> QueryResult<LanguageRevision> {
+        diesel::insert_into(languagerevisions::dsl::languagerevisions)
+            .values(self)
+            .get_result::<LanguageRevision>(conn)
+    }
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct LanguageRevision {
+    pub id: i32,
+    pub language: Uuid,
+    pub author: Option<String>,
+    pub action: RevisionAction,
+    pub created: chrono::NaiveDateTime,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl LanguageRevision {
+    /// List the revisions of a language, most recent first, provided
+    /// `user` can see it.
+    pub fn history(
+        db: &Database,
+        language: Uuid,
+        user: Option<&str>,
+    ) -> Result<Vec<LanguageRevision>, DatabaseError> {
+        use languagerevisions::dsl;
+        let conn = &mut db.conn()?;
+        check_readable(conn, language, user, false)?;
+        dsl::languagerevisions
+            .filter(dsl::language.eq(language))
+            .order(dsl::id.desc())
+            .load::<LanguageRevision>(conn)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve history of language {language}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl LanguageRevision {
+    #[graphql(description = "Number of the revision")]
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[graphql(description = "Unique identifier of the revised language")]
+    fn language(&self) -> String {
+        self.language.to_string()
+    }
+
+    #[graphql(description = "User who made the change, if they still exist")]
+    fn author(&self, context: &Context) -> Option<User> {
+        author(context, self.author.as_deref())
+    }
+
+    #[graphql(description = "What kind of change was made")]
+    fn action(&self) -> RevisionAction {
+        self.action.clone()
+    }
+
+    #[graphql(description = "Time at which the change was made")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(
+        description = "JSON representation of the language before the change, null if it was created"
+    )]
+    fn before(&self) -> Option<String> {
+        self.before.as_ref().map(ToString::to_string)
+    }
+
+    #[graphql(
+        description = "JSON representation of the language after the change, null if it was deleted"
+    )]
+    fn after(&self) -> Option<String> {
+        self.after.as_ref().map(ToString::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{models::languages::Release, testing};
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn history_of_words_deleted_for_good_follows_their_language() {
+        let db = testing::database();
+        let (owner, language, word) = {
+            let conn = &mut db.conn().unwrap();
+            let owner = testing::user(conn);
+            let language = testing::language(conn, &owner);
+            let word = testing::word(conn, language, "mjá", None);
+            NewWordRevision {
+                word,
+                language,
+                author: Some(owner.clone()),
+                action: RevisionAction::Delete,
+                before: None,
+                after: None,
+            }
+            .record(conn)
+            .unwrap();
+            diesel::delete(words::table.find(word))
+                .execute(conn)
+                .unwrap();
+            (owner, language, word)
+        };
+        assert_eq!(1, WordRevision::history(&db, word, None).unwrap().len());
+
+        diesel::update(languages::table.find(language))
+            .set(languages::release.eq(Release::Private))
+            .execute(&mut db.conn().unwrap())
+            .unwrap();
+        let err = WordRevision::history(&db, word, None).unwrap_err();
+        assert_eq!(ErrorKind::Forbidden, err.kind());
+        assert_eq!(
+            1,
+            WordRevision::history(&db, word, Some(&owner))
+                .unwrap()
+                .len()
+        );
+    }
+}
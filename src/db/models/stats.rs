@@ -0,0 +1,219 @@
+use super::super::schema;
+use super::words::{PartOfSpeech, WordLearningStatus};
+use crate::db::{Database, DatabaseError, ErrorKind};
+
+use chrono::NaiveDate;
+use diesel::dsl::{count_star, sum};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Uuid as SqlUuid, Varchar};
+use uuid::Uuid;
+
+use schema::{wordlearning, words};
+
+fn to_i32(value: i64) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, juniper::GraphQLObject)]
+#[graphql(description = "Amount of words learned by a user on a given day")]
+pub struct DailyLearning {
+    #[graphql(description = "Day on which the words were learned")]
+    pub day: String,
+    #[graphql(description = "Amount of words learned that day")]
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, juniper::GraphQLObject)]
+#[graphql(
+    description = "How much of a language's words of a part of speech a user knows"
+)]
+pub struct PartOfSpeechCoverage {
+    #[graphql(description = "Part of speech covered")]
+    pub part_of_speech: PartOfSpeech,
+    #[graphql(description = "Amount of words of this part of speech")]
+    pub total: i32,
+    #[graphql(description = "Amount of these words being learned")]
+    pub learning: i32,
+    #[graphql(description = "Amount of these words learned")]
+    pub learned: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, juniper::GraphQLObject)]
+#[graphql(description = "Learning progress of a user in a language")]
+pub struct LearningStats {
+    #[graphql(description = "Amount of words currently being learned")]
+    pub learning: i32,
+    #[graphql(description = "Amount of words learned")]
+    pub learned: i32,
+    #[graphql(
+        name = "learnedPerDay",
+        description = "Amount of words learned per day, oldest day first"
+    )]
+    pub learned_per_day: Vec<DailyLearning>,
+    #[graphql(
+        name = "currentStreak",
+        description = "Amount of consecutive days up to today or yesterday during which words were learned"
+    )]
+    pub current_streak: i32,
+    #[graphql(
+        description = "Ratio of successful reviews over all reviews, null if no word was reviewed yet"
+    )]
+    pub accuracy: Option<f64>,
+    #[graphql(description = "Coverage of the language by part of speech")]
+    pub coverage: Vec<PartOfSpeechCoverage>,
+}
+
+#[derive(QueryableByName, Debug)]
+struct DailyLearningRow {
+    #[diesel(sql_type = Date)]
+    day: NaiveDate,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+struct CoverageRow {
+    #[diesel(sql_type = crate::db::schema::sql_types::Partofspeech)]
+    partofspeech: PartOfSpeech,
+    #[diesel(sql_type = BigInt)]
+    total: i64,
+    #[diesel(sql_type = BigInt)]
+    learning: i64,
+    #[diesel(sql_type = BigInt)]
+    learned: i64,
+}
+
+/// Compute the amount of consecutive days during which words were
+/// learned.
+///
+/// `days` must be sorted in ascending order. A streak is still
+/// considered current if its last day is yesterday, so users do not
+/// lose it before they had a chance to learn something today.
+fn current_streak(days: &[NaiveDate], today: NaiveDate) -> i32 {
+    let mut days = days.iter().rev().peekable();
+    let mut expected = match days.peek() {
+        Some(&&day) if day == today || Some(day) == today.pred_opt() => day,
+        _ => return 0,
+    };
+    let mut streak = 0;
+    for &day in days {
+        if day != expected {
+            break;
+        }
+        streak += 1;
+        match expected.pred_opt() {
+            Some(previous) => expected = previous,
+            None => break,
+        }
+    }
+    streak
+}
+
+impl LearningStats {
+    /// Compute the learning statistics of `user` in `language`.
+    ///
+    /// Everything is aggregated by PostgreSQL, only the streak is
+    /// computed from the list of days during which words were learned.
+    pub fn compute(
+        db: &Database,
+        user: &str,
+        language: Uuid,
+    ) -> Result<Self, DatabaseError> {
+        let conn = &mut db.conn()?;
+        let error = |e: diesel::result::Error| {
+            DatabaseError::new(
+                format!("Failed to compute learning statistics of user {user} in language {language}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        };
+
+        let statuses = wordlearning::table
+            .inner_join(words::table)
+            .filter(wordlearning::userid.eq(user))
+            .filter(words::language.eq(language))
+            .filter(words::deleted.is_null())
+            .group_by(wordlearning::status)
+            .select((wordlearning::status, count_star()))
+            .load::<(WordLearningStatus, i64)>(conn)
+            .map_err(error)?;
+        let count_status = |status: WordLearningStatus| {
+            statuses
+                .iter()
+                .find(|(s, _)| *s == status)
+                .map_or(0, |(_, count)| to_i32(*count))
+        };
+
+        let (attempts, successes) = wordlearning::table
+            .inner_join(words::table)
+            .filter(wordlearning::userid.eq(user))
+            .filter(words::language.eq(language))
+            .filter(words::deleted.is_null())
+            .select((sum(wordlearning::attempts), sum(wordlearning::successes)))
+            .first::<(Option<i64>, Option<i64>)>(conn)
+            .map_err(error)?;
+        #[allow(clippy::cast_precision_loss)]
+        let accuracy = match (attempts, successes) {
+            (Some(attempts), Some(successes)) if attempts > 0 => {
+                Some(successes as f64 / attempts as f64)
+            }
+            _ => None,
+        };
+
+        let daily = diesel::sql_query(
+            "SELECT CAST(wl.learned AS DATE) AS day, COUNT(*) AS count
+             FROM wordlearning wl
+             INNER JOIN words w ON w.id = wl.word
+             WHERE wl.userid = $1
+               AND w.language = $2
+               AND w.deleted IS NULL
+               AND wl.learned IS NOT NULL
+             GROUP BY day
+             ORDER BY day",
+        )
+        .bind::<Varchar, _>(user)
+        .bind::<SqlUuid, _>(language)
+        .load::<DailyLearningRow>(conn)
+        .map_err(error)?;
+        let days = daily.iter().map(|row| row.day).collect::<Vec<_>>();
+        let today = chrono::Utc::now().naive_utc().date();
+
+        let coverage = diesel::sql_query(
+            "SELECT w.partofspeech AS partofspeech,
+                    COUNT(*) AS total,
+                    COUNT(wl.id) FILTER (WHERE wl.status = 'learning') AS learning,
+                    COUNT(wl.id) FILTER (WHERE wl.status = 'learned') AS learned
+             FROM words w
+             LEFT JOIN wordlearning wl ON wl.word = w.id AND wl.userid = $1
+             WHERE w.language = $2
+               AND w.deleted IS NULL
+             GROUP BY w.partofspeech",
+        )
+        .bind::<Varchar, _>(user)
+        .bind::<SqlUuid, _>(language)
+        .load::<CoverageRow>(conn)
+        .map_err(error)?;
+
+        Ok(Self {
+            learning: count_status(WordLearningStatus::Learning),
+            learned: count_status(WordLearningStatus::Learned),
+            learned_per_day: daily
+                .into_iter()
+                .map(|row| DailyLearning {
+                    day: row.day.to_string(),
+                    count: to_i32(row.count),
+                })
+                .collect(),
+            current_streak: current_streak(&days, today),
+            accuracy,
+            coverage: coverage
+                .into_iter()
+                .map(|row| PartOfSpeechCoverage {
+                    part_of_speech: row.partofspeech,
+                    total: to_i32(row.total),
+                    learning: to_i32(row.learning),
+                    learned: to_i32(row.learned),
+                })
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,470 @@
+use super::super::schema;
+use super::{
+    languages::{AgentLanguageRelation, Language},
+    notifications::{Event, NotificationKind},
+    revisions::{from_json, to_json},
+    users::User,
+    words::{NewWord, Word, WordUpdate},
+};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
+
+use diesel::prelude::*;
+use juniper::{FieldResult, GraphQLEnum};
+use tracing::info;
+use uuid::Uuid;
+
+use schema::{suggestions, words};
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Suggestionkind"]
+pub enum SuggestionKind {
+    New,
+    Edit,
+    Delete,
+}
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+)]
+#[DieselTypePath = "crate::db::schema::sql_types::Suggestionstatus"]
+pub enum SuggestionStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = suggestions)]
+struct NewSuggestion {
+    language: Uuid,
+    word: Option<Uuid>,
+    author: String,
+    kind: SuggestionKind,
+    content: Option<serde_json::Value>,
+    comment: Option<String>,
+}
+
+impl NewSuggestion {
+    fn insert(self, db: &Database) -> Result<Suggestion, DatabaseError> {
+        diesel::insert_into(suggestions::dsl::suggestions)
+            .values(self.clone())
+            .get_result::<Suggestion>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to insert suggestion {self:?}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct Suggestion {
+    id: i32,
+    language: Uuid,
+    word: Option<Uuid>,
+    author: String,
+    kind: SuggestionKind,
+    content: Option<serde_json::Value>,
+    comment: Option<String>,
+    status: SuggestionStatus,
+    created: chrono::NaiveDateTime,
+    reviewer: Option<String>,
+    reviewed: Option<chrono::NaiveDateTime>,
+    response: Option<String>,
+}
+
+/// Language `language` if `user` can read it, so that suggestions do
+/// not reveal private languages nor their words.
+fn readable_language(
+    db: &Database,
+    language: Uuid,
+    user: &str,
+) -> Result<Language, DatabaseError> {
+    let language = Language::find(db, language)?;
+    if !language.is_readable_by(Some(user)) {
+        return Err(DatabaseError::new(
+            "Language not found or not public",
+            ErrorKind::NotFound,
+        ));
+    }
+    Ok(language)
+}
+
+impl Suggestion {
+    /// Suggest adding a new word to a language.
+    pub fn new_word(
+        context: &Context,
+        author: &str,
+        word: &NewWord,
+        comment: Option<String>,
+    ) -> Result<Suggestion, DatabaseError> {
+        word.validate()?;
+        let language =
+            readable_language(&context.db, word.language()?, author)?;
+        NewSuggestion {
+            language: language.uuid(),
+            word: None,
+            author: author.to_string(),
+            kind: SuggestionKind::New,
+            content: Some(to_json(word)?),
+            comment,
+        }
+        .insert(&context.db)
+    }
+
+    /// Suggest changes to an existing word.
+    pub fn edit_word(
+        context: &Context,
+        author: &str,
+        word: Uuid,
+        changes: &WordUpdate,
+        comment: Option<String>,
+    ) -> Result<Suggestion, DatabaseError> {
+        changes.validate()?;
+        let word = Word::find(&context.db, word)?;
+        readable_language(&context.db, word.language_id(), author)?;
+        NewSuggestion {
+            language: word.language_id(),
+            word: Some(word.uuid()),
+            author: author.to_string(),
+            kind: SuggestionKind::Edit,
+            content: Some(to_json(changes)?),
+            comment,
+        }
+        .insert(&context.db)
+    }
+
+    /// Suggest deleting an existing word.
+    pub fn delete_word(
+        context: &Context,
+        author: &str,
+        word: Uuid,
+        comment: Option<String>,
+    ) -> Result<Suggestion, DatabaseError> {
+        let word = Word::find(&context.db, word)?;
+        readable_language(&context.db, word.language_id(), author)?;
+        NewSuggestion {
+            language: word.language_id(),
+            word: Some(word.uuid()),
+            author: author.to_string(),
+            kind: SuggestionKind::Delete,
+            content: None,
+            comment,
+        }
+        .insert(&context.db)
+    }
+
+    fn find(db: &Database, id: i32) -> Result<Suggestion, DatabaseError> {
+        suggestions::dsl::suggestions
+            .find(id)
+            .first::<Suggestion>(&mut db.conn()?)
+            .map_err(|e| match e {
+                diesel::NotFound => DatabaseError::new(
+                    format!("Suggestion {id} not found"),
+                    ErrorKind::NotFound,
+                ),
+                e => DatabaseError::new(
+                    format!(
+                        "Error fetching suggestion {id} from database: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                ),
+            })
+    }
+
+    /// Find a pending suggestion `user` is allowed to review.
+    fn find_reviewable(
+        context: &Context,
+        id: i32,
+        user: &str,
+    ) -> Result<(Suggestion, Language), DatabaseError> {
+        let suggestion = Suggestion::find(&context.db, id)?;
+        let language = Language::find(&context.db, suggestion.language)?;
+        if !language.can_review(&context.db, user)?
+            || !context.may_write(suggestion.language)
+        {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} cannot review suggestions for language {language}"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        if suggestion.status != SuggestionStatus::Pending {
+            return Err(DatabaseError::new(
+                format!("Suggestion {id} was already reviewed"),
+                ErrorKind::Conflict,
+            ));
+        }
+        Ok((suggestion, language))
+    }
+
+    /// List the pending suggestions of a language, oldest first.
+    pub fn pending(
+        context: &Context,
+        language: Uuid,
+        user: &str,
+    ) -> Result<Vec<Suggestion>, DatabaseError> {
+        use suggestions::dsl;
+        let language = Language::find(&context.db, language)?;
+        if !language.can_review(&context.db, user)? {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} cannot review suggestions for language {language}"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        dsl::suggestions
+            .filter(dsl::language.eq(language.uuid()))
+            .filter(dsl::status.eq(SuggestionStatus::Pending))
+            .order(dsl::created.asc())
+            .load::<Suggestion>(&mut context.db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve suggestions for language {language}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Make the suggested change on behalf of the suggestion's author.
+    fn apply(&self, conn: &mut PgConnection) -> Result<Event, DatabaseError> {
+        let id = self.id;
+        let author = &self.author;
+        let word = match self.word {
+            Some(word) => Some(
+                Word::visible()
+                    .filter(words::id.eq(word))
+                    .first::<Word>(conn)?,
+            ),
+            None => None,
+        };
+        match (&self.kind, word, self.content.clone()) {
+            (SuggestionKind::New, _, Some(content)) => {
+                let word: NewWord = from_json(content)?;
+                if word.language()? != self.language {
+                    return Err(DatabaseError::new(
+                        format!(
+                            "Suggestion {id} adds a word to another language"
+                        ),
+                        ErrorKind::Internal,
+                    ));
+                }
+                let word = word.insert_unchecked(conn, author)?;
+                Ok(Event::word(NotificationKind::WordAdded, author, &word))
+            }
+            (SuggestionKind::Edit, Some(word), Some(content)) => {
+                let changes: WordUpdate = from_json(content)?;
+                let word = word.update_unchecked(conn, author, changes)?;
+                Ok(Event::word(NotificationKind::WordChanged, author, &word))
+            }
+            (SuggestionKind::Delete, Some(word), _) => {
+                word.delete_unchecked(conn, author)?;
+                Ok(Event::word(NotificationKind::WordDeleted, author, &word))
+            }
+            _ => Err(DatabaseError::new(
+                format!("Suggestion {id} is malformed"),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+
+    /// Apply a suggestion and credit its author as an author of the
+    /// language.
+    ///
+    /// Everything happens in a single transaction: if the suggested
+    /// change cannot be applied, the suggestion stays pending.
+    pub fn accept(
+        context: &Context,
+        id: i32,
+        user: &str,
+    ) -> Result<Suggestion, DatabaseError> {
+        use suggestions::dsl;
+        let (_, language) = Suggestion::find_reviewable(context, id, user)?;
+        let conn = &mut context.db.conn()?;
+        let (suggestion, event) =
+            conn.transaction::<_, DatabaseError, _>(|conn| {
+                let suggestion = dsl::suggestions
+                    .find(id)
+                    .filter(dsl::status.eq(SuggestionStatus::Pending))
+                    .for_update()
+                    .first::<Suggestion>(conn)?;
+                let event = suggestion.apply(conn)?;
+                language.add_agent(
+                    conn,
+                    &suggestion.author,
+                    AgentLanguageRelation::Author,
+                )?;
+                let suggestion = diesel::update(dsl::suggestions.find(id))
+                    .set((
+                        dsl::status.eq(SuggestionStatus::Accepted),
+                        dsl::reviewer.eq(user),
+                        dsl::reviewed.eq(diesel::dsl::now.nullable()),
+                    ))
+                    .get_result::<Suggestion>(conn)?;
+                Ok((suggestion, event))
+            })?;
+        event.dispatch(context);
+        Ok(suggestion)
+    }
+
+    /// Reject a suggestion, optionally explaining why to its author.
+    pub fn reject(
+        context: &Context,
+        id: i32,
+        user: &str,
+        response: Option<String>,
+    ) -> Result<Suggestion, DatabaseError> {
+        use suggestions::dsl;
+        Suggestion::find_reviewable(context, id, user)?;
+        diesel::update(
+            dsl::suggestions
+                .find(id)
+                .filter(dsl::status.eq(SuggestionStatus::Pending)),
+        )
+        .set((
+            dsl::status.eq(SuggestionStatus::Rejected),
+            dsl::reviewer.eq(user),
+            dsl::reviewed.eq(diesel::dsl::now.nullable()),
+            dsl::response.eq(response),
+        ))
+        .get_result::<Suggestion>(&mut context.db.conn()?)
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to reject suggestion {id}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl Suggestion {
+    #[graphql(description = "Unique identifier of the suggestion")]
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[graphql(description = "Language the suggestion is made for")]
+    fn language(&self, context: &Context) -> FieldResult<Language> {
+        Language::find(&context.db, self.language).map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Word the suggestion is about, null when suggesting a new word"
+    )]
+    fn word(&self, context: &Context) -> Option<Word> {
+        match self.word {
+            Some(word) => match Word::find(&context.db, word) {
+                Ok(word) => Some(word),
+                Err(e) => {
+                    info!("Failed to retrieve word {word}: {e:?}");
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    #[graphql(description = "User who made the suggestion")]
+    fn author(&self, context: &Context) -> FieldResult<Option<User>> {
+        context.db.user(&self.author).map_err(Into::into)
+    }
+
+    #[graphql(description = "What kind of change is suggested")]
+    fn kind(&self) -> SuggestionKind {
+        self.kind.clone()
+    }
+
+    #[graphql(
+        description = "JSON representation of the suggested word or changes, null for deletions"
+    )]
+    fn content(&self) -> Option<String> {
+        self.content.as_ref().map(ToString::to_string)
+    }
+
+    #[graphql(
+        description = "Comment of the author on their suggestion, can be in Markdown format"
+    )]
+    fn comment(&self) -> Option<String> {
+        self.comment.clone()
+    }
+
+    #[graphql(description = "Whether the suggestion was reviewed yet")]
+    fn status(&self) -> SuggestionStatus {
+        self.status.clone()
+    }
+
+    #[graphql(description = "Time at which the suggestion was made")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+
+    #[graphql(description = "User who reviewed the suggestion")]
+    fn reviewer(&self, context: &Context) -> FieldResult<Option<User>> {
+        match &self.reviewer {
+            Some(reviewer) => context.db.user(reviewer).map_err(Into::into),
+            None => Ok(None),
+        }
+    }
+
+    #[graphql(description = "Time at which the suggestion was reviewed")]
+    fn reviewed(&self) -> Option<String> {
+        self.reviewed.map(|reviewed| reviewed.to_string())
+    }
+
+    #[graphql(
+        description = "Answer of the reviewer to the suggestion, can be in Markdown format"
+    )]
+    fn response(&self) -> Option<String> {
+        self.response.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::languages::Release;
+    use super::*;
+    use crate::db::{schema::languages, testing};
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn private_languages_get_no_suggestions() {
+        let db = testing::database();
+        let (owner, author, word) = {
+            let conn = &mut db.conn().unwrap();
+            let owner = testing::user(conn);
+            let language = testing::language(conn, &owner);
+            let word = testing::word(conn, language, "mjá", None);
+            diesel::update(languages::table.find(language))
+                .set(languages::release.eq(Release::Private))
+                .execute(conn)
+                .unwrap();
+            (owner, testing::user(conn), word)
+        };
+        let err = Suggestion::delete_word(
+            &Context::of_user(db.clone(), &author),
+            &author,
+            word,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+        assert!(Suggestion::delete_word(
+            &Context::of_user(db.clone(), &owner),
+            &owner,
+            word,
+            None,
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,393 @@
+use super::{
+    exports::{escape_xml, RelationTarget, WordRecord},
+    languages::LanguageMetadata,
+    lift::LiftHeader,
+    users::User,
+};
+
+use uuid::Uuid;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Identifier of the entry of a word, usable as an `xml:id` since
+/// UUIDs may start with a digit.
+fn entry_id(word: Uuid) -> String {
+    format!("w-{word}")
+}
+
+fn display_name(user: &User) -> &str {
+    user.display_name.as_deref().unwrap_or(&user.username)
+}
+
+/// Write the beginning of a TEI Lex-0 document describing the
+/// language `metadata`, up to the opening of its body.
+///
+/// Authors and the owner of the language are credited in the title
+/// statement, publishers in the publication statement along with the
+/// license and rights of the dictionary, and its abstract and genres
+/// in the profile description.
+pub fn write_start<W: Write>(
+    out: &mut W,
+    metadata: &LanguageMetadata,
+) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<TEI xmlns=\"http://www.tei-c.org/ns/1.0\">")?;
+    writeln!(out, "<teiHeader><fileDesc><titleStmt>")?;
+    writeln!(out, "<title>{}</title>", escape_xml(&metadata.name))?;
+    if let Some(native) = &metadata.native {
+        writeln!(out, "<title type=\"native\">{}</title>", escape_xml(native))?;
+    }
+    for author in &metadata.authors {
+        writeln!(out, "<author>{}</author>", escape_xml(display_name(author)))?;
+    }
+    writeln!(
+        out,
+        "<respStmt><resp>Owner</resp><name>{}</name></respStmt>",
+        escape_xml(display_name(&metadata.owner))
+    )?;
+    writeln!(out, "</titleStmt><publicationStmt>")?;
+    for publisher in &metadata.publishers {
+        writeln!(
+            out,
+            "<publisher>{}</publisher>",
+            escape_xml(display_name(publisher))
+        )?;
+    }
+    writeln!(out, "<distributor>Ordabok</distributor>")?;
+    writeln!(out, "<idno type=\"UUID\">{}</idno>", metadata.id)?;
+    writeln!(
+        out,
+        "<date when=\"{}\"/>",
+        metadata.created.format("%Y-%m-%d")
+    )?;
+    if metadata.license.is_some() || metadata.rights.is_some() {
+        write!(out, "<availability>")?;
+        if let Some(license) = &metadata.license {
+            write!(out, "<licence>{}</licence>", escape_xml(license))?;
+        }
+        if let Some(rights) = &metadata.rights {
+            write!(out, "<p>{}</p>", escape_xml(rights))?;
+        }
+        writeln!(out, "</availability>")?;
+    }
+    writeln!(out, "</publicationStmt>")?;
+    writeln!(out, "<sourceDesc><p>Exported from Ordabok</p></sourceDesc>")?;
+    writeln!(out, "</fileDesc>")?;
+    if metadata.abstract_.is_some() || !metadata.genre.is_empty() {
+        write!(out, "<profileDesc>")?;
+        if let Some(abstract_) = &metadata.abstract_ {
+            write!(
+                out,
+                "<abstract><p>{}</p></abstract>",
+                escape_xml(abstract_)
+            )?;
+        }
+        if !metadata.genre.is_empty() {
+            write!(out, "<textClass><keywords>")?;
+            for genre in &metadata.genre {
+                write!(out, "<term>{genre:?}</term>")?;
+            }
+            write!(out, "</keywords></textClass>")?;
+        }
+        writeln!(out, "</profileDesc>")?;
+    }
+    writeln!(out, "</teiHeader>")?;
+    writeln!(out, "<text><body>")
+}
+
+pub fn write_end<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, "</body></text>")?;
+    writeln!(out, "</TEI>")
+}
+
+/// Write a reference to the word `target`, pointing to its entry if
+/// it belongs to the exported language `language`.
+fn write_reference<W: Write>(
+    out: &mut W,
+    language: Uuid,
+    target: &RelationTarget,
+) -> io::Result<()> {
+    if target.language == language {
+        write!(
+            out,
+            "<ref type=\"entry\" target=\"#{}\">{}</ref>",
+            entry_id(target.id),
+            escape_xml(&target.norm)
+        )
+    } else {
+        write!(
+            out,
+            "<ref type=\"entry\">{}</ref>",
+            escape_xml(&target.norm)
+        )
+    }
+}
+
+/// Write `word` as a TEI Lex-0 entry of the language `language`,
+/// whose writing systems are those of its LIFT `header`.
+///
+/// Its norm and native form make up its lemma form, its part of speech
+/// and morphology its grammatical group, and its description, usage
+/// and translations its sense. Translations are the words the
+/// `definitions` of `word` point to, found in `targets`.
+pub fn write_entry<W: Write>(
+    out: &mut W,
+    header: &LiftHeader,
+    language: Uuid,
+    word: &WordRecord,
+    targets: &HashMap<Uuid, RelationTarget>,
+) -> io::Result<()> {
+    let id = entry_id(word.id);
+    let analysislang = escape_xml(header.analysislang());
+    write!(
+        out,
+        "<entry xml:id=\"{id}\" xml:lang=\"{}\">",
+        escape_xml(header.normlang())
+    )?;
+    write!(
+        out,
+        "<form type=\"lemma\"><orth>{}</orth>",
+        escape_xml(word.norm)
+    )?;
+    if let Some(native) = word.native {
+        write!(out, "<orth type=\"native\">{}</orth>", escape_xml(native))?;
+    }
+    write!(out, "</form>")?;
+    write!(
+        out,
+        "<gramGrp><gram type=\"pos\" norm=\"{}\">{}</gram>",
+        word.partofspeech.tag(),
+        word.partofspeech.name()
+    )?;
+    if let Some(morphology) = word.morphology {
+        write!(
+            out,
+            "<gram type=\"morphology\">{}</gram>",
+            escape_xml(morphology)
+        )?;
+    }
+    write!(out, "</gramGrp>")?;
+    if let Some(lemma) = &word.lemma {
+        write!(
+            out,
+            "<xr type=\"related\" subtype=\"lemma\"><ref type=\"entry\" target=\"#{}\">{}</ref></xr>",
+            entry_id(lemma.id),
+            escape_xml(lemma.norm)
+        )?;
+    }
+    write!(out, "<sense xml:id=\"{id}.1\" n=\"1\">")?;
+    if let Some(usage) = word.lusage {
+        write!(
+            out,
+            "<usg type=\"hint\" xml:lang=\"{analysislang}\">{}</usg>",
+            escape_xml(usage)
+        )?;
+    }
+    if let Some(description) = word.description {
+        write!(
+            out,
+            "<def xml:lang=\"{analysislang}\">{}</def>",
+            escape_xml(description)
+        )?;
+    }
+    for target in word.definitions.iter().filter_map(|id| targets.get(id)) {
+        write!(
+            out,
+            "<cit type=\"translationEquivalent\" xml:lang=\"{}\"><form type=\"lemma\"><orth>{}</orth></form></cit>",
+            escape_xml(&target.lang),
+            escape_xml(&target.norm)
+        )?;
+    }
+    for target in word.related.iter().filter_map(|id| targets.get(id)) {
+        write!(out, "<xr type=\"related\">")?;
+        write_reference(out, language, target)?;
+        write!(out, "</xr>")?;
+    }
+    write!(out, "</sense>")?;
+    if let Some(etymology) = word.etymology {
+        write!(
+            out,
+            "<etym xml:lang=\"{analysislang}\">{}</etym>",
+            escape_xml(etymology)
+        )?;
+    }
+    writeln!(out, "</entry>")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process::Command};
+
+    use super::super::{
+        exports::LemmaReference, languages::DictGenre, words::PartOfSpeech,
+    };
+    use super::*;
+
+    /// Official RelaxNG schema of TEI Lex-0, as published by the
+    /// DARIAH working group on lexical resources in
+    /// `Schemas/TEILex0/out/TEILex0.rng` of
+    /// <https://github.com/DARIAH-ERIC/lexicalresources>.
+    fn schema() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/TEILex0.rng")
+    }
+
+    /// Check `document` against the TEI Lex-0 schema with `xmllint`,
+    /// returning its complaints if it is invalid.
+    fn validate(document: &str) -> Result<(), String> {
+        let path = env::temp_dir().join(format!("{}.tei", Uuid::new_v4()));
+        fs::write(&path, document).unwrap();
+        let output = Command::new("xmllint")
+            .arg("--noout")
+            .arg("--relaxng")
+            .arg(schema())
+            .arg(&path)
+            .output()
+            .expect("xmllint is needed to validate TEI documents");
+        fs::remove_file(&path).unwrap();
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).into_owned())
+        }
+    }
+
+    fn user(name: &str) -> User {
+        User {
+            id: name.to_lowercase(),
+            username: name.to_string(),
+            admin: false,
+            display_name: Some(format!("{name} & co")),
+            bio: None,
+            avatar: None,
+            interface_language: None,
+            links: Vec::new(),
+            public_followers: true,
+            public_languages_followed: true,
+            public_words_learning: true,
+        }
+    }
+
+    fn metadata() -> LanguageMetadata {
+        LanguageMetadata {
+            id: Uuid::new_v4(),
+            name: "Proto-Cat".to_string(),
+            native: Some("Mjá".to_string()),
+            genre: vec![DictGenre::General, DictGenre::Etymology],
+            abstract_: Some("Words <of> cats".to_string()),
+            created: chrono::NaiveDate::from_ymd_opt(2023, 1, 4)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+                .unwrap(),
+            rights: Some("All rights reserved".to_string()),
+            license: Some("CC BY-SA 4.0".to_string()),
+            owner: user("Alice"),
+            authors: vec![user("Bob")],
+            publishers: vec![user("Carol")],
+        }
+    }
+
+    /// TEI Lex-0 document of `metadata` holding a complete entry, with
+    /// a lemma, a translation and relations within and outside of the
+    /// language, and a bare one.
+    fn document(metadata: &LanguageMetadata) -> String {
+        let header = LiftHeader::new(metadata.id);
+        let other = Uuid::new_v4();
+        let target = |norm: &str, language: Uuid, lang: &str| RelationTarget {
+            id: Uuid::new_v4(),
+            norm: norm.to_string(),
+            language,
+            lang: lang.to_string(),
+        };
+        let bare = target("mjá", metadata.id, header.normlang());
+        let translation = target("köttur", other, "is");
+        let related = target("kisa", other, "is");
+        let targets = [&bare, &translation, &related]
+            .into_iter()
+            .map(|target| (target.id, target.clone()))
+            .collect::<HashMap<_, _>>();
+        let definitions = [translation.id];
+        let relations = [bare.id, related.id];
+        let complete = WordRecord {
+            id: Uuid::new_v4(),
+            norm: "mjámjá",
+            native: Some("ᛗᛁᚨᛗᛁᚨ"),
+            lemma: Some(LemmaReference {
+                id: bare.id,
+                norm: "mjá",
+            }),
+            partofspeech: &PartOfSpeech::Noun,
+            audio: Some("mjamja.mp3"),
+            video: None,
+            image: None,
+            description: Some("A \"loud\" cat"),
+            etymology: Some("From mjá, doubled"),
+            lusage: Some("Informal"),
+            morphology: Some("mjámjá-r"),
+            definitions: &definitions,
+            related: &relations,
+        };
+        let bare = WordRecord {
+            id: bare.id,
+            norm: "mjá",
+            native: None,
+            lemma: None,
+            partofspeech: &PartOfSpeech::Interjection,
+            audio: None,
+            video: None,
+            image: None,
+            description: None,
+            etymology: None,
+            lusage: None,
+            morphology: None,
+            definitions: &[],
+            related: &[],
+        };
+        let mut out = Vec::new();
+        write_start(&mut out, metadata).unwrap();
+        for word in [&complete, &bare] {
+            write_entry(&mut out, &header, metadata.id, word, &targets)
+                .unwrap();
+        }
+        write_end(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs xmllint and the TEI Lex-0 schema in tests/fixtures"]
+    fn writes_valid_tei_lex_0() {
+        let full = metadata();
+        let document = document(&full);
+        assert_eq!(Ok(()), validate(&document));
+        assert!(document.contains("xml:lang=\"qaa-x-norm\""));
+        assert!(document
+            .contains("<cit type=\"translationEquivalent\" xml:lang=\"is\">"));
+
+        let bare = LanguageMetadata {
+            native: None,
+            genre: Vec::new(),
+            abstract_: None,
+            rights: None,
+            license: None,
+            authors: Vec::new(),
+            publishers: Vec::new(),
+            ..full
+        };
+        assert_eq!(Ok(()), validate(&document(&bare)));
+    }
+
+    #[test]
+    #[ignore = "needs xmllint and the TEI Lex-0 schema in tests/fixtures"]
+    fn rejects_invalid_documents() {
+        let written = document(&metadata());
+        assert_eq!(Ok(()), validate(&written));
+        assert!(validate(&written.replace("gramGrp>", "grammar>")).is_err());
+        assert!(validate(&written.replacen(
+            "<form type=\"lemma\">",
+            "<form type=\"lemma\"><entry/>",
+            1
+        ))
+        .is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use super::super::schema;
+use super::{
+    languages::{AgentLanguageRelation, Language},
+    revisions::RevisionAction,
+    users::User,
+};
+use crate::{
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::{subscription::LiveEvent, Context},
+};
+
+use diesel::prelude::*;
+use juniper::FieldResult;
+use uuid::Uuid;
+
+use schema::{languages, languagetransfers};
+
+#[derive(Queryable, Debug, Clone)]
+pub struct LanguageTransferOffer {
+    language: Uuid,
+    fromowner: String,
+    newowner: String,
+    created: chrono::NaiveDateTime,
+}
+
+impl LanguageTransferOffer {
+    /// Offer `new_owner` to become the owner of the language
+    /// `language` of `user`, replacing any pending offer for it.
+    pub fn offer(
+        context: &Context,
+        user: &str,
+        language: Uuid,
+        new_owner: &str,
+    ) -> Result<LanguageTransferOffer, DatabaseError> {
+        use languagetransfers::dsl;
+        let language = Language::find(&context.db, language)?;
+        if !language.is_owned_by(user) || !context.may_write(language.uuid()) {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} not allowed to transfer other user's language {language}"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        if new_owner == user {
+            return Err(DatabaseError::new(
+                format!("User {user} already owns language {language}"),
+                ErrorKind::InvalidInput,
+            ));
+        }
+        if context.db.user(new_owner)?.is_none() {
+            return Err(DatabaseError::new(
+                format!(
+                    "Cannot transfer language to non-existing user {new_owner}"
+                ),
+                ErrorKind::NotFound,
+            ));
+        }
+        let conn = &mut context.db.conn()?;
+        if language.collides_for(conn, new_owner)? {
+            return Err(language.collision(new_owner));
+        }
+        diesel::insert_into(dsl::languagetransfers)
+            .values((
+                dsl::language.eq(language.uuid()),
+                dsl::fromowner.eq(user),
+                dsl::newowner.eq(new_owner),
+            ))
+            .on_conflict(dsl::language)
+            .do_update()
+            .set((
+                dsl::fromowner.eq(user),
+                dsl::newowner.eq(new_owner),
+                dsl::created.eq(diesel::dsl::now),
+            ))
+            .get_result::<LanguageTransferOffer>(conn)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to offer language {language} to {new_owner}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Become the owner of the language `language` offered to `user`.
+    ///
+    /// The previous owner stays an author of the language.
+    pub fn accept(
+        context: &Context,
+        user: &str,
+        language: Uuid,
+    ) -> Result<Language, DatabaseError> {
+        use languagetransfers::dsl;
+        let conn = &mut context.db.conn()?;
+        let language = conn.transaction::<_, DatabaseError, _>(|conn| {
+            let offer = dsl::languagetransfers
+                .find(language)
+                .filter(dsl::newowner.eq(user))
+                .for_update()
+                .first::<LanguageTransferOffer>(conn)
+                .optional()?
+                .ok_or_else(|| {
+                    DatabaseError::new(
+                        format!(
+                            "Language {language} was not offered to user {user}"
+                        ),
+                        ErrorKind::NotFound,
+                    )
+                })?;
+            let current = languages::table
+                .find(language)
+                .filter(languages::deleted.is_null())
+                .for_update()
+                .first::<Language>(conn)
+                .optional()?
+                .ok_or_else(|| {
+                    DatabaseError::new(
+                        format!("Language {language} was deleted since it was offered"),
+                        ErrorKind::NotFound,
+                    )
+                })?;
+            if !current.is_owned_by(&offer.fromowner) {
+                diesel::delete(dsl::languagetransfers.find(language))
+                    .execute(conn)?;
+                return Err(DatabaseError::new(
+                    format!(
+                        "Language {current} changed owner since it was offered"
+                    ),
+                    ErrorKind::Conflict,
+                ));
+            }
+            let transferred = current.transfer_unchecked(conn, user, user)?;
+            transferred.add_agent(
+                conn,
+                &offer.fromowner,
+                AgentLanguageRelation::Author,
+            )?;
+            diesel::delete(dsl::languagetransfers.find(language))
+                .execute(conn)?;
+            Ok(transferred)
+        })?;
+        context.live.publish(LiveEvent::Language {
+            id: language.uuid(),
+            action: RevisionAction::Update,
+        });
+        Ok(language)
+    }
+
+    /// Withdraw or decline the offer for the language `language`, on
+    /// behalf of either its owner or its recipient `user`.
+    pub fn cancel(
+        db: &Database,
+        user: &str,
+        language: Uuid,
+    ) -> Result<(), DatabaseError> {
+        use languagetransfers::dsl;
+        match diesel::delete(
+            dsl::languagetransfers
+                .find(language)
+                .filter(dsl::fromowner.eq(user).or(dsl::newowner.eq(user))),
+        )
+        .execute(&mut db.conn()?)
+        {
+            Ok(0) => Err(DatabaseError::new(
+                format!(
+                    "No transfer of language {language} involves user {user}"
+                ),
+                ErrorKind::NotFound,
+            )),
+            Ok(_) => Ok(()),
+            Err(e) => Err(DatabaseError::new(
+                format!(
+                    "Failed to cancel transfer of language {language}: {e:?}"
+                ),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+
+    /// Pending offers made by or to `user`, most recent first.
+    pub fn involving(
+        db: &Database,
+        user: &str,
+    ) -> Result<Vec<LanguageTransferOffer>, DatabaseError> {
+        use languagetransfers::dsl;
+        dsl::languagetransfers
+            .filter(dsl::fromowner.eq(user).or(dsl::newowner.eq(user)))
+            .order(dsl::created.desc())
+            .load::<LanguageTransferOffer>(&mut db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to retrieve language transfers of {user}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl LanguageTransferOffer {
+    #[graphql(description = "Language offered")]
+    fn language(&self, context: &Context) -> FieldResult<Language> {
+        Language::find(&context.db, self.language).map_err(Into::into)
+    }
+
+    #[graphql(description = "Owner of the language offering it")]
+    fn from(&self, context: &Context) -> FieldResult<Option<User>> {
+        context.db.user(&self.fromowner).map_err(Into::into)
+    }
+
+    #[graphql(description = "User the language is offered to")]
+    fn to(&self, context: &Context) -> FieldResult<Option<User>> {
+        context.db.user(&self.newowner).map_err(Into::into)
+    }
+
+    #[graphql(description = "Time at which the language was offered")]
+    fn created(&self) -> String {
+        self.created.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+
+    /// Give the language `language` the name `name`.
+    fn rename(db: &Database, language: Uuid, name: &str) {
+        diesel::update(languages::table.find(language))
+            .set(languages::name.eq(name))
+            .execute(&mut db.conn().unwrap())
+            .unwrap();
+    }
+
+    /// Database with a language owned by the first user, and a second
+    /// user.
+    fn setup() -> (Database, String, String, Uuid) {
+        let db = testing::database();
+        let (owner, recipient, language) = {
+            let conn = &mut db.conn().unwrap();
+            let owner = testing::user(conn);
+            let language = testing::language(conn, &owner);
+            (owner, testing::user(conn), language)
+        };
+        (db, owner, recipient, language)
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn offered_languages_can_be_accepted() {
+        let (db, owner, recipient, language) = setup();
+        let offer = LanguageTransferOffer::offer(
+            &Context::of_user(db.clone(), &owner),
+            &owner,
+            language,
+            &recipient,
+        )
+        .unwrap();
+        assert_eq!(recipient, offer.newowner);
+        let err = LanguageTransferOffer::accept(
+            &Context::of_user(db.clone(), &owner),
+            &owner,
+            language,
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+
+        let transferred = LanguageTransferOffer::accept(
+            &Context::of_user(db.clone(), &recipient),
+            &recipient,
+            language,
+        )
+        .unwrap();
+        assert!(transferred.is_owned_by(&recipient));
+        assert!(LanguageTransferOffer::involving(&db, &owner)
+            .unwrap()
+            .is_empty());
+        assert!(transferred
+            .is_collaborator(&mut db.conn().unwrap(), &owner)
+            .unwrap());
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn languages_named_like_one_of_the_recipient_are_refused() {
+        let (db, owner, recipient, language) = setup();
+        let other = testing::language(&mut db.conn().unwrap(), &recipient);
+        rename(&db, language, "Proto-Cat");
+        rename(&db, other, "Proto-Cat");
+        let err = LanguageTransferOffer::offer(
+            &Context::of_user(db.clone(), &owner),
+            &owner,
+            language,
+            &recipient,
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+
+        // The recipient names a language like the offered one after
+        // the offer
+        rename(&db, other, "Proto-Dog");
+        LanguageTransferOffer::offer(
+            &Context::of_user(db.clone(), &owner),
+            &owner,
+            language,
+            &recipient,
+        )
+        .unwrap();
+        rename(&db, other, "Proto-Cat");
+        let err = LanguageTransferOffer::accept(
+            &Context::of_user(db.clone(), &recipient),
+            &recipient,
+            language,
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn users_cannot_own_two_languages_with_the_same_name() {
+        let (db, _, recipient, _) = setup();
+        let conn = &mut db.conn().unwrap();
+        let first = testing::language(conn, &recipient);
+        let second = testing::language(conn, &recipient);
+        let name = format!("test-{first}");
+        let err = conn
+            .transaction(|conn| {
+                diesel::update(languages::table.find(second))
+                    .set(languages::name.eq(&name))
+                    .execute(conn)
+            })
+            .unwrap_err();
+        assert!(super::super::languages::is_name_taken(&err));
+        // Languages in the trash do not count
+        diesel::update(languages::table.find(first))
+            .set(languages::deleted.eq(diesel::dsl::now.nullable()))
+            .execute(conn)
+            .unwrap();
+        diesel::update(languages::table.find(second))
+            .set(languages::name.eq(&name))
+            .execute(conn)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database"]
+    fn deleted_languages_cannot_be_accepted() {
+        let (db, owner, recipient, language) = setup();
+        LanguageTransferOffer::offer(
+            &Context::of_user(db.clone(), &owner),
+            &owner,
+            language,
+            &recipient,
+        )
+        .unwrap();
+        diesel::update(languages::table.find(language))
+            .set(languages::deleted.eq(diesel::dsl::now.nullable()))
+            .execute(&mut db.conn().unwrap())
+            .unwrap();
+        let err = LanguageTransferOffer::accept(
+            &Context::of_user(db.clone(), &recipient),
+            &recipient,
+            language,
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+    }
+}
@@ -0,0 +1,31 @@
+use super::{languages::Language, words::Word};
+use crate::graphql::Context;
+
+use juniper::FieldResult;
+
+/// Trash of a user, containing what they deleted from their
+/// languages and that was not purged yet.
+#[derive(Debug, Clone)]
+pub struct Trash {
+    pub owner: String,
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl Trash {
+    #[graphql(
+        description = "Languages in the trash, most recently deleted first"
+    )]
+    fn languages(&self, context: &Context) -> FieldResult<Vec<Language>> {
+        context
+            .db
+            .trashed_languages(&self.owner)
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Words in the trash whose language is not in the trash, most recently deleted first"
+    )]
+    fn words(&self, context: &Context) -> FieldResult<Vec<Word>> {
+        context.db.trashed_words(&self.owner).map_err(Into::into)
+    }
+}
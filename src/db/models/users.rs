@@ -1,20 +1,143 @@
 use super::{
     super::schema,
     languages::{Language, UserFollowLanguage},
+    notifications::Event,
+    profiles::{Privacy, SpokenLanguage},
+    stats::LearningStats,
     words::{Word, WordLearning, WordLearningStatus},
 };
 use diesel::prelude::*;
-use juniper::FieldResult;
+use juniper::{FieldResult, GraphQLObject};
 use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use std::{collections::HashMap, str::FromStr};
 
 use schema::{userfollows, users};
 
-use crate::{db::DatabaseError, graphql::Context};
+use crate::{
+    appwrite::AppwriteUser,
+    db::{Database, DatabaseError, ErrorKind},
+    graphql::Context,
+};
 
-#[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[derive(Queryable, Debug, Clone, PartialEq, Eq)]
 pub struct User {
     pub id: String,
     pub username: String,
+    pub admin: bool,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub interface_language: Option<String>,
+    pub links: Vec<String>,
+    pub public_followers: bool,
+    pub public_languages_followed: bool,
+    pub public_words_learning: bool,
+}
+
+#[derive(GraphQLObject, Debug, Clone, Copy, Default)]
+#[graphql(description = "Changes made to the users to match Appwrite")]
+pub struct UserReconciliation {
+    #[graphql(description = "Amount of users added to the database")]
+    created: i32,
+    #[graphql(description = "Amount of users whose username changed")]
+    updated: i32,
+    #[graphql(description = "Amount of users deleted from the database")]
+    deleted: i32,
+}
+
+impl User {
+    /// Whether the list the user made public or not according to
+    /// `public` can be seen by the requester.
+    fn shows(&self, context: &Context, public: bool) -> bool {
+        public || context.user_auth().map_or(false, |user| *user == self.id)
+    }
+
+    /// Insert the user `id`, or update their username if they already
+    /// exist.
+    pub(crate) fn upsert(
+        conn: &mut PgConnection,
+        id: &str,
+        username: &str,
+    ) -> Result<User, DatabaseError> {
+        use users::dsl;
+        diesel::insert_into(dsl::users)
+            .values((dsl::id.eq(id), dsl::username.eq(username)))
+            .on_conflict(dsl::id)
+            .do_update()
+            .set(dsl::username.eq(username))
+            .get_result::<User>(conn)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to upsert user {id}: {e:?}"),
+                    ErrorKind::Internal,
+                )
+            })
+    }
+
+    /// Create or update the user of the Appwrite account `account`.
+    pub fn sync(
+        db: &Database,
+        account: &AppwriteUser,
+    ) -> Result<User, DatabaseError> {
+        User::upsert(&mut db.conn()?, &account.id, &account.username())
+    }
+
+    /// Delete the user of the deleted Appwrite account `account`.
+    pub fn forget(
+        db: &Database,
+        account: &AppwriteUser,
+    ) -> Result<(), DatabaseError> {
+        Database::delete_user(&mut db.conn()?, &account.id)
+    }
+
+    /// Make the users of the database match `accounts`, the complete
+    /// list of users registered on Appwrite.
+    ///
+    /// Missing users are created, usernames are updated and users
+    /// absent from `accounts` are deleted along with their data.
+    pub fn reconcile(
+        conn: &mut PgConnection,
+        accounts: &[AppwriteUser],
+    ) -> Result<UserReconciliation, DatabaseError> {
+        use users::dsl;
+        let known: HashMap<String, String> = dsl::users
+            .select((dsl::id, dsl::username))
+            .load::<(String, String)>(conn)?
+            .into_iter()
+            .collect();
+        if accounts.is_empty() && !known.is_empty() {
+            return Err(DatabaseError::new(
+                "Appwrite lists no users, refusing to delete all users",
+                ErrorKind::Conflict,
+            ));
+        }
+        let mut report = UserReconciliation::default();
+        for account in accounts {
+            let username = account.username();
+            match known.get(&account.id) {
+                Some(current) if *current == username => continue,
+                Some(_) => report.updated += 1,
+                None => report.created += 1,
+            }
+            User::upsert(conn, &account.id, &username)?;
+        }
+        let ids: Vec<&str> =
+            accounts.iter().map(|account| account.id.as_str()).collect();
+        let deleted = diesel::delete(dsl::users.filter(dsl::id.ne_all(ids)))
+            .execute(conn)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to delete users unknown to Appwrite: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })?;
+        report.deleted = i32::try_from(deleted).unwrap_or(i32::MAX);
+        Ok(report)
+    }
 }
 
 #[juniper::graphql_object(Context = Context)]
@@ -29,13 +152,64 @@ impl User {
         self.username.clone()
     }
 
-    #[graphql(description = "Who the user follows")]
+    #[graphql(description = "Whether the user is an administrator")]
+    pub fn admin(&self) -> bool {
+        self.admin
+    }
+
+    #[graphql(description = "Name displayed instead of the username")]
+    pub fn display_name(&self) -> Option<String> {
+        self.display_name.clone()
+    }
+
+    #[graphql(description = "Presentation of the user, in Markdown format")]
+    pub fn bio(&self) -> Option<String> {
+        self.bio.clone()
+    }
+
+    #[graphql(description = "Path to the user's avatar picture")]
+    pub fn avatar(&self) -> Option<String> {
+        self.avatar.clone()
+    }
+
+    #[graphql(
+        description = "BCP 47 tag of the language the interface should be displayed in"
+    )]
+    pub fn interface_language(&self) -> Option<String> {
+        self.interface_language.clone()
+    }
+
+    #[graphql(description = "Languages the user speaks")]
+    pub fn spoken_languages(
+        &self,
+        context: &Context,
+    ) -> FieldResult<Vec<SpokenLanguage>> {
+        SpokenLanguage::of(&context.db, &self.id).map_err(Into::into)
+    }
+
+    #[graphql(description = "Links to the user's websites")]
+    pub fn links(&self) -> Vec<String> {
+        self.links.clone()
+    }
+
+    #[graphql(description = "Which of the user's lists others can see")]
+    pub fn privacy(&self) -> Privacy {
+        Privacy {
+            followers: self.public_followers,
+            languages_followed: self.public_languages_followed,
+            words_learning: self.public_words_learning,
+        }
+    }
+
+    #[graphql(
+        description = "Who the user follows, except users hiding their followers"
+    )]
     pub fn users_followed(&self, context: &Context) -> FieldResult<Vec<User>> {
         use schema::{userfollows, users};
         let conn = &mut context.db.conn().map_err(|e| {
             DatabaseError::new(
                 format!("Failed to connect to database: {e:?}"),
-                "Database connection error",
+                ErrorKind::Internal,
             )
         })?;
         Ok(userfollows::dsl::userfollows
@@ -46,7 +220,7 @@ impl User {
                     format!(
                         "Failed to retrieve user follows from database: {e:?}"
                     ),
-                    "Database reading error",
+                    ErrorKind::Internal,
                 )
             })?
            .iter()
@@ -54,13 +228,17 @@ impl User {
                match users::dsl::users
                 .find(f.following.clone())
                 .first::<User>(conn) {
-                    Ok(val) => Some(val),
+                    // Following a user hiding their followers is only
+                    // shown to both users
+                    Ok(val) if self.shows(context, val.public_followers)
+                        || val.shows(context, false) => Some(val),
+                    Ok(_) => None,
                     Err(e) => {
                         let err = DatabaseError::new(
                             format!("Failed to retrieve user {} from database: {e:?}",
                                     f.following.clone()),
-                            "Database reading error");
-                        debug!("{}", err);
+                            ErrorKind::Internal);
+                        debug!("{:?}", err);
                         None
                     }
                 }
@@ -68,13 +246,18 @@ impl User {
            .collect::<Vec<User>>())
     }
 
-    #[graphql(description = "Who follows this user")]
+    #[graphql(
+        description = "Who follows this user, empty if the user hides it"
+    )]
     pub fn followers(&self, context: &Context) -> FieldResult<Vec<User>> {
         use schema::userfollows::dsl;
+        if !self.shows(context, self.public_followers) {
+            return Ok(Vec::new());
+        }
         let conn = &mut context.db.conn().map_err(|e| {
             DatabaseError::new(
                 format!("Failed to connect to database: {e:?}"),
-                "Database connection error",
+                ErrorKind::Internal,
             )
         })?;
         Ok(dsl::userfollows
@@ -85,7 +268,7 @@ impl User {
                     format!(
                         "Failed to retrieve user follows from database: {e:?}"
                     ),
-                    "Database reading error",
+                    ErrorKind::Internal,
                 )
             })?
            .iter()
@@ -99,8 +282,8 @@ impl User {
                            let err = DatabaseError::new(
                                format!("Failed to retrieve user {} from database: {e:?}",
                                        user_follow.follower.clone()),
-                               "Database reading error");
-                           debug!("{}", err);
+                               ErrorKind::Internal);
+                           debug!("{:?}", err);
                            None
                        }
                    }
@@ -108,16 +291,21 @@ impl User {
            .collect::<Vec<User>>())
     }
 
-    #[graphql(description = "Which languages the user follows")]
+    #[graphql(
+        description = "Which languages the user follows, empty if the user hides it"
+    )]
     pub fn languages_followed(
         &self,
         context: &Context,
     ) -> FieldResult<Vec<Language>> {
         use schema::userfollowlanguage::dsl;
+        if !self.shows(context, self.public_languages_followed) {
+            return Ok(Vec::new());
+        }
         let conn = &mut context.db.conn().map_err(|e| {
             DatabaseError::new(
                 format!("Failed to connect to database: {e:?}"),
-                "Database connection error",
+                ErrorKind::Internal,
             )
         })?;
         Ok(dsl::userfollowlanguage
@@ -128,14 +316,14 @@ impl User {
                     format!(
                         "Failed to retrieve user follows from database: {e:?}"
                     ),
-                    "Database reading error",
+                    ErrorKind::Internal,
                 )
             })?
            .iter()
            .filter_map(|lang_follow| {
                use schema::languages::dsl;
-               match dsl::languages
-                   .find(lang_follow.lang)
+               match Language::visible()
+                   .filter(dsl::id.eq(lang_follow.lang))
                    .first::<Language>(conn) {
                        Ok(language) => Some(language),
                        Err(e) => {
@@ -152,7 +340,7 @@ impl User {
     }
 
     #[graphql(
-        description = "What words the user is learning or has learned",
+        description = "What words the user is learning or has learned, empty if the user hides it",
         arguments(status(
             description = "Display either words being learned or words learned"
         ))
@@ -163,10 +351,13 @@ impl User {
         status: WordLearningStatus,
     ) -> FieldResult<Vec<Word>> {
         use schema::wordlearning::dsl;
+        if !self.shows(context, self.public_words_learning) {
+            return Ok(Vec::new());
+        }
         let conn = &mut context.db.conn().map_err(|e| {
             DatabaseError::new(
                 format!("Failed to connect to database: {e:?}"),
-                "Database connection error",
+                ErrorKind::Internal,
             )
         })?;
         Ok(dsl::wordlearning
@@ -178,13 +369,16 @@ impl User {
                     format!(
                         "Failed to retrieve user follows from database: {e:?}"
                     ),
-                    "Database reading error",
+                    ErrorKind::Internal,
                 )
             })?
             .iter()
             .filter_map(|lang_learn| {
                 use schema::words::dsl;
-                match dsl::words.find(lang_learn.word).first::<Word>(conn) {
+                match Word::visible()
+                    .filter(dsl::id.eq(lang_learn.word))
+                    .first::<Word>(conn)
+                {
                     Ok(word) => Some(word),
                     Err(e) => {
                         info!(
@@ -197,6 +391,37 @@ impl User {
             })
             .collect::<Vec<Word>>())
     }
+
+    #[graphql(
+        name = "learningStats",
+        description = "Learning progress of the user in a language",
+        arguments(language(description = "UUID of the language"))
+    )]
+    pub fn learning_stats(
+        &self,
+        context: &Context,
+        language: String,
+    ) -> FieldResult<LearningStats> {
+        if !self.shows(context, self.public_words_learning) {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {} keeps their learning progress private",
+                    self.id
+                ),
+                ErrorKind::Forbidden,
+            )
+            .into());
+        }
+        match Uuid::from_str(&language) {
+            Ok(uuid) => LearningStats::compute(&context.db, &self.id, uuid)
+                .map_err(Into::into),
+            Err(e) => Err(DatabaseError::new(
+                format!("Failed to convert {language} to a UUID: {e:?}"),
+                ErrorKind::InvalidInput,
+            )
+            .into()),
+        }
+    }
 }
 
 #[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
@@ -205,4 +430,171 @@ pub struct UserFollow {
     pub id: i32,
     pub follower: String,
     pub following: String,
+    pub created: chrono::NaiveDateTime,
+}
+
+impl UserFollow {
+    /// Make `follower` follow the user `following`.
+    pub fn follow(
+        context: &Context,
+        follower: &str,
+        following: &str,
+    ) -> Result<User, DatabaseError> {
+        use userfollows::dsl;
+        if follower == following {
+            return Err(DatabaseError::new(
+                format!("User {follower} cannot follow themselves"),
+                ErrorKind::InvalidInput,
+            ));
+        }
+        let Some(user) = context.db.user(following)? else {
+            return Err(DatabaseError::new(
+                format!("Cannot follow non-existing user {following}"),
+                ErrorKind::NotFound,
+            ));
+        };
+        let inserted = diesel::insert_into(dsl::userfollows)
+            .values((dsl::follower.eq(follower), dsl::following.eq(following)))
+            .on_conflict((dsl::follower, dsl::following))
+            .do_nothing()
+            .execute(&mut context.db.conn()?)
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!(
+                        "Failed to make user {follower} follow user {following}: {e:?}"
+                    ),
+                    ErrorKind::Internal,
+                )
+            })?;
+        if inserted > 0 {
+            Event::NewFollower {
+                actor: follower.to_string(),
+                followed: following.to_string(),
+            }
+            .dispatch(context);
+        }
+        Ok(user)
+    }
+
+    /// Make `follower` stop following the user `following`.
+    pub fn unfollow(
+        context: &Context,
+        follower: &str,
+        following: &str,
+    ) -> Result<Option<User>, DatabaseError> {
+        use userfollows::dsl;
+        match diesel::delete(
+            dsl::userfollows
+                .filter(dsl::follower.eq(follower))
+                .filter(dsl::following.eq(following)),
+        )
+        .execute(&mut context.db.conn()?)
+        {
+            Ok(0) => Err(DatabaseError::new(
+                format!("User {follower} does not follow user {following}"),
+                ErrorKind::Conflict,
+            )),
+            Ok(_) => context.db.user(following),
+            Err(e) => Err(DatabaseError::new(
+                format!(
+                    "Failed to make user {follower} unfollow user {following}: {e:?}"
+                ),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{appwrite::APVariables, db::testing};
+
+    /// Mock Appwrite instance listing `users`, a page at a time.
+    async fn appwrite(users: &[Value]) -> (MockServer, APVariables) {
+        let server = MockServer::start().await;
+        let pages = users.chunks(100).enumerate().collect::<Vec<_>>();
+        for (page, accounts) in &pages {
+            Mock::given(method("GET"))
+                .and(path("/users"))
+                .and(query_param(
+                    "queries[]",
+                    format!("offset({})", page * 100),
+                ))
+                .and(header("X-Appwrite-Project", "ordabok"))
+                .and(header("X-Appwrite-Key", "api-key"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(
+                    json!({ "total": users.len(), "users": accounts }),
+                ))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        if pages.is_empty() {
+            Mock::given(method("GET"))
+                .and(path("/users"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({ "total": 0, "users": [] })),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let appwrite =
+            APVariables::new(server.uri(), "ordabok".into(), "api-key".into());
+        (server, appwrite)
+    }
+
+    fn find(conn: &mut PgConnection, id: &str) -> Option<User> {
+        users::dsl::users.find(id).first(conn).optional().unwrap()
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn reconciles_users_with_appwrite() {
+        let mut conn = testing::connection();
+        let renamed = testing::user(&mut conn);
+        let unchanged = testing::user(&mut conn);
+        let deleted = testing::user(&mut conn);
+        // Short enough for the IDs to fit in the 31 characters of Users.id
+        let batch = &testing::user_id()[..25];
+        let mut users = (0..148)
+            .map(|i| json!({ "$id": format!("test-{batch}-{i}"), "name": format!("User {i}") }))
+            .collect::<Vec<_>>();
+        users.push(json!({ "$id": renamed, "name": "Renamed" }));
+        users.push(json!({ "$id": unchanged, "name": unchanged }));
+
+        let (_server, appwrite) = appwrite(&users).await;
+        let accounts = appwrite.list_users().await.unwrap();
+        assert_eq!(150, accounts.len());
+        let report = User::reconcile(&mut conn, &accounts).unwrap();
+        assert_eq!(148, report.created);
+        assert_eq!(1, report.updated);
+        assert!(report.deleted >= 1);
+
+        assert_eq!("Renamed", find(&mut conn, &renamed).unwrap().username);
+        assert_eq!(unchanged, find(&mut conn, &unchanged).unwrap().username);
+        assert!(find(&mut conn, &deleted).is_none());
+        let created = find(&mut conn, &format!("test-{batch}-147")).unwrap();
+        assert_eq!("User 147", created.username);
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn refuses_to_delete_every_user() {
+        let mut conn = testing::connection();
+        let user = testing::user(&mut conn);
+        let (_server, appwrite) = appwrite(&[]).await;
+        let accounts = appwrite.list_users().await.unwrap();
+        let error = User::reconcile(&mut conn, &accounts).unwrap_err();
+        assert_eq!(ErrorKind::Conflict, error.kind());
+        assert!(find(&mut conn, &user).is_some());
+    }
 }
@@ -1,19 +1,33 @@
 use super::super::schema;
 use crate::{
-    db::{Database, DatabaseError},
+    db::{Database, DatabaseError, ErrorKind},
     graphql::Context,
 };
-use diesel::prelude::*;
+use diesel::{pg::Pg, prelude::*};
 use juniper::{FieldResult, GraphQLEnum};
-use schema::{wordlearning, wordrelation, words};
+use schema::{languages, wordlearning, wordrelation, words};
+use serde::{Deserialize, Serialize};
 use tracing::info;
 use uuid::Uuid;
 
 use std::{convert::Into, str::FromStr};
 
+use super::comments::Comment;
 use super::languages::Language;
+use super::notifications::{Event, NotificationKind};
+use super::revisions::{
+    from_json, to_json, NewWordRevision, RevisionAction, WordRevision,
+};
 
-#[derive(diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq)]
+#[derive(
+    diesel_derive_enum::DbEnum,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
 #[DieselTypePath = "crate::db::schema::sql_types::Wordrelationship"]
 pub enum WordRelationship {
     Definition,
@@ -35,7 +49,14 @@ pub enum WordLearningStatus {
 }
 
 #[derive(
-    diesel_derive_enum::DbEnum, Debug, Clone, PartialEq, Eq, GraphQLEnum,
+    diesel_derive_enum::DbEnum,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    GraphQLEnum,
+    Serialize,
+    Deserialize,
 )]
 #[DieselTypePath = "crate::db::schema::sql_types::Partofspeech"]
 pub enum PartOfSpeech {
@@ -67,7 +88,87 @@ impl Default for PartOfSpeech {
     }
 }
 
-#[derive(Debug, Clone, juniper::GraphQLInputObject)]
+impl PartOfSpeech {
+    /// Universal Dependencies tag of the part of speech.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::Adjective => "ADJ",
+            Self::Adposition => "ADP",
+            Self::Adverb => "ADV",
+            Self::Auxilliary => "AUX",
+            Self::CoordConj => "CCONJ",
+            Self::Determiner => "DET",
+            Self::Interjection => "INTJ",
+            Self::Noun => "NOUN",
+            Self::Numeral => "NUM",
+            Self::Particle => "PART",
+            Self::Pronoun => "PRON",
+            Self::ProperNoun => "PROPN",
+            Self::Punctuation => "PUNCT",
+            Self::SubjConj => "SCONJ",
+            Self::Symbol => "SYM",
+            Self::Verb => "VERB",
+            Self::Other => "X",
+        }
+    }
+
+    /// English name of the part of speech.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Adjective => "Adjective",
+            Self::Adposition => "Adposition",
+            Self::Adverb => "Adverb",
+            Self::Auxilliary => "Auxiliary",
+            Self::CoordConj => "Coordinating conjunction",
+            Self::Determiner => "Determiner",
+            Self::Interjection => "Interjection",
+            Self::Noun => "Noun",
+            Self::Numeral => "Numeral",
+            Self::Particle => "Particle",
+            Self::Pronoun => "Pronoun",
+            Self::ProperNoun => "Proper noun",
+            Self::Punctuation => "Punctuation",
+            Self::SubjConj => "Subordinating conjunction",
+            Self::Symbol => "Symbol",
+            Self::Verb => "Verb",
+            Self::Other => "Other",
+        }
+    }
+}
+
+impl FromStr for PartOfSpeech {
+    type Err = String;
+
+    /// Parse a part of speech from either its Universal Dependencies
+    /// tag (`NOUN`, `CCONJ`, ...) or its name as exposed by the API
+    /// (`NOUN`, `COORDINATING_CONJUNCTION`, ...), case insensitively.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().replace([' ', '-'], "_").as_str() {
+            "ADJ" | "ADJECTIVE" => Ok(Self::Adjective),
+            "ADP" | "ADPOSITION" | "PREPOSITION" | "POSTPOSITION" => {
+                Ok(Self::Adposition)
+            }
+            "ADV" | "ADVERB" => Ok(Self::Adverb),
+            "AUX" | "AUXILLIARY" | "AUXILIARY" => Ok(Self::Auxilliary),
+            "CCONJ" | "COORDINATING_CONJUNCTION" => Ok(Self::CoordConj),
+            "DET" | "DETERMINER" | "ARTICLE" => Ok(Self::Determiner),
+            "INTJ" | "INTERJECTION" => Ok(Self::Interjection),
+            "NOUN" => Ok(Self::Noun),
+            "NUM" | "NUMERAL" => Ok(Self::Numeral),
+            "PART" | "PARTICLE" => Ok(Self::Particle),
+            "PRON" | "PRONOUN" => Ok(Self::Pronoun),
+            "PROPN" | "PROPER_NOUN" => Ok(Self::ProperNoun),
+            "PUNCT" | "PUNCTUATION" => Ok(Self::Punctuation),
+            "SCONJ" | "SUBORDINATING_CONJUNCTION" => Ok(Self::SubjConj),
+            "SYM" | "SYMBOL" => Ok(Self::Symbol),
+            "VERB" => Ok(Self::Verb),
+            "X" | "OTHER" => Ok(Self::Other),
+            _ => Err(format!("Unknown part of speech {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, juniper::GraphQLInputObject, Serialize, Deserialize)]
 pub struct NewWord {
     norm: String,
     native: Option<String>,
@@ -84,56 +185,97 @@ pub struct NewWord {
 }
 
 impl NewWord {
+    /// Language to which the word is to be added.
+    pub fn language(&self) -> Result<Uuid, DatabaseError> {
+        Uuid::from_str(&self.language).map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to parse string as uuid: {e:?}"),
+                ErrorKind::InvalidInput,
+            )
+        })
+    }
+
+    /// Check the word's identifiers are valid UUIDs.
+    pub fn validate(&self) -> Result<(), DatabaseError> {
+        NewWordInternal::try_from(self.clone())
+            .map(|_| ())
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to parse string as uuid: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+            })
+    }
+
     pub fn insert(
         &self,
         context: &Context,
         user: &str,
+    ) -> Result<Word, DatabaseError> {
+        match Language::find(&context.db, self.language()?) {
+            Ok(language)
+                if language.is_owned_by(<&str>::clone(&user))
+                    && context.may_write(language.uuid()) =>
+            {
+                let word =
+                    self.insert_unchecked(&mut context.db.conn()?, user)?;
+                Event::word(NotificationKind::WordAdded, user, &word)
+                    .dispatch(context);
+                Ok(word)
+            }
+            Ok(language) => Err(DatabaseError::new(
+                format!("Language {language} is not owned by user {user}"),
+                ErrorKind::Forbidden,
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert the word on behalf of `author` without checking whether
+    /// they are allowed to.
+    pub fn insert_unchecked(
+        &self,
+        conn: &mut PgConnection,
+        author: &str,
     ) -> Result<Word, DatabaseError> {
         use words::dsl;
-        let conn = &mut context.db.conn()?;
         let mut word: NewWordInternal =
             self.clone().try_into().map_err(|e| {
                 DatabaseError::new(
                     format!("Failed to parse string as uuid: {e:?}"),
-                    "Invalid Input",
+                    ErrorKind::InvalidInput,
                 )
             })?;
-        match Language::find(&context.db, word.language) {
-            Ok(language) if language.is_owned_by(<&str>::clone(&user)) => {
-                // Check lemma exists
-                word.lemma = if let Some(id) = word.lemma {
-                    match dsl::words.find(id).first::<Word>(conn) {
-                        Ok(_) => Some(id),
-                        Err(_) => None,
-                    }
-                } else {
-                    None
-                };
-                match diesel::insert_into(dsl::words)
-                    .values(word.clone())
-                    .execute(conn)
-                {
-                    Ok(_) => dsl::words.filter(dsl::norm.eq(word.norm.clone()))
-                                       .filter(dsl::language.eq(word.language))
-                                       .first::<Word>(conn)
-                        .map_err(|e| DatabaseError::new(
-                            format!("Failed to find word {word:?} in database: {e:?}"),
-                            "Database Error"
-                        )),
-                    Err(e) => Err(DatabaseError::new(
-                        format!(
-                            "Failed to insert word {word:?} in database: {e:?}"
-                        ),
-                        "Database Error",
-                    )),
-                }
+        // Check lemma exists
+        word.lemma = match word.lemma {
+            Some(id) => Word::visible()
+                .filter(dsl::id.eq(id))
+                .select(dsl::id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            None => None,
+        };
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let inserted = diesel::insert_into(dsl::words)
+                .values(word.clone())
+                .get_result::<Word>(conn)?;
+            NewWordRevision {
+                word: inserted.id,
+                language: inserted.language,
+                author: Some(author.to_string()),
+                action: RevisionAction::Create,
+                before: None,
+                after: Some(to_json(&inserted.snapshot(conn)?)?),
             }
-            Ok(language) => Err(DatabaseError::new(
-                format!("Language {language} is not owned by user {user}"),
-                "Forbidden",
-            )),
-            Err(e) => Err(e),
-        }
+            .record(conn)?;
+            Ok(inserted)
+        })
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to insert word {word:?} in database: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
     }
 }
 
@@ -181,7 +323,88 @@ impl TryFrom<NewWord> for NewWordInternal {
     }
 }
 
-#[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, juniper::GraphQLInputObject, Serialize, Deserialize)]
+pub struct WordUpdate {
+    norm: Option<String>,
+    native: Option<String>,
+    lemma: Option<String>,
+    partofspeech: Option<PartOfSpeech>,
+    audio: Option<String>,
+    video: Option<String>,
+    image: Option<String>,
+    description: Option<String>,
+    etymology: Option<String>,
+    lusage: Option<String>,
+    morphology: Option<String>,
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = words)]
+struct WordChangeset {
+    norm: Option<String>,
+    native: Option<String>,
+    lemma: Option<Uuid>,
+    partofspeech: Option<PartOfSpeech>,
+    audio: Option<String>,
+    video: Option<String>,
+    image: Option<String>,
+    description: Option<String>,
+    etymology: Option<String>,
+    lusage: Option<String>,
+    morphology: Option<String>,
+}
+
+impl WordUpdate {
+    /// Check the lemma, if any, is a valid UUID.
+    pub fn validate(&self) -> Result<(), DatabaseError> {
+        WordChangeset::try_from(self.clone())
+            .map(|_| ())
+            .map_err(|e| {
+                DatabaseError::new(
+                    format!("Failed to parse string as uuid: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+            })
+    }
+}
+
+impl TryFrom<WordUpdate> for WordChangeset {
+    type Error = uuid::Error;
+
+    fn try_from(value: WordUpdate) -> Result<Self, Self::Error> {
+        let lemma = if let Some(original_lemma) = value.lemma {
+            Some(Uuid::from_str(&original_lemma)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            norm: value.norm,
+            native: value.native,
+            lemma,
+            partofspeech: value.partofspeech,
+            audio: value.audio,
+            video: value.video,
+            image: value.image,
+            description: value.description,
+            etymology: value.etymology,
+            lusage: value.lusage,
+            morphology: value.morphology,
+        })
+    }
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    AsChangeset,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(table_name = words, treat_none_as_null = true)]
 pub struct Word {
     id: Uuid,
     norm: String,
@@ -196,22 +419,55 @@ pub struct Word {
     etymology: Option<String>,
     lusage: Option<String>,
     morphology: Option<String>,
+    #[serde(default)]
+    deleted: Option<chrono::NaiveDateTime>,
 }
 
 impl Word {
+    /// Words that are not in the trash and do not belong to a
+    /// language in the trash.
+    ///
+    /// Every read path should start from this query rather than from
+    /// the `words` table itself.
+    pub fn visible() -> words::BoxedQuery<'static, Pg> {
+        words::table
+            .filter(words::deleted.is_null())
+            .filter(
+                words::language.eq_any(
+                    languages::table
+                        .filter(languages::deleted.is_null())
+                        .select(languages::id),
+                ),
+            )
+            .into_boxed()
+    }
+
+    pub fn is_trashed(&self) -> bool {
+        self.deleted.is_some()
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.id
+    }
+
+    /// Unique identifier of the language the word belongs to.
+    pub fn language_id(&self) -> Uuid {
+        self.language
+    }
+
     pub fn find(db: &Database, word: Uuid) -> Result<Word, DatabaseError> {
         use words::dsl;
-        dsl::words
-            .find(word)
+        Word::visible()
+            .filter(dsl::id.eq(word))
             .first::<Word>(&mut db.conn()?)
             .map_err(|e| match e {
                 diesel::NotFound => DatabaseError::new(
                     format!("Word {word} not found"),
-                    "Not Found",
+                    ErrorKind::NotFound,
                 ),
                 e => DatabaseError::new(
                     format!("Error fetching word {word} from database: {e:?}"),
-                    "Database Error",
+                    ErrorKind::Internal,
                 ),
             })
     }
@@ -232,32 +488,324 @@ impl Word {
     ) -> Result<(), DatabaseError> {
         use words::dsl;
         let conn = &mut context.db.conn()?;
-        match dsl::words.find(id).first::<Word>(conn) {
+        match Word::visible().filter(dsl::id.eq(id)).first::<Word>(conn) {
             Ok(word) => {
-                if let Ok(true) = word.is_owned_by(&context.db, user) {
-                    match diesel::delete(dsl::words.find(id))
-                        .execute(&mut context.db.conn()?)
-                    {
-                        Ok(_) => Ok(()),
-                        Err(e) => Err(DatabaseError::new(
-                            format!("Failed to delete word {id} from database: {e:?}"),
-                            "Database Error",
-                        )),
-                    }
+                if let (Ok(true), true) = (
+                    word.is_owned_by(&context.db, user),
+                    context.may_write(word.language),
+                ) {
+                    word.delete_unchecked(conn, user)?;
+                    Event::word(NotificationKind::WordDeleted, user, &word)
+                        .dispatch(context);
+                    Ok(())
                 } else {
                     Err(DatabaseError::new(
                         format!("User {user} cannot delete word from language he doesn't own"),
-                        "Forbidden"
+                        ErrorKind::Forbidden
                     ))
                 }
             }
+            Err(diesel::NotFound) => Err(DatabaseError::new(
+                format!("Word {id} not found"),
+                ErrorKind::NotFound,
+            )),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to find word {id} in database: {e:?}"),
-                "Database Error",
+                ErrorKind::Internal,
             )),
         }
     }
 
+    /// Put the word in the trash on behalf of `author` without
+    /// checking whether they are allowed to.
+    pub fn delete_unchecked(
+        &self,
+        conn: &mut PgConnection,
+        author: &str,
+    ) -> Result<(), DatabaseError> {
+        use words::dsl;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let before = to_json(&self.snapshot(conn)?)?;
+            diesel::update(dsl::words.find(self.id))
+                .set(dsl::deleted.eq(diesel::dsl::now.nullable()))
+                .execute(conn)?;
+            NewWordRevision {
+                word: self.id,
+                language: self.language,
+                author: Some(author.to_string()),
+                action: RevisionAction::Delete,
+                before: Some(before),
+                after: None,
+            }
+            .record(conn)?;
+            Ok(())
+        })
+        .map_err(|e| {
+            DatabaseError::new(
+                format!(
+                    "Failed to delete word {} from database: {e:?}",
+                    self.id
+                ),
+                ErrorKind::Internal,
+            )
+        })
+    }
+
+    /// Take a word out of the trash.
+    ///
+    /// The language of the word must not be in the trash itself.
+    pub fn restore(
+        context: &Context,
+        id: Uuid,
+        user: &str,
+    ) -> Result<Word, DatabaseError> {
+        use words::dsl;
+        let conn = &mut context.db.conn()?;
+        let word = match dsl::words.find(id).first::<Word>(conn) {
+            Ok(word) => word,
+            Err(diesel::NotFound) => {
+                return Err(DatabaseError::new(
+                    format!("Word {id} not found"),
+                    ErrorKind::NotFound,
+                ))
+            }
+            Err(e) => {
+                return Err(DatabaseError::new(
+                    format!("Error fetching word {id} from database: {e:?}"),
+                    ErrorKind::Internal,
+                ))
+            }
+        };
+        if !word.is_trashed() {
+            return Err(DatabaseError::new(
+                format!("Word {id} is not in the trash"),
+                ErrorKind::Conflict,
+            ));
+        }
+        if !word.is_owned_by(&context.db, user)?
+            || !context.may_write(word.language)
+        {
+            return Err(DatabaseError::new(
+                format!("User {user} cannot restore word from language they don't own"),
+                ErrorKind::Forbidden,
+            ));
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let before = to_json(&word.snapshot(conn)?)?;
+            let restored = diesel::update(dsl::words.find(id))
+                .set(dsl::deleted.eq(None::<chrono::NaiveDateTime>))
+                .get_result::<Word>(conn)?;
+            NewWordRevision {
+                word: id,
+                language: restored.language,
+                author: Some(user.to_string()),
+                action: RevisionAction::Restore,
+                before: Some(before),
+                after: Some(to_json(&restored.snapshot(conn)?)?),
+            }
+            .record(conn)?;
+            Ok(restored)
+        })
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to restore word {id}: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })
+        .map(|word| {
+            Event::word(NotificationKind::WordAdded, user, &word)
+                .dispatch(context);
+            word
+        })
+    }
+
+    /// Update a word owned by `user`, recording the change in the
+    /// word's history.
+    pub fn update(
+        context: &Context,
+        id: Uuid,
+        user: &str,
+        changes: WordUpdate,
+    ) -> Result<Word, DatabaseError> {
+        let word = Word::find(&context.db, id)?;
+        if !word.is_owned_by(&context.db, user)?
+            || !context.may_write(word.language)
+        {
+            return Err(DatabaseError::new(
+                format!(
+                    "User {user} cannot edit word from language they don't own"
+                ),
+                ErrorKind::Forbidden,
+            ));
+        }
+        let word =
+            word.update_unchecked(&mut context.db.conn()?, user, changes)?;
+        Event::word(NotificationKind::WordChanged, user, &word)
+            .dispatch(context);
+        Ok(word)
+    }
+
+    /// Update the word on behalf of `author` without checking whether
+    /// they are allowed to.
+    pub fn update_unchecked(
+        &self,
+        conn: &mut PgConnection,
+        author: &str,
+        changes: WordUpdate,
+    ) -> Result<Word, DatabaseError> {
+        use words::dsl;
+        let changes: WordChangeset = changes.try_into().map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to parse string as uuid: {e:?}"),
+                ErrorKind::InvalidInput,
+            )
+        })?;
+        if let Some(lemma) = changes.lemma {
+            if Word::visible()
+                .filter(dsl::id.eq(lemma))
+                .select(dsl::id)
+                .first::<Uuid>(conn)
+                .optional()?
+                .is_none()
+            {
+                return Err(DatabaseError::new(
+                    format!("Lemma {lemma} not found"),
+                    ErrorKind::NotFound,
+                ));
+            }
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let before = to_json(&self.snapshot(conn)?)?;
+            let updated = diesel::update(dsl::words.find(self.id))
+                .set(&changes)
+                .get_result::<Word>(conn)?;
+            NewWordRevision {
+                word: self.id,
+                language: updated.language,
+                author: Some(author.to_string()),
+                action: RevisionAction::Update,
+                before: Some(before),
+                after: Some(to_json(&updated.snapshot(conn)?)?),
+            }
+            .record(conn)?;
+            Ok(updated)
+        })
+        .map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to update word {}: {e:?}", self.id),
+                ErrorKind::Internal,
+            )
+        })
+    }
+
+    /// Bring a word back to the state it had in one of its revisions.
+    ///
+    /// The word is restored as it was right after `revision`, or right
+    /// before it if `revision` is its deletion. If the word no longer
+    /// exists, it is recreated with its original identifier along
+    /// with the relations and lemma links it had when it was
+    /// deleted, as long as the words on the other end still exist.
+    pub fn revert(
+        context: &Context,
+        id: Uuid,
+        revision: i32,
+        user: &str,
+    ) -> Result<Word, DatabaseError> {
+        use schema::wordrevisions::dsl;
+        let conn = &mut context.db.conn()?;
+        let target = match dsl::wordrevisions
+            .find(revision)
+            .filter(dsl::word.eq(id))
+            .first::<WordRevision>(conn)
+        {
+            Ok(target) => target,
+            Err(diesel::NotFound) => {
+                return Err(DatabaseError::new(
+                    format!("Revision {revision} of word {id} not found"),
+                    ErrorKind::NotFound,
+                ))
+            }
+            Err(e) => {
+                return Err(DatabaseError::new(
+                    format!("Failed to retrieve revision {revision} of word {id}: {e:?}"),
+                    ErrorKind::Internal,
+                ))
+            }
+        };
+        let language = Language::find(&context.db, target.language)?;
+        if !language.is_owned_by(user) || !context.may_write(target.language) {
+            return Err(DatabaseError::new(
+                format!("User {user} cannot revert word from language {language} they don't own"),
+                ErrorKind::Forbidden,
+            ));
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let snapshot: WordSnapshot = match target.after.or(target.before) {
+                Some(value) => from_json(value)?,
+                None => return Err(diesel::NotFound),
+            };
+            let current =
+                words::dsl::words.find(id).first::<Word>(conn).optional()?;
+            let before = match &current {
+                Some(word) => Some(to_json(&word.snapshot(conn)?)?),
+                None => None,
+            };
+            let word = if current.is_some() {
+                snapshot.overwrite(conn)?
+            } else {
+                snapshot.restore(conn)?
+            };
+            NewWordRevision {
+                word: id,
+                language: word.language,
+                author: Some(user.to_string()),
+                action: RevisionAction::Revert,
+                before,
+                after: Some(to_json(&word.snapshot(conn)?)?),
+            }
+            .record(conn)?;
+            Ok(word)
+        })
+        .map_err(|e| {
+            DatabaseError::new(
+                format!(
+                    "Failed to revert word {id} to revision {revision}: {e:?}"
+                ),
+                ErrorKind::Internal,
+            )
+        })
+        .map(|word| {
+            Event::word(NotificationKind::WordChanged, user, &word)
+                .dispatch(context);
+            word
+        })
+    }
+
+    /// Capture the word along with everything its deletion would
+    /// remove or alter.
+    fn snapshot(&self, conn: &mut PgConnection) -> QueryResult<WordSnapshot> {
+        let relations = {
+            use wordrelation::dsl;
+            dsl::wordrelation
+                .filter(
+                    dsl::wordsource.eq(self.id).or(dsl::wordtarget.eq(self.id)),
+                )
+                .load::<WordRelation>(conn)?
+        };
+        let lemma_of = {
+            use words::dsl;
+            dsl::words
+                .filter(dsl::lemma.eq(self.id))
+                .select(dsl::id)
+                .load::<Uuid>(conn)?
+        };
+        Ok(WordSnapshot {
+            word: self.clone(),
+            relations,
+            lemma_of,
+        })
+    }
+
     fn relationship(
         &self,
         db: &Database,
@@ -272,25 +820,121 @@ impl Word {
                 .map_err(|e| {
                     DatabaseError::new(
                         format!("Failed to retrieve word relations: {e:?}"),
-                        "Database reading failed",
+                        ErrorKind::Internal,
                     )
                 })?
                 .into_iter()
                 .flat_map(|word| {
                     use schema::words::dsl;
-                    dsl::words.find(word.wordtarget).first::<Word>(conn)
+                    Word::visible()
+                        .filter(dsl::id.eq(word.wordtarget))
+                        .first::<Word>(conn)
                 })
                 .collect::<Vec<Word>>()),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to connect to the database: {e:?}"),
-                "Database connection error",
+                ErrorKind::Internal,
             )),
         }
     }
 }
 
+/// State of a word stored in its revisions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordSnapshot {
+    pub word: Word,
+    /// Relations in which the word is either the source or the target
+    pub relations: Vec<WordRelation>,
+    /// Words using this word as their lemma
+    pub lemma_of: Vec<Uuid>,
+}
+
+impl WordSnapshot {
+    /// Lemma of the snapshot's word, if it still exists.
+    fn existing_lemma(
+        &self,
+        conn: &mut PgConnection,
+    ) -> QueryResult<Option<Uuid>> {
+        use words::dsl;
+        match self.word.lemma {
+            Some(lemma) => {
+                diesel::select(diesel::dsl::exists(dsl::words.find(lemma)))
+                    .get_result::<bool>(conn)
+                    .map(|exists| exists.then_some(lemma))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Overwrite the current state of an existing word with the
+    /// snapshot.
+    fn overwrite(&self, conn: &mut PgConnection) -> QueryResult<Word> {
+        use words::dsl;
+        let word = Word {
+            lemma: self.existing_lemma(conn)?,
+            ..self.word.clone()
+        };
+        diesel::update(dsl::words.find(word.id))
+            .set(&word)
+            .get_result::<Word>(conn)
+    }
+
+    /// Recreate a deleted word from the snapshot.
+    fn restore(&self, conn: &mut PgConnection) -> QueryResult<Word> {
+        let word = Word {
+            lemma: self.existing_lemma(conn)?,
+            ..self.word.clone()
+        };
+        let word = diesel::insert_into(words::dsl::words)
+            .values(&word)
+            .get_result::<Word>(conn)?;
+        let others = self
+            .relations
+            .iter()
+            .flat_map(|r| [r.wordsource, r.wordtarget])
+            .filter(|other| *other != word.id)
+            .collect::<Vec<Uuid>>();
+        let existing = words::dsl::words
+            .filter(words::dsl::id.eq_any(&others))
+            .select(words::dsl::id)
+            .load::<Uuid>(conn)?;
+        let relations = self
+            .relations
+            .iter()
+            .filter(|r| {
+                [r.wordsource, r.wordtarget]
+                    .iter()
+                    .all(|w| *w == word.id || existing.contains(w))
+            })
+            .map(|r| NewWordRelation {
+                wordsource: r.wordsource,
+                wordtarget: r.wordtarget,
+                relationship: r.relationship.clone(),
+            })
+            .collect::<Vec<_>>();
+        if !relations.is_empty() {
+            diesel::insert_into(wordrelation::dsl::wordrelation)
+                .values(&relations)
+                .execute(conn)?;
+        }
+        diesel::update(
+            words::dsl::words
+                .filter(words::dsl::id.eq_any(&self.lemma_of))
+                .filter(words::dsl::lemma.is_null()),
+        )
+        .set(words::dsl::lemma.eq(word.id))
+        .execute(conn)?;
+        Ok(word)
+    }
+}
+
 #[juniper::graphql_object(Context = Context)]
 impl Word {
+    #[graphql(description = "Unique identifier of the word")]
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+
     #[graphql(description = "Normal form of the word")]
     fn norm(&self) -> String {
         self.norm.clone()
@@ -306,7 +950,10 @@ impl Word {
         use schema::words::dsl;
         match self.lemma {
             Some(lemma) => match &mut context.db.conn() {
-                Ok(conn) => match dsl::words.find(lemma).first::<Word>(conn) {
+                Ok(conn) => match Word::visible()
+                    .filter(dsl::id.eq(lemma))
+                    .first::<Word>(conn)
+                {
                     Ok(word) => Some(word),
                     Err(e) => {
                         info!(
@@ -337,7 +984,7 @@ impl Word {
                     "Failed to retrieve language {} of word {} from database: {e:?}",
                     self.language, self.norm
                 ),
-                "Database Error"
+                ErrorKind::Internal
             ).into())
     }
 
@@ -398,9 +1045,47 @@ impl Word {
         self.relationship(&context.db, WordRelationship::Definition)
             .map_err(Into::into)
     }
+
+    #[graphql(
+        name = "deletedAt",
+        description = "Time at which the word was put in the trash, null if it is not in the trash"
+    )]
+    fn deleted_at(&self) -> Option<String> {
+        self.deleted.map(|deleted| deleted.to_string())
+    }
+
+    #[graphql(description = "Changes made to the word, most recent first")]
+    fn history(&self, context: &Context) -> FieldResult<Vec<WordRevision>> {
+        WordRevision::history(
+            &context.db,
+            self.id,
+            context.user_auth().map(String::as_str),
+        )
+        .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Discussion threads about the word, oldest first",
+        arguments(
+            first(description = "Maximum amount of threads to return"),
+            after(
+                description = "Only return threads started after this comment"
+            )
+        )
+    )]
+    fn comments(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<Vec<Comment>> {
+        Comment::of_word(context, self.id, first, after).map_err(Into::into)
+    }
 }
 
-#[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[derive(
+    Queryable, Insertable, Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+)]
 #[diesel(table_name = wordrelation)]
 pub struct WordRelation {
     id: i32,
@@ -409,6 +1094,24 @@ pub struct WordRelation {
     relationship: WordRelationship,
 }
 
+impl WordRelation {
+    pub fn source(&self) -> Uuid {
+        self.wordsource
+    }
+
+    pub fn target(&self) -> Uuid {
+        self.wordtarget
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = wordrelation)]
+pub struct NewWordRelation {
+    pub wordsource: Uuid,
+    pub wordtarget: Uuid,
+    pub relationship: WordRelationship,
+}
+
 #[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
 #[diesel(table_name = wordlearning)]
 pub struct WordLearning {
@@ -416,4 +1119,90 @@ pub struct WordLearning {
     pub word: Uuid,
     pub userid: String,
     pub status: WordLearningStatus,
+    pub created: chrono::NaiveDateTime,
+    pub learned: Option<chrono::NaiveDateTime>,
+    pub attempts: i32,
+    pub successes: i32,
+}
+
+impl WordLearning {
+    /// Set the learning status of a word for a user.
+    ///
+    /// If the user was not learning the word yet, it is added to
+    /// their learning list. Marking a word as learned records the
+    /// time at which it was learned, which is used for the user's
+    /// learning statistics.
+    pub fn set_status(
+        context: &Context,
+        user: &str,
+        word: Uuid,
+        status: WordLearningStatus,
+    ) -> Result<Word, DatabaseError> {
+        use wordlearning::dsl;
+        let word = Word::find(&context.db, word)?;
+        let learned = match status {
+            WordLearningStatus::Learned => Some(chrono::Utc::now().naive_utc()),
+            WordLearningStatus::Learning => None,
+        };
+        match diesel::insert_into(dsl::wordlearning)
+            .values((
+                dsl::word.eq(word.id),
+                dsl::userid.eq(user),
+                dsl::status.eq(status.clone()),
+                dsl::learned.eq(learned),
+            ))
+            .on_conflict((dsl::word, dsl::userid))
+            .do_update()
+            .set((dsl::status.eq(status), dsl::learned.eq(learned)))
+            .execute(&mut context.db.conn()?)
+        {
+            Ok(_) => Ok(word),
+            Err(e) => Err(DatabaseError::new(
+                format!(
+                    "Failed to update learning status of word {} for user {user}: {e:?}",
+                    word.id
+                ),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+
+    /// Record a review of a word by a user.
+    ///
+    /// Reviews are counted per word and per user, `correct` telling
+    /// whether the user remembered the word correctly.
+    pub fn review(
+        context: &Context,
+        user: &str,
+        word: Uuid,
+        correct: bool,
+    ) -> Result<Word, DatabaseError> {
+        use wordlearning::dsl;
+        let word = Word::find(&context.db, word)?;
+        let success = i32::from(correct);
+        match diesel::insert_into(dsl::wordlearning)
+            .values((
+                dsl::word.eq(word.id),
+                dsl::userid.eq(user),
+                dsl::attempts.eq(1),
+                dsl::successes.eq(success),
+            ))
+            .on_conflict((dsl::word, dsl::userid))
+            .do_update()
+            .set((
+                dsl::attempts.eq(dsl::attempts + 1),
+                dsl::successes.eq(dsl::successes + success),
+            ))
+            .execute(&mut context.db.conn()?)
+        {
+            Ok(_) => Ok(word),
+            Err(e) => Err(DatabaseError::new(
+                format!(
+                    "Failed to record review of word {} for user {user}: {e:?}",
+                    word.id
+                ),
+                ErrorKind::Internal,
+            )),
+        }
+    }
 }
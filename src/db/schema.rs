@@ -1,22 +1,50 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "apitokenscope"))]
+    pub struct Apitokenscope;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "agentlanguagerelation"))]
     pub struct Agentlanguagerelation;
 
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "auditaction"))]
+    pub struct Auditaction;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "dictgenre"))]
     pub struct Dictgenre;
 
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "notificationkind"))]
+    pub struct Notificationkind;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "partofspeech"))]
     pub struct Partofspeech;
 
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "proficiency"))]
+    pub struct Proficiency;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "release"))]
     pub struct Release;
 
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "revisionaction"))]
+    pub struct Revisionaction;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "suggestionkind"))]
+    pub struct Suggestionkind;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "suggestionstatus"))]
+    pub struct Suggestionstatus;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "wordlearningstatus"))]
     pub struct Wordlearningstatus;
@@ -26,6 +54,54 @@ pub mod sql_types {
     pub struct Wordrelationship;
 }
 
+diesel::table! {
+    accountdeletions (userid) {
+        userid -> Varchar,
+        requested -> Timestamp,
+        scheduled -> Timestamp,
+    }
+}
+
+diesel::table! {
+    accountdeletiontransfers (id) {
+        id -> Int4,
+        userid -> Varchar,
+        language -> Uuid,
+        newowner -> Varchar,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Apitokenscope;
+
+    apitokens (id) {
+        id -> Uuid,
+        owner -> Varchar,
+        name -> Varchar,
+        hash -> Bpchar,
+        scope -> Apitokenscope,
+        language -> Nullable<Uuid>,
+        created -> Timestamp,
+        expires -> Nullable<Timestamp>,
+        last_used -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Auditaction;
+
+    auditlog (id) {
+        id -> Int4,
+        admin -> Nullable<Varchar>,
+        action -> Auditaction,
+        target -> Nullable<Varchar>,
+        details -> Nullable<Jsonb>,
+        created -> Timestamp,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::Agentlanguagerelation;
@@ -38,6 +114,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    comments (id) {
+        id -> Int4,
+        language -> Uuid,
+        word -> Nullable<Uuid>,
+        parent -> Nullable<Int4>,
+        author -> Varchar,
+        body -> Text,
+        created -> Timestamp,
+        edited -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     langtranslatesto (id) {
         id -> Int4,
@@ -64,6 +153,113 @@ diesel::table! {
         rights -> Nullable<Text>,
         license -> Nullable<Text>,
         owner -> Varchar,
+        deleted -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Revisionaction;
+
+    languagerevisions (id) {
+        id -> Int4,
+        language -> Uuid,
+        author -> Nullable<Varchar>,
+        action -> Revisionaction,
+        created -> Timestamp,
+        before -> Nullable<Jsonb>,
+        after -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    languageforks (language) {
+        language -> Uuid,
+        source -> Nullable<Uuid>,
+        sourcename -> Varchar,
+        languagerevision -> Nullable<Int4>,
+        wordrevision -> Nullable<Int4>,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    languagetransfers (language) {
+        language -> Uuid,
+        fromowner -> Varchar,
+        newowner -> Varchar,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    liftentries (word) {
+        word -> Uuid,
+        extras -> Jsonb,
+    }
+}
+
+diesel::table! {
+    liftheaders (language) {
+        language -> Uuid,
+        header -> Nullable<Text>,
+        normlang -> Varchar,
+        nativelang -> Nullable<Varchar>,
+        analysislang -> Varchar,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Notificationkind;
+
+    notifications (id) {
+        id -> Int4,
+        recipient -> Varchar,
+        kind -> Notificationkind,
+        actor -> Nullable<Varchar>,
+        language -> Nullable<Uuid>,
+        word -> Nullable<Uuid>,
+        created -> Timestamp,
+        read -> Bool,
+    }
+}
+
+diesel::table! {
+    persistedqueries (hash) {
+        hash -> Bpchar,
+        query -> Text,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+
+    remoteaccountdeletions (userid) {
+        userid -> Varchar,
+        deleted -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Suggestionkind;
+    use super::sql_types::Suggestionstatus;
+
+    suggestions (id) {
+        id -> Int4,
+        language -> Uuid,
+        word -> Nullable<Uuid>,
+        author -> Varchar,
+        kind -> Suggestionkind,
+        content -> Nullable<Jsonb>,
+        comment -> Nullable<Text>,
+        status -> Suggestionstatus,
+        created -> Timestamp,
+        reviewer -> Nullable<Varchar>,
+        reviewed -> Nullable<Timestamp>,
+        response -> Nullable<Text>,
     }
 }
 
@@ -80,6 +276,19 @@ diesel::table! {
         id -> Int4,
         follower -> Varchar,
         following -> Varchar,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Proficiency;
+
+    userlanguages (id) {
+        id -> Int4,
+        userid -> Varchar,
+        language -> Varchar,
+        proficiency -> Proficiency,
     }
 }
 
@@ -87,6 +296,15 @@ diesel::table! {
     users (id) {
         id -> Varchar,
         username -> Varchar,
+        admin -> Bool,
+        display_name -> Nullable<Varchar>,
+        bio -> Nullable<Text>,
+        avatar -> Nullable<Varchar>,
+        interface_language -> Nullable<Varchar>,
+        links -> Array<Text>,
+        public_followers -> Bool,
+        public_languages_followed -> Bool,
+        public_words_learning -> Bool,
     }
 }
 
@@ -99,6 +317,10 @@ diesel::table! {
         word -> Uuid,
         userid -> Varchar,
         status -> Wordlearningstatus,
+        created -> Timestamp,
+        learned -> Nullable<Timestamp>,
+        attempts -> Int4,
+        successes -> Int4,
     }
 }
 
@@ -114,6 +336,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Revisionaction;
+
+    wordrevisions (id) {
+        id -> Int4,
+        word -> Uuid,
+        language -> Uuid,
+        author -> Nullable<Varchar>,
+        action -> Revisionaction,
+        created -> Timestamp,
+        before -> Nullable<Jsonb>,
+        after -> Nullable<Jsonb>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::Partofspeech;
@@ -132,26 +370,64 @@ diesel::table! {
         etymology -> Nullable<Text>,
         lusage -> Nullable<Text>,
         morphology -> Nullable<Text>,
+        deleted -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(accountdeletions -> users (userid));
+diesel::joinable!(accountdeletiontransfers -> accountdeletions (userid));
+diesel::joinable!(accountdeletiontransfers -> languages (language));
+diesel::joinable!(accountdeletiontransfers -> users (newowner));
+diesel::joinable!(apitokens -> languages (language));
+diesel::joinable!(apitokens -> users (owner));
+diesel::joinable!(auditlog -> users (admin));
+diesel::joinable!(comments -> languages (language));
+diesel::joinable!(comments -> users (author));
+diesel::joinable!(comments -> words (word));
 diesel::joinable!(langandagents -> languages (language));
 diesel::joinable!(langandagents -> users (agent));
+diesel::joinable!(languagerevisions -> users (author));
+diesel::joinable!(languagetransfers -> languages (language));
 diesel::joinable!(languages -> users (owner));
+diesel::joinable!(liftentries -> words (word));
+diesel::joinable!(liftheaders -> languages (language));
+diesel::joinable!(notifications -> languages (language));
+diesel::joinable!(notifications -> words (word));
+diesel::joinable!(suggestions -> languages (language));
+diesel::joinable!(suggestions -> words (word));
 diesel::joinable!(userfollowlanguage -> languages (lang));
 diesel::joinable!(userfollowlanguage -> users (userid));
+diesel::joinable!(userlanguages -> users (userid));
 diesel::joinable!(wordlearning -> users (userid));
 diesel::joinable!(wordlearning -> words (word));
+diesel::joinable!(wordrevisions -> languages (language));
+diesel::joinable!(wordrevisions -> users (author));
 diesel::joinable!(words -> languages (language));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    accountdeletions,
+    accountdeletiontransfers,
+    apitokens,
+    auditlog,
+    comments,
     langandagents,
     langtranslatesto,
+    languageforks,
+    languagerevisions,
+    languagetransfers,
     languages,
+    liftentries,
+    liftheaders,
+    notifications,
+    persistedqueries,
+    remoteaccountdeletions,
+    suggestions,
     userfollowlanguage,
     userfollows,
+    userlanguages,
     users,
     wordlearning,
     wordrelation,
+    wordrevisions,
     words,
 );
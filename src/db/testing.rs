@@ -0,0 +1,126 @@
+//! Helpers for tests needing the PostgreSQL database `DATABASE_URL`,
+//! with every migration applied.
+//!
+//! Those tests are ignored by default, run them with
+//! `cargo test -- --ignored`.
+
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+    r2d2::{self, ConnectionManager, CustomizeConnection, Pool},
+};
+use dotenvy::dotenv;
+use std::env;
+use uuid::Uuid;
+
+use super::models::{
+    languages::{DictGenre, Release},
+    users::User,
+    words::{NewWordRelation, PartOfSpeech, WordRelationship},
+};
+use super::schema::{languages, wordrelation, words};
+use super::Database;
+
+/// Connection to the test database, whose changes are rolled back
+/// when it is dropped.
+pub fn connection() -> PgConnection {
+    dotenv().ok();
+    let url = env::var("DATABASE_URL").expect("DATABASE_URL must be set!");
+    let mut conn = PgConnection::establish(&url)
+        .expect("Could not connect to the test database");
+    conn.begin_test_transaction()
+        .expect("Could not begin test transaction");
+    conn
+}
+
+#[derive(Debug)]
+struct TestTransaction;
+
+impl CustomizeConnection<PgConnection, r2d2::Error> for TestTransaction {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), r2d2::Error> {
+        conn.begin_test_transaction()
+            .map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// Database for code taking a [`Database`] rather than a connection,
+/// whose single connection never commits its changes.
+///
+/// Only one connection can be checked out of it at a time.
+pub fn database() -> Database {
+    dotenv().ok();
+    let url = env::var("DATABASE_URL").expect("DATABASE_URL must be set!");
+    let conn = Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(TestTransaction))
+        .build(ConnectionManager::<PgConnection>::new(url))
+        .expect("Could not build test connection pool");
+    Database { conn }
+}
+
+/// Random user ID, short enough to fit in the 31 characters of
+/// `Users.id`.
+pub fn user_id() -> String {
+    format!("test-{}", &Uuid::new_v4().simple().to_string()[..26])
+}
+
+/// Insert a user with a random ID, returning their ID.
+pub fn user(conn: &mut PgConnection) -> String {
+    let id = user_id();
+    User::upsert(conn, &id, &id).expect("Could not insert test user");
+    id
+}
+
+/// Insert a public language owned by `owner`, returning its ID.
+pub fn language(conn: &mut PgConnection, owner: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    diesel::insert_into(languages::table)
+        .values((
+            languages::id.eq(id),
+            languages::name.eq(format!("test-{id}")),
+            languages::release.eq(Release::Public),
+            languages::genre.eq(Vec::<Option<DictGenre>>::new()),
+            languages::owner.eq(owner),
+        ))
+        .execute(conn)
+        .expect("Could not insert test language");
+    id
+}
+
+/// Insert the noun `norm` into `language`, returning its ID.
+pub fn word(
+    conn: &mut PgConnection,
+    language: Uuid,
+    norm: &str,
+    lemma: Option<Uuid>,
+) -> Uuid {
+    let id = Uuid::new_v4();
+    diesel::insert_into(words::table)
+        .values((
+            words::id.eq(id),
+            words::norm.eq(norm),
+            words::lemma.eq(lemma),
+            words::language.eq(language),
+            words::partofspeech.eq(PartOfSpeech::Noun),
+        ))
+        .execute(conn)
+        .expect("Could not insert test word");
+    id
+}
+
+/// Relate the word `source` to the word `target`.
+pub fn relate(
+    conn: &mut PgConnection,
+    source: Uuid,
+    target: Uuid,
+    relationship: WordRelationship,
+) {
+    diesel::insert_into(wordrelation::table)
+        .values(NewWordRelation {
+            wordsource: source,
+            wordtarget: target,
+            relationship,
+        })
+        .execute(conn)
+        .expect("Could not insert test relation");
+}
@@ -0,0 +1,337 @@
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+
+use rocket::http::{Accept, ContentType, Status};
+use rocket::response::stream::ByteStream;
+use rocket::tokio::{sync::mpsc, task};
+use rocket::State;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::db::{
+    models::{
+        exports::{ExportFormat, WordExport},
+        words::Word,
+    },
+    Database, DatabaseError, ErrorKind,
+};
+use crate::graphql::{Context, UserAuth};
+
+/// Size of the chunks of an export sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Amount of chunks waiting to be sent before the export pauses.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Writer sending what is written to it through a channel, failing
+/// once the receiving end is gone.
+struct ChannelWriter(mpsc::Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.blocking_send(buf.to_vec()).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "Export was abandoned")
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn status(kind: ErrorKind) -> Status {
+    match kind {
+        ErrorKind::NotFound => Status::NotFound,
+        ErrorKind::Unauthenticated => Status::Unauthorized,
+        ErrorKind::Forbidden => Status::Forbidden,
+        ErrorKind::InvalidInput => Status::BadRequest,
+        ErrorKind::Conflict => Status::Conflict,
+        ErrorKind::Internal => Status::InternalServerError,
+    }
+}
+
+fn content_type(format: ExportFormat) -> ContentType {
+    match format {
+        ExportFormat::Csv => ContentType::CSV,
+        ExportFormat::Tsv => ContentType::new("text", "tab-separated-values"),
+        ExportFormat::Json => ContentType::JSON,
+        ExportFormat::Jsonl => ContentType::new("application", "jsonl"),
+        ExportFormat::Lift => ContentType::XML,
+        ExportFormat::Tei => ContentType::new("application", "tei+xml"),
+        ExportFormat::Turtle => ContentType::new("text", "turtle"),
+        ExportFormat::JsonLd => ContentType::new("application", "ld+json"),
+    }
+}
+
+/// RDF format preferred by a client according to its `Accept`
+/// header, Turtle by default.
+fn negotiate(
+    accept: Option<&Accept>,
+) -> Result<ExportFormat, (Status, String)> {
+    let Some(accept) = accept else {
+        return Ok(ExportFormat::Turtle);
+    };
+    let mut preferred = None;
+    for media in accept.iter() {
+        let media_type = media.media_type();
+        let format = match format!("{}/{}", media_type.top(), media_type.sub())
+            .to_lowercase()
+            .as_str()
+        {
+            "text/turtle" | "text/*" | "*/*" => ExportFormat::Turtle,
+            "application/ld+json" | "application/json" | "application/*" => {
+                ExportFormat::JsonLd
+            }
+            _ => continue,
+        };
+        let weight = media.weight_or(1.0);
+        if weight > 0.0
+            && preferred.map_or(true, |(_, preferred)| weight > preferred)
+        {
+            preferred = Some((format, weight));
+        }
+    }
+    preferred.map(|(format, _)| format).ok_or_else(|| {
+        (
+            Status::NotAcceptable,
+            "Only text/turtle and application/ld+json are available"
+                .to_string(),
+        )
+    })
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, (Status, String)> {
+    Uuid::from_str(id).map_err(|e| {
+        (
+            Status::BadRequest,
+            format!("Could not parse {id} as a valid UUID: {e:?}"),
+        )
+    })
+}
+
+/// Export of the language `language` as `format` if `context` can see
+/// it.
+fn visible_export(
+    context: &Context,
+    language: Uuid,
+    format: ExportFormat,
+) -> Result<WordExport, (Status, String)> {
+    let user = context.user_auth().cloned();
+    let export = WordExport::new(&context.db, language, format)
+        .map_err(|e| (status(e.kind()), e.public_message().to_string()))?;
+    if export.language().is_visible_to(user.as_deref()) {
+        Ok(export)
+    } else {
+        Err((Status::NotFound, format!("Language {language} not found")))
+    }
+}
+
+/// Stream what `write` writes as `format` from a blocking task while
+/// the client downloads it.
+///
+/// The response has already started when `write` fails, so the data
+/// sent so far is followed by the failure marker of the format rather
+/// than looking complete.
+fn stream<F>(format: ExportFormat, write: F) -> ByteStream![Vec<u8>]
+where
+    F: FnOnce(&mut BufWriter<ChannelWriter>) -> Result<(), DatabaseError>
+        + Send
+        + 'static,
+{
+    let (sender, mut receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    task::spawn_blocking(move || {
+        let mut out =
+            BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter(sender));
+        if write(&mut out).is_err() {
+            if let Err(e) =
+                format.write_failure(&mut out).and_then(|()| out.flush())
+            {
+                warn!("Failed to mark export as incomplete: {e:?}");
+            }
+        }
+    });
+    ByteStream! {
+        while let Some(chunk) = receiver.recv().await {
+            yield chunk;
+        }
+    }
+}
+
+/// Stream all the words of a language as CSV (the default), TSV,
+/// JSON, JSON Lines, LIFT, TEI Lex-0, Turtle or JSON-LD.
+///
+/// Words are read from the database and sent page by page while the
+/// client downloads them.
+#[rocket::get("/languages/<language>/export?<format>")]
+pub async fn export_language(
+    context: &State<Context>,
+    user_auth: UserAuth<'_>,
+    language: &str,
+    format: Option<&str>,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), (Status, String)> {
+    let format = format
+        .map(ExportFormat::from_str)
+        .transpose()
+        .map_err(|e| (Status::BadRequest, e))?
+        .unwrap_or(ExportFormat::Csv);
+    let language = parse_uuid(language)?;
+    let context = context.attach_auth(user_auth.credentials());
+    context.resolve_identity().await;
+    let export = visible_export(&context, language, format)?;
+    let db = context.db.clone();
+    Ok((
+        content_type(format),
+        stream(format, move |out| match export.write(&db, out) {
+            Ok(count) => {
+                info!("Exported {count} words of {language}");
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Failed to export words of {language}: {e:?}");
+                Err(e)
+            }
+        }),
+    ))
+}
+
+/// Serve the OntoLex-Lemon lexicon of a language at its IRI, as
+/// Turtle or JSON-LD depending on the `Accept` header.
+#[rocket::get("/languages/<language>")]
+pub async fn language_lexicon(
+    context: &State<Context>,
+    user_auth: UserAuth<'_>,
+    accept: Option<&Accept>,
+    language: &str,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), (Status, String)> {
+    let format = negotiate(accept)?;
+    let language = parse_uuid(language)?;
+    let context = context.attach_auth(user_auth.credentials());
+    context.resolve_identity().await;
+    let export = visible_export(&context, language, format)?;
+    let db = context.db.clone();
+    Ok((
+        content_type(format),
+        stream(format, move |out| {
+            export.write(&db, out).map(|_| ()).map_err(|e| {
+                warn!("Failed to describe lexicon of {language}: {e:?}");
+                e
+            })
+        }),
+    ))
+}
+
+/// Serve the OntoLex-Lemon lexical entry of a word at its IRI, as
+/// Turtle or JSON-LD depending on the `Accept` header.
+#[rocket::get("/words/<word>")]
+pub async fn word_entry(
+    context: &State<Context>,
+    user_auth: UserAuth<'_>,
+    accept: Option<&Accept>,
+    word: &str,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), (Status, String)> {
+    let format = negotiate(accept)?;
+    let word = parse_uuid(word)?;
+    let context = context.attach_auth(user_auth.credentials());
+    context.resolve_identity().await;
+    let language = Word::find(&context.db, word)
+        .map_err(|e| (status(e.kind()), e.public_message().to_string()))?
+        .language_id();
+    let export = visible_export(&context, language, format).map_err(
+        |(status, message)| {
+            if status == Status::NotFound {
+                (status, format!("Word {word} not found"))
+            } else {
+                (status, message)
+            }
+        },
+    )?;
+    let db = context.db.clone();
+    Ok((
+        content_type(format),
+        stream(format, move |out| {
+            export.write_word(&db, out, word).map_err(|e| {
+                warn!("Failed to describe word {word}: {e:?}");
+                e
+            })
+        }),
+    ))
+}
+
+/// Export the words of a language to the standard output, for
+/// `ordabok export <language> [csv|tsv|json|jsonl|lift|tei|ttl|jsonld]`.
+///
+/// Private languages are exported too, the command being meant for
+/// whoever operates the database.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (language, format) = match args {
+        [language] => (language, ExportFormat::Csv),
+        [language, format] => (language, ExportFormat::from_str(format)?),
+        _ => return Err(
+            "Usage: ordabok export <language> [csv|tsv|json|jsonl|lift|tei|ttl|jsonld]"
+                .into(),
+        ),
+    };
+    let language = Uuid::from_str(language)?;
+    let db = Database::default();
+    let export =
+        WordExport::new(&db, language, format).map_err(|e| format!("{e:?}"))?;
+    let stdout = io::stdout();
+    let mut out = BufWriter::with_capacity(CHUNK_SIZE, stdout.lock());
+    let count = export.write(&db, &mut out).map_err(|e| format!("{e:?}"))?;
+    info!("Exported {count} words of {}", export.language());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate_header(accept: &str) -> Result<ExportFormat, Status> {
+        negotiate(Some(&Accept::from_str(accept).unwrap()))
+            .map_err(|(status, _)| status)
+    }
+
+    #[test]
+    fn turtle_is_exported_by_default() {
+        assert_eq!(Ok(ExportFormat::Turtle), negotiate(None));
+        assert_eq!(Ok(ExportFormat::Turtle), negotiate_header("*/*"));
+        assert_eq!(
+            Ok(ExportFormat::Turtle),
+            negotiate_header(
+                "text/html,application/xhtml+xml,application/xml;q=0.9,\
+                 */*;q=0.8"
+            )
+        );
+    }
+
+    #[test]
+    fn requested_formats_are_exported() {
+        assert_eq!(Ok(ExportFormat::Turtle), negotiate_header("text/turtle"));
+        assert_eq!(
+            Ok(ExportFormat::JsonLd),
+            negotiate_header("application/ld+json")
+        );
+        assert_eq!(
+            Ok(ExportFormat::JsonLd),
+            negotiate_header("application/json")
+        );
+        assert_eq!(
+            Ok(ExportFormat::JsonLd),
+            negotiate_header("text/turtle;q=0.5, application/ld+json")
+        );
+        assert_eq!(
+            Ok(ExportFormat::Turtle),
+            negotiate_header("text/turtle, application/ld+json;q=0.5")
+        );
+    }
+
+    #[test]
+    fn other_formats_are_not_acceptable() {
+        assert_eq!(Err(Status::NotAcceptable), negotiate_header("text/html"));
+        assert_eq!(
+            Err(Status::NotAcceptable),
+            negotiate_header("text/html, text/turtle;q=0")
+        );
+    }
+}
@@ -1,76 +1,274 @@
-use crate::appwrite::APVariables;
-use crate::db::Database;
+use std::{panic, sync::Arc};
 
+use rocket::tokio::{runtime::Handle, sync::OnceCell, task};
 use tracing::info;
+use uuid::Uuid;
 
-macro_rules! from_env {
-    ($varname:expr) => {
-        std::env::var($varname)
-            .expect(format!("{} must be set!", $varname).as_str())
-    };
-}
+use crate::appwrite::Appwrite;
+use crate::auth::Auth;
+use crate::db::models::api_tokens::{ApiToken, API_TOKEN_PREFIX};
+use crate::db::{Database, DatabaseError, ErrorKind};
 
-#[derive(Debug, Clone)]
-pub struct OtherEnvVar {
-    pub admin_key: String,
-}
+use super::subscription::Live;
 
-impl Default for OtherEnvVar {
-    fn default() -> Self {
-        Self {
-            admin_key: from_env!("ADMIN_KEY"),
-        }
-    }
+/// User performing a request.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub user: String,
+    /// API token the user authentified with, if any.
+    pub token: Option<ApiToken>,
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct Context {
     pub db: Database,
-    pub appwrite: APVariables,
-    pub user_auth: Option<String>,
-    pub other_vars: OtherEnvVar,
+    pub auth: Auth,
+    pub appwrite: Appwrite,
+    /// Value of the `Authorization` header of the request.
+    credentials: Option<String>,
+    /// User authentified by `credentials`, checked on first use.
+    identity: Arc<OnceCell<Option<Identity>>>,
+    pub live: Live,
 }
 
 impl Context {
     /// Check if a request is performed by an autentificated user.
     ///
-    /// The HTTP header `Authorization` must be a single string in the
-    /// form `userId;userSessionId` with `userId` and `userSessionId`
-    /// being variables given by Appwrite to users that are logged in.
+    /// The HTTP header `Authorization` is either an API token sent as
+    /// `Bearer <token>`, or credentials checked by the provider
+    /// selected with `AUTH_PROVIDER`, see [`crate::auth`].
     ///
-    /// The function returns either the user's ID if the user is
+    /// The function returns either the user's identity if the user is
     /// authentified or `None`.
-    pub async fn user_auth<'r>(
-        &self,
-        auth_token: Option<&'r str>,
-    ) -> Option<String> {
-        if let Some(token) = auth_token {
-            let key = token.split(';').collect::<Vec<_>>();
-            if key.len() == 2 {
-                let user_id = key[0];
-                let session_id = key[1];
-                match self.appwrite.check_session(session_id, user_id).await {
-                    Ok(true) => Some(key[0].to_string()),
-                    Ok(false) => None,
-                    Err(e) => {
-                        info!("Error checking user session: {:?}", e);
-                        None
-                    }
+    async fn authenticate(&self, credentials: &str) -> Option<Identity> {
+        match credentials
+            .strip_prefix("Bearer ")
+            .map(str::trim)
+            .filter(|token| token.starts_with(API_TOKEN_PREFIX))
+        {
+            Some(token) => match ApiToken::authenticate(&self.db, token) {
+                Ok(token) => token.map(|token| Identity {
+                    user: token.owner().to_string(),
+                    token: Some(token),
+                }),
+                Err(e) => {
+                    info!("Error checking API token: {e:?}");
+                    None
                 }
-            } else {
-                info!("Invalid session key: {}", token);
-                None
-            }
-        } else {
-            None
+            },
+            None => self
+                .auth
+                .authenticate(credentials)
+                .await
+                .map(|user| Identity { user, token: None }),
         }
     }
 
-    pub async fn attach_auth<'r>(&self, auth_token: Option<&'r str>) -> Self {
+    /// Context of a request sent with the `Authorization` header
+    /// `auth_token`.
+    ///
+    /// The credentials are not checked until something needs to know
+    /// who the user is, so requests which never do skip the
+    /// authentication provider altogether.
+    pub fn attach_auth(&self, auth_token: Option<&str>) -> Self {
         let mut res = self.clone();
-        res.user_auth = self.user_auth(auth_token).await;
+        res.credentials = auth_token.map(ToString::to_string);
+        res.identity = Arc::default();
         res
     }
+
+    /// Identity of the user performing the request, if they are
+    /// authentified, checking their credentials if that was not done
+    /// yet.
+    pub async fn resolve_identity(&self) -> Option<&Identity> {
+        self.identity
+            .get_or_init(|| async {
+                match self.credentials.as_deref() {
+                    Some(credentials) => self.authenticate(credentials).await,
+                    None => None,
+                }
+            })
+            .await
+            .as_ref()
+    }
+
+    /// Identity of the user performing the request, if they are
+    /// authentified.
+    ///
+    /// The first call waits for the credentials to be checked, which
+    /// is only possible outside of the async runtime: GraphQL
+    /// resolvers run through [`Context::run`], async code must call
+    /// [`Context::resolve_identity`] first.
+    pub fn identity(&self) -> Option<&Identity> {
+        if let Some(identity) = self.identity.get() {
+            return identity.as_ref();
+        }
+        self.credentials.as_ref()?;
+        Handle::current().block_on(self.resolve_identity())
+    }
+
+    /// Run `f` with this context on a thread of the blocking pool.
+    ///
+    /// Resolvers query the database synchronously and may wait for
+    /// the credentials of the request to be checked, neither of which
+    /// may happen on the threads of the async runtime.
+    ///
+    /// # Panics
+    ///
+    /// If `f` panics.
+    pub async fn run<T, F>(self, f: F) -> T
+    where
+        F: FnOnce(&Context) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        task::spawn_blocking(move || f(&self))
+            .await
+            .unwrap_or_else(|e| panic::resume_unwind(e.into_panic()))
+    }
+
+    /// ID of the user performing the request, if they are
+    /// authentified.
+    pub fn user_auth(&self) -> Option<&String> {
+        self.identity().map(|identity| &identity.user)
+    }
+
+    /// ID of the user performing the request, if they are allowed to
+    /// modify data, which read-only API tokens and API tokens
+    /// restricted to a single language are not.
+    pub fn writer(&self) -> Option<&String> {
+        self.identity()
+            .filter(|identity| {
+                identity
+                    .token
+                    .as_ref()
+                    .map_or(true, ApiToken::can_write_anything)
+            })
+            .map(|identity| &identity.user)
+    }
+
+    /// ID of the user performing the request, if they are allowed to
+    /// modify data, possibly only that of a single language.
+    ///
+    /// Only meant for mutations of a language and its words, which
+    /// must check the language through [`Context::may_write`].
+    pub fn language_writer(&self) -> Option<&String> {
+        self.identity()
+            .filter(|identity| {
+                identity.token.as_ref().map_or(true, ApiToken::can_write)
+            })
+            .map(|identity| &identity.user)
+    }
+
+    /// ID of the user performing the request, if they did not
+    /// authentify with an API token.
+    pub fn session_user(&self) -> Option<&String> {
+        self.identity()
+            .filter(|identity| identity.token.is_none())
+            .map(|identity| &identity.user)
+    }
+
+    /// ID of the user performing the request, provided they are an
+    /// administrator logged in with a session, API tokens cannot be
+    /// used for admin actions.
+    pub fn admin(&self) -> Result<&String, DatabaseError> {
+        let Some(user) = self.session_user() else {
+            return Err(DatabaseError::new(
+                "User not authentificated with a session, cannot perform admin actions",
+                ErrorKind::Unauthenticated,
+            ));
+        };
+        match self.db.user(user)? {
+            Some(account) if account.admin => Ok(user),
+            _ => Err(DatabaseError::new(
+                format!("User {user} is not an administrator"),
+                ErrorKind::Forbidden,
+            )),
+        }
+    }
+
+    /// Whether the credentials of the request allow modifying the
+    /// language `language`, provided the user can.
+    pub fn may_write(&self, language: Uuid) -> bool {
+        self.identity().map_or(false, |identity| {
+            identity
+                .token
+                .as_ref()
+                .map_or(true, |token| token.may_write(language))
+        })
+    }
 }
 
 impl juniper::Context for Context {}
+
+#[cfg(test)]
+impl Context {
+    /// Context of a request of `user`, logged in with a session.
+    pub fn of_user(db: Database, user: &str) -> Self {
+        Self {
+            db,
+            auth: Auth::new(crate::auth::DevProvider),
+            appwrite: Appwrite::default(),
+            credentials: Some(user.to_string()),
+            identity: Arc::new(OnceCell::new_with(Some(Some(Identity {
+                user: user.to_string(),
+                token: None,
+            })))),
+            live: Live::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::auth::{AuthError, AuthProvider};
+    use crate::db::testing;
+
+    /// Provider accepting any credentials as the ID of the user,
+    /// counting how many times it is asked.
+    #[derive(Debug)]
+    struct Counting(Arc<AtomicUsize>);
+
+    #[rocket::async_trait]
+    impl AuthProvider for Counting {
+        async fn authenticate(
+            &self,
+            credentials: &str,
+        ) -> Result<Option<String>, AuthError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(credentials.to_string()))
+        }
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn credentials_are_checked_once_when_needed() {
+        let checks = Arc::new(AtomicUsize::new(0));
+        let context = Context {
+            db: testing::database(),
+            auth: Auth::new(Counting(Arc::clone(&checks))),
+            appwrite: Appwrite::default(),
+            credentials: None,
+            identity: Arc::default(),
+            live: Live::default(),
+        };
+        let anonymous = context.attach_auth(None);
+        let user = context.attach_auth(Some("test"));
+        assert_eq!(0, checks.load(Ordering::SeqCst));
+        let users = user
+            .clone()
+            .run(|context| {
+                (context.user_auth().cloned(), context.user_auth().cloned())
+            })
+            .await;
+        assert_eq!((Some("test".into()), Some("test".into())), users);
+        assert_eq!(1, checks.load(Ordering::SeqCst));
+        assert_eq!(
+            None,
+            anonymous.run(|context| context.user_auth().cloned()).await
+        );
+        assert_eq!(1, checks.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,273 @@
+use std::{env, sync::Arc};
+
+use futures::executor;
+use juniper::{http::GraphQLRequest, InputValue};
+use juniper_rocket::GraphQLResponse;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+use rocket::State;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use crate::db::models::persisted_queries::{sha256, PersistedQuery};
+
+use super::{Context, QueryLimits, ReadOnlySchema, RequestId, UserAuth};
+
+/// Amount of seconds anonymous `GET` responses may be cached for if
+/// `GRAPHQL_CACHE_MAX_AGE` is not set.
+const DEFAULT_CACHE_MAX_AGE: u32 = 60;
+
+fn cache_max_age() -> u32 {
+    match env::var("GRAPHQL_CACHE_MAX_AGE") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid GRAPHQL_CACHE_MAX_AGE {val}, defaulting to {DEFAULT_CACHE_MAX_AGE}: {e:?}"
+            );
+            DEFAULT_CACHE_MAX_AGE
+        }),
+        Err(_) => DEFAULT_CACHE_MAX_AGE,
+    }
+}
+
+/// Parameters of a GraphQL request sent through `GET`.
+///
+/// `query` may be omitted if `extensions` refers to a persisted query.
+#[derive(rocket::FromForm, Debug)]
+pub struct GetRequest {
+    query: Option<String>,
+    variables: Option<String>,
+    #[field(name = "operationName")]
+    operation_name: Option<String>,
+    extensions: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Extensions {
+    #[serde(rename = "persistedQuery")]
+    persisted_query: Option<PersistedQueryExtension>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PersistedQueryExtension {
+    version: i32,
+    #[serde(rename = "sha256Hash")]
+    sha256_hash: String,
+}
+
+/// Value of the `If-None-Match` header, if any.
+#[derive(Copy, Clone, Debug)]
+pub struct IfNoneMatch<'r>(Option<&'r str>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch<'r> {
+    type Error = std::convert::Infallible;
+    async fn from_request(
+        request: &'r Request<'_>,
+    ) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfNoneMatch(
+            request.headers().get_one("If-None-Match"),
+        ))
+    }
+}
+
+/// GraphQL response along with its caching headers.
+pub struct CachedResponse {
+    response: GraphQLResponse,
+    cache_control: String,
+    etag: Option<String>,
+    not_modified: bool,
+}
+
+impl CachedResponse {
+    fn uncached(status: Status, body: String) -> Self {
+        Self {
+            response: GraphQLResponse(status, body),
+            cache_control: "no-store".into(),
+            etag: None,
+            not_modified: false,
+        }
+    }
+
+    /// Error formatted like GraphQL errors, with a machine-readable
+    /// code.
+    fn error(status: Status, message: &str, code: &str) -> Self {
+        let body = json!({
+            "errors": [{
+                "message": message,
+                "extensions": { "code": code }
+            }]
+        });
+        Self::uncached(status, body.to_string())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for CachedResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = if self.not_modified {
+            Response::build().status(Status::NotModified).finalize()
+        } else {
+            self.response.respond_to(request)?
+        };
+        response.set_raw_header("Cache-Control", self.cache_control);
+        // Responses differ between anonymous and authentified requests
+        response.set_raw_header("Vary", "Authorization");
+        if let Some(etag) = self.etag {
+            response.set_raw_header("ETag", etag);
+        }
+        Ok(response)
+    }
+}
+
+/// Find the query document of a request, along with the persisted
+/// query hash it should be registered under if it is sent with one.
+fn resolve_query(
+    context: &Context,
+    request: &GetRequest,
+) -> Result<(String, Option<String>), CachedResponse> {
+    let persisted = match &request.extensions {
+        Some(extensions) => {
+            serde_json::from_str::<Extensions>(extensions)
+                .map_err(|e| {
+                    CachedResponse::error(
+                        Status::BadRequest,
+                        &format!("Invalid extensions: {e}"),
+                        "BAD_REQUEST",
+                    )
+                })?
+                .persisted_query
+        }
+        None => None,
+    };
+    match (&request.query, persisted) {
+        (_, Some(persisted)) if persisted.version != 1 => {
+            Err(CachedResponse::error(
+                Status::BadRequest,
+                "Unsupported persisted query version",
+                "PERSISTED_QUERY_NOT_SUPPORTED",
+            ))
+        }
+        (Some(query), Some(persisted)) => {
+            Ok((query.clone(), Some(persisted.sha256_hash)))
+        }
+        (Some(query), None) => Ok((query.clone(), None)),
+        (None, Some(persisted)) => {
+            match PersistedQuery::find(&context.db, &persisted.sha256_hash) {
+                Ok(Some(query)) => Ok((query, None)),
+                Ok(None) => Err(CachedResponse::error(
+                    Status::Ok,
+                    "PersistedQueryNotFound",
+                    "PERSISTED_QUERY_NOT_FOUND",
+                )),
+                Err(e) => {
+                    warn!("{e:?}");
+                    Err(CachedResponse::error(
+                        Status::InternalServerError,
+                        "Failed to retrieve persisted query",
+                        "INTERNAL_SERVER_ERROR",
+                    ))
+                }
+            }
+        }
+        (None, None) => Err(CachedResponse::error(
+            Status::BadRequest,
+            "Missing query",
+            "BAD_REQUEST",
+        )),
+    }
+}
+
+/// Execute a GraphQL query sent through `GET`.
+///
+/// Mutations are refused since `GET` requests must not have side
+/// effects, and queries exceeding the configured [`QueryLimits`] are
+/// rejected. Successful responses to anonymous requests can be cached
+/// publicly for `GRAPHQL_CACHE_MAX_AGE` seconds.
+#[rocket::get("/graphql?<request..>")]
+pub async fn get_graphql_handler(
+    context: &State<Context>,
+    user_auth: UserAuth<'_>,
+    if_none_match: IfNoneMatch<'_>,
+    request_id: RequestId,
+    limits: &State<QueryLimits>,
+    request: GetRequest,
+    schema: &State<Arc<ReadOnlySchema>>,
+) -> CachedResponse {
+    let (query, persist) = match resolve_query(context, &request) {
+        Ok(query) => query,
+        Err(response) => return response,
+    };
+    let variables = match request
+        .variables
+        .as_deref()
+        .map(serde_json::from_str::<InputValue>)
+        .transpose()
+    {
+        Ok(variables) => variables,
+        Err(e) => {
+            return CachedResponse::error(
+                Status::BadRequest,
+                &format!("Invalid variables: {e}"),
+                "BAD_REQUEST",
+            )
+        }
+    };
+    if let Err(e) = limits.check(
+        &query,
+        request.operation_name.as_deref(),
+        variables.as_ref(),
+    ) {
+        return CachedResponse::uncached(Status::BadRequest, e.to_json());
+    }
+    if let Some(hash) = persist {
+        if let Err(e) = PersistedQuery::register(&context.db, &hash, &query) {
+            return CachedResponse::error(
+                Status::BadRequest,
+                e.public_message(),
+                e.kind().code(),
+            );
+        }
+    }
+    let anonymous = user_auth.0.is_none();
+    let schema = Arc::clone(schema);
+    let request = GraphQLRequest::new(query, request.operation_name, variables);
+    let (ok, body) = context
+        .attach_auth(user_auth.0)
+        .run(move |context| {
+            let response = executor::block_on(
+                request_id.scope(request.execute(&schema, context)),
+            );
+            (response.is_ok(), serde_json::to_string(&response))
+        })
+        .await;
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to serialize GraphQL response: {e:?}");
+            return CachedResponse::error(
+                Status::InternalServerError,
+                "Failed to serialize response",
+                "INTERNAL_SERVER_ERROR",
+            );
+        }
+    };
+    if !ok {
+        return CachedResponse::uncached(Status::BadRequest, body);
+    }
+    if !anonymous {
+        return CachedResponse {
+            response: GraphQLResponse(Status::Ok, body),
+            cache_control: "private, no-cache".into(),
+            etag: None,
+            not_modified: false,
+        };
+    }
+    let etag = format!("\"{}\"", sha256(&body));
+    CachedResponse {
+        not_modified: if_none_match.0 == Some(etag.as_str()),
+        response: GraphQLResponse(Status::Ok, body),
+        cache_control: format!("public, max-age={}", cache_max_age()),
+        etag: Some(etag),
+    }
+}
@@ -0,0 +1,994 @@
+//! Limits on the shape of GraphQL queries, checked before executing
+//! them.
+//!
+//! Resolvers query PostgreSQL once per row, so deeply nested queries
+//! or queries with many aliases can trigger a huge amount of database
+//! requests. Queries are parsed into a lightweight representation of
+//! their selection sets to compute their depth, their amount of
+//! aliases and an estimation of their cost.
+
+use std::{cell::RefCell, collections::HashMap, env};
+
+use juniper::{http::GraphQLBatchRequest, InputValue};
+use serde_json::json;
+use tracing::warn;
+
+/// Maximum depth of queries if `GRAPHQL_MAX_DEPTH` is not set.
+const DEFAULT_MAX_DEPTH: usize = 10;
+/// Maximum complexity of queries if `GRAPHQL_MAX_COMPLEXITY` is not
+/// set.
+const DEFAULT_MAX_COMPLEXITY: u64 = 5000;
+/// Maximum amount of aliases in queries if `GRAPHQL_MAX_ALIASES` is
+/// not set.
+const DEFAULT_MAX_ALIASES: usize = 15;
+/// Maximum amount of operations in batched requests if
+/// `GRAPHQL_MAX_BATCH` is not set.
+const DEFAULT_MAX_BATCH: usize = 10;
+
+/// Maximum nesting of selection sets, including inline fragments,
+/// accepted by the parser regardless of the depth limit.
+const MAX_NESTING: usize = 64;
+
+/// Amount of items assumed to be returned by paginated fields when
+/// `first` is not set, same as the actual default page size.
+const DEFAULT_PAGE_ITEMS: u64 = 20;
+/// Amount of items assumed to be returned by unpaginated list fields.
+const DEFAULT_LIST_ITEMS: u64 = 10;
+
+/// Fields accepting a `first` argument to limit how many items they
+/// return.
+const PAGINATED_FIELDS: &[&str] = &[
+    "comments",
+    "feed",
+    "notifications",
+    "recentChanges",
+    "replies",
+];
+
+/// Fields returning unpaginated lists.
+const LIST_FIELDS: &[&str] = &[
+    "allLanguages",
+    "allUsers",
+    "authors",
+    "findLanguage",
+    "findUser",
+    "findWord",
+    "followers",
+    "history",
+    "languagesFollowed",
+    "maintainers",
+    "pendingSuggestions",
+    "publishers",
+    "related",
+    "targetLanguage",
+    "usersFollowed",
+    "wordHistory",
+    "words",
+    "wordsLearning",
+];
+
+fn from_env<T: std::str::FromStr + std::fmt::Display + Copy>(
+    name: &str,
+    default: T,
+) -> T
+where
+    T::Err: std::fmt::Debug,
+{
+    match env::var(name) {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!("Invalid {name} {val}, defaulting to {default}: {e:?}");
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// Query that exceeds one of the limits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitError {
+    message: String,
+    code: &'static str,
+    limit: Option<u64>,
+    actual: Option<u64>,
+}
+
+impl LimitError {
+    fn new(
+        message: String,
+        code: &'static str,
+        limit: u64,
+        actual: u64,
+    ) -> Self {
+        Self {
+            message,
+            code,
+            limit: Some(limit),
+            actual: Some(actual),
+        }
+    }
+
+    fn invalid(message: String) -> Self {
+        Self {
+            message,
+            code: "GRAPHQL_PARSE_FAILED",
+            limit: None,
+            actual: None,
+        }
+    }
+
+    /// Body of the response rejecting the query, formatted like
+    /// GraphQL errors.
+    pub fn to_json(&self) -> String {
+        json!({
+            "errors": [{
+                "message": self.message,
+                "extensions": {
+                    "code": self.code,
+                    "limit": self.limit,
+                    "actual": self.actual,
+                }
+            }]
+        })
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_complexity: u64,
+    pub max_aliases: usize,
+    pub max_batch: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: from_env("GRAPHQL_MAX_DEPTH", DEFAULT_MAX_DEPTH),
+            max_complexity: from_env(
+                "GRAPHQL_MAX_COMPLEXITY",
+                DEFAULT_MAX_COMPLEXITY,
+            ),
+            max_aliases: from_env("GRAPHQL_MAX_ALIASES", DEFAULT_MAX_ALIASES),
+            max_batch: from_env("GRAPHQL_MAX_BATCH", DEFAULT_MAX_BATCH),
+        }
+    }
+}
+
+impl QueryLimits {
+    /// Check a single query against the limits.
+    pub fn check(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: Option<&InputValue>,
+    ) -> Result<(), LimitError> {
+        let document = Parser::new(query, self.max_depth)?.document()?;
+        let analyzer = Analyzer::new(self, &document.fragments, variables);
+        for operation in document.operations.iter().filter(|operation| {
+            operation_name
+                .map_or(true, |name| operation.name.as_deref() == Some(name))
+        }) {
+            let stats =
+                analyzer.analyze(&operation.selections, 1, &mut Vec::new())?;
+            self.enforce(stats)?;
+        }
+        Ok(())
+    }
+
+    /// Reject `stats` if they exceed one of the limits.
+    fn enforce(&self, stats: Stats) -> Result<(), LimitError> {
+        if stats.depth > self.max_depth {
+            return Err(LimitError::new(
+                format!(
+                    "Query is nested {} levels deep, at most {} are allowed",
+                    stats.depth, self.max_depth
+                ),
+                "QUERY_TOO_DEEP",
+                self.max_depth as u64,
+                stats.depth as u64,
+            ));
+        }
+        if stats.aliases > self.max_aliases {
+            return Err(LimitError::new(
+                format!(
+                    "Query uses {} aliases, at most {} are allowed",
+                    stats.aliases, self.max_aliases
+                ),
+                "TOO_MANY_ALIASES",
+                self.max_aliases as u64,
+                stats.aliases as u64,
+            ));
+        }
+        if stats.complexity > self.max_complexity {
+            return Err(LimitError::new(
+                format!(
+                    "Query has an estimated complexity of {}, at most {} is allowed",
+                    stats.complexity, self.max_complexity
+                ),
+                "QUERY_TOO_COMPLEX",
+                self.max_complexity,
+                stats.complexity,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check every query of a possibly batched request against the
+    /// limits.
+    pub fn check_batch(
+        &self,
+        request: &GraphQLBatchRequest,
+    ) -> Result<(), LimitError> {
+        match request {
+            GraphQLBatchRequest::Single(request) => self.check(
+                &request.query,
+                request.operation_name.as_deref(),
+                request.variables.as_ref(),
+            ),
+            GraphQLBatchRequest::Batch(requests)
+                if requests.len() > self.max_batch =>
+            {
+                Err(LimitError::new(
+                    format!(
+                        "Request batches {} operations, at most {} are allowed",
+                        requests.len(),
+                        self.max_batch
+                    ),
+                    "BATCH_TOO_LARGE",
+                    self.max_batch as u64,
+                    requests.len() as u64,
+                ))
+            }
+            GraphQLBatchRequest::Batch(requests) => {
+                requests.iter().try_for_each(|request| {
+                    self.check(
+                        &request.query,
+                        request.operation_name.as_deref(),
+                        request.variables.as_ref(),
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// Amount of items a list field is asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum First {
+    Value(i64),
+    Variable(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selection {
+    Field {
+        name: String,
+        aliased: bool,
+        first: Option<First>,
+        selections: Vec<Selection>,
+    },
+    FragmentSpread(String),
+    InlineFragment(Vec<Selection>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Operation {
+    name: Option<String>,
+    selections: Vec<Selection>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Document {
+    operations: Vec<Operation>,
+    fragments: HashMap<String, Vec<Selection>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Punctuator(char),
+    Spread,
+    Name(String),
+    Int(i64),
+    /// Floats and strings, whose value never matters here.
+    Value,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => {}
+            '#' => {
+                while chars.next_if(|&c| c != '\n' && c != '\r').is_some() {}
+            }
+            '{' | '}' | '(' | ')' | '[' | ']' | ':' | '=' | '@' | '$' | '!'
+            | '|' | '&' => tokens.push(Token::Punctuator(c)),
+            '.' => {
+                if chars.next() != Some('.') || chars.next() != Some('.') {
+                    return Err("Unexpected character '.'".into());
+                }
+                tokens.push(Token::Spread);
+            }
+            '"' => {
+                if chars.next_if_eq(&'"').is_some() {
+                    if chars.next_if_eq(&'"').is_some() {
+                        // Block string, ends with unescaped """
+                        let mut quotes = 0;
+                        loop {
+                            match chars.next() {
+                                Some('"') => quotes += 1,
+                                Some('\\') => {
+                                    quotes = 0;
+                                    chars.next();
+                                }
+                                Some(_) => quotes = 0,
+                                None => {
+                                    return Err(
+                                        "Unterminated block string".into()
+                                    )
+                                }
+                            }
+                            if quotes == 3 {
+                                break;
+                            }
+                        }
+                    }
+                    // Otherwise an empty string
+                } else {
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => {
+                                chars.next();
+                            }
+                            Some('\n' | '\r') | None => {
+                                return Err("Unterminated string".into())
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+                tokens.push(Token::Value);
+            }
+            '-' | '0'..='9' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-')
+                }) {
+                    number.push(c);
+                }
+                tokens.push(match number.parse() {
+                    Ok(value) => Token::Int(value),
+                    Err(_) => Token::Value,
+                });
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let mut name = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|&c| c == '_' || c.is_ascii_alphanumeric())
+                {
+                    name.push(c);
+                }
+                tokens.push(Token::Name(name));
+            }
+            c => return Err(format!("Unexpected character '{c}'")),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parser of GraphQL documents, rejecting them as soon as their fields
+/// are nested deeper than `max_depth` so that recursion stays bounded.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    max_depth: usize,
+    /// Depth of the fields being parsed.
+    depth: usize,
+    /// Amount of selection sets being parsed.
+    nesting: usize,
+}
+
+impl Parser {
+    fn new(source: &str, max_depth: usize) -> Result<Self, LimitError> {
+        Ok(Self {
+            tokens: tokenize(source).map_err(LimitError::invalid)?,
+            position: 0,
+            max_depth,
+            depth: 1,
+            nesting: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, LimitError> {
+        let token =
+            self.tokens.get(self.position).cloned().ok_or_else(|| {
+                LimitError::invalid("Unexpected end of document".into())
+            })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn is_next(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punctuator(c))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), LimitError> {
+        match self.next()? {
+            Token::Punctuator(found) if found == c => Ok(()),
+            token => Err(LimitError::invalid(format!(
+                "Expected '{c}', found {token:?}"
+            ))),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, LimitError> {
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            token => Err(LimitError::invalid(format!(
+                "Expected a name, found {token:?}"
+            ))),
+        }
+    }
+
+    fn document(mut self) -> Result<Document, LimitError> {
+        let mut document = Document::default();
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Punctuator('{') => document.operations.push(Operation {
+                    name: None,
+                    selections: self.selection_set()?,
+                }),
+                Token::Name(keyword)
+                    if matches!(
+                        keyword.as_str(),
+                        "query" | "mutation" | "subscription"
+                    ) =>
+                {
+                    self.next()?;
+                    let name = match self.peek() {
+                        Some(Token::Name(_)) => Some(self.name()?),
+                        _ => None,
+                    };
+                    if self.is_next('(') {
+                        self.skip_balanced('(', ')')?;
+                    }
+                    self.directives()?;
+                    document.operations.push(Operation {
+                        name,
+                        selections: self.selection_set()?,
+                    });
+                }
+                Token::Name(keyword) if keyword == "fragment" => {
+                    self.next()?;
+                    let name = self.name()?;
+                    self.name()?; // on
+                    self.name()?; // type condition
+                    self.directives()?;
+                    let selections = self.selection_set()?;
+                    document.fragments.insert(name, selections);
+                }
+                token => {
+                    return Err(LimitError::invalid(format!(
+                        "Unexpected {token:?}"
+                    )))
+                }
+            }
+        }
+        Ok(document)
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Selection>, LimitError> {
+        self.expect('{')?;
+        self.nesting += 1;
+        if self.nesting > MAX_NESTING {
+            return Err(LimitError::invalid(format!(
+                "Selection sets cannot be nested more than {MAX_NESTING} times"
+            )));
+        }
+        let mut selections = Vec::new();
+        while !self.is_next('}') {
+            selections.push(self.selection()?);
+        }
+        self.expect('}')?;
+        self.nesting -= 1;
+        Ok(selections)
+    }
+
+    fn selection(&mut self) -> Result<Selection, LimitError> {
+        if self.peek() == Some(&Token::Spread) {
+            self.next()?;
+            return match self.peek().cloned() {
+                Some(Token::Name(name)) if name != "on" => {
+                    self.next()?;
+                    self.directives()?;
+                    Ok(Selection::FragmentSpread(name))
+                }
+                Some(Token::Name(_)) => {
+                    self.next()?; // on
+                    self.name()?; // type condition
+                    self.directives()?;
+                    Ok(Selection::InlineFragment(self.selection_set()?))
+                }
+                _ => {
+                    self.directives()?;
+                    Ok(Selection::InlineFragment(self.selection_set()?))
+                }
+            };
+        }
+        let mut name = self.name()?;
+        let aliased = self.is_next(':');
+        if aliased {
+            self.next()?;
+            name = self.name()?;
+        }
+        let first = if self.is_next('(') {
+            self.arguments()?
+        } else {
+            None
+        };
+        self.directives()?;
+        let selections = if self.is_next('{') {
+            self.depth += 1;
+            if self.depth > self.max_depth {
+                return Err(LimitError::new(
+                    format!(
+                        "Query is nested more than {} levels deep",
+                        self.max_depth
+                    ),
+                    "QUERY_TOO_DEEP",
+                    self.max_depth as u64,
+                    self.depth as u64,
+                ));
+            }
+            let selections = self.selection_set()?;
+            self.depth -= 1;
+            selections
+        } else {
+            Vec::new()
+        };
+        Ok(Selection::Field {
+            name,
+            aliased,
+            first,
+            selections,
+        })
+    }
+
+    /// Parse the arguments of a field, returning the value of `first`.
+    fn arguments(&mut self) -> Result<Option<First>, LimitError> {
+        self.expect('(')?;
+        let mut first = None;
+        while !self.is_next(')') {
+            let name = self.name()?;
+            self.expect(':')?;
+            match (name.as_str(), self.peek().cloned()) {
+                ("first", Some(Token::Int(value))) => {
+                    self.next()?;
+                    first = Some(First::Value(value));
+                }
+                ("first", Some(Token::Punctuator('$'))) => {
+                    self.next()?;
+                    first = Some(First::Variable(self.name()?));
+                }
+                _ => self.skip_value()?,
+            }
+        }
+        self.expect(')')
+    }
+
+    fn directives(&mut self) -> Result<(), LimitError> {
+        while self.is_next('@') {
+            self.next()?;
+            self.name()?;
+            if self.is_next('(') {
+                self.skip_balanced('(', ')')?;
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_value(&mut self) -> Result<(), LimitError> {
+        match self.peek() {
+            Some(Token::Punctuator('$')) => {
+                self.next()?;
+                self.name().map(|_| ())
+            }
+            Some(Token::Punctuator('[')) => self.skip_balanced('[', ']'),
+            Some(Token::Punctuator('{')) => self.skip_balanced('{', '}'),
+            Some(Token::Name(_) | Token::Int(_) | Token::Value) => {
+                self.next().map(|_| ())
+            }
+            token => Err(LimitError::invalid(format!(
+                "Expected a value, found {token:?}"
+            ))),
+        }
+    }
+
+    fn skip_balanced(
+        &mut self,
+        open: char,
+        close: char,
+    ) -> Result<(), LimitError> {
+        self.expect(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Punctuator(c) if c == open => depth += 1,
+                Token::Punctuator(c) if c == close => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Stats {
+    depth: usize,
+    aliases: usize,
+    complexity: u64,
+}
+
+struct Analyzer<'a> {
+    limits: &'a QueryLimits,
+    fragments: &'a HashMap<String, Vec<Selection>>,
+    variables: Option<&'a InputValue>,
+    /// Stats of the fragments already analyzed, with their depth
+    /// relative to where they are spread.
+    analyzed: RefCell<HashMap<String, Stats>>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(
+        limits: &'a QueryLimits,
+        fragments: &'a HashMap<String, Vec<Selection>>,
+        variables: Option<&'a InputValue>,
+    ) -> Self {
+        Self {
+            limits,
+            fragments,
+            variables,
+            analyzed: RefCell::default(),
+        }
+    }
+
+    /// Amount of items a field is expected to return.
+    fn multiplier(&self, name: &str, first: Option<&First>) -> u64 {
+        let first = match first {
+            Some(First::Value(value)) => Some(*value),
+            Some(First::Variable(variable)) => self
+                .variables
+                .and_then(InputValue::to_object_value)
+                .and_then(|variables| {
+                    variables
+                        .get(variable.as_str())
+                        .and_then(|value| value.as_int_value().map(i64::from))
+                }),
+            None => None,
+        };
+        match first {
+            Some(first) => u64::try_from(first).unwrap_or(0).max(1),
+            None if PAGINATED_FIELDS.contains(&name) => DEFAULT_PAGE_ITEMS,
+            None if LIST_FIELDS.contains(&name) => DEFAULT_LIST_ITEMS,
+            None => 1,
+        }
+    }
+
+    /// Compute the stats of a selection set found at depth `depth`,
+    /// stopping as soon as they exceed the limits.
+    ///
+    /// `spreads` holds the fragments being expanded, to detect cycles.
+    /// Each fragment is only analyzed once, no matter how many times
+    /// it is spread.
+    fn analyze(
+        &self,
+        selections: &[Selection],
+        depth: usize,
+        spreads: &mut Vec<String>,
+    ) -> Result<Stats, LimitError> {
+        let mut stats = Stats {
+            depth,
+            ..Stats::default()
+        };
+        for selection in selections {
+            let child = match selection {
+                // Introspection is bounded by the schema itself
+                Selection::Field { name, .. } if name.starts_with("__") => {
+                    continue
+                }
+                Selection::Field {
+                    name,
+                    aliased,
+                    first,
+                    selections,
+                } => {
+                    let mut child = if selections.is_empty() {
+                        Stats {
+                            depth,
+                            ..Stats::default()
+                        }
+                    } else {
+                        self.analyze(selections, depth + 1, spreads)?
+                    };
+                    child.complexity = self
+                        .multiplier(name, first.as_ref())
+                        .saturating_mul(child.complexity.saturating_add(1));
+                    child.aliases += usize::from(*aliased);
+                    child
+                }
+                Selection::FragmentSpread(name) => {
+                    let cached = self.analyzed.borrow().get(name).copied();
+                    if let Some(mut child) = cached {
+                        child.depth += depth;
+                        child
+                    } else {
+                        self.spread(name, depth, spreads)?
+                    }
+                }
+                Selection::InlineFragment(selections) => {
+                    self.analyze(selections, depth, spreads)?
+                }
+            };
+            stats.depth = stats.depth.max(child.depth);
+            stats.aliases += child.aliases;
+            stats.complexity =
+                stats.complexity.saturating_add(child.complexity);
+            self.limits.enforce(stats)?;
+        }
+        Ok(stats)
+    }
+
+    /// Analyze the fragment `name` spread at depth `depth` and remember
+    /// its stats.
+    fn spread(
+        &self,
+        name: &str,
+        depth: usize,
+        spreads: &mut Vec<String>,
+    ) -> Result<Stats, LimitError> {
+        if spreads.iter().any(|spread| spread == name) {
+            return Err(LimitError::invalid(format!(
+                "Fragment {name} spreads itself"
+            )));
+        }
+        let Some(fragment) = self.fragments.get(name) else {
+            return Err(LimitError::invalid(format!(
+                "Unknown fragment {name}"
+            )));
+        };
+        spreads.push(name.to_string());
+        let child = self.analyze(fragment, depth, spreads)?;
+        spreads.pop();
+        self.analyzed.borrow_mut().insert(
+            name.to_string(),
+            Stats {
+                depth: child.depth - depth,
+                ..child
+            },
+        );
+        Ok(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: QueryLimits = QueryLimits {
+        max_depth: 4,
+        max_complexity: 100,
+        max_aliases: 2,
+        max_batch: 2,
+    };
+
+    const UNLIMITED: QueryLimits = QueryLimits {
+        max_depth: usize::MAX,
+        max_complexity: u64::MAX,
+        max_aliases: usize::MAX,
+        max_batch: usize::MAX,
+    };
+
+    fn stats(query: &str, variables: Option<&InputValue>) -> Stats {
+        let document = Parser::new(query, usize::MAX)
+            .and_then(Parser::document)
+            .expect("query should parse");
+        let analyzer =
+            Analyzer::new(&UNLIMITED, &document.fragments, variables);
+        analyzer
+            .analyze(&document.operations[0].selections, 1, &mut Vec::new())
+            .expect("query should be analyzed")
+    }
+
+    fn code(result: Result<(), LimitError>) -> &'static str {
+        result.expect_err("query should be rejected").code
+    }
+
+    #[test]
+    fn depth_counts_nested_fields() {
+        assert_eq!(1, stats("{ me }", None).depth);
+        assert_eq!(3, stats("{ me { languages { name } } }", None).depth);
+        assert_eq!(
+            2,
+            stats("{ me { ... on User { ... { id } } } }", None).depth
+        );
+        assert!(LIMITS.check("{ a { b { c { d } } } }", None, None).is_ok());
+        assert_eq!(
+            "QUERY_TOO_DEEP",
+            code(LIMITS.check("{ a { b { c { d { e } } } } }", None, None))
+        );
+    }
+
+    #[test]
+    fn depth_includes_fragments() {
+        let query = "query { a { ...F } } fragment F on A { b { c { d } } }";
+        assert_eq!(4, stats(query, None).depth);
+        let query = "query { a { b { ...F } } } fragment F on A { c { d } }";
+        assert_eq!("QUERY_TOO_DEEP", code(LIMITS.check(query, None, None)));
+    }
+
+    #[test]
+    fn deep_queries_are_rejected_while_parsing() {
+        let query =
+            format!("{}{}", "{ a ".repeat(100_000), "}".repeat(100_000));
+        let error = LIMITS.check(&query, None, None).unwrap_err();
+        assert_eq!("QUERY_TOO_DEEP", error.code);
+        assert_eq!(Some(5), error.actual);
+        let query = format!(
+            "{{ a {}{}}}",
+            "... { ".repeat(100_000),
+            "}".repeat(100_000)
+        );
+        assert_eq!(
+            "GRAPHQL_PARSE_FAILED",
+            code(LIMITS.check(&query, None, None))
+        );
+    }
+
+    #[test]
+    fn aliases_are_counted() {
+        assert_eq!(0, stats("{ me { id } }", None).aliases);
+        assert_eq!(
+            3,
+            stats("{ a: me { b: id } ... { c: me { id } } }", None).aliases
+        );
+        assert!(LIMITS.check("{ a: me b: me }", None, None).is_ok());
+        assert_eq!(
+            "TOO_MANY_ALIASES",
+            code(LIMITS.check("{ a: me b: me c: me }", None, None))
+        );
+    }
+
+    #[test]
+    fn complexity_multiplies_lists() {
+        assert_eq!(2, stats("{ me { id } }", None).complexity);
+        // allLanguages returns DEFAULT_LIST_ITEMS languages
+        assert_eq!(20, stats("{ allLanguages { name } }", None).complexity);
+        // feed returns DEFAULT_PAGE_ITEMS events
+        assert_eq!(40, stats("{ feed { id } }", None).complexity);
+        assert_eq!(10, stats("{ feed(first: 5) { id } }", None).complexity);
+        assert_eq!(
+            210,
+            stats("{ allLanguages { words { id } } }", None).complexity
+        );
+        assert_eq!(
+            "QUERY_TOO_COMPLEX",
+            code(LIMITS.check("{ allLanguages { words { id } } }", None, None))
+        );
+    }
+
+    #[test]
+    fn first_reads_variables() {
+        let query = "query Feed($n: Int) { feed(first: $n) { id } }";
+        let variables: InputValue =
+            serde_json::from_value(json!({ "n": 3 })).unwrap();
+        assert_eq!(6, stats(query, Some(&variables)).complexity);
+        // Unknown variables fall back to the default page size
+        assert_eq!(40, stats(query, None).complexity);
+        let variables: InputValue =
+            serde_json::from_value(json!({ "n": 1000 })).unwrap();
+        assert_eq!(
+            "QUERY_TOO_COMPLEX",
+            code(LIMITS.check(query, None, Some(&variables)))
+        );
+    }
+
+    #[test]
+    fn introspection_is_ignored() {
+        assert_eq!(
+            Stats {
+                depth: 1,
+                aliases: 0,
+                complexity: 0
+            },
+            stats("{ __schema { types { fields { name } } } }", None)
+        );
+    }
+
+    #[test]
+    fn fragment_cycles_are_rejected() {
+        let query = "{ ...A } fragment A on Query { ...B } \
+                     fragment B on Query { ...A }";
+        assert_eq!(
+            "GRAPHQL_PARSE_FAILED",
+            code(LIMITS.check(query, None, None))
+        );
+        let query = "{ ...A } fragment A on Query { me { ...A } }";
+        assert_eq!(
+            "GRAPHQL_PARSE_FAILED",
+            code(LIMITS.check(query, None, None))
+        );
+        assert_eq!(
+            "GRAPHQL_PARSE_FAILED",
+            code(LIMITS.check("{ ...Missing }", None, None))
+        );
+    }
+
+    #[test]
+    fn fragments_are_analyzed_once() {
+        // Each fragment spreads the next one twice, expanding to 2^40
+        // fields if fragments were analyzed at every spread
+        let fragments = (0..40)
+            .map(|i| {
+                let next = i + 1;
+                format!("fragment F{i} on Query {{ ...F{next} ...F{next} }} ")
+            })
+            .collect::<String>();
+        let query =
+            format!("{{ ...F0 }} {fragments}fragment F40 on Query {{ me }}");
+        assert_eq!(
+            Stats {
+                depth: 1,
+                aliases: 0,
+                complexity: 1 << 40
+            },
+            stats(&query, None)
+        );
+        assert_eq!("QUERY_TOO_COMPLEX", code(LIMITS.check(&query, None, None)));
+        let query = "{ ...A } fragment A on Query { me { ...B } } \
+                     fragment B on User { id ...C } \
+                     fragment C on User { languages { ...B } }";
+        assert_eq!(
+            "GRAPHQL_PARSE_FAILED",
+            code(LIMITS.check(query, None, None))
+        );
+    }
+
+    #[test]
+    fn cached_fragments_keep_their_depth() {
+        let query = "{ ...F a { ...F } } fragment F on Query { b { c } }";
+        assert_eq!(3, stats(query, None).depth);
+    }
+
+    #[test]
+    fn batches_are_limited() {
+        let request = |query: &str| {
+            juniper::http::GraphQLRequest::new(query.to_string(), None, None)
+        };
+        let batch = GraphQLBatchRequest::Batch(vec![request("{ me }"); 2]);
+        assert!(LIMITS.check_batch(&batch).is_ok());
+        let batch = GraphQLBatchRequest::Batch(vec![
+            request("{ me }"),
+            request("{ a: me b: me c: me }"),
+        ]);
+        assert_eq!("TOO_MANY_ALIASES", code(LIMITS.check_batch(&batch)));
+        let batch = GraphQLBatchRequest::Batch(vec![request("{ me }"); 3]);
+        assert_eq!("BATCH_TOO_LARGE", code(LIMITS.check_batch(&batch)));
+    }
+
+    #[test]
+    fn only_the_selected_operation_is_checked() {
+        let query = "query Small { me } query Wide { a: me b: me c: me }";
+        assert!(LIMITS.check(query, Some("Small"), None).is_ok());
+        assert_eq!(
+            "TOO_MANY_ALIASES",
+            code(LIMITS.check(query, Some("Wide"), None))
+        );
+    }
+}
@@ -1,21 +1,44 @@
+use std::sync::Arc;
+
+use futures::executor;
+use rocket::data::{self, Data, FromData, ToByteUnit};
+use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome, Request};
 use rocket::response::content::RawHtml;
 use rocket::State;
 
-use tracing::debug;
+use tracing::{debug, warn};
 
-use juniper::EmptySubscription;
-use juniper_rocket::{GraphQLRequest, GraphQLResponse};
+use juniper::{
+    http::{GraphQLBatchRequest, GraphQLRequest},
+    EmptyMutation,
+};
+use juniper_rocket::GraphQLResponse;
 
 pub mod context;
 pub use context::Context;
 
+mod get;
+pub mod limits;
 mod mutation;
 mod query;
+pub mod request_id;
+pub mod subscription;
+pub use get::get_graphql_handler;
+pub use limits::QueryLimits;
+pub use request_id::{request_id_header, RequestId};
+pub use subscription::websocket_server;
 
 #[derive(Copy, Clone, Debug)]
 pub struct UserAuth<'r>(Option<&'r str>);
 
+impl<'r> UserAuth<'r> {
+    /// Value of the `Authorization` header of the request, if any.
+    pub fn credentials(self) -> Option<&'r str> {
+        self.0
+    }
+}
+
 #[derive(Debug)]
 pub enum UserAuthError {}
 
@@ -32,18 +55,90 @@ impl<'r> FromRequest<'r> for UserAuth<'r> {
     }
 }
 
+/// Maximum size of `POST` request bodies if the `graphql` limit is not
+/// configured.
+const DEFAULT_BODY_LIMIT: u64 = 1024 * 1024;
+
+/// GraphQL request sent through `POST`, either as JSON, possibly
+/// batched, or as a bare query with the `application/graphql` content
+/// type.
+#[derive(Debug)]
+pub struct PostRequest(GraphQLBatchRequest);
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for PostRequest {
+    type Error = String;
+    async fn from_data(
+        request: &'r Request<'_>,
+        data: Data<'r>,
+    ) -> data::Outcome<'r, Self> {
+        let limit = request
+            .limits()
+            .get("graphql")
+            .unwrap_or_else(|| DEFAULT_BODY_LIMIT.bytes());
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => {
+                return data::Outcome::Failure((
+                    Status::PayloadTooLarge,
+                    "Request body is too large".into(),
+                ))
+            }
+            Err(e) => {
+                return data::Outcome::Failure((
+                    Status::BadRequest,
+                    e.to_string(),
+                ))
+            }
+        };
+        let is_graphql = request.content_type().map_or(false, |ct| {
+            ct.top() == "application" && ct.sub() == "graphql"
+        });
+        if is_graphql {
+            return data::Outcome::Success(PostRequest(
+                GraphQLBatchRequest::Single(GraphQLRequest::new(
+                    body, None, None,
+                )),
+            ));
+        }
+        match serde_json::from_str(&body) {
+            Ok(request) => data::Outcome::Success(PostRequest(request)),
+            Err(e) => data::Outcome::Failure((
+                Status::BadRequest,
+                format!("Invalid GraphQL request: {e}"),
+            )),
+        }
+    }
+}
+
 pub type Schema = juniper::RootNode<
     'static,
     query::Query,
     mutation::Mutation,
-    EmptySubscription<Context>,
+    subscription::Subscription,
 >;
 
 pub fn create_schema() -> Schema {
     Schema::new(
         query::Query {},
         mutation::Mutation {},
-        EmptySubscription::default(),
+        subscription::Subscription {},
+    )
+}
+
+/// Schema used for `GET` requests, which cannot execute mutations.
+pub type ReadOnlySchema = juniper::RootNode<
+    'static,
+    query::Query,
+    EmptyMutation<Context>,
+    subscription::Subscription,
+>;
+
+pub fn create_read_only_schema() -> ReadOnlySchema {
+    ReadOnlySchema::new(
+        query::Query {},
+        EmptyMutation::new(),
+        subscription::Subscription {},
     )
 }
 
@@ -53,29 +148,43 @@ pub fn graphiql() -> RawHtml<String> {
     juniper_rocket::graphiql_source(graphql_endpoint_url, None)
 }
 
-#[rocket::get("/graphql?<request>")]
-pub async fn get_graphql_handler(
-    context: &State<Context>,
-    user_auth: UserAuth<'_>,
-    request: GraphQLRequest,
-    schema: &State<Schema>,
-) -> GraphQLResponse {
-    debug!("Current context: {:?}", context);
-    request
-        .execute(schema, &(*context).attach_auth(user_auth.0).await)
-        .await
-}
-
+/// Execute GraphQL requests sent through `POST`.
+///
+/// Queries exceeding the configured [`QueryLimits`] are rejected
+/// before being executed.
 #[allow(clippy::needless_pass_by_value)]
 #[rocket::post("/graphql", data = "<request>")]
 pub async fn post_graphql_handler(
     context: &State<Context>,
     user_auth: UserAuth<'_>,
-    request: GraphQLRequest,
-    schema: &State<Schema>,
+    request_id: RequestId,
+    limits: &State<QueryLimits>,
+    request: PostRequest,
+    schema: &State<Arc<Schema>>,
 ) -> GraphQLResponse {
-    debug!("Current context: {:?}", context);
-    request
-        .execute(schema, &(*context).attach_auth(user_auth.0).await)
-        .await
+    if let Err(e) = limits.check_batch(&request.0) {
+        debug!("Rejected query: {e:?}");
+        return GraphQLResponse(Status::BadRequest, e.to_json());
+    }
+    let schema = Arc::clone(schema);
+    let (ok, body) = context
+        .attach_auth(user_auth.0)
+        .run(move |context| {
+            let response = executor::block_on(
+                request_id.scope(request.0.execute(&schema, context)),
+            );
+            (response.is_ok(), serde_json::to_string(&response))
+        })
+        .await;
+    let status = if ok { Status::Ok } else { Status::BadRequest };
+    match body {
+        Ok(body) => GraphQLResponse(status, body),
+        Err(e) => {
+            warn!("Failed to serialize GraphQL response: {e:?}");
+            GraphQLResponse(
+                Status::InternalServerError,
+                "Failed to serialize response".into(),
+            )
+        }
+    }
 }
@@ -1,15 +1,30 @@
 use std::str::FromStr;
 
 use juniper::FieldResult;
+use serde_json::json;
 use uuid::Uuid;
 
+use crate::auth;
 use crate::db::{
     models::{
-        languages::{Language, NewLanguage, UserFollowLanguage},
-        users::User,
-        words::{NewWord, Word},
+        accounts::{export_data, AccountDeletion, LanguageTransfer},
+        api_tokens::{ApiToken, CreatedApiToken, NewApiToken},
+        audit::{AuditAction, AuditEntry},
+        comments::{Comment, NewComment},
+        forks::LanguageFork,
+        imports::{ColumnMapping, ImportFormat, ImportReport, WordImport},
+        languages::{
+            Language, LanguageUpdate, NewLanguage, UserFollowLanguage,
+        },
+        lift,
+        notifications::Notification,
+        profiles::ProfileUpdate,
+        suggestions::Suggestion,
+        transfers::LanguageTransferOffer,
+        users::{User, UserFollow, UserReconciliation},
+        words::{NewWord, Word, WordLearning, WordLearningStatus, WordUpdate},
     },
-    DatabaseError,
+    Database, DatabaseError, ErrorKind,
 };
 
 use super::Context;
@@ -19,7 +34,7 @@ pub struct Mutation;
 #[juniper::graphql_object(Context = Context)]
 impl Mutation {
     fn api_version(context: &Context) -> String {
-        if context.user_auth.is_some() {
+        if context.language_writer().is_some() {
             "0.1 (authentified)"
         } else {
             "0.1 (not authentified)"
@@ -27,36 +42,129 @@ impl Mutation {
         .into()
     }
 
+    #[graphql(description = "Add a user to the database, admins only")]
     pub fn db_only_new_user(
         context: &Context,
         username: String,
         id: String,
-        admin_key: String,
     ) -> FieldResult<User> {
-        if admin_key == context.other_vars.admin_key {
-            context
-                .db
-                .insert_user(username, id)
-                .map_err(std::convert::Into::into)
-        } else {
-            Err(DatabaseError::new("Invalid admin key", "Invalid admin key")
-                .into())
-        }
+        let admin = context.admin()?;
+        AuditEntry::perform(
+            &context.db,
+            admin,
+            AuditAction::CreateUser,
+            Some(&id),
+            Some(json!({ "username": username })),
+            |conn| Database::insert_user(conn, username.clone(), id.clone()),
+        )
+        .map_err(Into::into)
     }
 
+    #[graphql(description = "Delete a user from the database, admins only")]
     pub fn db_only_delete_user(
         context: &Context,
         id: String,
-        admin_key: String,
     ) -> FieldResult<String> {
-        if admin_key == context.other_vars.admin_key {
-            match context.db.delete_user(&id) {
-                Ok(_) => Ok("done".into()),
-                Err(e) => Err(e.into()),
-            }
+        let admin = context.admin()?;
+        AuditEntry::perform(
+            &context.db,
+            admin,
+            AuditAction::DeleteUser,
+            Some(&id),
+            None,
+            |conn| Database::delete_user(conn, &id),
+        )
+        .map(|_| "done".into())
+        .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Grant or deny admin rights to a user, admins only",
+        arguments(
+            user(description = "Appwrite ID of the user"),
+            admin(description = "Whether the user should be an admin")
+        )
+    )]
+    pub fn set_admin(
+        context: &Context,
+        user: String,
+        admin: bool,
+    ) -> FieldResult<User> {
+        let current = context.admin()?;
+        if *current == user && !admin {
+            return Err(DatabaseError::new(
+                "Admins cannot revoke their own admin rights",
+                ErrorKind::InvalidInput,
+            )
+            .into());
+        }
+        AuditEntry::perform(
+            &context.db,
+            current,
+            AuditAction::SetAdmin,
+            Some(&user),
+            Some(json!({ "admin": admin })),
+            |conn| Database::set_admin(conn, &user, admin),
+        )
+        .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Synchronise the users of the database with Appwrite, admins only"
+    )]
+    pub async fn reconcile_users(
+        context: &Context,
+    ) -> FieldResult<UserReconciliation> {
+        let admin = context.admin()?;
+        // Users authenticated otherwise are unknown to Appwrite and
+        // would all be deleted
+        if !auth::uses_appwrite() {
+            return Err(DatabaseError::new(
+                "Users do not log in with Appwrite, cannot reconcile users",
+                ErrorKind::Conflict,
+            )
+            .into());
+        }
+        let Some(appwrite) = context.appwrite.get() else {
+            return Err(DatabaseError::new(
+                "Appwrite is not configured, cannot reconcile users",
+                ErrorKind::Conflict,
+            )
+            .into());
+        };
+        let accounts = appwrite.list_users().await.map_err(|e| {
+            DatabaseError::new(
+                format!("Failed to list Appwrite users: {e:?}"),
+                ErrorKind::Internal,
+            )
+        })?;
+        AuditEntry::perform(
+            &context.db,
+            admin,
+            AuditAction::ReconcileUsers,
+            None,
+            Some(json!({ "appwriteUsers": accounts.len() })),
+            |conn| User::reconcile(conn, &accounts),
+        )
+        .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Update the profile of the current user",
+        arguments(changes(description = "Changes to make to the profile"))
+    )]
+    pub fn update_profile(
+        context: &Context,
+        changes: ProfileUpdate,
+    ) -> FieldResult<User> {
+        if let Some(user) = context.writer() {
+            changes.apply(&context.db, user).map_err(Into::into)
         } else {
-            Err(DatabaseError::new("Invalid admin key", "Invalid admin key")
-                .into())
+            Err(DatabaseError::new(
+                "User not authentificated, cannot update profile",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
         }
     }
 
@@ -64,13 +172,13 @@ impl Mutation {
         context: &Context,
         language: String,
     ) -> FieldResult<Language> {
-        if let Some(userid) = &context.user_auth {
+        if let Some(userid) = context.writer() {
             match Uuid::from_str(&language) {
                 Err(e) => Err(DatabaseError::new(
                     format!(
                         "Could not parse {language} as a valid UUID: {e:?}"
                     ),
-                    "Bad Request",
+                    ErrorKind::InvalidInput,
                 )
                 .into()),
                 Ok(lang) => UserFollowLanguage::user_follow_language(
@@ -83,7 +191,7 @@ impl Mutation {
         } else {
             Err(DatabaseError::new(
                 "User not authentificated, cannot proceed",
-                "Unauthorized",
+                ErrorKind::Unauthenticated,
             )
             .into())
         }
@@ -93,13 +201,13 @@ impl Mutation {
         context: &Context,
         language: String,
     ) -> FieldResult<Language> {
-        if let Some(userid) = &context.user_auth {
+        if let Some(userid) = context.writer() {
             match Uuid::from_str(&language) {
                 Err(e) => Err(DatabaseError::new(
                     format!(
                         "Could not parse {language} as a valid UUID: {e:?}"
                     ),
-                    "Bad Request",
+                    ErrorKind::InvalidInput,
                 )
                 .into()),
                 Ok(lang) => UserFollowLanguage::user_unfollow_language(
@@ -112,7 +220,7 @@ impl Mutation {
         } else {
             Err(DatabaseError::new(
                 "User not authentificated, cannot proceed",
-                "Unauthorized",
+                ErrorKind::Unauthenticated,
             )
             .into())
         }
@@ -122,12 +230,38 @@ impl Mutation {
         context: &Context,
         language: NewLanguage,
     ) -> FieldResult<Language> {
-        if let Some(owner) = &context.user_auth {
-            language.insert(&context.db, owner).map_err(Into::into)
+        if let Some(owner) = context.writer() {
+            language.insert(context, owner).map_err(Into::into)
         } else {
             Err(DatabaseError::new(
                 "User not authentificated, cannot create new language",
-                "Unauthorized",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    pub fn update_language(
+        context: &Context,
+        language: String,
+        changes: LanguageUpdate,
+    ) -> FieldResult<Language> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => Language::update(context, uuid, user, changes)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot update language",
+                ErrorKind::Unauthenticated,
             )
             .into())
         }
@@ -137,7 +271,7 @@ impl Mutation {
         context: &Context,
         language: String,
     ) -> FieldResult<Option<Language>> {
-        if context.user_auth.is_some() {
+        if context.language_writer().is_some() {
             match Uuid::from_str(&language) {
                 Ok(uuid) => Language::delete(context, uuid)
                     .map(|_| None)
@@ -146,26 +280,227 @@ impl Mutation {
                     format!(
                         "Could not parse {language} as a valid UUID: {e:?}"
                     ),
-                    "Bad Request",
+                    ErrorKind::InvalidInput,
                 )
                 .into()),
             }
         } else {
             Err(DatabaseError::new(
                 "User not authentificated, cannot create new language",
-                "Unauthorized",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    pub fn restore_language(
+        context: &Context,
+        language: String,
+    ) -> FieldResult<Language> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => {
+                    Language::restore(context, uuid, user).map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot restore language",
+                ErrorKind::Unauthenticated,
             )
             .into())
         }
     }
 
     pub fn new_word(context: &Context, word: NewWord) -> FieldResult<Word> {
-        if let Some(user) = &context.user_auth {
+        if let Some(user) = context.language_writer() {
             word.insert(context, user).map_err(Into::into)
         } else {
             Err(DatabaseError::new(
                 "User not authentificated, cannot create new language",
-                "Unauthorized",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Add words to a language from CSV or TSV data, all at once or not at all",
+        arguments(
+            language(description = "UUID of the language"),
+            data(description = "Imported data, with a header row"),
+            format(description = "Format of the data"),
+            mapping(
+                description = "Field held by each imported column, by default the fields columns are named after"
+            ),
+            dry_run(
+                description = "Only report the issues of the data without importing it"
+            )
+        )
+    )]
+    pub fn import_words(
+        context: &Context,
+        language: String,
+        data: String,
+        format: ImportFormat,
+        mapping: Option<Vec<ColumnMapping>>,
+        dry_run: Option<bool>,
+    ) -> FieldResult<ImportReport> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => WordImport::import(
+                    context,
+                    user,
+                    uuid,
+                    dry_run.unwrap_or(false),
+                    |conn, language| {
+                        WordImport::parse(
+                            conn,
+                            language,
+                            &data,
+                            format,
+                            mapping.as_deref(),
+                        )
+                    },
+                )
+                .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot import words",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Add the entries of a LIFT document to a language, all at once or not at all",
+        arguments(
+            language(description = "UUID of the language"),
+            data(description = "LIFT document"),
+            dry_run(
+                description = "Only report the issues of the document without importing it"
+            )
+        )
+    )]
+    pub fn import_lift(
+        context: &Context,
+        language: String,
+        data: String,
+        dry_run: Option<bool>,
+    ) -> FieldResult<ImportReport> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => WordImport::import(
+                    context,
+                    user,
+                    uuid,
+                    dry_run.unwrap_or(false),
+                    |conn, language| lift::parse(conn, language, &data),
+                )
+                .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot import words",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    pub fn update_word(
+        context: &Context,
+        word: String,
+        changes: WordUpdate,
+    ) -> FieldResult<Word> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&word) {
+                Ok(id) => {
+                    Word::update(context, id, user, changes).map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {word} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot update word",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Restore a word as it was after one of its revisions, or before it if the revision is its deletion",
+        arguments(
+            id(description = "Unique identifier of the word"),
+            revision(description = "Number of the revision to revert to")
+        )
+    )]
+    pub fn revert_word(
+        context: &Context,
+        id: String,
+        revision: i32,
+    ) -> FieldResult<Word> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&id) {
+                Ok(uuid) => Word::revert(context, uuid, revision, user)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {id} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot revert word",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    pub fn restore_word(context: &Context, word: String) -> FieldResult<Word> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&word) {
+                Ok(id) => Word::restore(context, id, user).map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {word} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot restore word",
+                ErrorKind::Unauthenticated,
             )
             .into())
         }
@@ -175,21 +510,638 @@ impl Mutation {
         context: &Context,
         word: String,
     ) -> FieldResult<Option<Word>> {
-        if let Some(user) = &context.user_auth {
+        if let Some(user) = context.language_writer() {
             match Uuid::from_str(&word) {
                 Ok(id) => Word::delete(context, id, user)
                     .map(|_| None)
                     .map_err(Into::into),
                 Err(e) => Err(DatabaseError::new(
                     format!("Could not parse {word} as a valid UUID: {e:?}"),
-                    "Bad Request",
+                    ErrorKind::InvalidInput,
                 )
                 .into()),
             }
         } else {
             Err(DatabaseError::new(
                 "User not authentificated, cannot create new language",
-                "Unauthorized",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    pub fn learn_word(
+        context: &Context,
+        word: String,
+        status: WordLearningStatus,
+    ) -> FieldResult<Word> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&word) {
+                Ok(id) => WordLearning::set_status(context, user, id, status)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {word} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot learn word",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    pub fn review_word(
+        context: &Context,
+        word: String,
+        correct: bool,
+    ) -> FieldResult<Word> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&word) {
+                Ok(id) => WordLearning::review(context, user, id, correct)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {word} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot review word",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Suggest adding a new word to someone else's language",
+        arguments(
+            word(description = "Word to add to the language"),
+            comment(description = "Why the word should be added")
+        )
+    )]
+    pub fn suggest_new_word(
+        context: &Context,
+        word: NewWord,
+        comment: Option<String>,
+    ) -> FieldResult<Suggestion> {
+        if let Some(user) = context.writer() {
+            Suggestion::new_word(context, user, &word, comment)
+                .map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot suggest new word",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Suggest changes to someone else's word",
+        arguments(
+            word(description = "Unique identifier of the word"),
+            changes(description = "Changes to make to the word"),
+            comment(description = "Why the word should be changed")
+        )
+    )]
+    pub fn suggest_word_edit(
+        context: &Context,
+        word: String,
+        changes: WordUpdate,
+        comment: Option<String>,
+    ) -> FieldResult<Suggestion> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&word) {
+                Ok(id) => {
+                    Suggestion::edit_word(context, user, id, &changes, comment)
+                        .map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {word} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot suggest word edit",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Suggest deleting someone else's word",
+        arguments(
+            word(description = "Unique identifier of the word"),
+            comment(description = "Why the word should be deleted")
+        )
+    )]
+    pub fn suggest_word_deletion(
+        context: &Context,
+        word: String,
+        comment: Option<String>,
+    ) -> FieldResult<Suggestion> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&word) {
+                Ok(id) => Suggestion::delete_word(context, user, id, comment)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {word} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot suggest word deletion",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Apply a pending suggestion and credit its author",
+        arguments(id(description = "Number of the suggestion"))
+    )]
+    pub fn accept_suggestion(
+        context: &Context,
+        id: i32,
+    ) -> FieldResult<Suggestion> {
+        if let Some(user) = context.language_writer() {
+            Suggestion::accept(context, id, user).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot accept suggestion",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Reject a pending suggestion",
+        arguments(
+            id(description = "Number of the suggestion"),
+            response(description = "Why the suggestion is rejected")
+        )
+    )]
+    pub fn reject_suggestion(
+        context: &Context,
+        id: i32,
+        response: Option<String>,
+    ) -> FieldResult<Suggestion> {
+        if let Some(user) = context.language_writer() {
+            Suggestion::reject(context, id, user, response).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot reject suggestion",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Allow a user to review suggestions made for one of your languages",
+        arguments(
+            language(description = "UUID of the language"),
+            user(description = "Appwrite ID of the new maintainer")
+        )
+    )]
+    pub fn add_language_maintainer(
+        context: &Context,
+        language: String,
+        user: String,
+    ) -> FieldResult<Language> {
+        if let Some(owner) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => {
+                    Language::set_maintainer(context, uuid, owner, &user, true)
+                        .map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot add maintainer",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Remove a maintainer from one of your languages",
+        arguments(
+            language(description = "UUID of the language"),
+            user(description = "Appwrite ID of the maintainer")
+        )
+    )]
+    pub fn remove_language_maintainer(
+        context: &Context,
+        language: String,
+        user: String,
+    ) -> FieldResult<Language> {
+        if let Some(owner) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => {
+                    Language::set_maintainer(context, uuid, owner, &user, false)
+                        .map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot remove maintainer",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Copy a language and its words into a new language owned by you",
+        arguments(
+            source(description = "UUID of the language to fork"),
+            new_name(description = "Name of the new language")
+        )
+    )]
+    pub fn fork_language(
+        context: &Context,
+        source: String,
+        new_name: String,
+    ) -> FieldResult<Language> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&source) {
+                Ok(uuid) => LanguageFork::fork(context, user, uuid, &new_name)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {source} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot fork language",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Offer one of your languages to another user, who becomes its owner once they accept",
+        arguments(
+            language(description = "UUID of the language"),
+            new_owner(description = "Appwrite ID of the future owner")
+        )
+    )]
+    pub fn transfer_language(
+        context: &Context,
+        language: String,
+        new_owner: String,
+    ) -> FieldResult<LanguageTransferOffer> {
+        if let Some(user) = context.language_writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => LanguageTransferOffer::offer(
+                    context, user, uuid, &new_owner,
+                )
+                .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot transfer language",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Accept the ownership of a language offered to you, its previous owner stays an author",
+        arguments(language(description = "UUID of the language"))
+    )]
+    pub fn accept_language_transfer(
+        context: &Context,
+        language: String,
+    ) -> FieldResult<Language> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => LanguageTransferOffer::accept(context, user, uuid)
+                    .map_err(Into::into),
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot accept language transfer",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Withdraw a language offered to another user, or decline a language offered to you",
+        arguments(language(description = "UUID of the language"))
+    )]
+    pub fn cancel_language_transfer(
+        context: &Context,
+        language: String,
+    ) -> FieldResult<String> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => {
+                    LanguageTransferOffer::cancel(&context.db, user, uuid)
+                        .map(|_| "done".into())
+                        .map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!(
+                        "Could not parse {language} as a valid UUID: {e:?}"
+                    ),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot cancel language transfer",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Comment on a word or a language, or reply to a comment",
+        arguments(comment(description = "Comment to post"))
+    )]
+    pub fn post_comment(
+        context: &Context,
+        comment: NewComment,
+    ) -> FieldResult<Comment> {
+        if let Some(user) = context.writer() {
+            comment.post(context, user).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot post comment",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Edit one of your comments",
+        arguments(
+            id(description = "Number of the comment"),
+            body(
+                description = "New content of the comment, in Markdown format"
+            )
+        )
+    )]
+    pub fn edit_comment(
+        context: &Context,
+        id: i32,
+        body: String,
+    ) -> FieldResult<Comment> {
+        if let Some(user) = context.writer() {
+            Comment::edit(context, id, user, body).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot edit comment",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Delete a comment and its replies, either yours or one posted on your language",
+        arguments(id(description = "Number of the comment"))
+    )]
+    pub fn delete_comment(
+        context: &Context,
+        id: i32,
+    ) -> FieldResult<Option<Comment>> {
+        if let Some(user) = context.writer() {
+            Comment::delete(context, id, user)
+                .map(|_| None)
+                .map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot delete comment",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Follow another user",
+        arguments(user(description = "Appwrite ID of the user to follow"))
+    )]
+    pub fn follow_user(context: &Context, user: String) -> FieldResult<User> {
+        if let Some(follower) = context.writer() {
+            UserFollow::follow(context, follower, &user).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot follow user",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Stop following another user",
+        arguments(user(description = "Appwrite ID of the user to unfollow"))
+    )]
+    pub fn unfollow_user(
+        context: &Context,
+        user: String,
+    ) -> FieldResult<Option<User>> {
+        if let Some(follower) = context.writer() {
+            UserFollow::unfollow(context, follower, &user).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot unfollow user",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Mark notifications as read and return how many were",
+        arguments(ids(
+            description = "Numbers of the notifications to mark as read, all of them if absent"
+        ))
+    )]
+    pub fn mark_notifications_read(
+        context: &Context,
+        ids: Option<Vec<i32>>,
+    ) -> FieldResult<i32> {
+        if let Some(user) = context.writer() {
+            Notification::mark_read(&context.db, user, ids).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot read notifications",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Export everything stored about the current user as a JSON document"
+    )]
+    pub fn export_my_data(context: &Context) -> FieldResult<String> {
+        match (context.session_user(), context.user_auth()) {
+            (Some(user), _) => {
+                export_data(&context.db, user).map_err(Into::into)
+            }
+            (None, Some(_)) => Err(DatabaseError::new(
+                "API tokens cannot export account data",
+                ErrorKind::Forbidden,
+            )
+            .into()),
+            (None, None) => Err(DatabaseError::new(
+                "User not authentificated, cannot export data",
+                ErrorKind::Unauthenticated,
+            )
+            .into()),
+        }
+    }
+
+    #[graphql(
+        description = "Schedule the deletion of the current user's account, which can be cancelled until the grace period is over",
+        arguments(transfers(
+            description = "Languages to hand over to a collaborator, the other languages are deleted with the account"
+        ))
+    )]
+    pub fn delete_my_account(
+        context: &Context,
+        transfers: Vec<LanguageTransfer>,
+    ) -> FieldResult<AccountDeletion> {
+        match (context.session_user(), context.user_auth()) {
+            (Some(user), _) => {
+                AccountDeletion::request(&context.db, user, &transfers)
+                    .map_err(Into::into)
+            }
+            (None, Some(_)) => Err(DatabaseError::new(
+                "API tokens cannot delete accounts",
+                ErrorKind::Forbidden,
+            )
+            .into()),
+            (None, None) => Err(DatabaseError::new(
+                "User not authentificated, cannot delete account",
+                ErrorKind::Unauthenticated,
+            )
+            .into()),
+        }
+    }
+
+    #[graphql(
+        description = "Cancel the scheduled deletion of the current user's account"
+    )]
+    pub fn cancel_account_deletion(context: &Context) -> FieldResult<String> {
+        if let Some(user) = context.writer() {
+            AccountDeletion::cancel(&context.db, user)
+                .map(|_| "done".into())
+                .map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot cancel account deletion",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Create an API token to authentify as the current user with `Authorization: Bearer <token>`",
+        arguments(token(description = "Name and permissions of the token"))
+    )]
+    pub fn create_api_token(
+        context: &Context,
+        token: NewApiToken,
+    ) -> FieldResult<CreatedApiToken> {
+        match (context.session_user(), context.user_auth()) {
+            (Some(user), _) => {
+                token.create(&context.db, user).map_err(Into::into)
+            }
+            (None, Some(_)) => Err(DatabaseError::new(
+                "API tokens cannot create other API tokens",
+                ErrorKind::Forbidden,
+            )
+            .into()),
+            (None, None) => Err(DatabaseError::new(
+                "User not authentificated, cannot create API token",
+                ErrorKind::Unauthenticated,
+            )
+            .into()),
+        }
+    }
+
+    #[graphql(
+        description = "Revoke one of your API tokens",
+        arguments(id(description = "Unique identifier of the token"))
+    )]
+    pub fn revoke_api_token(
+        context: &Context,
+        id: String,
+    ) -> FieldResult<ApiToken> {
+        if let Some(user) = context.writer() {
+            match Uuid::from_str(&id) {
+                Ok(id) => {
+                    ApiToken::revoke(&context.db, user, id).map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!("Could not parse {id} as a valid UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot revoke API token",
+                ErrorKind::Unauthenticated,
             )
             .into())
         }
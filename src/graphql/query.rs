@@ -3,8 +3,21 @@ use uuid::Uuid;
 
 use super::Context;
 use crate::db::{
-    models::{languages::Language, users::User, words::Word},
-    DatabaseError,
+    models::{
+        accounts::AccountDeletion,
+        api_tokens::ApiToken,
+        audit::{AuditAction, AuditEntry},
+        feed::FeedEvent,
+        languages::Language,
+        notifications::Notification,
+        revisions::WordRevision,
+        suggestions::Suggestion,
+        transfers::LanguageTransferOffer,
+        trash::Trash,
+        users::User,
+        words::Word,
+    },
+    DatabaseError, ErrorKind,
 };
 
 use std::str::FromStr;
@@ -38,22 +51,36 @@ impl Query {
 
     #[graphql(
         name = "allUsers",
-        description = "Fetch all users from database",
-        arguments(admin_key(
-            name = "adminKey",
-            description = "Administrator key. Without it, the query cannot be executed"
-        ))
+        description = "Fetch all users from database, admins only"
     )]
-    fn all_users(
+    fn all_users(context: &Context) -> FieldResult<Vec<User>> {
+        let admin = context.admin()?;
+        AuditEntry::perform(
+            &context.db,
+            admin,
+            AuditAction::ListUsers,
+            None,
+            None,
+            |_| context.db.all_users(),
+        )
+        .map_err(Into::into)
+    }
+
+    #[graphql(
+        name = "auditLog",
+        description = "Retrieve the actions performed by admins, most recent first, admins only",
+        arguments(
+            first(description = "Maximum amount of entries to return"),
+            after(description = "Only return entries older than this one")
+        )
+    )]
+    fn audit_log(
         context: &Context,
-        admin_key: String,
-    ) -> FieldResult<Vec<User>> {
-        if admin_key == context.other_vars.admin_key {
-            context.db.all_users().map_err(Into::into)
-        } else {
-            Err(DatabaseError::new("Invalid admin key", "Invalid admin key")
-                .into())
-        }
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<Vec<AuditEntry>> {
+        context.admin()?;
+        AuditEntry::list(&context.db, first, after).map_err(Into::into)
     }
 
     #[graphql(
@@ -91,6 +118,162 @@ impl Query {
         context.db.user(id.as_str()).map_err(Into::into)
     }
 
+    #[graphql(
+        description = "Retrieve what the authentificated user deleted and can still restore"
+    )]
+    fn trash(context: &Context) -> FieldResult<Trash> {
+        if let Some(owner) = context.user_auth() {
+            Ok(Trash {
+                owner: owner.to_string(),
+            })
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see trash",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Retrieve the API tokens of the authentificated user"
+    )]
+    fn api_tokens(context: &Context) -> FieldResult<Vec<ApiToken>> {
+        if let Some(user) = context.user_auth() {
+            ApiToken::list(&context.db, user).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see API tokens",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Retrieve the pending transfers of languages offered by or to the authentificated user"
+    )]
+    fn language_transfers(
+        context: &Context,
+    ) -> FieldResult<Vec<LanguageTransferOffer>> {
+        if let Some(user) = context.user_auth() {
+            LanguageTransferOffer::involving(&context.db, user)
+                .map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see language transfers",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Retrieve the scheduled deletion of the authentificated user's account, if any"
+    )]
+    fn account_deletion(
+        context: &Context,
+    ) -> FieldResult<Option<AccountDeletion>> {
+        if let Some(user) = context.user_auth() {
+            AccountDeletion::pending(&context.db, user).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see account deletion",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Retrieve recent changes to the languages and users followed by the authentificated user, most recent first",
+        arguments(
+            first(description = "Maximum amount of events to return"),
+            after(description = "Cursor of the event to start after")
+        )
+    )]
+    fn feed(
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<Vec<FeedEvent>> {
+        if let Some(user) = context.user_auth() {
+            FeedEvent::feed(&context.db, user, first, after).map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see feed",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        description = "Retrieve the notifications of the authentificated user, most recent first",
+        arguments(
+            unread_only(
+                name = "unreadOnly",
+                description = "Only return notifications not marked as read yet"
+            ),
+            first(description = "Maximum amount of notifications to return"),
+            after(
+                description = "Only return notifications older than this one"
+            )
+        )
+    )]
+    fn notifications(
+        context: &Context,
+        unread_only: Option<bool>,
+        first: Option<i32>,
+        after: Option<i32>,
+    ) -> FieldResult<Vec<Notification>> {
+        if let Some(user) = context.user_auth() {
+            Notification::list(
+                &context.db,
+                user,
+                unread_only.unwrap_or(false),
+                first,
+                after,
+            )
+            .map_err(Into::into)
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see notifications",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
+    #[graphql(
+        name = "pendingSuggestions",
+        description = "Retrieve the suggestions awaiting review for a language you own or maintain",
+        arguments(language(description = "UUID of the language"))
+    )]
+    fn pending_suggestions(
+        context: &Context,
+        language: String,
+    ) -> FieldResult<Vec<Suggestion>> {
+        if let Some(user) = context.user_auth() {
+            match Uuid::from_str(&language) {
+                Ok(uuid) => {
+                    Suggestion::pending(context, uuid, user).map_err(Into::into)
+                }
+                Err(e) => Err(DatabaseError::new(
+                    format!("Failed to convert {language} to a UUID: {e:?}"),
+                    ErrorKind::InvalidInput,
+                )
+                .into()),
+            }
+        } else {
+            Err(DatabaseError::new(
+                "User not authentificated, cannot see suggestions",
+                ErrorKind::Unauthenticated,
+            )
+            .into())
+        }
+    }
+
     #[graphql(
         description = "Retrieve a specific word from its id",
         arguments(id(description = "Unique identifier of a word"))
@@ -100,7 +283,31 @@ impl Query {
             Ok(uuid) => context.db.word_id(uuid).map_err(Into::into),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to convert {id} to a UUID: {e:?}"),
-                "Conversion Error",
+                ErrorKind::InvalidInput,
+            )
+            .into()),
+        }
+    }
+
+    #[graphql(
+        name = "wordHistory",
+        description = "Retrieve the revisions of a word, even if it was deleted",
+        arguments(id(description = "Unique identifier of a word"))
+    )]
+    fn word_history(
+        context: &Context,
+        id: String,
+    ) -> FieldResult<Vec<WordRevision>> {
+        match Uuid::from_str(&id) {
+            Ok(uuid) => WordRevision::history(
+                &context.db,
+                uuid,
+                context.user_auth().map(String::as_str),
+            )
+            .map_err(Into::into),
+            Err(e) => Err(DatabaseError::new(
+                format!("Failed to convert {id} to a UUID: {e:?}"),
+                ErrorKind::InvalidInput,
             )
             .into()),
         }
@@ -128,7 +335,7 @@ impl Query {
                 .map_err(Into::into),
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to convert {language} to a UUID: {e:?}"),
-                "Conversion Error",
+                ErrorKind::InvalidInput,
             )
             .into()),
         }
@@ -155,7 +362,7 @@ impl Query {
             }
             Err(e) => Err(DatabaseError::new(
                 format!("Failed to convert {language} to a UUID: {e:?}"),
-                "Conversion Error",
+                ErrorKind::InvalidInput,
             )
             .into()),
         }
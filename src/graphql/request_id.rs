@@ -0,0 +1,49 @@
+//! Identifiers of HTTP requests, sent back to clients in the
+//! `X-Request-Id` header and in the errors of their responses so they
+//! can quote them in bug reports.
+
+use std::future::Future;
+
+use rocket::fairing::AdHoc;
+use rocket::request::{FromRequest, Outcome, Request};
+use uuid::Uuid;
+
+rocket::tokio::task_local! {
+    static REQUEST_ID: Uuid;
+}
+
+/// Identifier of the current request, if running within
+/// [`RequestId::scope`].
+pub fn current() -> Option<Uuid> {
+    REQUEST_ID.try_with(|id| *id).ok()
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RequestId(Uuid);
+
+impl RequestId {
+    /// Run `f` with this identifier as the current request's.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        REQUEST_ID.scope(self.0, f).await
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = std::convert::Infallible;
+    async fn from_request(
+        request: &'r Request<'_>,
+    ) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestId(*request.local_cache(Uuid::new_v4)))
+    }
+}
+
+/// Fairing adding the `X-Request-Id` header to responses.
+pub fn request_id_header() -> AdHoc {
+    AdHoc::on_response("Request ID", |request, response| {
+        Box::pin(async move {
+            let id = request.local_cache(Uuid::new_v4);
+            response.set_raw_header("X-Request-Id", id.to_string());
+        })
+    })
+}
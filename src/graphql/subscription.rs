@@ -0,0 +1,335 @@
+use std::{env, pin::Pin, str::FromStr, sync::Arc};
+
+use futures::{future, stream, FutureExt, Stream, StreamExt};
+use juniper::{FieldError, FieldResult, GraphQLObject};
+use juniper_graphql_ws::ConnectionConfig;
+use rocket::fairing::AdHoc;
+use rocket::tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+use warp::Filter;
+
+use crate::db::{
+    models::{
+        languages::Language, notifications::Notification,
+        revisions::RevisionAction, words::Word,
+    },
+    DatabaseError, ErrorKind,
+};
+
+use super::{create_schema, Context};
+
+/// Amount of events kept for subscribers lagging behind.
+const LIVE_CAPACITY: usize = 1024;
+
+/// Port of the WebSocket server if `SUBSCRIPTIONS_PORT` is not set.
+const DEFAULT_SUBSCRIPTIONS_PORT: u16 = 8001;
+
+/// Change pushed to the subscribers of the API.
+#[derive(Debug, Clone)]
+pub enum LiveEvent {
+    Word {
+        language: Uuid,
+        word: Uuid,
+        action: RevisionAction,
+    },
+    Language {
+        id: Uuid,
+        action: RevisionAction,
+    },
+    Notification(Notification),
+}
+
+/// Channel through which changes are pushed to subscribers.
+///
+/// Cloning it gives another handle on the same channel.
+#[derive(Debug, Clone)]
+pub struct Live(broadcast::Sender<LiveEvent>);
+
+impl Default for Live {
+    fn default() -> Self {
+        Self(broadcast::channel(LIVE_CAPACITY).0)
+    }
+}
+
+impl Live {
+    /// Push an event to all current subscribers, if any.
+    pub fn publish(&self, event: LiveEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.0.send(event);
+    }
+
+    fn events(&self) -> impl Stream<Item = LiveEvent> + Send {
+        stream::unfold(self.0.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(count)) => {
+                        warn!(
+                            "Subscriber lagged behind, skipped {count} events"
+                        );
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+#[derive(GraphQLObject, Debug, Clone)]
+#[graphql(context = Context, description = "Change made to a word")]
+pub struct WordChange {
+    #[graphql(description = "What was done to the word")]
+    action: RevisionAction,
+    #[graphql(description = "Unique identifier of the word")]
+    id: String,
+    #[graphql(
+        description = "The word after the change, null if it was deleted"
+    )]
+    word: Option<Word>,
+}
+
+#[derive(GraphQLObject, Debug, Clone)]
+#[graphql(context = Context, description = "Change made to a language")]
+pub struct LanguageChange {
+    #[graphql(description = "What was done to the language")]
+    action: RevisionAction,
+    #[graphql(description = "Unique identifier of the language")]
+    id: String,
+    #[graphql(
+        description = "The language after the change, null if it was deleted"
+    )]
+    language: Option<Language>,
+}
+
+type LiveStream<T> = Pin<Box<dyn Stream<Item = FieldResult<T>> + Send>>;
+
+/// Stream made of a single error.
+fn failure<T: Send + 'static>(error: DatabaseError) -> LiveStream<T> {
+    Box::pin(stream::once(future::ready(Err(FieldError::from(error)))))
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, DatabaseError> {
+    Uuid::from_str(id).map_err(|e| {
+        DatabaseError::new(
+            format!("Failed to convert {id} to a UUID: {e:?}"),
+            ErrorKind::InvalidInput,
+        )
+    })
+}
+
+pub struct Subscription;
+
+#[juniper::graphql_subscription(Context = Context)]
+impl Subscription {
+    #[graphql(
+        name = "wordChanged",
+        description = "Receive changes made to the words of a language",
+        arguments(language(description = "UUID of the language"))
+    )]
+    async fn word_changed(
+        context: &Context,
+        language: String,
+    ) -> LiveStream<WordChange> {
+        let language = match parse_uuid(&language) {
+            Ok(language) => language,
+            Err(e) => return failure(e),
+        };
+        let user = context.user_auth().cloned();
+        let db = context.db.clone();
+        Box::pin(context.live.events().filter_map(move |event| {
+            let db = db.clone();
+            let user = user.clone();
+            async move {
+                let LiveEvent::Word {
+                    language: changed,
+                    word,
+                    action,
+                } = event
+                else {
+                    return None;
+                };
+                if changed != language {
+                    return None;
+                }
+                // The language may have become private since the
+                // subscription started
+                task::spawn_blocking(move || {
+                    let language = Language::find_with_trashed(&db, language)
+                        .ok()
+                        .flatten()?;
+                    language.is_readable_by(user.as_deref()).then(|| {
+                        Ok::<_, FieldError>(WordChange {
+                            action,
+                            id: word.to_string(),
+                            word: Word::find(&db, word).ok(),
+                        })
+                    })
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Failed to check change of word {word}: {e:?}");
+                    None
+                })
+            }
+        }))
+    }
+
+    #[graphql(
+        name = "languageChanged",
+        description = "Receive changes made to a language's metadata",
+        arguments(id(description = "UUID of the language"))
+    )]
+    async fn language_changed(
+        context: &Context,
+        id: String,
+    ) -> LiveStream<LanguageChange> {
+        let id = match parse_uuid(&id) {
+            Ok(id) => id,
+            Err(e) => return failure(e),
+        };
+        let user = context.user_auth().cloned();
+        let db = context.db.clone();
+        Box::pin(context.live.events().filter_map(move |event| {
+            let db = db.clone();
+            let user = user.clone();
+            async move {
+                let LiveEvent::Language {
+                    id: changed,
+                    action,
+                } = event
+                else {
+                    return None;
+                };
+                if changed != id {
+                    return None;
+                }
+                task::spawn_blocking(move || {
+                    let language =
+                        Language::find_with_trashed(&db, id).ok().flatten()?;
+                    language.is_readable_by(user.as_deref()).then(|| {
+                        Ok::<_, FieldError>(LanguageChange {
+                            action,
+                            id: id.to_string(),
+                            language: (!language.is_trashed())
+                                .then_some(language),
+                        })
+                    })
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Failed to check change of language {id}: {e:?}");
+                    None
+                })
+            }
+        }))
+    }
+
+    #[graphql(
+        name = "notificationReceived",
+        description = "Receive the notifications of the authentificated user as they are created"
+    )]
+    async fn notification_received(
+        context: &Context,
+    ) -> LiveStream<Notification> {
+        let Some(user) = context.user_auth().cloned() else {
+            return failure(DatabaseError::new(
+                "User not authentificated, cannot receive notifications",
+                ErrorKind::Unauthenticated,
+            ));
+        };
+        Box::pin(context.live.events().filter_map(move |event| {
+            future::ready(match event {
+                LiveEvent::Notification(notification)
+                    if notification.is_for(&user) =>
+                {
+                    Some(Ok(notification))
+                }
+                _ => None,
+            })
+        }))
+    }
+}
+
+fn subscriptions_port() -> u16 {
+    match env::var("SUBSCRIPTIONS_PORT") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid SUBSCRIPTIONS_PORT {val}, defaulting to {DEFAULT_SUBSCRIPTIONS_PORT}: {e:?}"
+            );
+            DEFAULT_SUBSCRIPTIONS_PORT
+        }),
+        Err(_) => DEFAULT_SUBSCRIPTIONS_PORT,
+    }
+}
+
+/// Fairing serving subscriptions over the graphql-ws WebSocket
+/// protocol on `/graphql`, on port `SUBSCRIPTIONS_PORT`.
+///
+/// Rocket 0.5.0-rc.2 cannot upgrade connections, which only became
+/// possible in later releases that `juniper_rocket` 0.8 does not
+/// support, hence the separate server. It serves the same path as the
+/// queries so that a reverse proxy sending the requests asking for a
+/// WebSocket upgrade to it exposes both on the same origin and path.
+///
+/// Clients authenticate by sending what they would send in the
+/// `Authorization` header as `Authorization` in the payload of their
+/// `connection_init` message.
+pub fn websocket_server() -> AdHoc {
+    AdHoc::on_liftoff("GraphQL subscriptions", |rocket| {
+        Box::pin(async move {
+            let Some(context) = rocket.state::<Context>() else {
+                warn!("No context managed by Rocket, subscriptions will not be served");
+                return;
+            };
+            let context = context.clone();
+            let schema = Arc::new(create_schema());
+            let address = (rocket.config().address, subscriptions_port());
+            let routes = warp::path("graphql").and(warp::ws()).map(
+                move |ws: warp::ws::Ws| {
+                    let context = context.clone();
+                    let schema = schema.clone();
+                    let reply = ws.on_upgrade(move |websocket| {
+                        juniper_warp::subscriptions::serve_graphql_ws(
+                            websocket,
+                            schema,
+                            move |payload: juniper::Variables| {
+                                let context = context.clone();
+                                async move {
+                                    let key = payload
+                                        .get("Authorization")
+                                        .and_then(|key| key.as_string_value())
+                                        .map(ToString::to_string);
+                                    // Resolvers run on the async
+                                    // runtime, the credentials are
+                                    // checked once per connection
+                                    let context =
+                                        context.attach_auth(key.as_deref());
+                                    context.resolve_identity().await;
+                                    Ok::<_, std::convert::Infallible>(
+                                        ConnectionConfig::new(context),
+                                    )
+                                }
+                            },
+                        )
+                        .map(|result| {
+                            if let Err(e) = result {
+                                info!("WebSocket connection closed: {e:?}");
+                            }
+                        })
+                    });
+                    warp::reply::with_header(
+                        reply,
+                        "Sec-WebSocket-Protocol",
+                        "graphql-ws",
+                    )
+                },
+            );
+            info!("Serving subscriptions on {}:{}", address.0, address.1);
+            rocket::tokio::spawn(warp::serve(routes).run(address));
+        })
+    })
+}
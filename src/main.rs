@@ -1,18 +1,26 @@
 #![warn(clippy::style, clippy::pedantic)]
 
 mod appwrite;
+mod auth;
 mod db;
+mod export;
 mod graphql;
+mod tasks;
+mod webhook;
 
-use std::{collections::HashSet, env, error::Error};
+use std::{collections::HashSet, env, error::Error, io, sync::Arc};
 
 use dotenvy::dotenv;
 use tracing::{debug, info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing_subscriber::{fmt::MakeWriter, FmtSubscriber};
 
-fn setup_logging() {
+fn setup_logging<W>(writer: W)
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
     let subscriber = FmtSubscriber::builder()
         .with_max_level(Level::DEBUG)
+        .with_writer(writer)
         .finish();
     tracing::subscriber::set_global_default(subscriber)
         .expect("Setting default subscriber failed");
@@ -31,6 +39,7 @@ fn make_cors() -> Result<rocket_cors::Cors, rocket_cors::Error> {
             "Accept",
             "Content-Type",
         ]),
+        expose_headers: HashSet::from(["X-Request-Id".to_string()]),
         allowed_origins: match env::var("ORDABOK_HOSTS") {
             Ok(val) => {
                 if val.is_empty() {
@@ -51,11 +60,21 @@ fn make_cors() -> Result<rocket_cors::Cors, rocket_cors::Error> {
 #[rocket::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     use graphql::{
-        create_schema, get_graphql_handler, graphiql, post_graphql_handler,
+        create_read_only_schema, create_schema, get_graphql_handler, graphiql,
+        post_graphql_handler,
     };
 
     color_eyre::install()?;
-    setup_logging();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Some(("export", args)) = args
+        .split_first()
+        .map(|(command, args)| (command.as_str(), args))
+    {
+        // The exported data goes to the standard output
+        setup_logging(io::stderr);
+        return export::run(args);
+    }
+    setup_logging(io::stdout);
 
     info!("Reading environment variables");
     dotenv().ok();
@@ -66,14 +85,26 @@ async fn main() -> Result<(), Box<dyn Error>> {
     #[allow(clippy::let_underscore_drop, clippy::no_effect_underscore_binding)]
     let _ = rocket::build()
         .attach(cors)
+        .attach(tasks::trash_purge())
+        .attach(tasks::account_deletions())
+        .attach(tasks::admin_bootstrap())
+        .attach(graphql::websocket_server())
+        .attach(graphql::request_id_header())
         .manage(graphql::Context::default())
-        .manage(create_schema())
+        .manage(Arc::new(create_schema()))
+        .manage(Arc::new(create_read_only_schema()))
+        .manage(graphql::QueryLimits::default())
+        .manage(webhook::WebhookConfig::default())
         .mount(
             "/",
             rocket::routes![
                 graphiql,
                 get_graphql_handler,
-                post_graphql_handler
+                post_graphql_handler,
+                export::export_language,
+                export::language_lexicon,
+                export::word_entry,
+                webhook::appwrite_webhook
             ],
         )
         .launch()
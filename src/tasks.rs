@@ -0,0 +1,170 @@
+use std::{env, time::Duration};
+
+use rocket::fairing::AdHoc;
+use tracing::{info, warn};
+
+use crate::db::models::accounts::AccountDeletion;
+use crate::graphql::Context;
+
+/// Amount of days items stay in the trash if `TRASH_RETENTION_DAYS`
+/// is not set.
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Interval between two purges of the trash.
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn trash_retention_days() -> i64 {
+    match env::var("TRASH_RETENTION_DAYS") {
+        Ok(val) => val.parse().unwrap_or_else(|e| {
+            warn!(
+                "Invalid TRASH_RETENTION_DAYS {val}, defaulting to {DEFAULT_TRASH_RETENTION_DAYS}: {e:?}"
+            );
+            DEFAULT_TRASH_RETENTION_DAYS
+        }),
+        Err(_) => DEFAULT_TRASH_RETENTION_DAYS,
+    }
+}
+
+/// Fairing launching a background task which permanently deletes
+/// words and languages that have been in the trash for longer than
+/// `TRASH_RETENTION_DAYS` days.
+pub fn trash_purge() -> AdHoc {
+    AdHoc::on_liftoff("Trash purge", |rocket| {
+        Box::pin(async move {
+            let days = trash_retention_days();
+            let Some(context) = rocket.state::<Context>() else {
+                warn!("No context managed by Rocket, trash will not be purged");
+                return;
+            };
+            let db = context.db.clone();
+            info!("Purging items in the trash for more than {days} days");
+            rocket::tokio::spawn(async move {
+                let mut interval =
+                    rocket::tokio::time::interval(TRASH_PURGE_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let db = db.clone();
+                    match rocket::tokio::task::spawn_blocking(move || {
+                        db.purge_trash(days)
+                    })
+                    .await
+                    {
+                        Ok(Ok((words, languages))) => info!(
+                            "Purged {words} words and {languages} languages from the trash"
+                        ),
+                        Ok(Err(e)) => warn!("Failed to purge the trash: {e:?}"),
+                        Err(e) => warn!("Trash purge task failed: {e:?}"),
+                    }
+                }
+            });
+        })
+    })
+}
+
+/// Interval between two checks for accounts to delete.
+const ACCOUNT_DELETION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Fairing launching a background task which deletes the accounts
+/// whose deletion grace period is over, from the database and from
+/// Appwrite.
+pub fn account_deletions() -> AdHoc {
+    AdHoc::on_liftoff("Account deletions", |rocket| {
+        Box::pin(async move {
+            let Some(context) = rocket.state::<Context>() else {
+                warn!("No context managed by Rocket, accounts will not be deleted");
+                return;
+            };
+            let db = context.db.clone();
+            let appwrite = context.appwrite.clone();
+            rocket::tokio::spawn(async move {
+                let mut interval =
+                    rocket::tokio::time::interval(ACCOUNT_DELETION_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    // Accounts deleted here are kept as pending remote
+                    // deletions until Appwrite deleted them too, so a
+                    // failure is retried on the next run
+                    let remote = appwrite.get().is_some();
+                    let local = db.clone();
+                    let pending =
+                        match rocket::tokio::task::spawn_blocking(move || {
+                            for deletion in AccountDeletion::due(&local)? {
+                                if let Err(e) = deletion.execute(&local, remote)
+                                {
+                                    warn!(
+                                        "Failed to delete account of {}: {e:?}",
+                                        deletion.user()
+                                    );
+                                }
+                            }
+                            AccountDeletion::remote_pending(&local)
+                        })
+                        .await
+                        {
+                            Ok(Ok(pending)) => pending,
+                            Ok(Err(e)) => {
+                                warn!("Failed to delete accounts: {e:?}");
+                                continue;
+                            }
+                            Err(e) => {
+                                warn!("Account deletion task failed: {e:?}");
+                                continue;
+                            }
+                        };
+                    let Some(appwrite) = appwrite.get() else {
+                        continue;
+                    };
+                    for user in pending {
+                        if let Err(e) = appwrite.delete_user(&user).await {
+                            warn!("Failed to delete Appwrite account of {user}, retrying later: {e:?}");
+                            continue;
+                        }
+                        let db = db.clone();
+                        match rocket::tokio::task::spawn_blocking(move || {
+                            AccountDeletion::remote_done(&db, &user)
+                        })
+                        .await
+                        {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => {
+                                warn!("Failed to record remote account deletion: {e:?}");
+                            }
+                            Err(e) => {
+                                warn!("Account deletion task failed: {e:?}");
+                            }
+                        }
+                    }
+                }
+            });
+        })
+    })
+}
+
+/// Fairing granting admin rights to the users listed in
+/// `ADMIN_USERS`, a comma-separated list of Appwrite IDs, so the first
+/// admins can be appointed.
+pub fn admin_bootstrap() -> AdHoc {
+    AdHoc::on_liftoff("Admin bootstrap", |rocket| {
+        Box::pin(async move {
+            let Ok(admins) = env::var("ADMIN_USERS") else {
+                return;
+            };
+            let admins = admins
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .collect::<Vec<_>>();
+            if admins.is_empty() {
+                return;
+            }
+            let Some(context) = rocket.state::<Context>() else {
+                warn!("No context managed by Rocket, admins will not be appointed");
+                return;
+            };
+            match context.db.promote_admins(&admins) {
+                Ok(count) => info!("Granted admin rights to {count} users"),
+                Err(e) => warn!("Failed to grant admin rights: {e:?}"),
+            }
+        })
+    })
+}
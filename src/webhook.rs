@@ -0,0 +1,456 @@
+//! Webhook keeping the users of the database in sync with Appwrite.
+//!
+//! Appwrite must send the `users.*.create`, `users.*.update` and
+//! `users.*.delete` events to `/webhooks/appwrite`. Each request is
+//! signed with the webhook's signature key, which must be set as
+//! `APPWRITE_WEBHOOK_SECRET`, along with the URL Appwrite sends events
+//! to as `APPWRITE_WEBHOOK_URL` since it is part of the signature.
+//!
+//! Only the body of events is signed, so users are looked up on
+//! Appwrite rather than trusting the event names sent along.
+
+use std::env;
+
+use hmac::{Hmac, Mac};
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+    State,
+};
+use sha1::Sha1;
+use tracing::{debug, info, warn};
+
+use crate::{
+    appwrite::{APVariables, AppwriteUser},
+    db::{models::users::User, Database},
+    graphql::Context,
+};
+
+/// Configuration of the Appwrite webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Signature key of the webhook, events are refused if unset.
+    secret: Option<String>,
+    /// URL Appwrite sends events to.
+    url: String,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        let secret = env::var("APPWRITE_WEBHOOK_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        if secret.is_none() {
+            warn!("APPWRITE_WEBHOOK_SECRET is not set, Appwrite webhook events will be refused");
+        }
+        Self {
+            secret,
+            url: env::var("APPWRITE_WEBHOOK_URL").unwrap_or_default(),
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Whether `signature` is the base64-encoded HMAC-SHA1 of the
+    /// webhook's URL followed by `body`.
+    fn verify(&self, signature: &str, body: &str) -> bool {
+        let Some(secret) = &self.secret else {
+            return false;
+        };
+        let Ok(signature) = base64::decode(signature) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(secret.as_bytes())
+        else {
+            return false;
+        };
+        mac.update(self.url.as_bytes());
+        mac.update(body.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+/// Headers Appwrite sends along with webhook events.
+#[derive(Debug, Clone, Copy)]
+pub struct WebhookHeaders<'r> {
+    signature: Option<&'r str>,
+    events: Option<&'r str>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WebhookHeaders<'r> {
+    type Error = std::convert::Infallible;
+    async fn from_request(
+        request: &'r Request<'_>,
+    ) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+        Outcome::Success(Self {
+            signature: headers.get_one("X-Appwrite-Webhook-Signature"),
+            events: headers.get_one("X-Appwrite-Webhook-Events"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UserEvent {
+    Upsert,
+    Delete,
+}
+
+/// Whether `events`, a comma-separated list such as
+/// `users.*.update.name,users.*.update,users.*`, is about the creation,
+/// update or deletion of a user.
+///
+/// Events about sessions, recoveries or verifications of users are
+/// ignored.
+fn is_user_event(events: &str) -> bool {
+    events.split(',').any(|event| {
+        let mut parts = event.trim().split('.');
+        matches!(
+            (parts.next(), parts.next(), parts.next()),
+            (Some("users"), Some(_), Some("create" | "update" | "delete"))
+        )
+    })
+}
+
+#[rocket::post("/webhooks/appwrite", data = "<body>")]
+pub async fn appwrite_webhook(
+    context: &State<Context>,
+    config: &State<WebhookConfig>,
+    headers: WebhookHeaders<'_>,
+    body: String,
+) -> Status {
+    handle_event(&context.db, context.appwrite.get(), config, headers, &body)
+        .await
+}
+
+/// Apply the Appwrite webhook event `body` to the database, provided it
+/// is properly signed.
+///
+/// The events header is not covered by the signature, so it only
+/// tells which events to look at: whether the user of the signed body
+/// was created, updated or deleted is asked to `appwrite`.
+async fn handle_event(
+    db: &Database,
+    appwrite: Option<&APVariables>,
+    config: &WebhookConfig,
+    headers: WebhookHeaders<'_>,
+    body: &str,
+) -> Status {
+    if !headers
+        .signature
+        .map_or(false, |signature| config.verify(signature, body))
+    {
+        info!("Refused Appwrite webhook event with an invalid signature");
+        return Status::Unauthorized;
+    }
+    if !headers.events.map_or(false, is_user_event) {
+        debug!("Ignoring Appwrite webhook events {:?}", headers.events);
+        return Status::NoContent;
+    }
+    let user: AppwriteUser = match serde_json::from_str(body) {
+        Ok(user) => user,
+        Err(e) => {
+            warn!("Invalid user in Appwrite webhook event: {e:?}");
+            return Status::BadRequest;
+        }
+    };
+    let Some(appwrite) = appwrite else {
+        warn!("Appwrite is not configured, cannot check webhook event about user {}", user.id);
+        return Status::ServiceUnavailable;
+    };
+    let account = match appwrite.get_user(&user.id).await {
+        Ok(account) => account,
+        Err(e) => {
+            warn!("Failed to fetch user {} from Appwrite: {e:?}", user.id);
+            return Status::BadGateway;
+        }
+    };
+    let (event, result) = match account {
+        Some(account) => {
+            (UserEvent::Upsert, User::sync(db, &account).map(|_| ()))
+        }
+        None => (UserEvent::Delete, User::forget(db, &user)),
+    };
+    match result {
+        Ok(()) => {
+            info!("Synchronised user {} with Appwrite ({event:?})", user.id);
+            Status::NoContent
+        }
+        Err(e) => {
+            warn!("Failed to synchronise user {}: {e:?}", user.id);
+            Status::InternalServerError
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::db::testing;
+
+    const SECRET: &str = "webhook-secret";
+    const URL: &str = "https://ordabok.example.com/webhooks/appwrite";
+
+    fn config() -> WebhookConfig {
+        WebhookConfig {
+            secret: Some(SECRET.into()),
+            url: URL.into(),
+        }
+    }
+
+    fn sign(secret: &str, url: &str, body: &str) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(url.as_bytes());
+        mac.update(body.as_bytes());
+        base64::encode(mac.finalize().into_bytes())
+    }
+
+    fn headers<'r>(signature: &'r str, events: &'r str) -> WebhookHeaders<'r> {
+        WebhookHeaders {
+            signature: Some(signature),
+            events: Some(events),
+        }
+    }
+
+    fn account(id: &str, name: &str) -> String {
+        json!({ "$id": id, "name": name, "email": "user@example.com" })
+            .to_string()
+    }
+
+    #[test]
+    fn accepts_signed_events() {
+        let body = account("alice", "Alice");
+        assert!(config().verify(&sign(SECRET, URL, &body), &body));
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        let config = config();
+        let body = account("alice", "Alice");
+        assert!(!config.verify(&sign("other-secret", URL, &body), &body));
+        assert!(!config.verify(
+            &sign(SECRET, "https://elsewhere.example.com/", &body),
+            &body
+        ));
+        let tampered = account("alice", "Mallory");
+        assert!(!config.verify(&sign(SECRET, URL, &body), &tampered));
+        assert!(!config.verify("not base64!", &body));
+        assert!(!config.verify("", &body));
+    }
+
+    #[test]
+    fn rejects_events_without_secret() {
+        let config = WebhookConfig {
+            secret: None,
+            url: URL.into(),
+        };
+        let body = account("alice", "Alice");
+        assert!(!config.verify(&sign("", URL, &body), &body));
+    }
+
+    #[test]
+    fn parses_user_events() {
+        assert!(is_user_event("users.alice.create,users.*.create,users.*"));
+        assert!(is_user_event("users.*.update.name, users.*.update"));
+        assert!(is_user_event("users.*.delete,users.*"));
+        assert!(!is_user_event("users.*.sessions.*.create"));
+        assert!(!is_user_event("databases.*.create"));
+        assert!(!is_user_event(""));
+    }
+
+    /// Mock Appwrite instance, which does not know any user until
+    /// [`knows`] is called.
+    async fn appwrite() -> (MockServer, APVariables) {
+        let server = MockServer::start().await;
+        let appwrite =
+            APVariables::new(server.uri(), "ordabok".into(), "api-key".into());
+        (server, appwrite)
+    }
+
+    /// Make `server` describe the user `id` as `name`.
+    async fn knows(server: &MockServer, id: &str, name: &str) {
+        server.reset().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/users/{id}")))
+            .and(header("X-Appwrite-Key", "api-key"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(account(id, name)),
+            )
+            .mount(server)
+            .await;
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn synchronises_users() {
+        let db = testing::database();
+        let (server, appwrite) = appwrite().await;
+        let config = config();
+        let id = testing::user_id();
+
+        knows(&server, &id, "Alice").await;
+        let body = account(&id, "Alice");
+        let signature = sign(SECRET, URL, &body);
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            headers(&signature, "users.*.create,users.*"),
+            &body,
+        )
+        .await;
+        assert_eq!(Status::NoContent, status);
+        assert_eq!("Alice", db.user(&id).unwrap().unwrap().username);
+
+        knows(&server, &id, "Alicia").await;
+        let body = account(&id, "Alicia");
+        let signature = sign(SECRET, URL, &body);
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            headers(&signature, "users.*.update.name,users.*.update"),
+            &body,
+        )
+        .await;
+        assert_eq!(Status::NoContent, status);
+        assert_eq!("Alicia", db.user(&id).unwrap().unwrap().username);
+
+        server.reset().await;
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            headers(&signature, "users.*.delete"),
+            &body,
+        )
+        .await;
+        assert_eq!(Status::NoContent, status);
+        assert!(db.user(&id).unwrap().is_none());
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn event_names_are_not_trusted() {
+        let db = testing::database();
+        let (server, appwrite) = appwrite().await;
+        let config = config();
+        let id = testing::user_id();
+        knows(&server, &id, "Alice").await;
+        let body = account(&id, "Alice");
+        let signature = sign(SECRET, URL, &body);
+        let event = |events: &'static str| {
+            handle_event(
+                &db,
+                Some(&appwrite),
+                &config,
+                headers(&signature, events),
+                &body,
+            )
+        };
+        assert_eq!(Status::NoContent, event("users.*.create").await);
+
+        // A replayed creation passed off as a deletion keeps the user
+        assert_eq!(Status::NoContent, event("users.*.delete").await);
+        assert_eq!("Alice", db.user(&id).unwrap().unwrap().username);
+
+        // And a replayed deletion cannot bring a deleted user back
+        server.reset().await;
+        assert_eq!(Status::NoContent, event("users.*.delete").await);
+        assert_eq!(Status::NoContent, event("users.*.create").await);
+        assert!(db.user(&id).unwrap().is_none());
+
+        // Appwrite being down fails the event so that it is retried
+        let unreachable = APVariables::new(
+            "http://127.0.0.1:9".into(),
+            "ordabok".into(),
+            "api-key".into(),
+        );
+        assert_eq!(
+            Status::BadGateway,
+            handle_event(
+                &db,
+                Some(&unreachable),
+                &config,
+                headers(&signature, "users.*.create"),
+                &body,
+            )
+            .await
+        );
+        assert_eq!(
+            Status::ServiceUnavailable,
+            handle_event(
+                &db,
+                None,
+                &config,
+                headers(&signature, "users.*.create"),
+                &body,
+            )
+            .await
+        );
+    }
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn ignores_unsigned_and_unrelated_events() {
+        let db = testing::database();
+        let (server, appwrite) = appwrite().await;
+        let config = config();
+        let id = testing::user_id();
+        knows(&server, &id, "Mallory").await;
+        let body = account(&id, "Mallory");
+        let signature = sign("other-secret", URL, &body);
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            headers(&signature, "users.*.create"),
+            &body,
+        )
+        .await;
+        assert_eq!(Status::Unauthorized, status);
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            WebhookHeaders {
+                signature: None,
+                events: Some("users.*.create"),
+            },
+            &body,
+        )
+        .await;
+        assert_eq!(Status::Unauthorized, status);
+
+        let signature = sign(SECRET, URL, &body);
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            headers(&signature, "users.*.sessions.*.create"),
+            &body,
+        )
+        .await;
+        assert_eq!(Status::NoContent, status);
+        assert!(db.user(&id).unwrap().is_none());
+
+        let body = "{}";
+        let signature = sign(SECRET, URL, body);
+        let status = handle_event(
+            &db,
+            Some(&appwrite),
+            &config,
+            headers(&signature, "users.*.create"),
+            body,
+        )
+        .await;
+        assert_eq!(Status::BadRequest, status);
+    }
+}